@@ -2,21 +2,28 @@ fn main() {
     windows::build!(
         Windows::Foundation::Numerics::{Vector2, Vector3},
         Windows::Foundation::TimeSpan,
+        Windows::Foundation::TypedEventHandler,
         Windows::Foundation::Size,
         Windows::Graphics::SizeInt32,
         Windows::Graphics::DirectX::DirectXAlphaMode,
         Windows::Graphics::DirectX::DirectXPixelFormat,
+        Windows::Gaming::Input::{Gamepad, GamepadButtons, GamepadReading},
+        Windows::Globalization::NumberFormatting::DecimalFormatter,
         Windows::System::DispatcherQueueController,
         Windows::UI::Composition::{
             CompositionRoundedRectangleGeometry,
+            CompositionScopedBatch,
             CompositionShapeCollection,
             CompositionBrush,
             VisualCollection,
+            AnimationDelayBehavior,
             AnimationIterationBehavior,
+            CompositionBatchCompletedEventArgs,
             CompositionBatchTypes,
             CompositionBorderMode,
             CompositionColorBrush,
             CompositionGeometry,
+            CompositionInsetClip,
             CompositionShape,
             CompositionSpriteShape,
             Compositor,
@@ -25,19 +32,43 @@ fn main() {
             ShapeVisual,
             CompositionGraphicsDevice,
             CompositionContainerShape,
+            CompositionEasingFunction,
+            CubicBezierEasingFunction,
             CompositionSurfaceBrush,
-            Vector3KeyFrameAnimation
+            DropShadow,
+            Vector2KeyFrameAnimation,
+            Vector3KeyFrameAnimation,
+            ScalarKeyFrameAnimation
         },
         Windows::UI::Composition::Desktop::DesktopWindowTarget,
         Windows::UI::Composition::CompositionDrawingSurface,
         Windows::UI::{Color, Colors, ColorHelper},
+        Windows::UI::ViewManagement::{UIColorType, UISettings},
         Windows::Win32::System::SystemServices::{
             CreateDispatcherQueueController, BOOL, DQTYPE_THREAD_CURRENT, DQTAT_COM_NONE,
         },
         Windows::Win32::System::WinRT::ICompositorDesktopInterop,
         Windows::Win32::UI::WindowsAndMessaging::HWND,
+        Windows::Win32::Graphics::Gdi::{
+            BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC,
+            GetDIBits, ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, DIB_USAGE, ROP_CODE,
+        },
+        Windows::Win32::System::DataExchange::{
+            CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+        },
+        Windows::Win32::System::Memory::{
+            GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GLOBAL_ALLOC_FLAGS,
+        },
+        Windows::Win32::System::SystemServices::HANDLE,
+        Windows::Win32::Graphics::Dwm::{DwmExtendFrameIntoClientArea, DwmSetWindowAttribute},
+        Windows::Win32::UI::Controls::MARGINS,
+        Windows::Win32::UI::Shell::{DefSubclassProc, SetWindowSubclass},
+        Windows::Win32::UI::WindowsAndMessaging::{GetClientRect, IsZoomed},
+        Windows::Win32::UI::DisplayDevices::{POINT, RECT},
+        Windows::Win32::Graphics::Gdi::{ClientToScreen, ScreenToClient},
         Microsoft::Graphics::Canvas::CanvasDrawingSession,
         Microsoft::Graphics::Canvas::CanvasDevice,
+        Microsoft::Graphics::Canvas::Geometry::{CanvasFigureLoop, CanvasGeometry, CanvasPathBuilder},
         Microsoft::Graphics::Canvas::Text::*,
         Microsoft::Graphics::Canvas::UI::Composition::*,
     );
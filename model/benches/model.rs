@@ -0,0 +1,108 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use model::ai;
+use model::bitfield::BitField;
+use model::board::Board;
+use model::field::{Field, Side};
+use ndarray::Array2;
+use std::convert::TryFrom;
+
+const SIDES: [Side; 4] = [Side::Up, Side::Left, Side::Right, Side::Down];
+
+fn mid_game() -> Field {
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((4, 4), vec![
+        2,   4,   0,  2,
+        16,  8,   4,  0,
+        32,  64,  8,  4,
+        512, 128, 32, 2,
+    ]).unwrap();
+    Field::from_array(array)
+}
+
+fn large_board() -> Field {
+    let array = Array2::from_shape_fn((8, 8), |(y, x)| match (x + y) % 3 {
+        0 => 0,
+        1 => 2 << (x % 4),
+        _ => 4 << (y % 4),
+    });
+    Field::from_array(array)
+}
+
+fn bench_swipe(c: &mut Criterion) {
+    for (name, field) in [("swipe 4x4", mid_game()), ("swipe 8x8", large_board())] {
+        c.bench_function(name, |b| {
+            b.iter_batched(
+                || field.clone(),
+                |mut field| {
+                    for side in SIDES {
+                        black_box(field.swipe(side));
+                    }
+                    field
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+fn bench_can_swipe(c: &mut Criterion) {
+    for (name, field) in [
+        ("can_swipe 4x4", mid_game()),
+        ("can_swipe 8x8", large_board()),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                SIDES
+                    .iter()
+                    .filter(|&&side| black_box(&field).can_swipe(side))
+                    .count()
+            })
+        });
+    }
+}
+
+fn bench_bitfield(c: &mut Criterion) {
+    let bitfield = BitField::try_from(&mid_game()).unwrap();
+    c.bench_function("bitfield swipe 4x4", |b| {
+        b.iter(|| {
+            let mut bitfield = black_box(bitfield);
+            for side in SIDES {
+                black_box(bitfield.swipe(side));
+            }
+            bitfield
+        })
+    });
+    c.bench_function("bitfield can_swipe 4x4", |b| {
+        b.iter(|| {
+            SIDES
+                .iter()
+                .filter(|&&side| black_box(&bitfield).can_swipe(side))
+                .count()
+        })
+    });
+}
+
+fn bench_ai(c: &mut Criterion) {
+    let field = mid_game();
+    let bitfield = BitField::try_from(&field).unwrap();
+    let mut group = c.benchmark_group("best_move 4x4");
+    group.sample_size(10);
+    for depth in 1..=ai::DEFAULT_DEPTH + 1 {
+        group.bench_function(format!("field depth {}", depth), |b| {
+            b.iter(|| ai::best_board_move(black_box(&field), depth))
+        });
+        group.bench_function(format!("bitfield depth {}", depth), |b| {
+            b.iter(|| ai::best_board_move(black_box(&bitfield), depth))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_swipe,
+    bench_can_swipe,
+    bench_bitfield,
+    bench_ai
+);
+criterion_main!(benches);
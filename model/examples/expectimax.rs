@@ -0,0 +1,50 @@
+// Plays full games with the expectimax AI and reports search speed and results.
+// Usage: cargo run --release --example expectimax -- [games] [depth] [size]
+use model::ai;
+use model::game::Game;
+use std::env;
+use std::time::{Duration, Instant};
+
+fn arg(n: usize, default: usize) -> usize {
+    env::args()
+        .nth(n)
+        .map(|s| s.parse().expect("Expected a positive number"))
+        .unwrap_or(default)
+}
+
+fn main() {
+    let games = arg(1, 5);
+    let depth = arg(2, ai::DEFAULT_DEPTH);
+    let size = arg(3, 4);
+    println!(
+        "{} games on {}x{} board, depth {}",
+        games, size, size, depth
+    );
+    let (mut total_moves, mut total_time) = (0, Duration::default());
+    for n in 1..=games {
+        let mut game = Game::new(size, size);
+        let mut moves = 0;
+        let start = Instant::now();
+        while let Some(side) = ai::best_move_with_depth(game.field(), depth) {
+            game.swipe(side);
+            moves += 1;
+        }
+        let elapsed = start.elapsed();
+        println!(
+            "game {}: score {}, max tile {}, {} moves, {:.2?} per move",
+            n,
+            game.score(),
+            game.field().max_tile(),
+            moves,
+            elapsed / moves.max(1)
+        );
+        total_moves += moves;
+        total_time += elapsed;
+    }
+    println!(
+        "total: {} moves in {:.2?}, {:.0} moves/s",
+        total_moves,
+        total_time,
+        total_moves as f64 / total_time.as_secs_f64().max(f64::EPSILON)
+    );
+}
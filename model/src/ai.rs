@@ -0,0 +1,199 @@
+use std::convert::TryFrom;
+
+use crate::{
+    bitfield::BitField,
+    board::Board,
+    field::{Field, Side},
+};
+
+pub const DEFAULT_DEPTH: usize = 2;
+// Chance nodes consider at most this number of free cells to keep search time bounded
+const MAX_CHANCE_CELLS: usize = 6;
+const SIDES: [Side; 4] = [Side::Up, Side::Left, Side::Right, Side::Down];
+
+const EMPTY_WEIGHT: f32 = 2.7;
+const MONOTONICITY_WEIGHT: f32 = 1.0;
+const SMOOTHNESS_WEIGHT: f32 = 0.1;
+const MAX_TILE_WEIGHT: f32 = 1.0;
+const GAME_OVER_PENALTY: f32 = 1000.;
+
+pub fn best_move(field: &Field) -> Option<Side> {
+    best_move_with_depth(field, DEFAULT_DEPTH)
+}
+
+// Classic 4x4 fields without blockers are searched on BitField, which gives the same
+// moves much faster
+pub fn best_move_with_depth(field: &Field, depth: usize) -> Option<Side> {
+    match BitField::try_from(field) {
+        Ok(bitfield) => best_board_move(&bitfield, depth),
+        Err(_) => best_board_move(field, depth),
+    }
+}
+
+pub fn best_board_move<B: Board>(board: &B, depth: usize) -> Option<Side> {
+    search(board, depth).map(|(side, _)| side)
+}
+
+fn search<B: Board>(field: &B, depth: usize) -> Option<(Side, f32)> {
+    let mut best = None;
+    let mut best_value = f32::NEG_INFINITY;
+    for side in SIDES.iter().copied() {
+        if field.can_swipe(side) {
+            let mut next = field.clone();
+            next.swipe(side);
+            let value = chance_value(&next, depth);
+            if value > best_value {
+                best = Some(side);
+                best_value = value;
+            }
+        }
+    }
+    best.map(|side| (side, best_value))
+}
+
+fn max_value<B: Board>(field: &B, depth: usize) -> f32 {
+    match search(field, depth) {
+        Some((_, value)) => value,
+        None => evaluate(field) - GAME_OVER_PENALTY,
+    }
+}
+
+// New tile of one of the rule's appear levels appears in a free cell with the chances
+// given by the spawn policy. Only one tile is assumed, even if the policy adds more
+fn chance_value<B: Board>(field: &B, depth: usize) -> f32 {
+    let cells = field.get_free_cells();
+    if depth == 0 || cells.is_empty() {
+        return evaluate(field);
+    }
+    // Rounded up, cells are not empty here
+    let step = (cells.len() - 1) / MAX_CHANCE_CELLS + 1;
+    let cells = cells.into_iter().step_by(step).collect::<Vec<_>>();
+    let rule = field.rule().rule();
+    let weights = field.spawn_policy().weights(rule);
+    let total = weights.iter().sum::<u32>() as f32;
+    let mut sum = 0.;
+    for (x, y) in &cells {
+        for (level, weight) in rule.appear_levels().iter().zip(&weights) {
+            if *weight == 0 {
+                continue;
+            }
+            let mut next = field.clone();
+            next.put_level(*x, *y, *level);
+            sum += max_value(&next, depth - 1) * *weight as f32 / total;
+        }
+    }
+    sum / cells.len() as f32
+}
+
+// Heuristic value of the position: prefer free cells, monotonic rows and columns,
+// small differences between neighbours and large tiles
+pub fn evaluate<B: Board>(field: &B) -> f32 {
+    let (width, height) = (field.width(), field.height());
+    // Tile level grows by one with each merge for all rules, so it works as log of value
+    let value = |x: usize, y: usize| field.level(x, y).map_or(0., |level| level as f32);
+    // Blockers have no level but the cells are taken
+    let empty = field.get_free_cells().len() as f32;
+    let mut smoothness = 0.;
+    let mut max_tile: f32 = 0.;
+    for x in 0..width {
+        for y in 0..height {
+            let v = value(x, y);
+            if v == 0. {
+                continue;
+            }
+            max_tile = max_tile.max(v);
+            if x + 1 < width && value(x + 1, y) != 0. {
+                smoothness -= (v - value(x + 1, y)).abs();
+            }
+            if y + 1 < height && value(x, y + 1) != 0. {
+                smoothness -= (v - value(x, y + 1)).abs();
+            }
+        }
+    }
+    let monotonicity = |line: &[f32]| {
+        let (mut inc, mut dec) = (0., 0.);
+        for pair in line.windows(2) {
+            if pair[0] > pair[1] {
+                dec += pair[0] - pair[1];
+            } else {
+                inc += pair[1] - pair[0];
+            }
+        }
+        -f32::min(inc, dec)
+    };
+    let mut monotonic = 0.;
+    for y in 0..height {
+        let row = (0..width).map(|x| value(x, y)).collect::<Vec<_>>();
+        monotonic += monotonicity(&row);
+    }
+    for x in 0..width {
+        let column = (0..height).map(|y| value(x, y)).collect::<Vec<_>>();
+        monotonic += monotonicity(&column);
+    }
+    empty * EMPTY_WEIGHT
+        + monotonic * MONOTONICITY_WEIGHT
+        + smoothness * SMOOTHNESS_WEIGHT
+        + max_tile * MAX_TILE_WEIGHT
+}
+
+#[test]
+fn best_move_game_over() {
+    use ndarray::Array2;
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((2, 2), vec![
+        2, 4,
+        4, 2,
+    ]).unwrap();
+    assert_eq!(best_move(&Field::from_array(array)), None);
+}
+
+#[test]
+fn best_move_single_choice() {
+    use ndarray::Array2;
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((3, 3), vec![
+        2, 4, 2,
+        4, 2, 4,
+        2, 4, 0,
+    ]).unwrap();
+    let field = Field::from_array(array);
+    let side = best_move(&field).unwrap();
+    assert!(field.can_swipe(side));
+    assert!(matches!(side, Side::Down | Side::Right));
+}
+
+#[test]
+fn best_move_plays_better_than_random() {
+    use crate::game::Game;
+    let mut game = Game::new(4, 4);
+    while let Some(side) = best_move_with_depth(game.field(), 1) {
+        assert!(game.swipe(side).is_some());
+    }
+    assert!(game.field().max_tile() >= 128);
+}
+
+#[test]
+fn best_move_same_on_bitfield() {
+    use crate::game::Game;
+    let mut game = Game::new(4, 4);
+    for _ in 0..50 {
+        let field = game.field();
+        let bitfield = BitField::try_from(field).unwrap();
+        let side = best_board_move(field, 1);
+        assert_eq!(best_board_move(&bitfield, 1), side);
+        match side {
+            Some(side) => assert!(game.swipe(side).is_some()),
+            None => break,
+        }
+    }
+}
+
+#[test]
+fn evaluate_blockers_are_not_empty() {
+    use crate::field::Tile;
+    use ndarray::Array2;
+    let field = Field::from_array(Array2::from_shape_vec((1, 3), vec![2, 0, 0]).unwrap());
+    let mut blocked = field.clone();
+    blocked.put(2, 0, Some(Tile::blocker(2, 0)));
+    assert!(evaluate(&blocked) < evaluate(&field));
+}
@@ -0,0 +1,314 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::{
+    board::Board,
+    field::{Field, Side, SwipeResult, Tile},
+    merge_rule::MergeRuleKind,
+    spawn::SpawnPolicy,
+};
+
+pub const BITFIELD_SIZE: usize = 4;
+// Each cell keeps the level in 4 bits and 0 means empty cell, so 32768 is the largest tile.
+// Tiles of this level don't merge
+pub const BITFIELD_MAX_LEVEL: u32 = 15;
+
+// 4x4 field with the classic rule packed into u64: cell (x, y) is the nibble number y * 4 + x,
+// so each row is 16 bits. Tiles have no origins, use Field to show them
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BitField(u64, SpawnPolicy);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BitFieldError {
+    BadSize(usize, usize),
+    BadRule(MergeRuleKind),
+    Blocker,
+    BadLevel(u32),
+}
+
+impl fmt::Display for BitFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitFieldError::BadSize(w, h) => write!(f, "field {}x{} is not 4x4", w, h),
+            BitFieldError::BadRule(rule) => write!(f, "rule '{}' is not classic", rule.name()),
+            BitFieldError::Blocker => write!(f, "blockers are not supported"),
+            BitFieldError::BadLevel(level) => write!(f, "level {} doesn't fit 4 bits", level),
+        }
+    }
+}
+
+impl std::error::Error for BitFieldError {}
+
+impl BitField {
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+    fn shift(x: usize, y: usize) -> usize {
+        (y * BITFIELD_SIZE + x) * 4
+    }
+    // Rows are moved to the left, so other sides are reduced to it by
+    // transposing the board and reversing the rows
+    fn swipe_bits(&self, side: Side) -> (u64, SwipeResult) {
+        let (bits, reverse, transposed) = match side {
+            Side::Left => (self.0, false, false),
+            Side::Right => (self.0, true, false),
+            Side::Up => (transpose(self.0), false, true),
+            Side::Down => (transpose(self.0), true, true),
+        };
+        let mut moved = 0;
+        let mut result = SwipeResult::default();
+        for y in 0..BITFIELD_SIZE {
+            let mut row = (bits >> (y * 16)) as u16;
+            if reverse {
+                row = reverse_row(row);
+            }
+            let row_move = ROW_MOVES[row as usize];
+            let mut row = row_move.row;
+            if reverse {
+                row = reverse_row(row);
+            }
+            moved |= (row as u64) << (y * 16);
+            result.score += row_move.score;
+            result.merges += row_move.merges as u32;
+        }
+        if transposed {
+            moved = transpose(moved);
+        }
+        (moved, result)
+    }
+}
+
+// Row (x, x+1, x+2, x+3) becomes (x, x+4, x+8, x+12) and vice versa
+fn transpose(bits: u64) -> u64 {
+    let a1 = bits & 0xF0F0_0F0F_F0F0_0F0F;
+    let a2 = bits & 0x0000_F0F0_0000_F0F0;
+    let a3 = bits & 0x0F0F_0000_0F0F_0000;
+    let a = a1 | (a2 << 12) | (a3 >> 12);
+    let b1 = a & 0xFF00_FF00_00FF_00FF;
+    let b2 = a & 0x00FF_00FF_0000_0000;
+    let b3 = a & 0x0000_0000_FF00_FF00;
+    b1 | (b2 >> 24) | (b3 << 24)
+}
+
+fn reverse_row(row: u16) -> u16 {
+    (row >> 12) | ((row >> 4) & 0x00F0) | ((row << 4) & 0x0F00) | (row << 12)
+}
+
+#[derive(Copy, Clone)]
+struct RowMove {
+    row: u16,
+    merges: u16,
+    score: u32,
+}
+
+// Result of swiping to the left each of 65536 possible rows
+static ROW_MOVES: [RowMove; 1 << 16] = row_moves();
+
+const fn row_moves() -> [RowMove; 1 << 16] {
+    let mut moves = [RowMove {
+        row: 0,
+        merges: 0,
+        score: 0,
+    }; 1 << 16];
+    let mut row = 0;
+    while row < moves.len() {
+        moves[row] = swipe_row(row as u16);
+        row += 1;
+    }
+    moves
+}
+
+// Tiles slide to the lowest nibble, each tile merges at most once
+const fn swipe_row(row: u16) -> RowMove {
+    let mut result = RowMove {
+        row: 0,
+        merges: 0,
+        score: 0,
+    };
+    let mut count = 0;
+    let mut last = 0;
+    let mut i = 0;
+    while i < BITFIELD_SIZE {
+        let level = (row >> (i * 4)) & 0xF;
+        i += 1;
+        if level == 0 {
+            continue;
+        }
+        if level == last && (level as u32) < BITFIELD_MAX_LEVEL {
+            result.row += 1 << ((count - 1) * 4);
+            result.score += 1 << (level + 1);
+            result.merges += 1;
+            // Merged tile doesn't merge again
+            last = 0;
+        } else {
+            result.row |= level << (count * 4);
+            count += 1;
+            last = level;
+        }
+    }
+    result
+}
+
+impl Board for BitField {
+    fn width(&self) -> usize {
+        BITFIELD_SIZE
+    }
+    fn height(&self) -> usize {
+        BITFIELD_SIZE
+    }
+    fn rule(&self) -> MergeRuleKind {
+        MergeRuleKind::Classic
+    }
+    fn spawn_policy(&self) -> SpawnPolicy {
+        self.1
+    }
+    fn level(&self, x: usize, y: usize) -> Option<u32> {
+        match (self.0 >> Self::shift(x, y)) & 0xF {
+            0 => None,
+            level => Some(level as u32),
+        }
+    }
+    fn put_level(&mut self, x: usize, y: usize, level: u32) {
+        let shift = Self::shift(x, y);
+        self.0 = (self.0 & !(0xF << shift)) | ((level.min(BITFIELD_MAX_LEVEL) as u64) << shift);
+    }
+    fn can_swipe(&self, side: Side) -> bool {
+        self.swipe_bits(side).0 != self.0
+    }
+    fn swipe(&mut self, side: Side) -> SwipeResult {
+        let (bits, result) = self.swipe_bits(side);
+        self.0 = bits;
+        result
+    }
+    fn get_free_cells(&self) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        for x in 0..BITFIELD_SIZE {
+            for y in 0..BITFIELD_SIZE {
+                if self.level(x, y).is_none() {
+                    result.push((x, y));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl TryFrom<&Field> for BitField {
+    type Error = BitFieldError;
+    fn try_from(field: &Field) -> Result<Self, Self::Error> {
+        if field.width() != BITFIELD_SIZE || field.height() != BITFIELD_SIZE {
+            return Err(BitFieldError::BadSize(field.width(), field.height()));
+        }
+        if field.rule() != MergeRuleKind::Classic {
+            return Err(BitFieldError::BadRule(field.rule()));
+        }
+        let mut bitfield = BitField(0, field.spawn_policy());
+        for x in 0..BITFIELD_SIZE {
+            for y in 0..BITFIELD_SIZE {
+                match field.get(x, y) {
+                    None => {}
+                    Some(tile) if tile.is_blocker() => return Err(BitFieldError::Blocker),
+                    Some(tile) => {
+                        let level = tile.get_level();
+                        if level == 0 || level > BITFIELD_MAX_LEVEL {
+                            return Err(BitFieldError::BadLevel(level));
+                        }
+                        bitfield.put_level(x, y, level);
+                    }
+                }
+            }
+        }
+        Ok(bitfield)
+    }
+}
+
+// All tiles get Hold origin
+impl From<BitField> for Field {
+    fn from(bitfield: BitField) -> Field {
+        let mut field = Field::new(BITFIELD_SIZE, BITFIELD_SIZE);
+        field.set_spawn_policy(bitfield.1);
+        for x in 0..BITFIELD_SIZE {
+            for y in 0..BITFIELD_SIZE {
+                if let Some(level) = bitfield.level(x, y) {
+                    field.put(x, y, Some(Tile::appear(level)));
+                }
+            }
+        }
+        field.hold_all();
+        field
+    }
+}
+
+#[test]
+fn bitfield_conversion() {
+    use ndarray::Array2;
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((4, 4), vec![
+        2,   4,   0,  2,
+        16,  8,   4,  0,
+        32,  64,  8,  4,
+        512, 128, 32, 32768,
+    ]).unwrap();
+    let mut field = Field::from_array(array.clone());
+    field.set_spawn_policy(SpawnPolicy::Hard);
+    let bitfield = BitField::try_from(&field).unwrap();
+    assert_eq!(bitfield.level(0, 3), Some(9));
+    assert_eq!(bitfield.level(2, 0), None);
+    assert_eq!(bitfield.spawn_policy(), SpawnPolicy::Hard);
+    assert_eq!(Field::from(bitfield), field);
+
+    let mut big = array.clone();
+    big[(0, 2)] = 65536;
+    assert_eq!(
+        BitField::try_from(&Field::from_array(big)),
+        Err(BitFieldError::BadLevel(16))
+    );
+    assert_eq!(
+        BitField::try_from(&Field::new(5, 4)),
+        Err(BitFieldError::BadSize(5, 4))
+    );
+    assert_eq!(
+        BitField::try_from(&Field::with_rule(4, 4, MergeRuleKind::Threes)),
+        Err(BitFieldError::BadRule(MergeRuleKind::Threes))
+    );
+    let mut blocked = Field::new(4, 4);
+    blocked.append_blocker();
+    assert_eq!(BitField::try_from(&blocked), Err(BitFieldError::Blocker));
+}
+
+#[test]
+fn bitfield_swipe_matches_field() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    for _ in 0..200 {
+        let mut field = Field::new(4, 4);
+        for x in 0..4 {
+            for y in 0..4 {
+                if rng.gen_bool(0.6) {
+                    field.put(x, y, Some(Tile::appear(rng.gen_range(1..5))));
+                }
+            }
+        }
+        field.hold_all();
+        let bitfield = BitField::try_from(&field).unwrap();
+        for side in [Side::Up, Side::Down, Side::Left, Side::Right] {
+            let (mut field, mut bitfield) = (field.clone(), bitfield);
+            assert_eq!(bitfield.can_swipe(side), field.can_swipe(side));
+            assert_eq!(bitfield.swipe(side), field.swipe(side));
+            field.hold_all();
+            assert_eq!(Field::from(bitfield), field);
+        }
+    }
+}
+
+#[test]
+fn bitfield_max_level() {
+    let mut bitfield = BitField(0, SpawnPolicy::Classic);
+    bitfield.put_level(0, 0, BITFIELD_MAX_LEVEL);
+    bitfield.put_level(1, 0, BITFIELD_MAX_LEVEL);
+    assert!(!bitfield.can_swipe(Side::Left));
+    assert!(bitfield.can_swipe(Side::Down));
+}
@@ -0,0 +1,59 @@
+use crate::{
+    field::{Field, Side, SwipeResult, Tile},
+    merge_rule::MergeRuleKind,
+    spawn::SpawnPolicy,
+};
+
+// Moves and tile levels without origins. Implemented by Field, which keeps everything
+// the UI needs, and by BitField, which is much faster to copy and swipe
+pub trait Board: Clone {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn rule(&self) -> MergeRuleKind;
+    fn spawn_policy(&self) -> SpawnPolicy;
+    // Level of the tile on the cell, None for empty cells and blockers
+    fn level(&self, x: usize, y: usize) -> Option<u32>;
+    // Puts the new tile on the free cell
+    fn put_level(&mut self, x: usize, y: usize, level: u32);
+    fn can_swipe(&self, side: Side) -> bool;
+    fn swipe(&mut self, side: Side) -> SwipeResult;
+    // Cells ordered by x, then by y
+    fn get_free_cells(&self) -> Vec<(usize, usize)>;
+    fn is_game_over(&self) -> bool {
+        ![Side::Up, Side::Down, Side::Left, Side::Right]
+            .iter()
+            .any(|side| self.can_swipe(*side))
+    }
+}
+
+impl Board for Field {
+    fn width(&self) -> usize {
+        Field::width(self)
+    }
+    fn height(&self) -> usize {
+        Field::height(self)
+    }
+    fn rule(&self) -> MergeRuleKind {
+        Field::rule(self)
+    }
+    fn spawn_policy(&self) -> SpawnPolicy {
+        Field::spawn_policy(self)
+    }
+    fn level(&self, x: usize, y: usize) -> Option<u32> {
+        self.get(x, y)
+            .filter(|tile| !tile.is_blocker())
+            .map(|tile| tile.get_level())
+    }
+    fn put_level(&mut self, x: usize, y: usize, level: u32) {
+        self.put(x, y, Some(Tile::appear(level)))
+    }
+    fn can_swipe(&self, side: Side) -> bool {
+        Field::can_swipe(self, side)
+    }
+    fn swipe(&mut self, side: Side) -> SwipeResult {
+        Field::swipe(self, side)
+    }
+    fn get_free_cells(&self) -> Vec<(usize, usize)> {
+        Field::get_free_cells(self)
+    }
+}
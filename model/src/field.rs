@@ -1,9 +1,18 @@
 use ndarray::Array2;
 use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{
+    merge_rule::{MergeRule, MergeRuleKind},
+    spawn::SpawnPolicy,
+};
 use Origin::{Appear, Hold, Merged, Moved};
 use Side::{Down, Left, Right, Up};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Side {
     Down,
     Left,
@@ -12,6 +21,7 @@ pub enum Side {
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Origin {
     Appear,
     Hold(usize, usize),
@@ -20,11 +30,25 @@ pub enum Origin {
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tile(u32, Origin);
 
+// Blocker is a tile which never moves and never merges
+pub const BLOCKER_LEVEL: u32 = u32::MAX;
+
 impl Tile {
-    pub fn get_n(&self) -> u32 {
-        1 << self.0
+    pub(crate) fn appear(level: u32) -> Self {
+        Tile(level, Appear)
+    }
+    pub fn blocker(x: usize, y: usize) -> Self {
+        Tile(BLOCKER_LEVEL, Hold(x, y))
+    }
+    pub fn is_blocker(&self) -> bool {
+        self.0 == BLOCKER_LEVEL
+    }
+    // Number on the tile depends on the merge rule, see Field::value
+    pub fn get_level(&self) -> u32 {
+        self.0
     }
     pub fn get_origin(&self) -> Origin {
         self.1
@@ -34,12 +58,6 @@ impl Tile {
     }
 }
 
-impl From<Tile> for u32 {
-    fn from(tile: Tile) -> u32 {
-        tile.get_n()
-    }
-}
-
 impl From<Tile> for Origin {
     fn from(tile: Tile) -> Origin {
         tile.get_origin()
@@ -51,15 +69,140 @@ impl Origin {
         Hold(arr_index.1, arr_index.0)
     }
 }
-fn can_join_tiles(dst: Option<Tile>, src: Option<Tile>) -> bool {
+
+// Outcome of a single swipe
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SwipeResult {
+    // Sum of values of merged tiles
+    pub score: u32,
+    pub merges: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    BadHeader(String),
+    BadTile(String),
+    BadShape,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::BadHeader(s) => write!(f, "bad header '{}'", s),
+            ParseError::BadTile(s) => write!(f, "bad tile '{}'", s),
+            ParseError::BadShape => write!(f, "rows don't match field size"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ResizeError {
+    EmptySize,
+    TooManyTiles { tiles: usize, cells: usize },
+}
+
+impl fmt::Display for ResizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResizeError::EmptySize => write!(f, "field size can't be zero"),
+            ResizeError::TooManyTiles { tiles, cells } => {
+                write!(f, "{} tiles don't fit into {} cells", tiles, cells)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResizeError {}
+
+// Tile which didn't fit into the resized field and was moved to the nearest free cell.
+// Positions outside of the field can't be kept in origins, so they are returned separately
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResizeMove {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+fn parse_usize(s: &str) -> Option<usize> {
+    s.parse().ok()
+}
+
+fn parse_pos(s: &str) -> Option<(usize, usize)> {
+    let mut it = s.split(',');
+    let x = parse_usize(it.next()?)?;
+    let y = parse_usize(it.next()?)?;
+    if it.next().is_some() {
+        return None;
+    }
+    Some((x, y))
+}
+
+impl Tile {
+    // Tile is stored as level followed by origin: "3h0,1", "2m1,2", "4g0,0;0,1", "1a".
+    // Blocker is stored as "#", its position is known from the field
+    fn serialize(&self) -> String {
+        if self.is_blocker() {
+            return "#".to_string();
+        }
+        match self.1 {
+            Appear => format!("{}a", self.0),
+            Hold(x, y) => format!("{}h{},{}", self.0, x, y),
+            Moved(x, y) => format!("{}m{},{}", self.0, x, y),
+            Merged((xa, ya), (xb, yb)) => format!("{}g{},{};{},{}", self.0, xa, ya, xb, yb),
+        }
+    }
+    fn deserialize(s: &str) -> Result<Self, ParseError> {
+        let bad_tile = || ParseError::BadTile(s.to_string());
+        let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(bad_tile)?;
+        let level = s[..split].parse().map_err(|_| bad_tile())?;
+        let kind = s[split..].chars().next().ok_or_else(bad_tile)?;
+        let rest = &s[split + kind.len_utf8()..];
+        let origin = match kind {
+            'a' if rest.is_empty() => Appear,
+            'h' => {
+                let (x, y) = parse_pos(rest).ok_or_else(bad_tile)?;
+                Hold(x, y)
+            }
+            'm' => {
+                let (x, y) = parse_pos(rest).ok_or_else(bad_tile)?;
+                Moved(x, y)
+            }
+            'g' => {
+                let mut it = rest.split(';');
+                let a = it.next().and_then(parse_pos).ok_or_else(bad_tile)?;
+                let b = it.next().and_then(parse_pos).ok_or_else(bad_tile)?;
+                Merged(a, b)
+            }
+            _ => return Err(bad_tile()),
+        };
+        Ok(Tile(level, origin))
+    }
+}
+// Tiles of the max level stay as they are, their sum wouldn't fit u32
+fn merge_levels(rule: &dyn MergeRule, dst: u32, src: u32) -> Option<u32> {
+    rule.merge(dst, src)
+        .filter(|level| *level <= rule.max_level())
+}
+fn is_blocker(tile: Option<Tile>) -> bool {
+    matches!(tile, Some(tile) if tile.is_blocker())
+}
+fn can_join_tiles(rule: &dyn MergeRule, dst: Option<Tile>, src: Option<Tile>) -> bool {
+    if is_blocker(dst) || is_blocker(src) {
+        return false;
+    }
     match (dst, src) {
         (None, Some(_)) => true,
-        (Some(Tile(ld, _)), Some(Tile(ls, _))) => ld == ls,
+        (Some(Tile(ld, _)), Some(Tile(ls, _))) => merge_levels(rule, ld, ls).is_some(),
         (Some(_), None) => false,
         (None, None) => false,
     }
 }
-fn join_tiles(dst: &mut Option<Tile>, src: &mut Option<Tile>) -> Option<u32> {
+fn join_tiles(rule: &dyn MergeRule, dst: &mut Option<Tile>, src: &mut Option<Tile>) -> Option<u32> {
+    if is_blocker(*dst) || is_blocker(*src) {
+        return None;
+    }
     match (*dst, *src) {
         (None, Some(Tile(level, Hold(x, y)))) | (None, Some(Tile(level, Moved(x, y)))) => {
             *dst = Some(Tile(level, Moved(x, y)));
@@ -75,27 +218,87 @@ fn join_tiles(dst: &mut Option<Tile>, src: &mut Option<Tile>) -> Option<u32> {
         | (Some(Tile(ld, Hold(xd, yd))), Some(Tile(ls, Moved(xs, ys))))
         | (Some(Tile(ld, Moved(xd, yd))), Some(Tile(ls, Hold(xs, ys))))
         | (Some(Tile(ld, Moved(xd, yd))), Some(Tile(ls, Moved(xs, ys)))) => {
-            if ld == ls {
-                let dst_tile = Tile(ld + 1, Merged((xd, yd), (xs, ys)));
-                let score = dst_tile.get_n();
-                *dst = Some(dst_tile);
-                *src = None;
-                Some(score)
-            } else {
-                None
-            }
+            let level = merge_levels(rule, ld, ls)?;
+            *dst = Some(Tile(level, Merged((xd, yd), (xs, ys))));
+            *src = None;
+            Some(rule.value(level))
         }
         _ => None,
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct Field(Array2<Option<Tile>>);
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "FieldData", try_from = "FieldData")
+)]
+pub struct Field(Array2<Option<Tile>>, MergeRuleKind, SpawnPolicy);
+
+// Serde form of the field with tiles listed row by row
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct FieldData {
+    width: usize,
+    height: usize,
+    rule: MergeRuleKind,
+    spawn_policy: SpawnPolicy,
+    tiles: Vec<Option<Tile>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Field> for FieldData {
+    fn from(field: Field) -> Self {
+        Self {
+            width: field.width(),
+            height: field.height(),
+            rule: field.1,
+            spawn_policy: field.2,
+            tiles: field.0.iter().copied().collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<FieldData> for Field {
+    type Error = ParseError;
+    fn try_from(data: FieldData) -> Result<Self, Self::Error> {
+        if data.width == 0 || data.height == 0 {
+            return Err(ParseError::BadShape);
+        }
+        let tiles = Array2::from_shape_vec((data.height, data.width), data.tiles)
+            .map_err(|_| ParseError::BadShape)?;
+        let field = Field(tiles, data.rule, data.spawn_policy);
+        field.check_tiles()?;
+        Ok(field)
+    }
+}
 
 impl Field {
     pub fn new(width: usize, height: usize) -> Self {
-        Self(Array2::default((height, width)))
+        Self::with_rule(width, height, MergeRuleKind::Classic)
+    }
+    pub fn with_rule(width: usize, height: usize, rule: MergeRuleKind) -> Self {
+        Self(Array2::default((height, width)), rule, SpawnPolicy::Classic)
+    }
+    pub fn rule(&self) -> MergeRuleKind {
+        self.1
+    }
+    pub fn spawn_policy(&self) -> SpawnPolicy {
+        self.2
+    }
+    pub fn set_spawn_policy(&mut self, spawn_policy: SpawnPolicy) {
+        self.2 = spawn_policy
+    }
+    // Number shown on the tile, blockers have no number
+    pub fn value(&self, tile: Tile) -> u32 {
+        if tile.is_blocker() {
+            0
+        } else {
+            self.1.rule().value(tile.0)
+        }
     }
+    // Values are interpreted with the classic rule
     pub fn from_array(array: Array2<u32>) -> Self {
         let (h, w) = (array.shape()[0], array.shape()[1]);
         let mut field = Self::new(w, h);
@@ -113,11 +316,10 @@ impl Field {
     pub fn into_array(&self) -> Array2<u32> {
         let (h, w) = (self.0.shape()[0], self.0.shape()[1]);
         Array2::from_shape_fn((h, w), |index| {
-            if let Some(Tile(level, _)) = self.0.get(index).unwrap() {
-                1 << *level
-            } else {
-                0
-            }
+            self.0
+                .get(index)
+                .unwrap()
+                .map_or(0, |tile| self.value(tile))
         })
     }
     fn width_from_side(&self, side: Side) -> usize {
@@ -132,6 +334,95 @@ impl Field {
             Left | Right => self.0.shape()[1],
         }
     }
+    // Text representation: "<width> <height> [<rule>] [<spawn policy>]" line followed by
+    // one line per row, "." for empty cells. Origins are kept so undo still works after
+    // restore. Rule and spawn policy names are omitted when they are classic
+    pub fn serialize(&self) -> String {
+        let mut result = format!("{} {}", self.width(), self.height());
+        if self.1 != MergeRuleKind::Classic {
+            result += &format!(" {}", self.1.name());
+        }
+        if self.2 != SpawnPolicy::Classic {
+            result += &format!(" {}", self.2.name());
+        }
+        result += "\n";
+        for y in 0..self.height() {
+            let row = (0..self.width())
+                .map(|x| match self.get(x, y) {
+                    Some(tile) => tile.serialize(),
+                    None => ".".to_string(),
+                })
+                .collect::<Vec<_>>();
+            result += &row.join(" ");
+            result += "\n";
+        }
+        result
+    }
+    pub fn deserialize(s: &str) -> Result<Self, ParseError> {
+        let mut lines = s.lines();
+        let header = lines.next().unwrap_or("");
+        let mut tokens = header.split_whitespace();
+        let width = tokens.next().and_then(parse_usize);
+        let height = tokens.next().and_then(parse_usize);
+        let mut tokens = tokens.peekable();
+        // "classic" is the name of both, it's the rule as it comes first
+        let rule = match tokens
+            .peek()
+            .and_then(|name| MergeRuleKind::from_name(name))
+        {
+            Some(rule) => {
+                tokens.next();
+                rule
+            }
+            None => MergeRuleKind::Classic,
+        };
+        let spawn_policy = match tokens.next() {
+            Some(name) => SpawnPolicy::from_name(name),
+            None => Some(SpawnPolicy::Classic),
+        };
+        let (width, height, spawn_policy) = match (width, height, spawn_policy, tokens.next()) {
+            (Some(w), Some(h), Some(spawn_policy), None) if w > 0 && h > 0 => (w, h, spawn_policy),
+            _ => return Err(ParseError::BadHeader(header.to_string())),
+        };
+        let mut field = Self::with_rule(width, height, rule);
+        field.2 = spawn_policy;
+        for y in 0..height {
+            let row = lines.next().ok_or(ParseError::BadShape)?;
+            let tokens = row.split_whitespace().collect::<Vec<_>>();
+            if tokens.len() != width {
+                return Err(ParseError::BadShape);
+            }
+            for (x, token) in tokens.into_iter().enumerate() {
+                match token {
+                    "." => {}
+                    "#" => field.put(x, y, Some(Tile::blocker(x, y))),
+                    _ => field.put(x, y, Some(Tile::deserialize(token)?)),
+                }
+            }
+        }
+        field.check_tiles()?;
+        Ok(field)
+    }
+    // Origins must point inside the field, otherwise undo can't put tiles back,
+    // and levels must have values fitting u32
+    fn check_tiles(&self) -> Result<(), ParseError> {
+        let inside = |(x, y): (usize, usize)| x < self.width() && y < self.height();
+        let max_level = self.1.rule().max_level();
+        for tile in self.0.iter().flatten() {
+            if !tile.is_blocker() && tile.0 > max_level {
+                return Err(ParseError::BadTile(tile.serialize()));
+            }
+            let valid = match tile.1 {
+                Appear => true,
+                Hold(x, y) | Moved(x, y) => inside((x, y)),
+                Merged(a, b) => inside(a) && inside(b),
+            };
+            if !valid {
+                return Err(ParseError::BadTile(tile.serialize()));
+            }
+        }
+        Ok(())
+    }
     pub fn width(&self) -> usize {
         self.width_from_side(Up)
     }
@@ -173,16 +464,73 @@ impl Field {
         }
     }
 
-    fn swipe_step(&mut self, side: Side, x: usize, score_acc: &mut u32) -> bool {
+    // Field of other size with the same tiles, all of them get Hold origin. Tiles which
+    // don't fit are moved to the nearest free cells, the moves are returned so the UI can
+    // show them sliding in. The new field can't be undone
+    pub fn resize(
+        &self,
+        width: usize,
+        height: usize,
+    ) -> Result<(Field, Vec<ResizeMove>), ResizeError> {
+        if width == 0 || height == 0 {
+            return Err(ResizeError::EmptySize);
+        }
+        let tiles = self.0.iter().flatten().count();
+        let cells = width * height;
+        if tiles > cells {
+            return Err(ResizeError::TooManyTiles { tiles, cells });
+        }
+        let mut field = Self(Array2::default((height, width)), self.1, self.2);
+        let mut outside = Vec::new();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if let Some(mut tile) = self.get(x, y) {
+                    if x < width && y < height {
+                        tile.set_origin(Hold(x, y));
+                        field.put(x, y, Some(tile));
+                    } else {
+                        outside.push((x, y, tile));
+                    }
+                }
+            }
+        }
+        let mut moves = Vec::new();
+        for (from_x, from_y, mut tile) in outside {
+            let (cx, cy) = (from_x.min(width - 1), from_y.min(height - 1));
+            let distance = |&(x, y): &(usize, usize)| {
+                (x as isize - cx as isize).abs() + (y as isize - cy as isize).abs()
+            };
+            // tiles count is checked above, so free cell always exists
+            let (x, y) = field
+                .get_free_cells()
+                .into_iter()
+                .min_by_key(distance)
+                .unwrap();
+            tile.set_origin(Hold(x, y));
+            field.put(x, y, Some(tile));
+            moves.push(ResizeMove {
+                from: (from_x, from_y),
+                to: (x, y),
+            });
+        }
+        Ok((field, moves))
+    }
+
+    fn swipe_step(&mut self, side: Side, x: usize, swipe_result: &mut SwipeResult) -> bool {
         let mut result = false;
         let height = self.height_from_side(side);
         for y in 0..height - 1 {
             let mut up = self.get_from_side(side, x, y);
             let mut down = self.get_from_side(side, x, y + 1);
-            if let Some(score) = join_tiles(&mut up, &mut down) {
+            // Tile moving to the occupied cell is merged with it
+            let merge = up.is_some();
+            if let Some(score) = join_tiles(self.1.rule(), &mut up, &mut down) {
                 self.put_from_side(side, x, y, up);
                 self.put_from_side(side, x, y + 1, down);
-                *score_acc += score;
+                swipe_result.score += score;
+                if merge {
+                    swipe_result.merges += 1;
+                }
                 result = true;
             }
         }
@@ -196,7 +544,7 @@ impl Field {
             for y in 0..height - 1 {
                 let up = self.get_from_side(side, x, y);
                 let down = self.get_from_side(side, x, y + 1);
-                if can_join_tiles(up, down) {
+                if can_join_tiles(self.1.rule(), up, down) {
                     return true;
                 }
             }
@@ -204,14 +552,29 @@ impl Field {
         return false;
     }
 
-    pub fn swipe(&mut self, side: Side) -> u32 {
-        let mut score = 0;
+    pub fn is_game_over(&self) -> bool {
+        ![Up, Down, Left, Right]
+            .iter()
+            .any(|side| self.can_swipe(*side))
+    }
+
+    pub fn swipe(&mut self, side: Side) -> SwipeResult {
+        let mut result = SwipeResult::default();
         let width = self.width_from_side(side);
         self.hold_all();
         for x in 0..width {
-            while self.swipe_step(side, x, &mut score) {}
+            while self.swipe_step(side, x, &mut result) {}
         }
-        score
+        result
+    }
+
+    pub fn max_tile(&self) -> u32 {
+        self.0
+            .iter()
+            .flatten()
+            .map(|tile| self.value(*tile))
+            .max()
+            .unwrap_or(0)
     }
 
     pub fn get_free_cells(&self) -> Vec<(usize, usize)> {
@@ -233,11 +596,42 @@ impl Field {
             return false;
         }
         let (x, y) = poses[rng.gen_range(0..poses.len())];
-        let v = rng.gen_range(1..3);
-        self.put(x, y, Some(Tile(v, Appear)));
+        let rule = self.1.rule();
+        let total = self.2.weights(rule).iter().sum::<u32>();
+        let level = self.2.pick_level(rule, rng.gen_range(0..total));
+        self.put(x, y, Some(Tile(level, Appear)));
         return true;
     }
 
+    // Tiles appearing after a swipe, their number depends on the spawn policy.
+    // Returns false if there was no place for any of them
+    pub fn spawn_tiles(&mut self) -> bool {
+        let mut result = false;
+        for _ in 0..self.2.tiles_per_swipe() {
+            result |= self.append_tile();
+        }
+        result
+    }
+
+    pub fn append_blocker(&mut self) -> bool {
+        let mut rng = rand::thread_rng();
+        let poses = self.get_free_cells();
+        if poses.is_empty() {
+            return false;
+        }
+        let (x, y) = poses[rng.gen_range(0..poses.len())];
+        self.put(x, y, Some(Tile::blocker(x, y)));
+        true
+    }
+
+    pub fn blockers_count(&self) -> usize {
+        self.0
+            .iter()
+            .flatten()
+            .filter(|tile| tile.is_blocker())
+            .count()
+    }
+
     pub fn can_undo(&self) -> bool {
         for x in 0..self.width() {
             for y in 0..self.height() {
@@ -268,14 +662,14 @@ impl Field {
                         let index = self.index_from_side(Up, from_x, from_y);
                         *arr.get_mut(index).unwrap() = Some(Tile(level, Moved(x, y)));
                     }
-                    tile @ Some(Tile(_, Merged(_, _))) => {
-                        score += tile.unwrap().get_n();
-                        if let Some(Tile(level, Merged(a, b))) = tile {
-                            let index_a = self.index_from_side(Up, a.0, a.1);
-                            let index_b = self.index_from_side(Up, b.0, b.1);
-                            *arr.get_mut(index_a).unwrap() = Some(Tile(level - 1, Moved(x, y)));
-                            *arr.get_mut(index_b).unwrap() = Some(Tile(level - 1, Moved(x, y)));
-                        }
+                    Some(Tile(level, Merged(a, b))) => {
+                        let rule = self.1.rule();
+                        score += rule.value(level);
+                        let (level_a, level_b) = rule.split(level);
+                        let index_a = self.index_from_side(Up, a.0, a.1);
+                        let index_b = self.index_from_side(Up, b.0, b.1);
+                        *arr.get_mut(index_a).unwrap() = Some(Tile(level_a, Moved(x, y)));
+                        *arr.get_mut(index_b).unwrap() = Some(Tile(level_b, Moved(x, y)));
                     }
                     _ => {}
                 }
@@ -306,12 +700,16 @@ mod tests {
 fn field_widht_height_at() {
     use ndarray::arr2;
     use tests::hold;
-    let field = Field(arr2(&[
-        [hold(0, 0, 0), hold(10, 1, 0), hold(20, 2, 0)],
-        [hold(1, 0, 1), hold(11, 1, 1), hold(21, 2, 1)],
-        [hold(2, 0, 2), hold(12, 1, 2), hold(22, 2, 2)],
-        [hold(3, 0, 3), hold(13, 1, 3), hold(23, 2, 3)],
-    ]));
+    let field = Field(
+        arr2(&[
+            [hold(0, 0, 0), hold(10, 1, 0), hold(20, 2, 0)],
+            [hold(1, 0, 1), hold(11, 1, 1), hold(21, 2, 1)],
+            [hold(2, 0, 2), hold(12, 1, 2), hold(22, 2, 2)],
+            [hold(3, 0, 3), hold(13, 1, 3), hold(23, 2, 3)],
+        ]),
+        MergeRuleKind::Classic,
+        SpawnPolicy::Classic,
+    );
     assert_eq!(field.width(), 3);
     assert_eq!(field.height(), 4);
     assert_eq!(field.get(2, 1).unwrap().0, 21);
@@ -360,7 +758,7 @@ fn field_into_array() {
         [hold(1, 0, 0), hold(0, 0, 0), None],
         [hold(0, 0, 0), None, hold(4, 0, 0)],
     ]);
-    let array = Field(source).into_array();
+    let array = Field(source, MergeRuleKind::Classic, SpawnPolicy::Classic).into_array();
     #[rustfmt::skip]
     let expected = Array2::from_shape_vec((4, 3), vec![
         8, 4, 2,
@@ -408,8 +806,14 @@ fn swipe_up() {
         [None, None, None, None],
         [None, None, None, None],
     ]);
-    let score = field.swipe(Up);
-    assert_eq!(score, 20);
+    let result = field.swipe(Up);
+    assert_eq!(
+        result,
+        SwipeResult {
+            score: 20,
+            merges: 4
+        }
+    );
     assert_eq!(field.into_array(), expected);
     assert_eq!(field.0, expected_field);
 }
@@ -431,7 +835,7 @@ fn swipe_down() {
         0, 0, 4, 8,
         0, 4, 4, 4,
     ]).unwrap();
-    let score = field.swipe(Down);
+    let score = field.swipe(Down).score;
     assert_eq!(score, 20);
     assert_eq!(field.into_array(), expected);
 }
@@ -463,7 +867,7 @@ fn swipe_left() {
         [hold(2, 0, 2), merged(2, (2, 2), (3, 2)), None, None],
         [merged(2, (0, 3), (3, 3)), None, None, None],
     ]);
-    let score = field.swipe(Left);
+    let score = field.swipe(Left).score;
     #[rustfmt::skip]
     assert_eq!(score, 20);
     assert_eq!(field.into_array(), expected);
@@ -487,7 +891,245 @@ fn swipe_right() {
         0, 0, 0, 4,
         0, 0, 0, 2
     ]).unwrap();
-    let score = field.swipe(Right);
+    let score = field.swipe(Right).score;
     assert_eq!(score, 16);
     assert_eq!(field.into_array(), expected);
 }
+
+#[test]
+fn field_is_game_over() {
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((3, 3), vec![
+        2, 4, 2,
+        4, 2, 4,
+        2, 4, 2,
+    ]).unwrap();
+    assert!(Field::from_array(array).is_game_over());
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((3, 3), vec![
+        2, 4, 2,
+        4, 2, 4,
+        2, 4, 4,
+    ]).unwrap();
+    assert!(!Field::from_array(array).is_game_over());
+}
+
+#[test]
+fn field_serialize_deserialize() {
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((3, 4), vec![
+        0, 2, 4, 4,
+        0, 2, 2, 4,
+        8, 0, 2, 2,
+    ]).unwrap();
+    let mut field = Field::from_array(array);
+    field.swipe(Left);
+    field.append_tile();
+    let s = field.serialize();
+    let restored = Field::deserialize(&s).unwrap();
+    assert_eq!(restored, field);
+    assert!(restored.can_undo());
+}
+
+#[test]
+fn field_deserialize_errors() {
+    assert_eq!(
+        Field::deserialize("2"),
+        Err(ParseError::BadHeader("2".to_string()))
+    );
+    assert_eq!(Field::deserialize("2 2\n. .\n"), Err(ParseError::BadShape));
+    assert_eq!(
+        Field::deserialize("2 2\n. 1x\n. .\n"),
+        Err(ParseError::BadTile("1x".to_string()))
+    );
+    assert_eq!(
+        Field::deserialize("1 1\n1g0,0\n"),
+        Err(ParseError::BadTile("1g0,0".to_string()))
+    );
+    assert_eq!(
+        Field::deserialize("1 1\n2é\n"),
+        Err(ParseError::BadTile("2é".to_string()))
+    );
+    assert_eq!(
+        Field::deserialize("2 2\n1m9,9 .\n. .\n"),
+        Err(ParseError::BadTile("1m9,9".to_string()))
+    );
+    assert_eq!(
+        Field::deserialize("2 2\n2g0,0;0,2 .\n. .\n"),
+        Err(ParseError::BadTile("2g0,0;0,2".to_string()))
+    );
+    assert_eq!(
+        Field::deserialize("1 1\n32h0,0\n"),
+        Err(ParseError::BadTile("32h0,0".to_string()))
+    );
+    assert!(Field::deserialize("1 1 threes\n33h0,0\n").is_ok());
+}
+
+#[test]
+fn field_swipe_with_blocker() {
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((1, 4), vec![
+        2, 0, 0, 2,
+    ]).unwrap();
+    let mut field = Field::from_array(array);
+    field.put(1, 0, Some(Tile::blocker(1, 0)));
+    let score = field.swipe(Right).score;
+    assert_eq!(score, 0);
+    assert_eq!(field.value(field.get(0, 0).unwrap()), 2);
+    assert!(field.get(1, 0).unwrap().is_blocker());
+    assert_eq!(field.value(field.get(3, 0).unwrap()), 2);
+    field.swipe(Left);
+    assert!(field.get(1, 0).unwrap().is_blocker());
+    assert_eq!(field.value(field.get(2, 0).unwrap()), 2);
+    assert!(!field.can_swipe(Left));
+}
+
+#[test]
+fn field_blocker_game_over() {
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((2, 2), vec![
+        2, 4,
+        4, 0,
+    ]).unwrap();
+    let mut field = Field::from_array(array);
+    assert!(!field.is_game_over());
+    field.put(1, 1, Some(Tile::blocker(1, 1)));
+    assert!(field.is_game_over());
+    assert_eq!(field.blockers_count(), 1);
+    assert_eq!(field.max_tile(), 4);
+    assert!(field.get_free_cells().is_empty());
+}
+
+#[test]
+fn field_serialize_blocker() {
+    let mut field = Field::new(3, 2);
+    field.append_tile();
+    field.append_blocker();
+    let s = field.serialize();
+    assert!(s.contains('#'));
+    assert_eq!(Field::deserialize(&s).unwrap(), field);
+}
+
+#[test]
+fn field_swipe_fibonacci() {
+    let mut field = Field::with_rule(4, 1, MergeRuleKind::Fibonacci);
+    // values 1, 2, 3, 3
+    field.put(0, 0, Some(Tile(1, Hold(0, 0))));
+    field.put(1, 0, Some(Tile(2, Hold(1, 0))));
+    field.put(2, 0, Some(Tile(3, Hold(2, 0))));
+    field.put(3, 0, Some(Tile(3, Hold(3, 0))));
+    let result = field.swipe(Left);
+    assert_eq!(
+        result,
+        SwipeResult {
+            score: 3,
+            merges: 1
+        }
+    );
+    let values = (0..4)
+        .map(|x| field.get(x, 0).map_or(0, |tile| field.value(tile)))
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec![3, 3, 3, 0]);
+    let mut reverted = field.clone();
+    assert_eq!(reverted.undo(), 3);
+    // Order of merged tiles can't be restored, only their values
+    let array = reverted.into_array();
+    assert_eq!(array[[0, 0]] + array[[0, 1]], 3);
+    let restored = Field::deserialize(&field.serialize()).unwrap();
+    assert_eq!(restored, field);
+}
+
+#[test]
+fn field_resize() {
+    use ndarray::arr2;
+    use tests::hold;
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((3, 3), vec![
+        2, 0, 4,
+        0, 0, 0,
+        0, 8, 16,
+    ]).unwrap();
+    let field = Field::from_array(array);
+    let (grown, moves) = field.resize(4, 3).unwrap();
+    assert_eq!(grown.width(), 4);
+    assert_eq!(grown.height(), 3);
+    assert_eq!(grown.get(2, 2), hold(4, 2, 2));
+    assert_eq!(grown.get(3, 2), None);
+    assert!(moves.is_empty());
+    let (shrunk, moves) = field.resize(2, 2).unwrap();
+    let expected = arr2(&[
+        [hold(1, 0, 0), hold(2, 1, 0)],
+        [hold(4, 0, 1), hold(3, 1, 1)],
+    ]);
+    assert_eq!(shrunk.0, expected);
+    assert_eq!(shrunk.into_array().iter().sum::<u32>(), 30);
+    let move_to = |from, to| ResizeMove { from, to };
+    assert_eq!(
+        moves,
+        vec![
+            move_to((2, 0), (1, 0)),
+            move_to((1, 2), (1, 1)),
+            move_to((2, 2), (0, 1)),
+        ]
+    );
+    assert!(!shrunk.can_undo());
+    assert_eq!(Field::deserialize(&shrunk.serialize()).unwrap(), shrunk);
+}
+
+#[test]
+fn field_resize_errors() {
+    let mut field = Field::new(3, 3);
+    for _ in 0..5 {
+        field.append_tile();
+    }
+    assert_eq!(field.resize(0, 3), Err(ResizeError::EmptySize));
+    assert_eq!(
+        field.resize(2, 2),
+        Err(ResizeError::TooManyTiles { tiles: 5, cells: 4 })
+    );
+    field.append_blocker();
+    assert_eq!(field.resize(3, 2).unwrap().0.blockers_count(), 1);
+}
+
+#[test]
+fn field_spawn_policy() {
+    let mut field = Field::with_rule(3, 3, MergeRuleKind::Threes);
+    assert!(field.spawn_tiles());
+    assert_eq!(field.get_free_cells().len(), 8);
+    field.set_spawn_policy(SpawnPolicy::Hard);
+    assert!(field.spawn_tiles());
+    assert_eq!(field.get_free_cells().len(), 6);
+    let s = field.serialize();
+    assert!(s.starts_with("3 3 threes hard\n"));
+    assert_eq!(Field::deserialize(&s).unwrap(), field);
+    field.set_spawn_policy(SpawnPolicy::Easy);
+    let restored = Field::deserialize(&field.serialize()).unwrap();
+    assert_eq!(restored.spawn_policy(), SpawnPolicy::Easy);
+    assert_eq!(restored.rule(), MergeRuleKind::Threes);
+    assert_eq!(
+        Field::deserialize("1 1 easy threes\n.\n"),
+        Err(ParseError::BadHeader("1 1 easy threes".to_string()))
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn field_serde() {
+    let mut field = Field::with_rule(3, 2, MergeRuleKind::Fibonacci);
+    field.append_tile();
+    field.append_blocker();
+    let json = serde_json::to_string(&field).unwrap();
+    assert_eq!(serde_json::from_str::<Field>(&json).unwrap(), field);
+    let bad_shape = json.replace("\"width\":3", "\"width\":4");
+    assert!(serde_json::from_str::<Field>(&bad_shape).is_err());
+
+    let mut field = Field::new(2, 1);
+    field.put(0, 0, Some(Tile(1, Moved(0, 0))));
+    let json = serde_json::to_string(&field).unwrap();
+    let bad_origin = json.replace("{\"Moved\":[0,0]}", "{\"Moved\":[5,0]}");
+    assert_ne!(bad_origin, json);
+    assert!(serde_json::from_str::<Field>(&bad_origin).is_err());
+    let bad_level = json.replace("[1,{", "[32,{");
+    assert_ne!(bad_level, json);
+    assert!(serde_json::from_str::<Field>(&bad_level).is_err());
+}
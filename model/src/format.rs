@@ -0,0 +1,121 @@
+// Display form of scores and tile values
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumberFormat {
+    // Inserted between groups of three digits, empty string disables grouping
+    pub group_separator: String,
+    // Smaller numbers are not grouped, so 2048 stays "2048" as in many locales
+    pub group_from: u64,
+    // Used by compact notation, e.g. "1.5k"
+    pub decimal_separator: String,
+    // Values starting from this one are shown as "16k" or "1.5M", None keeps full numbers
+    pub compact_from: Option<u64>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            group_separator: ",".to_string(),
+            group_from: 10_000,
+            decimal_separator: ".".to_string(),
+            compact_from: None,
+        }
+    }
+}
+
+const COMPACT_UNITS: [(u64, &str); 4] = [
+    (1_000_000_000_000, "T"),
+    (1_000_000_000, "G"),
+    (1_000_000, "M"),
+    (1_000, "k"),
+];
+
+impl NumberFormat {
+    pub fn format(&self, value: u64) -> String {
+        if matches!(self.compact_from, Some(from) if value >= from) {
+            if let Some(&(unit, suffix)) = COMPACT_UNITS.iter().find(|(unit, _)| value >= *unit) {
+                return self.format_compact(value, unit, suffix);
+            }
+        }
+        self.group(value)
+    }
+
+    // Digits are cut, not rounded: 65536 is "65k", it hasn't reached 66k yet.
+    // One fractional digit is kept below 10 units, where it's still significant
+    fn format_compact(&self, value: u64, unit: u64, suffix: &str) -> String {
+        let whole = value / unit;
+        let tenths = (value % unit) * 10 / unit;
+        if whole < 10 && tenths != 0 {
+            format!("{}{}{}{}", whole, self.decimal_separator, tenths, suffix)
+        } else {
+            format!("{}{}", self.group(whole), suffix)
+        }
+    }
+
+    fn group(&self, value: u64) -> String {
+        let digits = value.to_string();
+        if self.group_separator.is_empty() || value < self.group_from {
+            return digits;
+        }
+        // First group is shorter when the number of digits isn't divisible by 3
+        let mut pos = match digits.len() % 3 {
+            0 => 3,
+            n => n,
+        };
+        let mut result = digits[..pos].to_string();
+        while pos < digits.len() {
+            result.push_str(&self.group_separator);
+            result.push_str(&digits[pos..pos + 3]);
+            pos += 3;
+        }
+        result
+    }
+}
+
+#[test]
+fn format_groups_digits() {
+    let format = NumberFormat::default();
+    assert_eq!(format.format(0), "0");
+    assert_eq!(format.format(2048), "2048");
+    assert_eq!(format.format(10_000), "10,000");
+    assert_eq!(format.format(123_456_789), "123,456,789");
+    let format = NumberFormat {
+        group_from: 0,
+        ..NumberFormat::default()
+    };
+    assert_eq!(format.format(999), "999");
+    assert_eq!(format.format(1000), "1,000");
+    let format = NumberFormat {
+        group_separator: "\u{a0}".to_string(),
+        ..NumberFormat::default()
+    };
+    assert_eq!(format.format(16384), "16\u{a0}384");
+    let format = NumberFormat {
+        group_separator: String::new(),
+        ..NumberFormat::default()
+    };
+    assert_eq!(format.format(16384), "16384");
+}
+
+#[test]
+fn format_compact() {
+    let format = NumberFormat {
+        decimal_separator: ",".to_string(),
+        compact_from: Some(10_000),
+        ..NumberFormat::default()
+    };
+    assert_eq!(format.format(8192), "8192");
+    assert_eq!(format.format(16384), "16k");
+    assert_eq!(format.format(65536), "65k");
+    assert_eq!(format.format(131_072), "131k");
+    assert_eq!(format.format(1_048_576), "1M");
+    assert_eq!(format.format(1_500_000), "1,5M");
+    assert_eq!(format.format(20_000_000_000_000_000), "20,000T");
+    // Threshold below the first unit doesn't shorten small numbers
+    let format = NumberFormat {
+        compact_from: Some(0),
+        ..NumberFormat::default()
+    };
+    assert_eq!(format.format(999), "999");
+    assert_eq!(format.format(1024), "1k");
+    assert_eq!(format.format(2560), "2.5k");
+}
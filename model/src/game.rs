@@ -0,0 +1,548 @@
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{
+    field::{Field, ParseError, ResizeError, ResizeMove, Side, SwipeResult},
+    merge_rule::MergeRuleKind,
+    spawn::SpawnPolicy,
+};
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+pub const DEFAULT_TARGET: u32 = 2048;
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Snapshot {
+    field: Field,
+    score: u32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "GameData")
+)]
+pub struct Game {
+    field: Field,
+    score: u32,
+    undo_history: VecDeque<Snapshot>,
+    redo_history: Vec<Snapshot>,
+    history_depth: usize,
+    target: u32,
+    keep_playing: bool,
+    // Undone moves are not counted
+    moves: u32,
+    // Time spent on moves, counted by the UI, see add_play_time
+    play_time: Duration,
+    // Most merges made by a single swipe
+    longest_chain: u32,
+}
+
+// Serde form of the game, histories are trimmed to the depth when it's converted back
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct GameData {
+    field: Field,
+    score: u32,
+    undo_history: VecDeque<Snapshot>,
+    redo_history: Vec<Snapshot>,
+    history_depth: usize,
+    target: u32,
+    keep_playing: bool,
+    moves: u32,
+    play_time: Duration,
+    longest_chain: u32,
+}
+
+#[cfg(feature = "serde")]
+impl From<GameData> for Game {
+    fn from(data: GameData) -> Self {
+        let mut game = Self {
+            field: data.field,
+            score: data.score,
+            undo_history: data.undo_history,
+            redo_history: data.redo_history,
+            history_depth: data.history_depth,
+            target: data.target,
+            keep_playing: data.keep_playing,
+            moves: data.moves,
+            play_time: data.play_time,
+            longest_chain: data.longest_chain,
+        };
+        game.trim_history();
+        // Redo stack top is its end, the farthest moves are dropped
+        let extra = game.redo_history.len().saturating_sub(game.history_depth);
+        game.redo_history.drain(..extra);
+        game
+    }
+}
+
+impl Game {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_rule(width, height, MergeRuleKind::Classic, false)
+    }
+    pub fn new_challenge(width: usize, height: usize) -> Self {
+        Self::with_rule(width, height, MergeRuleKind::Classic, true)
+    }
+    // Challenge game starts with 1 or 2 blockers placed on random cells
+    pub fn with_rule(width: usize, height: usize, rule: MergeRuleKind, challenge: bool) -> Self {
+        let mut field = Field::with_rule(width, height, rule);
+        if challenge {
+            let blockers = rand::thread_rng().gen_range(1..3);
+            for _ in 0..blockers {
+                field.append_blocker();
+            }
+        }
+        field.append_tile();
+        field.append_tile();
+        field.hold_all();
+        Self::from_field(field, 0)
+    }
+    fn from_field(field: Field, score: u32) -> Self {
+        Self {
+            field,
+            score,
+            undo_history: VecDeque::new(),
+            redo_history: Vec::new(),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+            target: DEFAULT_TARGET,
+            keep_playing: false,
+            moves: 0,
+            play_time: Duration::ZERO,
+            longest_chain: 0,
+        }
+    }
+    pub fn field(&self) -> &Field {
+        &self.field
+    }
+    // Applies to the tiles appearing after next swipes
+    pub fn set_spawn_policy(&mut self, spawn_policy: SpawnPolicy) {
+        self.field.set_spawn_policy(spawn_policy)
+    }
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history_depth = depth;
+        self.trim_history();
+    }
+    pub fn history_len(&self) -> usize {
+        self.undo_history.len()
+    }
+    pub fn target(&self) -> u32 {
+        self.target
+    }
+    pub fn set_target(&mut self, target: u32) {
+        self.target = target;
+    }
+    pub fn has_won(&self) -> bool {
+        self.field.max_tile() >= self.target
+    }
+    // Win is announced only once per game, after that player continues
+    pub fn is_win_announced(&self) -> bool {
+        self.keep_playing
+    }
+    pub fn keep_playing(&mut self) {
+        self.keep_playing = true;
+    }
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+    pub fn play_time(&self) -> Duration {
+        self.play_time
+    }
+    pub fn longest_chain(&self) -> u32 {
+        self.longest_chain
+    }
+    pub fn add_play_time(&mut self, time: Duration) {
+        self.play_time += time;
+    }
+    // None until at least a second is played, the speed of the first moves is meaningless
+    pub fn moves_per_minute(&self) -> Option<f32> {
+        if self.play_time < Duration::from_secs(1) {
+            None
+        } else {
+            Some(self.moves as f32 * 60. / self.play_time.as_secs_f32())
+        }
+    }
+    fn snapshot(&self) -> Snapshot {
+        let mut field = self.field.clone();
+        field.hold_all();
+        Snapshot {
+            field,
+            score: self.score,
+        }
+    }
+    fn trim_history(&mut self) {
+        while self.undo_history.len() > self.history_depth {
+            self.undo_history.pop_front();
+        }
+    }
+    // None if tiles can't move to this side
+    pub fn swipe(&mut self, side: Side) -> Option<SwipeResult> {
+        if self.field.can_swipe(side) {
+            let snapshot = self.snapshot();
+            self.undo_history.push_back(snapshot);
+            self.trim_history();
+            self.redo_history.clear();
+            let result = self.field.swipe(side);
+            self.score += result.score;
+            self.moves += 1;
+            self.longest_chain = self.longest_chain.max(result.merges);
+            self.field.spawn_tiles();
+            Some(result)
+        } else {
+            None
+        }
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.undo_history.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo_history.is_empty()
+    }
+    // After undo the field keeps tile origins of the reverted swipe when they are
+    // still available, so that the UI can animate tiles moving back
+    pub fn undo(&mut self) -> bool {
+        if let Some(snapshot) = self.undo_history.pop_back() {
+            let mut reverted = self.field.clone();
+            let current = std::mem::replace(&mut self.field, snapshot.field);
+            self.redo_history.push(Snapshot {
+                field: current,
+                score: self.score,
+            });
+            self.score = snapshot.score;
+            self.moves = self.moves.saturating_sub(1);
+            if reverted.can_undo() {
+                reverted.undo();
+                if reverted.into_array() == self.field.into_array() {
+                    self.field = reverted;
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+    pub fn redo(&mut self) -> bool {
+        if let Some(snapshot) = self.redo_history.pop() {
+            let current = self.snapshot();
+            self.undo_history.push_back(current);
+            self.trim_history();
+            self.field = snapshot.field;
+            self.score = snapshot.score;
+            self.moves += 1;
+            true
+        } else {
+            false
+        }
+    }
+    pub fn hold_all(&mut self) {
+        self.field.hold_all()
+    }
+    // Score is kept, history of the old size is dropped. See Field::resize for moves
+    pub fn resize(&mut self, width: usize, height: usize) -> Result<Vec<ResizeMove>, ResizeError> {
+        let (field, moves) = self.field.resize(width, height)?;
+        self.field = field;
+        self.undo_history.clear();
+        self.redo_history.clear();
+        Ok(moves)
+    }
+
+    // Text representation: "score <n>" line followed by the field, then
+    // "undo <n>" and "redo <n>" blocks of the same form for the history
+    // and optional "keep_playing", "moves <n>", "play_time <milliseconds>" and
+    // "longest_chain <n>" lines
+    pub fn serialize(&self) -> String {
+        let mut result = format!("score {}\n{}", self.score, self.field.serialize());
+        if self.keep_playing {
+            result += "keep_playing\n";
+        }
+        if self.moves != 0 {
+            result += &format!("moves {}\n", self.moves);
+        }
+        if self.play_time != Duration::ZERO {
+            result += &format!("play_time {}\n", self.play_time.as_millis());
+        }
+        if self.longest_chain != 0 {
+            result += &format!("longest_chain {}\n", self.longest_chain);
+        }
+        for snapshot in &self.undo_history {
+            result += &format!("undo {}\n{}", snapshot.score, snapshot.field.serialize());
+        }
+        for snapshot in &self.redo_history {
+            result += &format!("redo {}\n{}", snapshot.score, snapshot.field.serialize());
+        }
+        result
+    }
+    pub fn deserialize(s: &str) -> Result<Self, ParseError> {
+        let mut blocks: Vec<(&str, String)> = Vec::new();
+        for line in s.lines() {
+            if line.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                blocks.push((line, String::new()));
+            } else if let Some((_, body)) = blocks.last_mut() {
+                *body += line;
+                *body += "\n";
+            } else {
+                return Err(ParseError::BadHeader(line.to_string()));
+            }
+        }
+        let mut blocks = blocks.into_iter();
+        let (header, body) = blocks
+            .next()
+            .ok_or_else(|| ParseError::BadHeader(String::new()))?;
+        let snapshot = Self::deserialize_block("score", header, &body)?;
+        let mut game = Self::from_field(snapshot.field, snapshot.score);
+        for (header, body) in blocks {
+            if header == "keep_playing" {
+                game.keep_playing = true;
+            } else if let Some(moves) = header.strip_prefix("moves ") {
+                game.moves = moves
+                    .parse()
+                    .map_err(|_| ParseError::BadHeader(header.to_string()))?;
+            } else if let Some(time) = header.strip_prefix("play_time ") {
+                let millis = time
+                    .parse()
+                    .map_err(|_| ParseError::BadHeader(header.to_string()))?;
+                game.play_time = Duration::from_millis(millis);
+            } else if let Some(chain) = header.strip_prefix("longest_chain ") {
+                game.longest_chain = chain
+                    .parse()
+                    .map_err(|_| ParseError::BadHeader(header.to_string()))?;
+            } else if header.starts_with("undo ") {
+                let snapshot = Self::deserialize_block("undo", header, &body)?;
+                game.undo_history.push_back(snapshot);
+            } else {
+                let snapshot = Self::deserialize_block("redo", header, &body)?;
+                game.redo_history.push(snapshot);
+            }
+        }
+        game.trim_history();
+        Ok(game)
+    }
+    fn deserialize_block(name: &str, header: &str, body: &str) -> Result<Snapshot, ParseError> {
+        let score = match header.split_once(' ') {
+            Some((n, score)) if n == name => score.parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| ParseError::BadHeader(header.to_string()))?;
+        let field = Field::deserialize(body)?;
+        Ok(Snapshot { field, score })
+    }
+}
+
+#[cfg(test)]
+fn swipe_any(game: &mut Game) -> bool {
+    [Side::Up, Side::Down, Side::Left, Side::Right]
+        .iter()
+        .any(|side| game.swipe(*side).is_some())
+}
+
+#[test]
+fn game_serialize_deserialize() {
+    let mut game = Game::new(4, 4);
+    assert!(swipe_any(&mut game));
+    assert!(swipe_any(&mut game));
+    assert!(game.undo());
+    let restored = Game::deserialize(&game.serialize()).unwrap();
+    assert_eq!(restored, game);
+    assert_eq!(restored.history_len(), 1);
+    assert!(restored.can_undo());
+    assert!(restored.can_redo());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn game_serde() {
+    let mut game = Game::new(4, 4);
+    assert!(swipe_any(&mut game));
+    assert!(swipe_any(&mut game));
+    assert!(game.undo());
+    game.add_play_time(Duration::from_millis(1500));
+    let restored: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+    assert_eq!(restored, game);
+    assert!(restored.can_undo());
+    assert!(restored.can_redo());
+
+    // History longer than the depth is trimmed the same way as by Game::deserialize
+    let json = serde_json::to_string(&game)
+        .unwrap()
+        .replace("\"history_depth\":100", "\"history_depth\":0");
+    let restored: Game = serde_json::from_str(&json).unwrap();
+    assert!(!restored.can_undo());
+    assert!(!restored.can_redo());
+    assert_eq!(restored.field(), game.field());
+}
+
+#[test]
+fn game_deserialize_bad_score() {
+    assert_eq!(
+        Game::deserialize("scor 1\n1 1\n.\n"),
+        Err(ParseError::BadHeader("scor 1".to_string()))
+    );
+    assert_eq!(
+        Game::deserialize("score 1\n1 1\n.\nundo x\n1 1\n.\n"),
+        Err(ParseError::BadHeader("undo x".to_string()))
+    );
+}
+
+#[test]
+fn game_undo_redo() {
+    let mut game = Game::new(4, 4);
+    let mut states = vec![(game.field().into_array(), game.score())];
+    for _ in 0..3 {
+        assert!(swipe_any(&mut game));
+        states.push((game.field().into_array(), game.score()));
+    }
+    assert_eq!(game.history_len(), 3);
+    assert!(!game.redo());
+    for i in (0..3).rev() {
+        assert!(game.undo());
+        assert_eq!((game.field().into_array(), game.score()), states[i]);
+    }
+    assert!(!game.can_undo());
+    assert!(!game.undo());
+    for state in states.iter().skip(1) {
+        assert!(game.redo());
+        assert_eq!((game.field().into_array(), game.score()), *state);
+    }
+    assert!(!game.can_redo());
+}
+
+#[test]
+fn game_win_and_keep_playing() {
+    use ndarray::Array2;
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((2, 2), vec![
+        1024, 1024,
+        0, 0,
+    ]).unwrap();
+    let mut game = Game::from_field(Field::from_array(array), 0);
+    assert!(!game.has_won());
+    assert!(game.swipe(Side::Left).is_some());
+    assert!(game.has_won());
+    assert!(!game.is_win_announced());
+    game.keep_playing();
+    let restored = Game::deserialize(&game.serialize()).unwrap();
+    assert!(restored.is_win_announced());
+    assert_eq!(restored, game);
+    game.set_target(4096);
+    assert!(!game.has_won());
+}
+
+#[test]
+fn game_history_depth() {
+    let mut game = Game::new(4, 4);
+    game.set_history_depth(2);
+    for _ in 0..3 {
+        assert!(swipe_any(&mut game));
+    }
+    assert_eq!(game.history_len(), 2);
+    assert!(game.undo());
+    assert!(game.undo());
+    assert!(!game.undo());
+    game.set_history_depth(0);
+    assert!(swipe_any(&mut game));
+    assert!(!game.can_undo());
+}
+
+#[test]
+fn game_challenge() {
+    let mut game = Game::new_challenge(4, 4);
+    let blockers = game.field().blockers_count();
+    assert!((1..=2).contains(&blockers));
+    assert_eq!(game.field().get_free_cells().len(), 14 - blockers);
+    while swipe_any(&mut game) {}
+    assert_eq!(game.field().blockers_count(), blockers);
+    let restored = Game::deserialize(&game.serialize()).unwrap();
+    assert_eq!(restored.field(), game.field());
+}
+
+#[test]
+fn game_threes() {
+    let mut game = Game::with_rule(4, 4, MergeRuleKind::Threes, false);
+    while swipe_any(&mut game) {}
+    let field = game.field();
+    assert_eq!(field.rule(), MergeRuleKind::Threes);
+    for x in 0..field.width() {
+        for y in 0..field.height() {
+            let value = field.value(field.get(x, y).unwrap());
+            assert!(value < 3 || (value / 3).is_power_of_two() && value / 3 * 3 == value);
+        }
+    }
+    let restored = Game::deserialize(&game.serialize()).unwrap();
+    assert_eq!(restored.field(), game.field());
+}
+
+#[test]
+fn game_resize() {
+    let mut game = Game::new(4, 4);
+    assert!(swipe_any(&mut game));
+    let score = game.score();
+    let tiles = 16 - game.field().get_free_cells().len();
+    assert!(game.resize(5, 3).is_ok());
+    assert_eq!((game.field().width(), game.field().height()), (5, 3));
+    assert_eq!(15 - game.field().get_free_cells().len(), tiles);
+    assert_eq!(game.score(), score);
+    assert!(!game.can_undo());
+    assert_eq!(
+        game.resize(1, 1),
+        Err(ResizeError::TooManyTiles { tiles, cells: 1 })
+    );
+    assert_eq!(game.field().width(), 5);
+    // Shrinking moves tiles from outside of the field, the game must stay loadable
+    let array = ndarray::arr2(&[[2, 0, 0], [0, 0, 0], [0, 0, 4]]);
+    let mut game = Game::from_field(Field::from_array(array), 0);
+    let moves = game.resize(2, 2).unwrap();
+    assert_eq!(moves.len(), 1);
+    assert!(!game.can_undo() && !game.field().can_undo());
+    let mut restored = Game::deserialize(&game.serialize()).unwrap();
+    assert_eq!(restored, game);
+    assert!(!restored.undo());
+    let mut field = game.field().clone();
+    assert_eq!(field.undo(), 0);
+    assert_eq!(&field, game.field());
+}
+
+#[test]
+fn game_moves() {
+    let mut game = Game::new(4, 4);
+    assert_eq!(game.moves(), 0);
+    assert!(swipe_any(&mut game));
+    assert!(swipe_any(&mut game));
+    assert_eq!(game.moves(), 2);
+    assert!(game.undo());
+    assert_eq!(game.moves(), 1);
+    assert!(game.redo());
+    assert_eq!(game.moves(), 2);
+    assert_eq!(game.moves_per_minute(), None);
+    game.add_play_time(Duration::from_secs(30));
+    assert_eq!(game.moves_per_minute(), Some(4.));
+    let restored = Game::deserialize(&game.serialize()).unwrap();
+    assert_eq!(restored.moves(), 2);
+    assert_eq!(restored.play_time(), Duration::from_secs(30));
+    assert_eq!(restored, game);
+}
+
+#[test]
+fn game_longest_chain() {
+    use ndarray::Array2;
+    #[rustfmt::skip]
+    let array = Array2::from_shape_vec((2, 4), vec![
+        2, 2, 4, 4,
+        8, 8, 0, 0,
+    ]).unwrap();
+    let mut game = Game::from_field(Field::from_array(array), 0);
+    let result = game.swipe(Side::Left).unwrap();
+    assert_eq!(result.score, 28);
+    assert_eq!(result.merges, 3);
+    assert_eq!(game.longest_chain(), 3);
+    let restored = Game::deserialize(&game.serialize()).unwrap();
+    assert_eq!(restored.longest_chain(), 3);
+}
@@ -0,0 +1,58 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GameMode {
+    Classic,
+    // Whole game must be played in fixed time
+    TimeLimit,
+    // Each move must be made before the countdown expires
+    MoveCountdown,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [
+        GameMode::Classic,
+        GameMode::TimeLimit,
+        GameMode::MoveCountdown,
+    ];
+
+    pub fn time_limit(&self) -> Option<Duration> {
+        match self {
+            GameMode::Classic => None,
+            GameMode::TimeLimit => Some(Duration::from_secs(120)),
+            GameMode::MoveCountdown => Some(Duration::from_secs(5)),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeLimit => "2 minutes",
+            GameMode::MoveCountdown => "5 seconds per move",
+        }
+    }
+
+    // Name used in settings and high scores files
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::TimeLimit => "time_limit",
+            GameMode::MoveCountdown => "move_countdown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|mode| mode.name() == name)
+    }
+}
+
+#[test]
+fn game_mode_names() {
+    for mode in GameMode::ALL.iter() {
+        assert_eq!(GameMode::from_name(mode.name()), Some(*mode));
+    }
+    assert_eq!(GameMode::from_name("unknown"), None);
+}
@@ -0,0 +1,227 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::game_mode::GameMode;
+
+pub const MAX_HIGH_SCORES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HighScore {
+    pub score: u32,
+    pub width: usize,
+    pub height: usize,
+    // seconds since unix epoch
+    pub timestamp: u64,
+    pub mode: GameMode,
+}
+
+impl HighScore {
+    pub fn new(score: u32, width: usize, height: usize, mode: GameMode) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            score,
+            width,
+            height,
+            timestamp,
+            mode,
+        }
+    }
+
+    // Date in YYYY-MM-DD form (UTC)
+    pub fn date(&self) -> String {
+        let (year, month, day) = civil_from_days((self.timestamp / 86400) as i64);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+
+    fn serialize(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.score,
+            self.width,
+            self.height,
+            self.timestamp,
+            self.mode.name()
+        )
+    }
+
+    fn deserialize(s: &str) -> Option<Self> {
+        let mut it = s.split_whitespace();
+        let score = it.next()?.parse().ok()?;
+        let width = it.next()?.parse().ok()?;
+        let height = it.next()?.parse().ok()?;
+        let timestamp = it.next()?.parse().ok()?;
+        // Files written before timed modes have no mode column
+        let mode = match it.next() {
+            Some(name) => GameMode::from_name(name)?,
+            None => GameMode::Classic,
+        };
+        Some(Self {
+            score,
+            width,
+            height,
+            timestamp,
+            mode,
+        })
+    }
+}
+
+// Days since 1970-01-01 to (year, month, day), see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Each game mode has its own table, all tables are stored in one file
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "Vec<HighScore>", into = "Vec<HighScore>")
+)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl From<Vec<HighScore>> for HighScores {
+    fn from(mut entries: Vec<HighScore>) -> Self {
+        entries.sort_by_key(|e| Reverse(e.score));
+        let mut high_scores = Self { entries };
+        high_scores.truncate();
+        high_scores
+    }
+}
+
+impl From<HighScores> for Vec<HighScore> {
+    fn from(high_scores: HighScores) -> Self {
+        high_scores.entries
+    }
+}
+
+impl HighScores {
+    // One entry per line, unreadable lines are skipped
+    pub fn from_text(s: &str) -> Self {
+        s.lines()
+            .filter_map(HighScore::deserialize)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    // Keeps MAX_HIGH_SCORES best entries for each mode
+    fn truncate(&mut self) {
+        let mut counts = [0; GameMode::ALL.len()];
+        self.entries.retain(|e| {
+            let count = &mut counts[GameMode::ALL.iter().position(|m| *m == e.mode).unwrap()];
+            *count += 1;
+            *count <= MAX_HIGH_SCORES
+        });
+    }
+
+    pub fn to_text(&self) -> String {
+        self.entries.iter().map(|e| e.serialize() + "\n").collect()
+    }
+
+    pub fn entries(&self, mode: GameMode) -> Vec<&HighScore> {
+        self.entries.iter().filter(|e| e.mode == mode).collect()
+    }
+
+    // Returns position in the table of entry's mode if score made it there
+    pub fn add(&mut self, entry: HighScore) -> Option<usize> {
+        if entry.score == 0 {
+            return None;
+        }
+        let mode_entries = self.entries(entry.mode);
+        let pos = mode_entries
+            .iter()
+            .position(|e| e.score < entry.score)
+            .unwrap_or(mode_entries.len());
+        if pos >= MAX_HIGH_SCORES {
+            return None;
+        }
+        let index = self
+            .entries
+            .iter()
+            .position(|e| e.score < entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+        self.truncate();
+        Some(pos)
+    }
+}
+
+#[cfg(test)]
+fn entry(score: u32, mode: GameMode) -> HighScore {
+    HighScore {
+        score,
+        width: 4,
+        height: 4,
+        timestamp: 0,
+        mode,
+    }
+}
+
+#[test]
+fn high_scores_add() {
+    let mut high_scores = HighScores::default();
+    assert_eq!(high_scores.add(entry(0, GameMode::Classic)), None);
+    for score in 1..=MAX_HIGH_SCORES as u32 {
+        assert_eq!(
+            high_scores.add(entry(score * 10, GameMode::Classic)),
+            Some(0)
+        );
+    }
+    assert_eq!(high_scores.add(entry(5, GameMode::Classic)), None);
+    assert_eq!(high_scores.add(entry(15, GameMode::Classic)), Some(9));
+    assert_eq!(high_scores.add(entry(5, GameMode::TimeLimit)), Some(0));
+    assert_eq!(
+        high_scores.entries(GameMode::Classic).len(),
+        MAX_HIGH_SCORES
+    );
+    assert_eq!(high_scores.entries(GameMode::TimeLimit).len(), 1);
+    assert_eq!(entry(0, GameMode::Classic).date(), "1970-01-01");
+}
+
+#[test]
+fn high_scores_text() {
+    let text = "10 4 4 0 classic\nbad line\n30 5 5 86400\n20 4 4 0 time_limit\n";
+    let high_scores = HighScores::from_text(text);
+    let classic = high_scores.entries(GameMode::Classic);
+    assert_eq!(classic.len(), 2);
+    assert_eq!((classic[0].score, classic[0].width), (30, 5));
+    assert_eq!(classic[0].date(), "1970-01-02");
+    assert_eq!(high_scores.entries(GameMode::TimeLimit)[0].score, 20);
+    assert_eq!(HighScores::from_text(&high_scores.to_text()), high_scores);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn high_scores_serde() {
+    let entries = (1..=20)
+        .map(|score| entry(score, GameMode::MoveCountdown))
+        .collect::<Vec<_>>();
+    let json = serde_json::to_string(&entries).unwrap();
+    let high_scores: HighScores = serde_json::from_str(&json).unwrap();
+    let kept = high_scores.entries(GameMode::MoveCountdown);
+    assert_eq!(kept.len(), MAX_HIGH_SCORES);
+    assert_eq!(kept[0].score, 20);
+    let json = serde_json::to_string(&high_scores).unwrap();
+    assert_eq!(
+        serde_json::from_str::<HighScores>(&json).unwrap(),
+        high_scores
+    );
+}
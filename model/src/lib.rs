@@ -1 +1,10 @@
+pub mod ai;
+pub mod bitfield;
+pub mod board;
 pub mod field;
+pub mod format;
+pub mod game;
+pub mod game_mode;
+pub mod high_scores;
+pub mod merge_rule;
+pub mod spawn;
@@ -0,0 +1,219 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Tiles store level, the rule decides which levels merge and which number is shown
+pub trait MergeRule {
+    // Level of the tile made of tiles with given levels, None if they don't merge
+    fn merge(&self, dst: u32, src: u32) -> Option<u32>;
+    // Levels of the tiles which were merged into the tile of given level. Used by undo,
+    // when the result is ambiguous any valid pair is returned
+    fn split(&self, level: u32) -> (u32, u32);
+    fn value(&self, level: u32) -> u32;
+    // Highest level with the value fitting u32, tiles don't merge above it
+    fn max_level(&self) -> u32;
+    // New tile gets one of these levels, see SpawnPolicy
+    fn appear_levels(&self) -> &'static [u32];
+    // Relative chances of appear levels under the original rules of the game
+    fn appear_weights(&self) -> &'static [u32];
+}
+
+// 2, 4, 8, 16... equal tiles merge
+pub struct Classic;
+
+impl MergeRule for Classic {
+    fn merge(&self, dst: u32, src: u32) -> Option<u32> {
+        if dst == src {
+            Some(dst + 1)
+        } else {
+            None
+        }
+    }
+    fn split(&self, level: u32) -> (u32, u32) {
+        (level.saturating_sub(1), level.saturating_sub(1))
+    }
+    fn value(&self, level: u32) -> u32 {
+        1 << level
+    }
+    fn max_level(&self) -> u32 {
+        31
+    }
+    fn appear_levels(&self) -> &'static [u32] {
+        &[1, 2]
+    }
+    // 2 appears in 90% of cases, 4 in 10%
+    fn appear_weights(&self) -> &'static [u32] {
+        &[9, 1]
+    }
+}
+
+// 1, 2, 3, 5, 8... neighbour numbers of the sequence merge
+pub struct Fibonacci;
+
+impl MergeRule for Fibonacci {
+    fn merge(&self, dst: u32, src: u32) -> Option<u32> {
+        if dst == 1 && src == 1 {
+            Some(2)
+        } else if dst + 1 == src || src + 1 == dst {
+            Some(dst.max(src) + 1)
+        } else {
+            None
+        }
+    }
+    fn split(&self, level: u32) -> (u32, u32) {
+        if level == 2 {
+            (1, 1)
+        } else {
+            (level.saturating_sub(1), level.saturating_sub(2))
+        }
+    }
+    fn value(&self, level: u32) -> u32 {
+        let (mut a, mut b) = (1, 1);
+        for _ in 0..level {
+            let next = a + b;
+            a = b;
+            b = next;
+        }
+        a
+    }
+    // The loop above computes one number ahead
+    fn max_level(&self) -> u32 {
+        45
+    }
+    fn appear_levels(&self) -> &'static [u32] {
+        &[1, 2]
+    }
+    fn appear_weights(&self) -> &'static [u32] {
+        &[3, 1]
+    }
+}
+
+// 1 and 2 make 3, then equal tiles merge: 3, 6, 12, 24...
+pub struct Threes;
+
+impl MergeRule for Threes {
+    fn merge(&self, dst: u32, src: u32) -> Option<u32> {
+        match (dst, src) {
+            (1, 2) | (2, 1) => Some(3),
+            (dst, src) if dst == src && dst >= 3 => Some(dst + 1),
+            _ => None,
+        }
+    }
+    fn split(&self, level: u32) -> (u32, u32) {
+        if level == 3 {
+            (1, 2)
+        } else {
+            (level.saturating_sub(1), level.saturating_sub(1))
+        }
+    }
+    fn value(&self, level: u32) -> u32 {
+        if level < 3 {
+            level
+        } else {
+            3 << (level - 3)
+        }
+    }
+    fn max_level(&self) -> u32 {
+        33
+    }
+    fn appear_levels(&self) -> &'static [u32] {
+        &[1, 2, 3]
+    }
+    // 1 and 2 come in equal numbers to pair up, 3 is rarer
+    fn appear_weights(&self) -> &'static [u32] {
+        &[2, 2, 1]
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MergeRuleKind {
+    #[default]
+    Classic,
+    Fibonacci,
+    Threes,
+}
+
+impl MergeRuleKind {
+    pub const ALL: [MergeRuleKind; 3] = [
+        MergeRuleKind::Classic,
+        MergeRuleKind::Fibonacci,
+        MergeRuleKind::Threes,
+    ];
+
+    pub fn rule(&self) -> &'static dyn MergeRule {
+        match self {
+            MergeRuleKind::Classic => &Classic,
+            MergeRuleKind::Fibonacci => &Fibonacci,
+            MergeRuleKind::Threes => &Threes,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MergeRuleKind::Classic => "classic",
+            MergeRuleKind::Fibonacci => "fibonacci",
+            MergeRuleKind::Threes => "threes",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+}
+
+#[test]
+fn classic_rule() {
+    let rule = MergeRuleKind::Classic.rule();
+    assert_eq!(rule.value(11), 2048);
+    assert_eq!(rule.merge(3, 3), Some(4));
+    assert_eq!(rule.merge(3, 4), None);
+    assert_eq!(rule.split(4), (3, 3));
+}
+
+#[test]
+fn fibonacci_rule() {
+    let rule = MergeRuleKind::Fibonacci.rule();
+    let values = (1..8).map(|level| rule.value(level)).collect::<Vec<_>>();
+    assert_eq!(values, vec![1, 2, 3, 5, 8, 13, 21]);
+    assert_eq!(rule.merge(1, 1), Some(2));
+    assert_eq!(rule.merge(2, 2), None);
+    assert_eq!(rule.merge(4, 3), Some(5));
+    assert_eq!(rule.merge(2, 4), None);
+    for level in 2..8 {
+        let (a, b) = rule.split(level);
+        assert_eq!(rule.merge(a, b), Some(level));
+        assert_eq!(rule.value(a) + rule.value(b), rule.value(level));
+    }
+}
+
+#[test]
+fn threes_rule() {
+    let rule = MergeRuleKind::Threes.rule();
+    let values = (1..7).map(|level| rule.value(level)).collect::<Vec<_>>();
+    assert_eq!(values, vec![1, 2, 3, 6, 12, 24]);
+    assert_eq!(rule.merge(1, 1), None);
+    assert_eq!(rule.merge(2, 1), Some(3));
+    assert_eq!(rule.merge(3, 3), Some(4));
+    for level in 3..7 {
+        let (a, b) = rule.split(level);
+        assert_eq!(rule.merge(a, b), Some(level));
+    }
+}
+
+#[test]
+fn merge_rule_names() {
+    for kind in MergeRuleKind::ALL.iter() {
+        assert_eq!(MergeRuleKind::from_name(kind.name()), Some(*kind));
+    }
+    assert_eq!(MergeRuleKind::from_name("unknown"), None);
+}
+
+#[test]
+fn rule_max_level() {
+    for kind in MergeRuleKind::ALL.iter() {
+        let rule = kind.rule();
+        let max = rule.max_level();
+        assert!(rule.value(max) > rule.value(max - 1));
+        assert_eq!(rule.split(0), (0, 0));
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::merge_rule::MergeRule;
+
+// How many tiles appear after each swipe and how often each of the rule's appear
+// levels is picked
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SpawnPolicy {
+    // One tile, mostly of the lowest level, as in the original games
+    Classic,
+    // One tile, the highest of the appear levels never appears
+    Easy,
+    // Two tiles, all appear levels are equally likely
+    Hard,
+}
+
+impl SpawnPolicy {
+    pub const ALL: [SpawnPolicy; 3] = [SpawnPolicy::Classic, SpawnPolicy::Easy, SpawnPolicy::Hard];
+
+    pub fn tiles_per_swipe(&self) -> usize {
+        match self {
+            SpawnPolicy::Classic | SpawnPolicy::Easy => 1,
+            SpawnPolicy::Hard => 2,
+        }
+    }
+
+    // Relative chances of the rule's appear levels, in the same order
+    pub fn weights(&self, rule: &dyn MergeRule) -> Vec<u32> {
+        let weights = rule.appear_weights();
+        match self {
+            SpawnPolicy::Classic => weights.to_vec(),
+            SpawnPolicy::Easy => {
+                let mut weights = weights.to_vec();
+                if let Some(last) = weights.last_mut() {
+                    *last = 0;
+                }
+                weights
+            }
+            SpawnPolicy::Hard => vec![1; weights.len()],
+        }
+    }
+
+    // Level of the appearing tile for the random number in 0..sum of weights
+    pub fn pick_level(&self, rule: &dyn MergeRule, mut n: u32) -> u32 {
+        let levels = rule.appear_levels();
+        for (level, weight) in levels.iter().zip(self.weights(rule)) {
+            if n < weight {
+                return *level;
+            }
+            n -= weight;
+        }
+        levels[0]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpawnPolicy::Classic => "classic",
+            SpawnPolicy::Easy => "easy",
+            SpawnPolicy::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|policy| policy.name() == name)
+    }
+}
+
+#[test]
+fn spawn_weights() {
+    use crate::merge_rule::MergeRuleKind;
+    let classic = MergeRuleKind::Classic.rule();
+    assert_eq!(SpawnPolicy::Classic.weights(classic), vec![9, 1]);
+    assert_eq!(SpawnPolicy::Easy.weights(classic), vec![9, 0]);
+    assert_eq!(SpawnPolicy::Hard.weights(classic), vec![1, 1]);
+    let picked = (0..10)
+        .map(|n| SpawnPolicy::Classic.pick_level(classic, n))
+        .collect::<Vec<_>>();
+    assert_eq!(picked, vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 2]);
+    // Easy threes keeps both 1 and 2, they are needed to make 3
+    let threes = MergeRuleKind::Threes.rule();
+    assert_eq!(SpawnPolicy::Easy.weights(threes), vec![2, 2, 0]);
+    for policy in SpawnPolicy::ALL.iter() {
+        assert_eq!(SpawnPolicy::from_name(policy.name()), Some(*policy));
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+// Keyboard shortcut. Registered accelerators are checked by the event loop before
+// keyboard input is passed to panels, so they work regardless of focus
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Accelerator {
+    pub key: VirtualKeyCode,
+    pub modifiers: ModifiersState,
+}
+
+impl Accelerator {
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> Self {
+        Self { key, modifiers }
+    }
+    pub fn key(key: VirtualKeyCode) -> Self {
+        Self::new(key, ModifiersState::empty())
+    }
+    pub fn ctrl(key: VirtualKeyCode) -> Self {
+        Self::new(key, ModifiersState::CTRL)
+    }
+}
+
+// Text like "Ctrl+Z" for tooltips and menus
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.ctrl() {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt() {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift() {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.logo() {
+            write!(f, "Win+")?;
+        }
+        let key = format!("{:?}", self.key);
+        // Digit keys are named Key0..Key9
+        match key.strip_prefix("Key") {
+            Some(digit) if !digit.is_empty() => write!(f, "{}", digit),
+            _ => write!(f, "{}", key),
+        }
+    }
+}
+
+// Sent to the panel which registered the accelerator when its keys are pressed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AcceleratorEvent(pub Accelerator);
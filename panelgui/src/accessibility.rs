@@ -0,0 +1,539 @@
+use std::{
+    borrow::Cow,
+    ffi::c_void,
+    ptr,
+    sync::atomic::{fence, AtomicU32, Ordering},
+};
+
+use bindings::Windows::{
+    Win32::{
+        Graphics::Gdi::ClientToScreen,
+        UI::{
+            DisplayDevices::POINT,
+            WindowsAndMessaging::{HWND, LPARAM, LRESULT, WPARAM},
+        },
+    },
+    UI::Composition::ContainerVisual,
+};
+
+use crate::{capture::visual_offset, globals::accessible_entries};
+
+// Description of panel for assistive technologies. Panels register it on init and
+// remove it on close, so the registry mirrors the visible panel tree. The window's
+// UI Automation provider lists registered panels as children of the window
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AccessibleRole {
+    Button,
+    Text,
+    Grid,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccessibleInfo {
+    pub role: AccessibleRole,
+    pub name: Cow<'static, str>,
+}
+
+// Registered panel with what's needed to find it on the screen
+#[derive(Clone)]
+pub(crate) struct AccessibleEntry {
+    pub(crate) info: AccessibleInfo,
+    pub(crate) hwnd: HWND,
+    pub(crate) visual: ContainerVisual,
+    // Its origin is the top left corner of the window's client area
+    pub(crate) root_visual: ContainerVisual,
+}
+
+impl AccessibleRole {
+    fn control_type(self) -> i32 {
+        match self {
+            AccessibleRole::Button => UIA_BUTTON_CONTROL_TYPE_ID,
+            AccessibleRole::Text => UIA_TEXT_CONTROL_TYPE_ID,
+            AccessibleRole::Grid => UIA_TABLE_CONTROL_TYPE_ID,
+        }
+    }
+}
+
+// The provider is a hand-written COM object: windows 0.10 can't implement COM interfaces,
+// so the functions taking it are declared here instead of in bindings
+#[link(name = "uiautomationcore")]
+extern "system" {
+    fn UiaReturnRawElementProvider(
+        hwnd: isize,
+        wparam: usize,
+        lparam: isize,
+        provider: *mut c_void,
+    ) -> isize;
+    fn UiaHostProviderFromHwnd(hwnd: isize, provider: *mut *mut c_void) -> i32;
+}
+
+#[link(name = "oleaut32")]
+extern "system" {
+    fn SysAllocStringLen(text: *const u16, len: u32) -> *mut u16;
+    fn SafeArrayCreateVector(vt: u16, lower_bound: i32, len: u32) -> *mut c_void;
+    fn SafeArrayPutElement(array: *mut c_void, index: *const i32, value: *const c_void) -> i32;
+}
+
+const S_OK: i32 = 0;
+const E_NOINTERFACE: i32 = 0x8000_4002_u32 as i32;
+const E_POINTER: i32 = 0x8000_4003_u32 as i32;
+const E_OUTOFMEMORY: i32 = 0x8007_000E_u32 as i32;
+const UIA_E_ELEMENTNOTAVAILABLE: i32 = 0x8004_0201_u32 as i32;
+
+const UIA_ROOT_OBJECT_ID: i32 = -25;
+const PROVIDER_OPTIONS_SERVER_SIDE: i32 = 1;
+const UIA_APPEND_RUNTIME_ID: i32 = 3;
+const UIA_CONTROL_TYPE_PROPERTY_ID: i32 = 30003;
+const UIA_NAME_PROPERTY_ID: i32 = 30005;
+const UIA_BUTTON_CONTROL_TYPE_ID: i32 = 50000;
+const UIA_TEXT_CONTROL_TYPE_ID: i32 = 50020;
+const UIA_TABLE_CONTROL_TYPE_ID: i32 = 50036;
+
+const NAVIGATE_PARENT: i32 = 0;
+const NAVIGATE_NEXT_SIBLING: i32 = 1;
+const NAVIGATE_PREVIOUS_SIBLING: i32 = 2;
+const NAVIGATE_FIRST_CHILD: i32 = 3;
+const NAVIGATE_LAST_CHILD: i32 = 4;
+
+const VT_EMPTY: u16 = 0;
+const VT_I4: u16 = 3;
+const VT_BSTR: u16 = 8;
+
+#[repr(C)]
+#[derive(PartialEq)]
+struct Guid(u32, u16, u16, [u8; 8]);
+
+const IID_IUNKNOWN: Guid = Guid(0x0000_0000, 0x0000, 0x0000, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]);
+const IID_SIMPLE: Guid = Guid(
+    0xD6DD_68D1,
+    0x86FD,
+    0x4332,
+    [0x86, 0x66, 0x9A, 0xBE, 0xDE, 0xA2, 0xD2, 0x4C],
+);
+const IID_FRAGMENT: Guid = Guid(
+    0xF706_3DA8,
+    0x8359,
+    0x439C,
+    [0x92, 0x97, 0xBB, 0xC5, 0x29, 0x9A, 0x7D, 0x87],
+);
+const IID_FRAGMENT_ROOT: Guid = Guid(
+    0x620C_E2A5,
+    0xAB8F,
+    0x40A9,
+    [0x86, 0xCB, 0xDE, 0x3C, 0x75, 0x59, 0x9B, 0x58],
+);
+
+#[repr(C)]
+struct Variant {
+    vt: u16,
+    reserved: [u16; 3],
+    value: VariantValue,
+}
+
+#[repr(C)]
+union VariantValue {
+    int: i32,
+    bstr: *mut u16,
+    // VARIANT is as large as two pointers, e.g. for VT_RECORD
+    record: [usize; 2],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct UiaRect {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+impl UiaRect {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.left && x < self.left + self.width && y >= self.top && y < self.top + self.height
+    }
+}
+
+type This = *mut c_void;
+
+#[repr(C)]
+struct UnknownVtbl {
+    query_interface: unsafe extern "system" fn(This, *const Guid, *mut *mut c_void) -> i32,
+    add_ref: unsafe extern "system" fn(This) -> u32,
+    release: unsafe extern "system" fn(This) -> u32,
+}
+
+// IRawElementProviderSimple
+#[repr(C)]
+struct SimpleVtbl {
+    unknown: UnknownVtbl,
+    provider_options: unsafe extern "system" fn(This, *mut i32) -> i32,
+    pattern_provider: unsafe extern "system" fn(This, i32, *mut *mut c_void) -> i32,
+    property_value: unsafe extern "system" fn(This, i32, *mut Variant) -> i32,
+    host_provider: unsafe extern "system" fn(This, *mut *mut c_void) -> i32,
+}
+
+// IRawElementProviderFragment
+#[repr(C)]
+struct FragmentVtbl {
+    unknown: UnknownVtbl,
+    navigate: unsafe extern "system" fn(This, i32, *mut *mut c_void) -> i32,
+    runtime_id: unsafe extern "system" fn(This, *mut *mut c_void) -> i32,
+    bounding_rectangle: unsafe extern "system" fn(This, *mut UiaRect) -> i32,
+    embedded_fragment_roots: unsafe extern "system" fn(This, *mut *mut c_void) -> i32,
+    set_focus: unsafe extern "system" fn(This) -> i32,
+    fragment_root: unsafe extern "system" fn(This, *mut *mut c_void) -> i32,
+}
+
+// IRawElementProviderFragmentRoot
+#[repr(C)]
+struct FragmentRootVtbl {
+    unknown: UnknownVtbl,
+    element_from_point: unsafe extern "system" fn(This, f64, f64, *mut *mut c_void) -> i32,
+    focus: unsafe extern "system" fn(This, *mut *mut c_void) -> i32,
+}
+
+static SIMPLE_VTBL: SimpleVtbl = SimpleVtbl {
+    unknown: UnknownVtbl {
+        query_interface: query_interface::<0>,
+        add_ref: add_ref::<0>,
+        release: release::<0>,
+    },
+    provider_options,
+    pattern_provider,
+    property_value,
+    host_provider,
+};
+
+static FRAGMENT_VTBL: FragmentVtbl = FragmentVtbl {
+    unknown: UnknownVtbl {
+        query_interface: query_interface::<1>,
+        add_ref: add_ref::<1>,
+        release: release::<1>,
+    },
+    navigate,
+    runtime_id,
+    bounding_rectangle,
+    embedded_fragment_roots,
+    set_focus,
+    fragment_root,
+};
+
+static FRAGMENT_ROOT_VTBL: FragmentRootVtbl = FragmentRootVtbl {
+    unknown: UnknownVtbl {
+        query_interface: query_interface::<2>,
+        add_ref: add_ref::<2>,
+        release: release::<2>,
+    },
+    element_from_point,
+    focus,
+};
+
+// COM object for the window or for one registered panel. Interface pointers point to
+// the vtable fields, so methods of interface number SLOT find the object SLOT pointers
+// before their `this`. UI Automation calls the methods on the window's thread, but
+// may release the object on another one
+#[repr(C)]
+struct Provider {
+    simple: *const SimpleVtbl,
+    fragment: *const FragmentVtbl,
+    fragment_root: *const FragmentRootVtbl,
+    refs: AtomicU32,
+    hwnd: HWND,
+    // None for the window itself
+    panel_id: Option<usize>,
+}
+
+// Returns IRawElementProviderSimple with one reference
+fn new_provider(hwnd: HWND, panel_id: Option<usize>) -> *mut c_void {
+    Box::into_raw(Box::new(Provider {
+        simple: &SIMPLE_VTBL,
+        fragment: &FRAGMENT_VTBL,
+        fragment_root: &FRAGMENT_ROOT_VTBL,
+        refs: AtomicU32::new(1),
+        hwnd,
+        panel_id,
+    })) as *mut c_void
+}
+
+unsafe fn interface(provider: *mut c_void, slot: usize) -> *mut c_void {
+    (provider as *mut *const c_void).add(slot) as *mut c_void
+}
+
+unsafe fn provider<'a>(this: This, slot: usize) -> &'a Provider {
+    &*((this as *mut *const c_void).sub(slot) as *const Provider)
+}
+
+unsafe extern "system" fn query_interface<const SLOT: usize>(
+    this: This,
+    iid: *const Guid,
+    out: *mut *mut c_void,
+) -> i32 {
+    if iid.is_null() || out.is_null() {
+        return E_POINTER;
+    }
+    let provider = provider(this, SLOT);
+    let iid = &*iid;
+    // IUnknown must always be the same pointer, it's the object's identity
+    let slot = if *iid == IID_IUNKNOWN || *iid == IID_SIMPLE {
+        0
+    } else if *iid == IID_FRAGMENT {
+        1
+    } else if *iid == IID_FRAGMENT_ROOT && provider.panel_id.is_none() {
+        2
+    } else {
+        *out = ptr::null_mut();
+        return E_NOINTERFACE;
+    };
+    provider.refs.fetch_add(1, Ordering::Relaxed);
+    *out = interface(provider as *const Provider as *mut c_void, slot);
+    S_OK
+}
+
+unsafe extern "system" fn add_ref<const SLOT: usize>(this: This) -> u32 {
+    provider(this, SLOT).refs.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+unsafe extern "system" fn release<const SLOT: usize>(this: This) -> u32 {
+    let provider = provider(this, SLOT);
+    let refs = provider.refs.fetch_sub(1, Ordering::Release) - 1;
+    if refs == 0 {
+        fence(Ordering::Acquire);
+        drop(Box::from_raw(provider as *const Provider as *mut Provider));
+    }
+    refs
+}
+
+unsafe extern "system" fn provider_options(_this: This, options: *mut i32) -> i32 {
+    if options.is_null() {
+        return E_POINTER;
+    }
+    *options = PROVIDER_OPTIONS_SERVER_SIDE;
+    S_OK
+}
+
+// Panels don't support control patterns
+unsafe extern "system" fn pattern_provider(
+    _this: This,
+    _pattern_id: i32,
+    out: *mut *mut c_void,
+) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = ptr::null_mut();
+    S_OK
+}
+
+unsafe extern "system" fn property_value(this: This, property_id: i32, value: *mut Variant) -> i32 {
+    if value.is_null() {
+        return E_POINTER;
+    }
+    (*value).vt = VT_EMPTY;
+    let provider = provider(this, 0);
+    // Properties of the window itself come from the host provider
+    let panel_id = match provider.panel_id {
+        Some(panel_id) => panel_id,
+        None => return S_OK,
+    };
+    let entry = match find_entry(provider.hwnd, panel_id) {
+        Some(entry) => entry,
+        None => return UIA_E_ELEMENTNOTAVAILABLE,
+    };
+    match property_id {
+        UIA_CONTROL_TYPE_PROPERTY_ID => {
+            (*value).vt = VT_I4;
+            (*value).value.int = entry.info.role.control_type();
+        }
+        UIA_NAME_PROPERTY_ID => {
+            let name: Vec<u16> = entry.info.name.encode_utf16().collect();
+            let bstr = SysAllocStringLen(name.as_ptr(), name.len() as u32);
+            if bstr.is_null() {
+                return E_OUTOFMEMORY;
+            }
+            (*value).vt = VT_BSTR;
+            (*value).value.bstr = bstr;
+        }
+        _ => {}
+    }
+    S_OK
+}
+
+unsafe extern "system" fn host_provider(this: This, out: *mut *mut c_void) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = ptr::null_mut();
+    let provider = provider(this, 0);
+    if provider.panel_id.is_none() {
+        UiaHostProviderFromHwnd(provider.hwnd.0, out)
+    } else {
+        S_OK
+    }
+}
+
+// Registered panels are flat children of the window
+unsafe extern "system" fn navigate(this: This, direction: i32, out: *mut *mut c_void) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = ptr::null_mut();
+    let provider = provider(this, 1);
+    let entries = match accessible_entries(provider.hwnd) {
+        Ok(entries) => entries,
+        Err(_) => return UIA_E_ELEMENTNOTAVAILABLE,
+    };
+    // Some(None) is the window itself
+    let target = match provider.panel_id {
+        None => match direction {
+            NAVIGATE_FIRST_CHILD => entries.first().map(|(panel_id, _)| Some(*panel_id)),
+            NAVIGATE_LAST_CHILD => entries.last().map(|(panel_id, _)| Some(*panel_id)),
+            _ => None,
+        },
+        Some(panel_id) => {
+            let index = match entries.iter().position(|(id, _)| *id == panel_id) {
+                Some(index) => index,
+                None => return UIA_E_ELEMENTNOTAVAILABLE,
+            };
+            match direction {
+                NAVIGATE_PARENT => Some(None),
+                NAVIGATE_NEXT_SIBLING => entries.get(index + 1).map(|(id, _)| Some(*id)),
+                NAVIGATE_PREVIOUS_SIBLING => index.checked_sub(1).map(|i| Some(entries[i].0)),
+                _ => None,
+            }
+        }
+    };
+    if let Some(panel_id) = target {
+        *out = interface(new_provider(provider.hwnd, panel_id), 1);
+    }
+    S_OK
+}
+
+// Window has no runtime id of its own, the host provider gives it
+unsafe extern "system" fn runtime_id(this: This, out: *mut *mut c_void) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = ptr::null_mut();
+    if let Some(panel_id) = provider(this, 1).panel_id {
+        let array = SafeArrayCreateVector(VT_I4, 0, 2);
+        if array.is_null() {
+            return E_OUTOFMEMORY;
+        }
+        for (index, value) in [UIA_APPEND_RUNTIME_ID, panel_id as i32].iter().enumerate() {
+            SafeArrayPutElement(array, &(index as i32), value as *const i32 as *const c_void);
+        }
+        *out = array;
+    }
+    S_OK
+}
+
+unsafe extern "system" fn bounding_rectangle(this: This, rect: *mut UiaRect) -> i32 {
+    if rect.is_null() {
+        return E_POINTER;
+    }
+    *rect = UiaRect::default();
+    let provider = provider(this, 1);
+    if let Some(panel_id) = provider.panel_id {
+        match find_entry(provider.hwnd, panel_id) {
+            Some(entry) => *rect = screen_rect(&entry).unwrap_or_default(),
+            None => return UIA_E_ELEMENTNOTAVAILABLE,
+        }
+    }
+    S_OK
+}
+
+unsafe extern "system" fn embedded_fragment_roots(_this: This, out: *mut *mut c_void) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = ptr::null_mut();
+    S_OK
+}
+
+unsafe extern "system" fn set_focus(_this: This) -> i32 {
+    S_OK
+}
+
+unsafe extern "system" fn fragment_root(this: This, out: *mut *mut c_void) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = interface(new_provider(provider(this, 1).hwnd, None), 2);
+    S_OK
+}
+
+// Point is in screen coordinates, null result means the window itself
+unsafe extern "system" fn element_from_point(
+    this: This,
+    x: f64,
+    y: f64,
+    out: *mut *mut c_void,
+) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = ptr::null_mut();
+    let hwnd = provider(this, 2).hwnd;
+    let entries = accessible_entries(hwnd).unwrap_or_default();
+    // Panels registered later are usually on top
+    let hit = entries
+        .iter()
+        .rev()
+        .find(|(_, entry)| matches!(screen_rect(entry), Some(rect) if rect.contains(x, y)));
+    if let Some((panel_id, _)) = hit {
+        *out = interface(new_provider(hwnd, Some(*panel_id)), 1);
+    }
+    S_OK
+}
+
+// Keyboard focus isn't reported, null result means the window itself
+unsafe extern "system" fn focus(_this: This, out: *mut *mut c_void) -> i32 {
+    if out.is_null() {
+        return E_POINTER;
+    }
+    *out = ptr::null_mut();
+    S_OK
+}
+
+fn find_entry(hwnd: HWND, panel_id: usize) -> Option<AccessibleEntry> {
+    accessible_entries(hwnd)
+        .ok()?
+        .into_iter()
+        .find(|(id, _)| *id == panel_id)
+        .map(|(_, entry)| entry)
+}
+
+// None for hidden panels, empty rectangle tells that the element is offscreen
+fn screen_rect(entry: &AccessibleEntry) -> Option<UiaRect> {
+    let offset = visual_offset(&entry.visual, &entry.root_visual).ok()??;
+    let size = entry.visual.Size().ok()?;
+    let mut origin = POINT { x: 0, y: 0 };
+    if !unsafe { ClientToScreen(entry.hwnd, &mut origin) }.as_bool() {
+        return None;
+    }
+    Some(UiaRect {
+        left: (origin.x as f32 + offset.X) as f64,
+        top: (origin.y as f32 + offset.Y) as f64,
+        width: size.X as f64,
+        height: size.Y as f64,
+    })
+}
+
+// Answers WM_GETOBJECT asking for the UI Automation root, None passes the message on
+pub(crate) fn get_object(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> Option<LRESULT> {
+    // Object id is 32 bit even in 64 bit lparam
+    if lparam.0 as i32 != UIA_ROOT_OBJECT_ID {
+        return None;
+    }
+    unsafe {
+        let root = new_provider(hwnd, None);
+        let result = UiaReturnRawElementProvider(hwnd.0, wparam.0, lparam.0, root);
+        release::<0>(root);
+        Some(LRESULT(result))
+    }
+}
+
+// Called on WM_DESTROY, lets UI Automation release the providers of the window
+pub(crate) fn disconnect_providers(hwnd: HWND) {
+    unsafe { UiaReturnRawElementProvider(hwnd.0, 0, 0, ptr::null_mut()) };
+}
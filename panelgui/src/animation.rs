@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use bindings::Windows::{
+    Foundation::{Numerics::Vector2, TimeSpan},
+    UI::Composition::CompositionEasingFunction,
+};
+
+use crate::globals::compositor;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationSettings {
+    // When disabled, properties are set to final values immediately
+    pub enabled: bool,
+    pub duration: Duration,
+    // Control points of the cubic bezier easing curve
+    pub easing: (Vector2, Vector2),
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration: Duration::from_millis(150),
+            // "ease-out" curve: fast start, slow finish
+            easing: (Vector2 { X: 0.0, Y: 0.0 }, Vector2 { X: 0.58, Y: 1.0 }),
+        }
+    }
+}
+
+impl AnimationSettings {
+    // Composition uses 100ns units for time
+    pub fn time_span(&self) -> TimeSpan {
+        TimeSpan {
+            Duration: (self.duration.as_nanos() / 100) as i64,
+        }
+    }
+    pub fn easing_function(&self) -> windows::Result<CompositionEasingFunction> {
+        Ok(compositor()
+            .CreateCubicBezierEasingFunction(self.easing.0.clone(), self.easing.1.clone())?
+            .into())
+    }
+}
@@ -0,0 +1,80 @@
+use std::{ffi::c_void, mem::size_of};
+
+use bindings::Windows::Win32::{
+    Graphics::Dwm::{DwmExtendFrameIntoClientArea, DwmSetWindowAttribute},
+    UI::{Controls::MARGINS, WindowsAndMessaging::HWND},
+};
+
+// DWM window attributes missing in the bindings metadata
+const DWMWA_USE_IMMERSIVE_DARK_MODE: u32 = 20;
+const DWMWA_SYSTEMBACKDROP_TYPE: u32 = 38;
+// Undocumented predecessor of DWMWA_SYSTEMBACKDROP_TYPE in the first Windows 11 release
+const DWMWA_MICA_EFFECT: u32 = 1029;
+
+// Material drawn by the system beneath the window content. It's visible only where
+// panels leave the window transparent, see BackgroundParams::transparent_to_backdrop
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum WindowBackdrop {
+    None,
+    Mica,
+    Acrylic,
+    // Mica variant with stronger tint, for windows with tabs
+    Tabbed,
+}
+
+impl WindowBackdrop {
+    // Values of DWM_SYSTEMBACKDROP_TYPE
+    fn system_backdrop_type(self) -> u32 {
+        match self {
+            WindowBackdrop::None => 1,
+            WindowBackdrop::Mica => 2,
+            WindowBackdrop::Acrylic => 3,
+            WindowBackdrop::Tabbed => 4,
+        }
+    }
+}
+
+fn set_window_attribute(hwnd: HWND, attribute: u32, value: u32) -> bool {
+    unsafe {
+        DwmSetWindowAttribute(
+            hwnd,
+            attribute,
+            &value as *const u32 as *const c_void,
+            size_of::<u32>() as u32,
+        )
+        .is_ok()
+    }
+}
+
+// Returns false if the system doesn't support requested backdrop, then the window
+// keeps its usual opaque background
+pub(crate) fn apply_backdrop(hwnd: HWND, backdrop: WindowBackdrop) -> bool {
+    if backdrop == WindowBackdrop::None {
+        return set_window_attribute(
+            hwnd,
+            DWMWA_SYSTEMBACKDROP_TYPE,
+            backdrop.system_backdrop_type(),
+        );
+    }
+    // Backdrop is drawn in the frame area, which is extended to the whole window
+    let margins = MARGINS {
+        cxLeftWidth: -1,
+        cxRightWidth: -1,
+        cyTopHeight: -1,
+        cyBottomHeight: -1,
+    };
+    if unsafe { DwmExtendFrameIntoClientArea(hwnd, &margins) }.is_err() {
+        return false;
+    }
+    set_window_attribute(
+        hwnd,
+        DWMWA_SYSTEMBACKDROP_TYPE,
+        backdrop.system_backdrop_type(),
+    ) || (backdrop == WindowBackdrop::Mica && set_window_attribute(hwnd, DWMWA_MICA_EFFECT, 1))
+}
+
+// Backdrop and window frame follow dark or light mode set for the window, not the
+// system one
+pub(crate) fn apply_dark_mode(hwnd: HWND, dark_mode: bool) -> bool {
+    set_window_attribute(hwnd, DWMWA_USE_IMMERSIVE_DARK_MODE, dark_mode as u32)
+}
@@ -4,49 +4,71 @@ use bindings::Windows::{
     Foundation::Numerics::Vector2,
     UI::{
         Color, Colors,
-        Composition::{CompositionShape, ContainerVisual, ShapeVisual},
+        Composition::{CompositionShape, ContainerVisual, ShapeVisual, SpriteVisual},
     },
 };
 use float_ord::FloatOrd;
-use winit::event::{ElementState, KeyboardInput, MouseButton};
 
 use crate::{
-    globals::{compositor, get_next_id, winrt_error},
-    panel::{Handle, Panel, PanelEvent, PanelHandle},
+    backdrop::WindowBackdrop,
+    context::{current_context, FrameworkContext},
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelHandle},
+    shadow::{create_rounded_mask, create_shadow_visual},
 };
 
 #[derive(Builder)]
 #[builder(setter(into))]
 pub struct BackgroundParams {
-    #[builder(default = "{Colors::White().unwrap()}")]
-    color: Color,
+    // Theme background color is used when not set
+    #[builder(default = "{None}")]
+    color: Option<Color>,
+    // Use theme dialog background instead of window one
+    #[builder(default = "{false}")]
+    dialog: bool,
     #[builder(default = "{false}")]
     round_corners: bool,
+    // Casts theme shadow, for backgrounds of dialogs raised over other content
+    #[builder(default = "{false}")]
+    shadow: bool,
+    // Nothing is drawn when the window has system backdrop, so it shows through
+    #[builder(default = "{false}")]
+    transparent_to_backdrop: bool,
 }
 
 impl Default for BackgroundParams {
     fn default() -> Self {
         Self {
-            color: Colors::Transparent().unwrap(),
+            color: Some(Colors::Transparent().unwrap()),
+            dialog: false,
             round_corners: false,
+            shadow: false,
+            transparent_to_backdrop: false,
         }
     }
 }
 
 impl BackgroundParamsBuilder {
     pub fn create(&self) -> windows::Result<BackgroundPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(&self, context: &FrameworkContext) -> windows::Result<BackgroundPanel> {
         match self.build() {
-            Ok(params) => Ok(BackgroundPanel::new(params)?),
+            Ok(params) => Ok(BackgroundPanel::new(context, params)?),
             Err(e) => Err(winrt_error(e)()),
         }
     }
 }
 
 pub struct BackgroundPanel {
+    context: FrameworkContext,
     id: usize,
     params: BackgroundParams,
     visual: ContainerVisual,
     background_shape: ShapeVisual,
+    shadow_visual: Option<SpriteVisual>,
+    // Background is redrawn in on_idle, once per frame
+    dirty: bool,
 }
 
 pub struct BackgroundPanelHandle {
@@ -62,55 +84,101 @@ impl Handle for BackgroundPanelHandle {
 impl PanelHandle<BackgroundPanel> for BackgroundPanelHandle {}
 
 impl BackgroundPanel {
-    pub fn new(params: BackgroundParams) -> windows::Result<Self> {
-        let id = get_next_id();
-        let visual = compositor().CreateContainerVisual()?;
-        let background_shape = compositor().CreateShapeVisual()?;
+    pub fn new(context: &FrameworkContext, params: BackgroundParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        let background_shape = context.compositor()?.CreateShapeVisual()?;
         visual
             .Children()?
             .InsertAtBottom(background_shape.clone())?;
         Ok(Self {
+            context: context.clone(),
             id,
             params,
             visual,
             background_shape,
+            shadow_visual: None,
+            dirty: false,
         })
     }
     pub fn handle(&self) -> BackgroundPanelHandle {
         BackgroundPanelHandle { id: self.id }
     }
     pub fn set_color(&mut self, color: Color) -> windows::Result<()> {
-        self.params.color = color;
-        self.redraw_background()
+        self.params.color = Some(color);
+        self.invalidate()
     }
     pub fn set_round_corners(&mut self, round_corners: bool) -> windows::Result<()> {
         self.params.round_corners = round_corners;
-        self.redraw_background()
+        self.invalidate()
+    }
+    fn invalidate(&mut self) -> windows::Result<()> {
+        self.dirty = true;
+        self.context.request_idle()
     }
     fn redraw_background(&mut self) -> windows::Result<()> {
+        let span = tracing::trace_span!("redraw_background", panel_id = self.id);
+        let _enter = span.enter();
         self.background_shape.SetSize(self.visual.Size()?)?;
         self.background_shape.Shapes()?.Clear()?;
         self.background_shape
             .Shapes()?
             .Append(self.create_background_shape()?)?;
+        self.redraw_shadow()
+    }
+    fn corner_radius(&self, size: &Vector2) -> windows::Result<f32> {
+        Ok(if self.params.round_corners {
+            std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0
+                * self.context.theme()?.corner_radius
+        } else {
+            0.
+        })
+    }
+    fn redraw_shadow(&mut self) -> windows::Result<()> {
+        if let Some(shadow_visual) = self.shadow_visual.take() {
+            self.visual.Children()?.Remove(&shadow_visual)?;
+        }
+        let style = match self.context.theme()?.shadow {
+            Some(style) if self.params.shadow => style,
+            _ => return Ok(()),
+        };
+        let size = self.visual.Size()?;
+        if size.X <= 0. || size.Y <= 0. {
+            return Ok(());
+        }
+        let mask = create_rounded_mask(&size, self.corner_radius(&size)?)?;
+        let shadow_visual =
+            create_shadow_visual(&size, &mask, &style, self.context.scale_factor()?)?;
+        self.visual
+            .Children()?
+            .InsertAtBottom(shadow_visual.clone())?;
+        self.shadow_visual = Some(shadow_visual);
         Ok(())
     }
     fn create_background_shape(&self) -> windows::Result<CompositionShape> {
-        let container_shape = compositor().CreateContainerShape()?;
-        let rect_geometry = compositor().CreateRoundedRectangleGeometry()?;
-        rect_geometry.SetSize(self.background_shape.Size()?)?;
-        if self.params.round_corners {
-            let size = rect_geometry.Size()?;
-            let radius = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 / 20.;
-            rect_geometry.SetCornerRadius(Vector2 {
-                X: radius,
-                Y: radius,
-            })?;
+        let compositor = self.context.compositor()?;
+        let container_shape = compositor.CreateContainerShape()?;
+        let rect_geometry = compositor.CreateRoundedRectangleGeometry()?;
+        let size = self.background_shape.Size()?;
+        let radius = self.corner_radius(&size)?;
+        rect_geometry.SetSize(size)?;
+        rect_geometry.SetCornerRadius(Vector2 {
+            X: radius,
+            Y: radius,
+        })?;
+        let color = if self.params.transparent_to_backdrop
+            && self.context.window_backdrop()? != WindowBackdrop::None
+        {
+            Colors::Transparent()?
         } else {
-            rect_geometry.SetCornerRadius(Vector2 { X: 0., Y: 0. })?;
-        }
-        let brush = compositor().CreateColorBrushWithColor(self.params.color.clone())?;
-        let rect = compositor().CreateSpriteShapeWithGeometry(rect_geometry)?;
+            match self.params.color.clone() {
+                Some(color) => color,
+                None if self.params.dialog => self.context.theme()?.dialog_background,
+                None => self.context.theme()?.background,
+            }
+        };
+        let brush = compositor.CreateColorBrushWithColor(color)?;
+        let rect = compositor.CreateSpriteShapeWithGeometry(rect_geometry)?;
         rect.SetFillBrush(brush)?;
         rect.SetOffset(Vector2 { X: 0., Y: 0. })?;
         container_shape.Shapes()?.Append(rect)?;
@@ -132,44 +200,19 @@ impl Panel for BackgroundPanel {
         self
     }
 
-    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
-        if id == self.id() {
-            return Some(self.as_any_mut());
-        } else {
-            None
-        }
-    }
-
     fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
         self.visual.SetSize(size.clone())?;
-        self.redraw_background()
+        self.invalidate()
     }
 
     fn on_idle(&mut self) -> windows::Result<()> {
+        if std::mem::take(&mut self.dirty) {
+            self.redraw_background()?;
+        }
         Ok(())
     }
 
-    fn on_mouse_input(
-        &mut self,
-        _button: MouseButton,
-        _state: ElementState,
-    ) -> windows::Result<bool> {
-        Ok(false)
-    }
-
-    fn on_keyboard_input(&mut self, _input: KeyboardInput) -> windows::Result<bool> {
-        Ok(false)
-    }
-
-    fn on_init(&mut self) -> windows::Result<()> {
-        Ok(())
-    }
-
-    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
-        Ok(())
-    }
-
-    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
-        Ok(())
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.invalidate()
     }
 }
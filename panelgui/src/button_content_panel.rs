@@ -0,0 +1,243 @@
+use std::{any::Any, borrow::Cow, rc::Rc};
+
+use bindings::Windows::{Foundation::Numerics::Vector2, UI::Composition::ContainerVisual};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    control::{Control, ControlHandle},
+    gamepad::GamepadInput,
+    globals::winrt_error,
+    icon::IconId,
+    panel::{EmptyPanel, Panel, PanelEvent},
+    ribbon_panel::{RibbonCellParamsBuilder, RibbonOrientation, RibbonPanel, RibbonParamsBuilder},
+    text_panel::TextParamsBuilder,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ButtonLayout {
+    IconLeft,
+    IconAbove,
+}
+
+// Part of the button size left for the content, the rest is padding
+const DEFAULT_CONTENT_RATIO: f32 = 0.85;
+// Gap between icon and text relative to the icon size
+const ICON_SPACING: f32 = 0.2;
+const ICON_LEFT_TEXT_RATIO: f32 = 3.;
+const ICON_ABOVE_ICON_RATIO: f32 = 2.;
+
+// Content of toolbar-style buttons made of several panels. Controls listed in params
+// follow the enabled state of the button, e.g. texts are grayed out with it
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct ButtonContentParams {
+    #[builder(private, setter(name = "ribbon_private"))]
+    ribbon: RibbonPanel,
+    #[builder(default = "{Vec::new()}")]
+    controls: Vec<Rc<dyn ControlHandle>>,
+    #[builder(default = "{Vector2 { X: DEFAULT_CONTENT_RATIO, Y: DEFAULT_CONTENT_RATIO }}")]
+    content_ratio: Vector2,
+}
+
+impl ButtonContentParamsBuilder {
+    pub fn create(self) -> windows::Result<ButtonContentPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<ButtonContentPanel> {
+        match self.build() {
+            Ok(params) => Ok(ButtonContentPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+    pub fn ribbon(self, ribbon: RibbonPanel) -> Self {
+        self.ribbon_private(ribbon)
+    }
+    pub fn add_control(mut self, handle: impl ControlHandle + 'static) -> Self {
+        let handle: Rc<dyn ControlHandle> = Rc::new(handle);
+        self.controls.get_or_insert_with(Vec::new).push(handle);
+        self
+    }
+    // Ribbon of icon and text with gap between them
+    pub fn icon_with_text(
+        self,
+        icon: IconId,
+        text: impl Into<Cow<'static, str>>,
+        layout: ButtonLayout,
+    ) -> windows::Result<Self> {
+        let icon_panel = TextParamsBuilder::default().icon(icon).create()?;
+        let text_panel = TextParamsBuilder::default().text(text).create()?;
+        let icon_handle = icon_panel.handle();
+        let text_handle = text_panel.handle();
+        let ribbon = match layout {
+            ButtonLayout::IconLeft => RibbonParamsBuilder::default()
+                .orientation(RibbonOrientation::Horizontal)
+                .add_panel(icon_panel)?
+                .add_panel_with_ratio(EmptyPanel::new()?, ICON_SPACING)?
+                .add_panel_with_ratio(text_panel, ICON_LEFT_TEXT_RATIO)?,
+            ButtonLayout::IconAbove => RibbonParamsBuilder::default()
+                .orientation(RibbonOrientation::Vertical)
+                .add_panel_with_ratio(icon_panel, ICON_ABOVE_ICON_RATIO)?
+                .add_panel_with_ratio(EmptyPanel::new()?, ICON_SPACING)?
+                .add_panel(text_panel)?,
+        };
+        Ok(self
+            .ribbon(ribbon.create()?)
+            .add_control(icon_handle)
+            .add_control(text_handle))
+    }
+}
+
+pub struct ButtonContentPanel {
+    id: usize,
+    // Stack ribbon applying padding to the content ribbon
+    root_panel: RibbonPanel,
+    controls: Vec<Rc<dyn ControlHandle>>,
+}
+
+impl ButtonContentPanel {
+    pub fn new(context: &FrameworkContext, params: ButtonContentParams) -> windows::Result<Self> {
+        let root_panel = RibbonParamsBuilder::default()
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel(params.ribbon)
+                    .content_ratio(params.content_ratio)
+                    .create_in(context)?,
+            )
+            .create_in(context)?;
+        Ok(Self {
+            id: context.next_id()?,
+            root_panel,
+            controls: params.controls,
+        })
+    }
+}
+
+impl Panel for ButtonContentPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.root_panel.visual()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id {
+            Some(self.as_any_mut())
+        } else {
+            self.root_panel.find_panel(id)
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.root_panel.on_init()
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.root_panel.on_close()
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.root_panel.on_resize(size)
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        self.root_panel.measure()
+    }
+
+    fn desired_size(&self, available: &Vector2) -> windows::Result<Vector2> {
+        self.root_panel.desired_size(available)
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        self.root_panel.on_idle()
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        self.root_panel.on_timer(timer_id)
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        self.root_panel.on_focus_changed(focused)
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.root_panel.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.root_panel.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.root_panel.on_device_lost()
+    }
+
+    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
+        self.root_panel.on_mouse_move(position)
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        self.root_panel.on_mouse_input(button, state)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        self.root_panel.on_keyboard_input(input, modifiers)
+    }
+
+    fn on_char(&mut self, ch: char) -> windows::Result<bool> {
+        self.root_panel.on_char(ch)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        self.root_panel.on_gamepad_input(input)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)
+    }
+
+    fn on_mouse_wheel(&mut self, delta: MouseScrollDelta) -> windows::Result<bool> {
+        self.root_panel.on_mouse_wheel(delta)
+    }
+
+    fn on_cursor_inside_changed(&mut self, inside: bool) -> windows::Result<()> {
+        self.root_panel.on_cursor_inside_changed(inside)
+    }
+
+    fn on_modifiers_changed(&mut self, modifiers: ModifiersState) -> windows::Result<()> {
+        self.root_panel.on_modifiers_changed(modifiers)
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        vec![&self.root_panel]
+    }
+}
+
+impl Control for ButtonContentPanel {
+    fn on_enable(&mut self, enable: bool) -> windows::Result<()> {
+        for handle in &self.controls {
+            if let Some(control) = handle.as_control(&mut self.root_panel) {
+                control.on_enable(enable)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn as_panel(&self) -> &dyn Panel {
+        self
+    }
+}
@@ -1,45 +1,63 @@
 use std::{any::Any, borrow::Cow, collections::HashMap};
 
 use bindings::Windows::{
-    Foundation::Numerics::Vector2,
-    UI::{
-        Colors,
-        Composition::{CompositionShape, ContainerVisual, ShapeVisual},
-    },
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::Composition::{CompositionShape, ContainerVisual, ShapeVisual},
 };
 use float_ord::FloatOrd;
-use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode};
+use winit::{
+    event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    },
+    window::CursorIcon,
+};
 
 use crate::{
+    accelerator::{Accelerator, AcceleratorEvent},
+    accessibility::AccessibleRole,
+    button_content_panel::{ButtonContentParamsBuilder, ButtonLayout},
+    context::{current_context, FrameworkContext},
     control::{Control, ControlHandle},
-    globals::{compositor, get_next_id, send_panel_event, winrt_error},
+    gamepad::{GamepadButton, GamepadInput},
+    globals::winrt_error,
+    icon::IconId,
     panel::{Handle, Panel, PanelEvent, PanelHandle},
+    shadow::{create_rounded_mask, create_shadow_visual},
+    sound::SoundId,
+    style::{ControlStyle, StyleState},
     text_panel::TextParamsBuilder,
+    theme::Theme,
 };
 
 #[derive(PartialEq)]
 pub enum ButtonPanelEvent {
     Pressed,
 }
-#[derive(Eq, PartialEq, Hash, Copy, Clone)]
-enum ButtonMode {
-    Norm,
-    Disabled,
-    Focused,
-}
 #[derive(Builder)]
 #[builder(pattern = "owned", setter(into))]
 pub struct ButtonParams {
     #[builder(default = "{true}")]
     enabled: bool,
+    // Highlighted as the button activated by Enter
+    #[builder(default = "{false}")]
+    default_button: bool,
+    // Set from text by text builders, announced by screen readers
+    #[builder(default = "{None}")]
+    accessible_name: Option<Cow<'static, str>>,
+    // Keyboard shortcut pressing the button
+    #[builder(default = "{None}")]
+    accelerator: Option<Accelerator>,
     #[builder(private, setter(name = "panel_private"))]
     panel: Box<dyn Control>,
 }
 
 impl ButtonParamsBuilder {
     pub fn create(self) -> windows::Result<ButtonPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<ButtonPanel> {
         match self.build() {
-            Ok(params) => Ok(ButtonPanel::new(params)?),
+            Ok(params) => Ok(ButtonPanel::new(context, params)?),
             Err(e) => Err(winrt_error(e)()),
         }
     }
@@ -48,16 +66,75 @@ impl ButtonParamsBuilder {
         self.panel_private(panel)
     }
     pub fn text(self, text: impl Into<Cow<'static, str>>) -> windows::Result<Self> {
-        Ok(self.panel(TextParamsBuilder::default().text(text).create()?))
+        let text = text.into();
+        Ok(self
+            .accessible_name(Some(text.clone()))
+            .panel(TextParamsBuilder::default().text(text).create()?))
+    }
+    // Icon is shown instead of the text when the button is too small
+    pub fn text_with_icon(
+        self,
+        text: impl Into<Cow<'static, str>>,
+        icon: impl Into<Cow<'static, str>>,
+    ) -> windows::Result<Self> {
+        let text = text.into();
+        Ok(self.accessible_name(Some(text.clone())).panel(
+            TextParamsBuilder::default()
+                .text(text)
+                .compact_text(icon)
+                .create()?,
+        ))
+    }
+    // Vector icon doesn't depend on glyphs available in the font
+    pub fn text_with_vector_icon(
+        self,
+        text: impl Into<Cow<'static, str>>,
+        icon: IconId,
+    ) -> windows::Result<Self> {
+        let text = text.into();
+        Ok(self.accessible_name(Some(text.clone())).panel(
+            TextParamsBuilder::default()
+                .text(text)
+                .icon(icon)
+                .create()?,
+        ))
+    }
+    pub fn icon_with_text(
+        self,
+        icon: IconId,
+        text: impl Into<Cow<'static, str>>,
+        layout: ButtonLayout,
+    ) -> windows::Result<Self> {
+        let text = text.into();
+        Ok(self.accessible_name(Some(text.clone())).panel(
+            ButtonContentParamsBuilder::default()
+                .icon_with_text(icon, text, layout)?
+                .create()?,
+        ))
+    }
+    pub fn icon(self, icon: IconId) -> windows::Result<Self> {
+        Ok(self
+            .accessible_name(Some(icon.name().into()))
+            .panel(TextParamsBuilder::default().icon(icon).create()?))
     }
 }
 
 pub struct ButtonPanel {
+    context: FrameworkContext,
     handle: ButtonPanelHandle,
     visual: ContainerVisual,
-    background: ShapeVisual,
-    shapes: HashMap<ButtonMode, (Vector2, CompositionShape)>,
+    // Holds one layer per style state, changing state cross-fades between layers
+    background: ContainerVisual,
+    layers: HashMap<StyleState, ShapeVisual>,
+    // State whose layer is visible, None after layers are dropped
+    state: Option<StyleState>,
     focused: bool,
+    hovered: bool,
+    // Mouse button went down on the button and wasn't released yet. Release over the
+    // button is a click, release outside cancels it
+    pressed: bool,
+    // Background is redrawn in on_idle, once per frame
+    background_dirty: bool,
     params: ButtonParams,
 }
 
@@ -65,8 +142,10 @@ pub struct ButtonPanel {
 pub struct ButtonPanelHandle(usize);
 
 impl ButtonPanelHandle {
-    fn new() -> Self {
-        Self { 0: get_next_id() }
+    fn new(context: &FrameworkContext) -> windows::Result<Self> {
+        Ok(Self {
+            0: context.next_id()?,
+        })
     }
 }
 
@@ -84,21 +163,27 @@ impl ControlHandle for ButtonPanelHandle {
     }
 }
 impl ButtonPanel {
-    pub fn new(params: ButtonParams) -> windows::Result<Self> {
-        let handle = ButtonPanelHandle::new();
-        let visual = compositor().CreateContainerVisual()?;
-        let background = compositor().CreateShapeVisual()?;
+    pub fn new(context: &FrameworkContext, params: ButtonParams) -> windows::Result<Self> {
+        let handle = ButtonPanelHandle::new(context)?;
+        let compositor = context.compositor()?;
+        let visual = compositor.CreateContainerVisual()?;
+        let background = compositor.CreateContainerVisual()?;
         visual.Children()?.InsertAtBottom(background.clone())?;
         visual
             .Children()?
             .InsertAtTop(params.panel.visual().clone())?;
         Ok(Self {
+            context: context.clone(),
             handle,
             params,
             visual,
             background,
-            shapes: HashMap::new(),
+            layers: HashMap::new(),
+            state: None,
             focused: false,
+            hovered: false,
+            pressed: false,
+            background_dirty: false,
         })
     }
     pub fn handle(&self) -> ButtonPanelHandle {
@@ -117,28 +202,34 @@ impl ButtonPanel {
     }
     fn press(&mut self) -> windows::Result<()> {
         if self.params.enabled {
-            send_panel_event(self.handle.id(), ButtonPanelEvent::Pressed)?;
+            self.context.play_sound(SoundId::ButtonPress)?;
+            self.context
+                .send_panel_event(self.handle.id(), ButtonPanelEvent::Pressed)?;
         }
         Ok(())
     }
-    fn get_shape(&mut self, mode: ButtonMode) -> windows::Result<CompositionShape> {
-        let size = self.background.Size()?;
-        if let Some((shape_size, shape)) = self.shapes.get(&mode) {
-            if *shape_size == size {
-                return Ok(shape.clone());
-            }
-        }
-        let shape = Self::create_shape(mode, &size)?;
-        self.shapes.insert(mode, (size, shape.clone()));
-        Ok(shape)
+    // Background shape is inset from the button edges by this offset
+    fn shape_offset(size: &Vector2) -> f32 {
+        std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 / 20.
+    }
+    fn corner_radius(theme: &Theme, size: &Vector2) -> f32 {
+        std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 * theme.corner_radius
     }
-    fn create_shape(mode: ButtonMode, size: &Vector2) -> windows::Result<CompositionShape> {
-        let container_shape = compositor().CreateContainerShape()?;
-        let round_rect_geometry = compositor().CreateRoundedRectangleGeometry()?;
-        let offset = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 / 20.;
+    fn create_shape(
+        &self,
+        style: ControlStyle,
+        size: &Vector2,
+        default_button: bool,
+    ) -> windows::Result<CompositionShape> {
+        let compositor = self.context.compositor()?;
+        let theme = self.context.theme()?;
+        let container_shape = compositor.CreateContainerShape()?;
+        let round_rect_geometry = compositor.CreateRoundedRectangleGeometry()?;
+        let offset = Self::shape_offset(size);
+        let radius = Self::corner_radius(&theme, size);
         round_rect_geometry.SetCornerRadius(Vector2 {
-            X: offset,
-            Y: offset,
+            X: radius,
+            Y: radius,
         })?;
         round_rect_geometry.SetSize(Vector2 {
             X: size.X - offset * 2.,
@@ -148,42 +239,116 @@ impl ButtonPanel {
             X: offset,
             Y: offset,
         })?;
-        let (border_color, border_thickness) = match mode {
-            // ButtonMode::Norm => (Colors::black()?, 1.),
-            // ButtonMode::Disabled => (Colors::gray()?, 1.),
-            // ButtonMode::Focused => (Colors::black()?, 3.),
-            ButtonMode::Norm => (Colors::White()?, 1.),
-            ButtonMode::Disabled => (Colors::White()?, 1.),
-            ButtonMode::Focused => (Colors::Black()?, 1.),
+        let (stroke, stroke_width) = if default_button {
+            (theme.default_ring, style.stroke_width.max(2.))
+        } else {
+            (style.stroke, style.stroke_width)
         };
-        let fill_brush = compositor().CreateColorBrushWithColor(Colors::White()?)?;
-        let stroke_brush = compositor().CreateColorBrushWithColor(border_color)?;
-        let rect = compositor().CreateSpriteShapeWithGeometry(round_rect_geometry)?;
+        let fill_brush = compositor.CreateColorBrushWithColor(style.fill)?;
+        let stroke_brush = compositor.CreateColorBrushWithColor(stroke)?;
+        let rect = compositor.CreateSpriteShapeWithGeometry(round_rect_geometry)?;
         rect.SetFillBrush(fill_brush)?;
         rect.SetStrokeBrush(stroke_brush)?;
-        rect.SetStrokeThickness(border_thickness)?;
+        rect.SetStrokeThickness(stroke_width * self.context.scale_factor()?)?;
         rect.SetOffset(Vector2 { X: 0., Y: 0. })?;
         container_shape.Shapes()?.Append(rect)?;
         let shape = container_shape.into();
         Ok(shape)
     }
-    fn get_mode(&self) -> ButtonMode {
-        if self.params.enabled {
-            if self.focused {
-                ButtonMode::Focused
-            } else {
-                ButtonMode::Norm
-            }
-        } else {
-            ButtonMode::Disabled
+    fn style_state(&self) -> StyleState {
+        StyleState::new(
+            self.params.enabled,
+            self.focused,
+            self.hovered,
+            self.pressed,
+        )
+    }
+    fn get_layer(&mut self, state: StyleState) -> windows::Result<ShapeVisual> {
+        if let Some(layer) = self.layers.get(&state) {
+            return Ok(layer.clone());
+        }
+        let size = self.visual.Size()?;
+        // Default button keeps its ring in normal and hovered states only
+        let default_button = self.params.default_button
+            && (state == StyleState::Normal || state == StyleState::Hovered);
+        let layer = self.context.compositor()?.CreateShapeVisual()?;
+        layer.SetSize(size.clone())?;
+        layer.SetOpacity(0.)?;
+        layer.Shapes()?.Append(self.create_shape(
+            self.context.theme()?.control_style(state),
+            &size,
+            default_button,
+        )?)?;
+        self.background.Children()?.InsertAtTop(layer.clone())?;
+        self.layers.insert(state, layer.clone());
+        Ok(layer)
+    }
+    // Shadow is shared by all layers and stays below them
+    fn create_shadow(&self) -> windows::Result<()> {
+        let theme = self.context.theme()?;
+        let style = match theme.shadow.clone() {
+            Some(style) => style,
+            None => return Ok(()),
+        };
+        let size = self.visual.Size()?;
+        let offset = Self::shape_offset(&size);
+        let shape_size = Vector2 {
+            X: size.X - offset * 2.,
+            Y: size.Y - offset * 2.,
+        };
+        if shape_size.X <= 0. || shape_size.Y <= 0. {
+            return Ok(());
+        }
+        let mask = create_rounded_mask(&shape_size, Self::corner_radius(&theme, &size))?;
+        let shadow =
+            create_shadow_visual(&shape_size, &mask, &style, self.context.scale_factor()?)?;
+        shadow.SetOffset(Vector3 {
+            X: offset,
+            Y: offset,
+            Z: 0.,
+        })?;
+        self.background.Children()?.InsertAtBottom(shadow)
+    }
+    // Layers are recreated on resize, they depend on size and theme
+    fn drop_layers(&mut self) -> windows::Result<()> {
+        self.background.Children()?.RemoveAll()?;
+        self.layers.clear();
+        self.state = None;
+        Ok(())
+    }
+    fn fade_layer(&self, layer: &ShapeVisual, to: f32, animate: bool) -> windows::Result<()> {
+        let settings = self.context.animation_settings()?;
+        if !animate || !settings.enabled {
+            return layer.SetOpacity(to);
         }
+        let animation = self.context.compositor()?.CreateScalarKeyFrameAnimation()?;
+        animation.InsertKeyFrameWithEasingFunction(1.0, to, settings.easing_function()?)?;
+        animation.SetDuration(settings.time_span())?;
+        layer.SetOpacity(to)?;
+        layer.StartAnimation("Opacity", animation)
+    }
+    fn invalidate_background(&mut self) -> windows::Result<()> {
+        self.background_dirty = true;
+        self.context.request_idle()
     }
     fn redraw_background(&mut self) -> windows::Result<()> {
-        self.background.SetSize(self.visual.Size()?)?;
-        self.background.Shapes()?.Clear()?;
-        self.background
-            .Shapes()?
-            .Append(self.get_shape(self.get_mode())?)?;
+        let span = tracing::trace_span!("redraw_button", panel_id = self.handle.id());
+        let _enter = span.enter();
+        let state = self.style_state();
+        if self.state == Some(state) {
+            return Ok(());
+        }
+        // First state after resize is shown immediately
+        let animate = self.state.is_some();
+        if !animate {
+            self.create_shadow()?;
+        }
+        if let Some(old_layer) = self.state.and_then(|s| self.layers.get(&s)) {
+            self.fade_layer(old_layer, 0., animate)?;
+        }
+        let layer = self.get_layer(state)?;
+        self.fade_layer(&layer, 1., animate)?;
+        self.state = Some(state);
         Ok(())
     }
 }
@@ -196,27 +361,76 @@ impl Panel for ButtonPanel {
         self.visual.clone()
     }
 
+    fn measure(&self) -> windows::Result<Vector2> {
+        self.params.panel.measure()
+    }
+
+    fn desired_size(&self, available: &Vector2) -> windows::Result<Vector2> {
+        self.params.panel.desired_size(available)
+    }
+
     fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
         self.visual.SetSize(self.visual.Parent()?.Size()?)?;
-        self.redraw_background()?;
+        // Shapes depend on theme which may be changed, so recreate them
+        self.drop_layers()?;
+        self.invalidate_background()?;
         self.panel()?.on_resize(size)
     }
 
     fn on_idle(&mut self) -> windows::Result<()> {
+        if std::mem::take(&mut self.background_dirty) {
+            self.redraw_background()?;
+        }
         self.panel()?.on_idle()
     }
 
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        self.panel()?.on_timer(timer_id)
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        self.panel()?.on_focus_changed(focused)
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.panel()?.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.panel()?.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        // Shadow mask surface is gone with the device
+        self.drop_layers()?;
+        self.invalidate_background()?;
+        self.panel()?.on_device_lost()
+    }
+
     fn on_mouse_input(
         &mut self,
         button: MouseButton,
         state: ElementState,
     ) -> windows::Result<bool> {
-        if self.is_enabled()? && button == MouseButton::Left && state == ElementState::Pressed {
-            self.set_focus()?;
-            self.press()?;
-            Ok(true)
-        } else {
-            Ok(false)
+        if button != MouseButton::Left {
+            return Ok(false);
+        }
+        match state {
+            ElementState::Pressed if self.is_enabled()? => {
+                self.set_focus()?;
+                self.pressed = true;
+                self.invalidate_background()?;
+                Ok(true)
+            }
+            ElementState::Released if self.pressed => {
+                self.pressed = false;
+                self.invalidate_background()?;
+                if self.hovered {
+                    self.press()?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
         }
     }
 
@@ -232,7 +446,11 @@ impl Panel for ButtonPanel {
         }
     }
 
-    fn on_keyboard_input(&mut self, input: KeyboardInput) -> windows::Result<bool> {
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
         if self.is_focused()? && self.is_enabled()? {
             if input.state == ElementState::Pressed {
                 if let Some(code) = input.virtual_keycode {
@@ -242,7 +460,18 @@ impl Panel for ButtonPanel {
                             return Ok(true);
                         }
                         VirtualKeyCode::Tab => {
-                            // TODO: Check WindowEvent::ModifiersChanged modifiers for shift-tab
+                            if modifiers.shift() {
+                                self.set_focus_to_prev()?;
+                            } else {
+                                self.set_focus_to_next()?;
+                            }
+                            return Ok(true);
+                        }
+                        VirtualKeyCode::Left | VirtualKeyCode::Up => {
+                            self.set_focus_to_prev()?;
+                            return Ok(true);
+                        }
+                        VirtualKeyCode::Right | VirtualKeyCode::Down => {
                             self.set_focus_to_next()?;
                             return Ok(true);
                         }
@@ -258,28 +487,101 @@ impl Panel for ButtonPanel {
         Ok(false)
     }
 
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if self.is_focused()? && self.is_enabled()? && input.state == ElementState::Pressed {
+            match input.button {
+                GamepadButton::A => {
+                    self.press()?;
+                    return Ok(true);
+                }
+                GamepadButton::B => {
+                    self.clear_focus()?;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+
     fn on_init(&mut self) -> windows::Result<()> {
+        if let Some(name) = self.params.accessible_name.clone() {
+            self.context
+                .set_accessible(self.id(), &self.visual, AccessibleRole::Button, name)?;
+        }
+        if let Some(accelerator) = self.params.accelerator {
+            self.context.register_accelerator(self.id(), accelerator)?;
+        }
         self.panel()?.on_init()
     }
 
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.context.remove_accessible(self.id())?;
+        self.context.unregister_accelerators(self.id())?;
+        self.panel()?.on_close()
+    }
+
     fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
+        // Owner ribbon sends positions outside of the button when mouse leaves it
+        let size = self.visual.Size()?;
+        let hovered =
+            position.X >= 0. && position.X < size.X && position.Y >= 0. && position.Y < size.Y;
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            self.invalidate_background()?;
+        }
+        if hovered && self.params.enabled {
+            self.context.set_cursor(CursorIcon::Hand)?;
+        }
         self.panel()?.on_mouse_move(position)
     }
 
     fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        if panel_event.extract::<AcceleratorEvent>(self.id()).is_some() {
+            return self.press();
+        }
         self.panel()?.on_panel_event(panel_event)
     }
+
+    fn on_mouse_wheel(&mut self, delta: MouseScrollDelta) -> windows::Result<bool> {
+        self.panel()?.on_mouse_wheel(delta)
+    }
+
+    fn on_cursor_inside_changed(&mut self, inside: bool) -> windows::Result<()> {
+        self.panel()?.on_cursor_inside_changed(inside)
+    }
+
+    fn on_modifiers_changed(&mut self, modifiers: ModifiersState) -> windows::Result<()> {
+        self.panel()?.on_modifiers_changed(modifiers)
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        vec![self.params.panel.as_panel()]
+    }
 }
 
 impl Control for ButtonPanel {
     fn on_enable(&mut self, enable: bool) -> windows::Result<()> {
         self.params.enabled = enable;
+        if !enable {
+            self.pressed = false;
+        }
+        self.invalidate_background()?;
         self.panel()?.on_enable(enable)
     }
 
     fn on_set_focus(&mut self) -> windows::Result<()> {
         self.focused = true;
-        self.redraw_background()
+        self.invalidate_background()?;
+        Ok(())
+    }
+
+    fn on_activate(&mut self) -> windows::Result<()> {
+        self.press()
     }
 
     fn as_panel(&self) -> &dyn Panel {
@@ -296,6 +598,7 @@ impl Control for ButtonPanel {
 
     fn on_clear_focus(&mut self) -> windows::Result<()> {
         self.focused = false;
-        self.redraw_background()
+        self.invalidate_background()?;
+        Ok(())
     }
 }
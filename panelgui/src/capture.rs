@@ -0,0 +1,266 @@
+use std::{ffi::c_void, fs::File, io::BufWriter, mem::size_of, path::Path, ptr};
+
+use bindings::Windows::{
+    Foundation::Numerics::Vector2,
+    Win32::{
+        Graphics::Gdi::{
+            BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC,
+            GetDIBits, ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, DIB_USAGE, ROP_CODE,
+        },
+        System::{
+            DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData},
+            Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GLOBAL_ALLOC_FLAGS},
+            SystemServices::HANDLE,
+        },
+        UI::WindowsAndMessaging::HWND,
+    },
+    UI::Composition::ContainerVisual,
+};
+
+use crate::globals::{root_visual, window_client_rect, window_hwnd, winrt_error};
+
+// Standard clipboard format for device independent bitmap with BITMAPINFOHEADER
+const CF_DIB: u32 = 8;
+
+// Pixels of the window client area, rows from top to bottom
+pub struct WindowImage {
+    pub width: u32,
+    pub height: u32,
+    // 4 bytes per pixel in R, G, B, A order
+    pub rgba: Vec<u8>,
+}
+
+// Copies the client area from the screen: composition visuals are not rendered into the
+// window's own device context. So the window should be visible and not covered by other
+// windows. Not available in headless mode
+pub fn capture_window() -> windows::Result<WindowImage> {
+    let (position, size) =
+        window_client_rect().ok_or_else(winrt_error("Window has no visible client area"))?;
+    let (width, height) = (size.width, size.height);
+    let mut rgba = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        let screen_dc = GetDC(HWND(0));
+        let memory_dc = CreateCompatibleDC(screen_dc);
+        let bitmap = CreateCompatibleBitmap(screen_dc, width as i32, height as i32);
+        let old_bitmap = SelectObject(memory_dc, bitmap);
+        let copied = BitBlt(
+            memory_dc,
+            0,
+            0,
+            width as i32,
+            height as i32,
+            screen_dc,
+            position.x,
+            position.y,
+            ROP_CODE::SRCCOPY,
+        );
+        SelectObject(memory_dc, old_bitmap);
+        let mut info = BITMAPINFO::default();
+        info.bmiHeader = BITMAPINFOHEADER {
+            biSize: size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            // Negative height requests top-down rows
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            // BI_RGB, uncompressed
+            biCompression: 0,
+            ..Default::default()
+        };
+        let lines = if copied.as_bool() {
+            GetDIBits(
+                memory_dc,
+                bitmap,
+                0,
+                height,
+                rgba.as_mut_ptr() as *mut c_void,
+                &mut info,
+                DIB_USAGE::DIB_RGB_COLORS,
+            )
+        } else {
+            0
+        };
+        DeleteObject(bitmap);
+        DeleteDC(memory_dc);
+        ReleaseDC(HWND(0), screen_dc);
+        if lines != height as i32 {
+            return Err(winrt_error("Failed to copy window content from the screen")());
+        }
+    }
+    // GDI gives BGRX pixels
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+        pixel[3] = 0xFF;
+    }
+    Ok(WindowImage {
+        width,
+        height,
+        rgba,
+    })
+}
+
+impl WindowImage {
+    // Part of the image from left top to right bottom corner, clamped to the image size
+    pub fn crop(&self, left: u32, top: u32, right: u32, bottom: u32) -> WindowImage {
+        let (right, bottom) = (right.min(self.width), bottom.min(self.height));
+        let (left, top) = (left.min(right), top.min(bottom));
+        let width = right - left;
+        let mut rgba = Vec::with_capacity((width * (bottom - top) * 4) as usize);
+        for y in top..bottom {
+            let row = ((y * self.width + left) * 4) as usize;
+            rgba.extend_from_slice(&self.rgba[row..row + (width * 4) as usize]);
+        }
+        WindowImage {
+            width,
+            height: bottom - top,
+            rgba,
+        }
+    }
+
+    // Image is put as bitmap which can be pasted into chats and image editors
+    pub fn copy_to_clipboard(&self) -> windows::Result<()> {
+        let header = BITMAPINFOHEADER {
+            biSize: size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: self.width as i32,
+            // Bottom-up rows, not all applications accept top-down bitmaps
+            biHeight: self.height as i32,
+            biPlanes: 1,
+            biBitCount: 32,
+            // BI_RGB, uncompressed
+            biCompression: 0,
+            biSizeImage: self.rgba.len() as u32,
+            ..Default::default()
+        };
+        let header_size = size_of::<BITMAPINFOHEADER>();
+        let row_size = (self.width * 4) as usize;
+        unsafe {
+            if !OpenClipboard(window_hwnd()).as_bool() {
+                return Err(winrt_error("Failed to open clipboard")());
+            }
+            let memory = GlobalAlloc(
+                GLOBAL_ALLOC_FLAGS::GMEM_MOVEABLE,
+                header_size + self.rgba.len(),
+            );
+            let data = GlobalLock(memory) as *mut u8;
+            let copied = !data.is_null();
+            if copied {
+                ptr::copy_nonoverlapping(
+                    &header as *const BITMAPINFOHEADER as *const u8,
+                    data,
+                    header_size,
+                );
+                let pixels = data.add(header_size);
+                for (y, row) in self.rgba.chunks_exact(row_size).rev().enumerate() {
+                    for (x, pixel) in row.chunks_exact(4).enumerate() {
+                        let target = pixels.add(y * row_size + x * 4);
+                        *target = pixel[2];
+                        *target.add(1) = pixel[1];
+                        *target.add(2) = pixel[0];
+                        *target.add(3) = pixel[3];
+                    }
+                }
+                GlobalUnlock(memory);
+            }
+            // Clipboard owns the memory after successful SetClipboardData
+            let set = copied
+                && EmptyClipboard().as_bool()
+                && SetClipboardData(CF_DIB, HANDLE(memory)).0 != 0;
+            if !set {
+                GlobalFree(memory);
+            }
+            CloseClipboard();
+            if set {
+                Ok(())
+            } else {
+                Err(winrt_error("Failed to copy image to clipboard")())
+            }
+        }
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> windows::Result<()> {
+        let file = File::create(path).map_err(|e| winrt_error(e)())?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| winrt_error(e)())?;
+        writer
+            .write_image_data(&self.rgba)
+            .map_err(|e| winrt_error(e)())
+    }
+}
+
+pub fn capture_window_to_png(path: impl AsRef<Path>) -> windows::Result<()> {
+    capture_window()?.save_png(path)
+}
+
+// Offset of the visual relative to its ancestor, None if it or any visual between them
+// is hidden
+pub(crate) fn visual_offset(
+    visual: &ContainerVisual,
+    origin: &ContainerVisual,
+) -> windows::Result<Option<Vector2>> {
+    let mut offset = Vector2 { X: 0., Y: 0. };
+    let mut current = visual.clone();
+    while &current != origin {
+        if !current.IsVisible()? {
+            return Ok(None);
+        }
+        let current_offset = current.Offset()?;
+        offset.X += current_offset.X;
+        offset.Y += current_offset.Y;
+        current = current.Parent()?;
+    }
+    Ok(Some(offset))
+}
+
+// Offset of the visual from another one in the same window, neither has to contain the
+// other. None if either is hidden
+pub fn relative_offset(
+    visual: &ContainerVisual,
+    origin: &ContainerVisual,
+) -> windows::Result<Option<Vector2>> {
+    let root = root_visual();
+    match (visual_offset(visual, &root)?, visual_offset(origin, &root)?) {
+        (Some(offset), Some(origin_offset)) => Ok(Some(Vector2 {
+            X: offset.X - origin_offset.X,
+            Y: offset.Y - origin_offset.Y,
+        })),
+        _ => Ok(None),
+    }
+}
+
+// Part of the window covered by given visuals, e.g. to capture a group of panels
+pub fn capture_visuals(visuals: &[ContainerVisual]) -> windows::Result<WindowImage> {
+    let origin = root_visual();
+    let mut bounds: Option<(Vector2, Vector2)> = None;
+    for visual in visuals {
+        if let Some(offset) = visual_offset(visual, &origin)? {
+            let size = visual.Size()?;
+            let end = Vector2 {
+                X: offset.X + size.X,
+                Y: offset.Y + size.Y,
+            };
+            bounds = Some(match bounds {
+                Some((start, bounds_end)) => (
+                    Vector2 {
+                        X: start.X.min(offset.X),
+                        Y: start.Y.min(offset.Y),
+                    },
+                    Vector2 {
+                        X: bounds_end.X.max(end.X),
+                        Y: bounds_end.Y.max(end.Y),
+                    },
+                ),
+                None => (offset, end),
+            });
+        }
+    }
+    let (start, end) = bounds.ok_or_else(winrt_error("Nothing to capture"))?;
+    let image = capture_window()?;
+    Ok(image.crop(
+        start.X.max(0.) as u32,
+        start.Y.max(0.) as u32,
+        end.X.ceil() as u32,
+        end.Y.ceil() as u32,
+    ))
+}
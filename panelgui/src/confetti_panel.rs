@@ -0,0 +1,316 @@
+use std::{
+    any::Any,
+    f32::consts::PI,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bindings::Windows::{
+    Foundation::{
+        Numerics::{Vector2, Vector3},
+        TimeSpan,
+    },
+    UI::{
+        Color,
+        Composition::{ContainerVisual, SpriteVisual},
+    },
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelEvent, PanelHandle},
+};
+
+#[derive(Copy, Clone)]
+pub struct ConfettiPanelHandle {
+    id: usize,
+}
+
+impl Handle for ConfettiPanelHandle {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl PanelHandle<ConfettiPanel> for ConfettiPanelHandle {}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color {
+        A: 0xFF,
+        R: r,
+        G: g,
+        B: b,
+    }
+}
+
+const DEFAULT_COLORS: [Color; 6] = [
+    rgb(0xED, 0xC2, 0x2E),
+    rgb(0xF6, 0x5E, 0x3B),
+    rgb(0x3B, 0x9C, 0xF6),
+    rgb(0x5C, 0xC9, 0x6B),
+    rgb(0xC9, 0x5C, 0xC4),
+    rgb(0xFF, 0xFF, 0xFF),
+];
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct ConfettiParams {
+    #[builder(default = "{60}")]
+    particle_count: usize,
+    #[builder(default = "{Duration::from_millis(1500)}")]
+    duration: Duration,
+    // Particle length relative to the smaller side of the panel
+    #[builder(default = "{0.03}")]
+    particle_size: f32,
+    #[builder(default = "{DEFAULT_COLORS.to_vec()}")]
+    colors: Vec<Color>,
+}
+
+impl ConfettiParamsBuilder {
+    pub fn create(self) -> windows::Result<ConfettiPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<ConfettiPanel> {
+        match self.build() {
+            Ok(params) => Ok(ConfettiPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+}
+
+struct Particle {
+    visual: SpriteVisual,
+    expires: Instant,
+}
+
+// Transparent layer showing bursts of confetti, usually stacked over other content with
+// input_transparent cell. Particles are animated by the compositor, the panel only
+// removes them by timer when their animations end
+pub struct ConfettiPanel {
+    context: FrameworkContext,
+    id: usize,
+    params: ConfettiParams,
+    visual: ContainerVisual,
+    particles: Vec<Particle>,
+    // State of xorshift generator, quality of randomness doesn't matter here
+    seed: u64,
+}
+
+impl ConfettiPanel {
+    pub fn new(context: &FrameworkContext, params: ConfettiParams) -> windows::Result<Self> {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64)
+            | 1;
+        Ok(Self {
+            context: context.clone(),
+            id: context.next_id()?,
+            params,
+            visual: context.compositor()?.CreateContainerVisual()?,
+            particles: Vec::new(),
+            seed,
+        })
+    }
+
+    pub fn handle(&self) -> ConfettiPanelHandle {
+        ConfettiPanelHandle { id: self.id }
+    }
+
+    // In range [0, 1)
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Particles fly out of the center in random directions, then fall down and fade out.
+    // Nothing is shown when animations are disabled
+    pub fn burst(&mut self) -> windows::Result<()> {
+        let size = self.visual.Size()?;
+        if !self.context.animation_settings()?.enabled || size.X <= 0. || size.Y <= 0. {
+            return Ok(());
+        }
+        let compositor = self.context.compositor()?;
+        let length = size.X.min(size.Y) * self.params.particle_size;
+        let duration = TimeSpan {
+            Duration: (self.params.duration.as_nanos() / 100) as i64,
+        };
+        let expires = Instant::now() + self.params.duration;
+        let origin = Vector3 {
+            X: size.X / 2.,
+            Y: size.Y / 2.,
+            Z: 0.,
+        };
+        for index in 0..self.params.particle_count {
+            let visual = compositor.CreateSpriteVisual()?;
+            visual.SetSize(Vector2 {
+                X: length,
+                Y: length / 2.,
+            })?;
+            visual.SetCenterPoint(Vector3 {
+                X: length / 2.,
+                Y: length / 4.,
+                Z: 0.,
+            })?;
+            if !self.params.colors.is_empty() {
+                let color = self.params.colors[index % self.params.colors.len()].clone();
+                visual.SetBrush(compositor.CreateColorBrushWithColor(color)?)?;
+            }
+
+            let angle = self.random() * 2. * PI;
+            let distance = (0.2 + 0.3 * self.random()) * size.Y;
+            let peak = Vector3 {
+                X: origin.X + angle.cos() * distance,
+                Y: origin.Y + angle.sin() * distance - size.Y * 0.1,
+                Z: 0.,
+            };
+            let fall = Vector3 {
+                X: peak.X + angle.cos() * distance * 0.3,
+                Y: peak.Y + size.Y * 0.5,
+                Z: 0.,
+            };
+            let offset_animation = compositor.CreateVector3KeyFrameAnimation()?;
+            offset_animation.InsertKeyFrame(0.0, origin.clone())?;
+            offset_animation.InsertKeyFrame(0.3, peak)?;
+            offset_animation.InsertKeyFrame(1.0, fall.clone())?;
+            offset_animation.SetDuration(&duration)?;
+
+            let rotation_animation = compositor.CreateScalarKeyFrameAnimation()?;
+            rotation_animation.InsertKeyFrame(0.0, 0.)?;
+            rotation_animation.InsertKeyFrame(1.0, self.random() * 1440. - 720.)?;
+            rotation_animation.SetDuration(&duration)?;
+
+            let opacity_animation = compositor.CreateScalarKeyFrameAnimation()?;
+            opacity_animation.InsertKeyFrame(0.0, 1.)?;
+            opacity_animation.InsertKeyFrame(0.7, 1.)?;
+            opacity_animation.InsertKeyFrame(1.0, 0.)?;
+            opacity_animation.SetDuration(&duration)?;
+
+            // Final values stay if animation is interrupted
+            visual.SetOffset(fall)?;
+            visual.SetOpacity(0.)?;
+            self.visual.Children()?.InsertAtTop(visual.clone())?;
+            visual.StartAnimation("Offset", offset_animation)?;
+            visual.StartAnimation("RotationAngleInDegrees", rotation_animation)?;
+            visual.StartAnimation("Opacity", opacity_animation)?;
+            self.particles.push(Particle { visual, expires });
+        }
+        self.context.set_timer(self.id, self.params.duration)?;
+        Ok(())
+    }
+
+    fn remove_expired(&mut self) -> windows::Result<()> {
+        let now = Instant::now();
+        let children = self.visual.Children()?;
+        for particle in &self.particles {
+            if particle.expires <= now {
+                children.Remove(&particle.visual)?;
+            }
+        }
+        self.particles.retain(|particle| particle.expires > now);
+        if self.particles.is_empty() {
+            self.context.kill_timer(self.id)?;
+        }
+        Ok(())
+    }
+}
+
+impl Panel for ConfettiPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id {
+            Some(self.as_any_mut())
+        } else {
+            None
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.context.kill_timer(self.id)
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        Ok(Vector2 { X: 0., Y: 0. })
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        if timer_id == self.id {
+            self.remove_expired()?;
+        }
+        Ok(())
+    }
+
+    fn on_focus_changed(&mut self, _focused: bool) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        _button: MouseButton,
+        _state: ElementState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        _input: KeyboardInput,
+        _modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, _input: GamepadInput) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
+        Ok(())
+    }
+}
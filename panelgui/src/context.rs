@@ -0,0 +1,106 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use winit::window::WindowId;
+
+use crate::{
+    globals::{winrt_error, Globals, RootPanel},
+    window_host::WindowParams,
+};
+
+thread_local! {
+    // Context used by compat free functions like compositor() or send_panel_event() and
+    // by builders' create(). Each thread has its own, so tests running in parallel don't
+    // see each other
+    static CURRENT_CONTEXT: RefCell<Option<FrameworkContext>> = RefCell::new(None);
+}
+
+// Framework state: devices, window hosts, event queue and root panels of all windows.
+// It's cheap to clone, clones share the state. Panels get it at construction through
+// builders' create_in() and keep it, so application which embeds panelgui passes its
+// context explicitly instead of relying on the current one
+#[derive(Clone)]
+pub struct FrameworkContext {
+    globals: Rc<RefCell<Globals>>,
+    // Root panels are kept separately from globals because panels access globals while
+    // processing events. Each one is borrowed on its own, so a panel of one window may
+    // open or close another window
+    roots: Rc<RefCell<HashMap<WindowId, Rc<RefCell<RootPanel>>>>>,
+}
+
+impl FrameworkContext {
+    // Creates the main window with empty root panel
+    pub fn new(params: WindowParams) -> windows::Result<Self> {
+        let context = Self {
+            globals: Rc::new(RefCell::new(Globals::new(params)?)),
+            roots: Rc::new(RefCell::new(HashMap::new())),
+        };
+        Globals::init_main_root(&context)?;
+        Ok(context)
+    }
+
+    // None also while thread local storage is being destroyed
+    pub fn current() -> Option<Self> {
+        CURRENT_CONTEXT
+            .try_with(|current| current.borrow().clone())
+            .ok()
+            .flatten()
+    }
+
+    // Returns the context which was current before
+    pub fn make_current(&self) -> Option<Self> {
+        CURRENT_CONTEXT.with(|current| current.borrow_mut().replace(self.clone()))
+    }
+
+    // Fails instead of panicking if globals are already borrowed up the stack
+    pub(crate) fn with<F, T>(&self, f: F) -> windows::Result<T>
+    where
+        F: FnOnce(&mut Globals) -> windows::Result<T>,
+    {
+        let mut globals = self
+            .globals
+            .try_borrow_mut()
+            .map_err(|_| winrt_error("Globals are busy")())?;
+        f(&mut globals)
+    }
+
+    pub(crate) fn try_with<F, T>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut Globals) -> T,
+    {
+        self.globals
+            .try_borrow_mut()
+            .ok()
+            .map(|mut globals| f(&mut globals))
+    }
+
+    pub(crate) fn root(&self, window_id: WindowId) -> Option<Rc<RefCell<RootPanel>>> {
+        self.roots.borrow().get(&window_id).cloned()
+    }
+
+    pub(crate) fn has_root(&self, window_id: WindowId) -> bool {
+        self.roots.borrow().contains_key(&window_id)
+    }
+
+    pub(crate) fn insert_root(&self, window_id: WindowId, root_panel: RootPanel) {
+        self.roots
+            .borrow_mut()
+            .insert(window_id, Rc::new(RefCell::new(root_panel)));
+    }
+
+    pub(crate) fn remove_root(&self, window_id: WindowId) {
+        self.roots.borrow_mut().remove(&window_id);
+    }
+}
+
+pub(crate) fn restore_current_context(previous: Option<FrameworkContext>) {
+    match previous {
+        Some(previous) => {
+            previous.make_current();
+        }
+        None => CURRENT_CONTEXT.with(|current| *current.borrow_mut() = None),
+    }
+}
+
+pub(crate) fn current_context() -> windows::Result<FrameworkContext> {
+    FrameworkContext::current().ok_or_else(winrt_error("Framework context not initialized"))
+}
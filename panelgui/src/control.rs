@@ -1,7 +1,13 @@
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode};
 
 use crate::{
-    globals::send_panel_event,
+    gamepad::{GamepadButton, GamepadInput},
+    globals::{active_focus_scope, send_panel_event, set_focused_control},
     panel::{Handle, Panel, PanelEvent},
 };
 
@@ -29,6 +35,10 @@ pub trait Control: Panel {
     fn on_clear_focus(&mut self) -> windows::Result<()> {
         Ok(())
     }
+    // Performs control action as if it was clicked, used for default and cancel controls
+    fn on_activate(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
     fn as_panel(&self) -> &dyn Panel;
     fn set_focus_to_next(&self) -> windows::Result<()> {
         send_panel_event(self.id(), ControlEvent::FocusNext)
@@ -51,20 +61,109 @@ pub trait ControlHandle: Handle {
     fn as_control<'a>(&self, root_panel: &'a mut dyn Panel) -> Option<&'a mut dyn Control>;
 }
 
-type ControlHandles = Vec<Box<dyn ControlHandle>>;
+enum ControlEntry {
+    Control(Rc<dyn ControlHandle>),
+    // Controls of nested panel's manager, placed in traversal order of the parent
+    Manager(Rc<RefCell<Vec<ControlEntry>>>),
+}
+
+fn flatten_controls(entries: &[ControlEntry], controls: &mut Vec<Rc<dyn ControlHandle>>) {
+    for entry in entries {
+        match entry {
+            ControlEntry::Control(h) => controls.push(h.clone()),
+            ControlEntry::Manager(entries) => flatten_controls(&entries.borrow(), controls),
+        }
+    }
+}
 
 pub struct ControlManager {
-    controls: ControlHandles,
+    entries: Rc<RefCell<Vec<ControlEntry>>>,
+    // Manager is added to parent one, which navigates focus through controls of both
+    attached: Rc<Cell<bool>>,
+    // Id of modal panel owning focus scope, None for regular panels
+    scope: Option<usize>,
+    // Controls activated by Enter and Escape when focused control doesn't handle them
+    default_control: Option<usize>,
+    cancel_control: Option<usize>,
 }
 
 impl ControlManager {
     pub fn new() -> Self {
         ControlManager {
-            controls: Vec::new(),
+            entries: Rc::new(RefCell::new(Vec::new())),
+            attached: Rc::new(Cell::new(false)),
+            scope: None,
+            default_control: None,
+            cancel_control: None,
+        }
+    }
+    // Manager of modal panel, active only while its scope is on top of the scope stack
+    pub fn with_focus_scope(owner_id: usize) -> Self {
+        ControlManager {
+            entries: Rc::new(RefCell::new(Vec::new())),
+            attached: Rc::new(Cell::new(false)),
+            scope: Some(owner_id),
+            default_control: None,
+            cancel_control: None,
+        }
+    }
+    fn is_active(&self) -> bool {
+        if self.attached.get() {
+            return false;
+        }
+        match active_focus_scope() {
+            Some(owner_id) => self.scope == Some(owner_id),
+            None => self.scope.is_none(),
         }
     }
     pub fn add_control<T: ControlHandle + 'static>(&mut self, control_handle: T) {
-        self.controls.push(Box::new(control_handle));
+        self.entries
+            .borrow_mut()
+            .push(ControlEntry::Control(Rc::new(control_handle)));
+    }
+    // Controls of nested panel's manager take part in Tab order of this one at the current
+    // position. Nested manager then leaves focus navigation to this one
+    pub fn add_manager(&mut self, manager: &ControlManager) {
+        manager.attached.set(true);
+        self.entries
+            .borrow_mut()
+            .push(ControlEntry::Manager(manager.entries.clone()));
+    }
+    fn controls(&self) -> Vec<Rc<dyn ControlHandle>> {
+        let mut controls = Vec::new();
+        flatten_controls(&self.entries.borrow(), &mut controls);
+        controls
+    }
+    pub fn set_default_control<T: ControlHandle>(&mut self, control_handle: &T) {
+        self.default_control = Some(control_handle.id());
+    }
+    pub fn set_cancel_control<T: ControlHandle>(&mut self, control_handle: &T) {
+        self.cancel_control = Some(control_handle.id());
+    }
+    // Returns false if there is no default control
+    pub fn activate_default(&mut self, root_panel: &mut dyn Panel) -> windows::Result<bool> {
+        self.activate(root_panel, self.default_control)
+    }
+    // Returns false if there is no cancel control
+    pub fn activate_cancel(&mut self, root_panel: &mut dyn Panel) -> windows::Result<bool> {
+        self.activate(root_panel, self.cancel_control)
+    }
+    fn activate(
+        &self,
+        root_panel: &mut dyn Panel,
+        panel_id: Option<usize>,
+    ) -> windows::Result<bool> {
+        for h in &self.controls() {
+            if Some(h.id()) == panel_id {
+                if let Some(c) = h.as_control(root_panel) {
+                    if c.is_enabled()? {
+                        c.on_activate()?;
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+        Ok(false)
     }
 
     pub fn process_panel_event(
@@ -72,7 +171,10 @@ impl ControlManager {
         panel_event: &mut PanelEvent,
         root_panel: &mut dyn Panel,
     ) -> windows::Result<bool> {
-        for h in &self.controls {
+        if self.attached.get() {
+            return Ok(false);
+        }
+        for h in &self.controls() {
             if h.id() == panel_event.panel_id {
                 if let Some(data) = panel_event.data.take() {
                     match data.downcast::<ControlEvent>() {
@@ -109,26 +211,94 @@ impl ControlManager {
     pub fn process_keyboard_input(
         &mut self,
         input: KeyboardInput,
+        modifiers: ModifiersState,
         root_panel: &mut dyn Panel,
     ) -> windows::Result<bool> {
-        // TODO: process Shift-Tab
+        if !self.is_active() {
+            return Ok(false);
+        }
         if input.state == ElementState::Pressed {
-            if let Some(virtual_keycode) = input.virtual_keycode {
-                if virtual_keycode == VirtualKeyCode::Tab {
+            match input.virtual_keycode {
+                Some(VirtualKeyCode::Tab) => {
                     if let Some(panel_id) = self.get_focused_panel_id(root_panel)? {
-                        self.focus_next(root_panel, panel_id)?;
+                        if modifiers.shift() {
+                            self.focus_prev(root_panel, panel_id)?;
+                        } else {
+                            self.focus_next(root_panel, panel_id)?;
+                        }
+                    } else if modifiers.shift() {
+                        if let Some(panel_id) = self.get_last_enabled_panel_id(root_panel)? {
+                            self.focus_set(root_panel, panel_id)?;
+                        }
                     } else if let Some(panel_id) = self.get_first_enabled_panel_id(root_panel)? {
                         self.focus_set(root_panel, panel_id)?;
                     }
                     return Ok(true);
                 }
+                // Arrows move focus only when some control is already focused
+                Some(VirtualKeyCode::Left) | Some(VirtualKeyCode::Up) => {
+                    if let Some(panel_id) = self.get_focused_panel_id(root_panel)? {
+                        self.focus_prev(root_panel, panel_id)?;
+                        return Ok(true);
+                    }
+                }
+                Some(VirtualKeyCode::Right) | Some(VirtualKeyCode::Down) => {
+                    if let Some(panel_id) = self.get_focused_panel_id(root_panel)? {
+                        self.focus_next(root_panel, panel_id)?;
+                        return Ok(true);
+                    }
+                }
+                Some(VirtualKeyCode::Return) => {
+                    if self.activate_default(root_panel)? {
+                        return Ok(true);
+                    }
+                    // Modal scope doesn't let navigation keys fall through to panels below
+                    return Ok(self.scope.is_some());
+                }
+                Some(VirtualKeyCode::Escape) => {
+                    if self.activate_cancel(root_panel)? {
+                        return Ok(true);
+                    }
+                    return Ok(self.scope.is_some());
+                }
+                _ => {}
             }
         }
         Ok(false)
     }
 
+    pub fn process_gamepad_input(
+        &mut self,
+        input: GamepadInput,
+        root_panel: &mut dyn Panel,
+    ) -> windows::Result<bool> {
+        if !self.is_active() {
+            return Ok(false);
+        }
+        if input.state == ElementState::Pressed {
+            let forward = match input.button {
+                GamepadButton::RightShoulder | GamepadButton::Down | GamepadButton::Right => true,
+                GamepadButton::LeftShoulder | GamepadButton::Up | GamepadButton::Left => false,
+                GamepadButton::A => return self.activate_default(root_panel),
+                GamepadButton::B => return self.activate_cancel(root_panel),
+                _ => return Ok(false),
+            };
+            if let Some(panel_id) = self.get_focused_panel_id(root_panel)? {
+                if forward {
+                    self.focus_next(root_panel, panel_id)?;
+                } else {
+                    self.focus_prev(root_panel, panel_id)?;
+                }
+            } else if let Some(panel_id) = self.get_first_enabled_panel_id(root_panel)? {
+                self.focus_set(root_panel, panel_id)?;
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
     fn get_focused_panel_id(&self, root_panel: &mut dyn Panel) -> windows::Result<Option<usize>> {
-        for h in &self.controls {
+        for h in &self.controls() {
             if let Some(c) = h.as_control(root_panel) {
                 if c.is_enabled()? && c.is_focused()? {
                     return Ok(Some(c.id()));
@@ -142,7 +312,21 @@ impl ControlManager {
         &self,
         root_panel: &mut dyn Panel,
     ) -> windows::Result<Option<usize>> {
-        for h in &self.controls {
+        for h in &self.controls() {
+            if let Some(c) = h.as_control(root_panel) {
+                if c.is_enabled()? {
+                    return Ok(Some(c.id()));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn get_last_enabled_panel_id(
+        &self,
+        root_panel: &mut dyn Panel,
+    ) -> windows::Result<Option<usize>> {
+        for h in self.controls().iter().rev() {
             if let Some(c) = h.as_control(root_panel) {
                 if c.is_enabled()? {
                     return Ok(Some(c.id()));
@@ -159,7 +343,7 @@ impl ControlManager {
         enable: bool,
     ) -> windows::Result<()> {
         let mut focus_next = false;
-        for h in &self.controls {
+        for h in &self.controls() {
             if let Some(c) = h.as_control(root_panel) {
                 if c.id() == panel_id {
                     if c.is_focused()? && !enable {
@@ -176,7 +360,8 @@ impl ControlManager {
     }
 
     fn focus_clear(&self, root_panel: &mut dyn Panel) -> windows::Result<()> {
-        for h in &self.controls {
+        set_focused_control(None);
+        for h in &self.controls() {
             if let Some(c) = h.as_control(root_panel) {
                 c.on_clear_focus()?;
             }
@@ -186,9 +371,10 @@ impl ControlManager {
 
     fn focus_set(&self, root_panel: &mut dyn Panel, panel_id: usize) -> windows::Result<()> {
         self.focus_clear(root_panel)?;
-        for h in &self.controls {
+        for h in &self.controls() {
             if let Some(c) = h.as_control(root_panel) {
                 if c.id() == panel_id {
+                    set_focused_control(Some(panel_id));
                     return c.on_set_focus();
                 }
             }
@@ -197,7 +383,7 @@ impl ControlManager {
     }
 
     fn focus_next_impl<'a>(
-        iter: impl Iterator<Item = &'a Box<dyn ControlHandle>> + Clone,
+        iter: impl Iterator<Item = &'a Rc<dyn ControlHandle>> + Clone,
         root_panel: &'a mut dyn Panel,
         panel_id: usize,
     ) -> windows::Result<()> {
@@ -207,6 +393,7 @@ impl ControlManager {
             if let Some(c) = h.as_control(root_panel) {
                 if found {
                     if c.is_enabled()? {
+                        set_focused_control(Some(c.id()));
                         return c.on_set_focus();
                     }
                 } else if c.id() == panel_id {
@@ -217,6 +404,7 @@ impl ControlManager {
         for h in iter_first {
             if let Some(c) = h.as_control(root_panel) {
                 if c.is_enabled()? {
+                    set_focused_control(Some(c.id()));
                     return c.on_set_focus();
                 }
             }
@@ -226,11 +414,11 @@ impl ControlManager {
 
     fn focus_next(&self, root_panel: &mut dyn Panel, panel_id: usize) -> windows::Result<()> {
         self.focus_clear(root_panel)?;
-        Self::focus_next_impl(self.controls.iter(), root_panel, panel_id)
+        Self::focus_next_impl(self.controls().iter(), root_panel, panel_id)
     }
 
     fn focus_prev(&self, root_panel: &mut dyn Panel, panel_id: usize) -> windows::Result<()> {
         self.focus_clear(root_panel)?;
-        Self::focus_next_impl(self.controls.iter().rev(), root_panel, panel_id)
+        Self::focus_next_impl(self.controls().iter().rev(), root_panel, panel_id)
     }
 }
@@ -0,0 +1,368 @@
+use std::{any::Any, fmt::Write, time::Duration};
+
+use bindings::{
+    Microsoft::Graphics::Canvas::{
+        Geometry::{CanvasFigureLoop, CanvasGeometry, CanvasPathBuilder},
+        Text::{CanvasHorizontalAlignment, CanvasVerticalAlignment},
+        UI::Composition::CanvasComposition,
+    },
+    Windows::{
+        Foundation::Numerics::Vector2,
+        Foundation::Size,
+        Graphics::DirectX::DirectXAlphaMode,
+        Graphics::DirectX::DirectXPixelFormat,
+        UI::{
+            Color, Colors,
+            Composition::{CompositionDrawingSurface, ContainerVisual, SpriteVisual},
+        },
+    },
+};
+use winit::event::{ElementState, Event, KeyboardInput, ModifiersState, MouseButton};
+
+use crate::{
+    background_panel::BackgroundParamsBuilder,
+    capture::visual_offset,
+    context::FrameworkContext,
+    gamepad::GamepadInput,
+    globals::pixel_size,
+    panel::{EmptyPanel, Panel, PanelEvent, PanelHandle},
+    ribbon_panel::{RibbonOrientation, RibbonPanel, RibbonParamsBuilder},
+    text_panel::{TextPanelHandle, TextParamsBuilder},
+};
+
+const DEBUG_FONT_SIZE: f32 = 12.;
+// Text column takes this part of the window width, outlines are drawn over all of it
+const DEBUG_TEXT_RATIO: f32 = 0.4;
+// In logical pixels
+const OUTLINE_STROKE_WIDTH: f32 = 1.;
+const OUTLINE_COLOR: Color = Color {
+    A: 0xFF,
+    R: 0xFF,
+    G: 0x00,
+    B: 0xFF,
+};
+
+// Events counted by the event loop since the last refresh of the debug panel
+#[derive(Default)]
+pub(crate) struct DebugCounters {
+    // Passes of the event loop after all pending events are processed
+    frames: usize,
+    window_events: usize,
+    panel_events: usize,
+}
+
+impl DebugCounters {
+    pub fn count<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::WindowEvent { .. } => self.window_events += 1,
+            Event::UserEvent(_) => self.panel_events += 1,
+            Event::MainEventsCleared => self.frames += 1,
+            _ => {}
+        }
+    }
+}
+
+// Rectangle of panel's visual relative to the root panel
+pub(crate) struct Outline {
+    offset: Vector2,
+    size: Vector2,
+}
+
+// Outlines of visible panels except the debug panel itself
+pub(crate) fn collect_outlines(
+    panel: &dyn Panel,
+    origin: &ContainerVisual,
+    skip_id: usize,
+    outlines: &mut Vec<Outline>,
+) -> windows::Result<()> {
+    if panel.id() == skip_id {
+        return Ok(());
+    }
+    let visual = panel.visual();
+    let offset = match visual_offset(&visual, origin)? {
+        Some(offset) => offset,
+        None => return Ok(()),
+    };
+    outlines.push(Outline {
+        offset,
+        size: visual.Size()?,
+    });
+    for child in panel.children() {
+        collect_outlines(child, origin, skip_id, outlines)?;
+    }
+    Ok(())
+}
+
+// Type name without module path and generic arguments
+fn short_type_name(name: &str) -> &str {
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+fn write_panel_tree(
+    text: &mut String,
+    panel: &dyn Panel,
+    skip_id: usize,
+    focused: Option<usize>,
+    depth: usize,
+) {
+    if panel.id() == skip_id {
+        return;
+    }
+    let _ = write!(
+        text,
+        "\n{:indent$}{} #{}",
+        "",
+        short_type_name(panel.type_name()),
+        panel.id(),
+        indent = depth * 2
+    );
+    if Some(panel.id()) == focused {
+        text.push_str(" *");
+    }
+    for child in panel.children() {
+        write_panel_tree(text, child, skip_id, focused, depth + 1);
+    }
+}
+
+// Counters are shown as rates per second over the refresh period
+pub(crate) fn debug_text(
+    root_panel: &dyn Panel,
+    skip_id: usize,
+    focused: Option<usize>,
+    counters: &DebugCounters,
+    period: Duration,
+) -> String {
+    let seconds = period.as_secs_f32().max(f32::EPSILON);
+    let rate = |count: usize| count as f32 / seconds;
+    let mut text = format!(
+        "Frames/s: {:.1}\nWindow events/s: {:.1}\nPanel events/s: {:.1}\nFocused: {}\n",
+        rate(counters.frames),
+        rate(counters.window_events),
+        rate(counters.panel_events),
+        focused.map_or("none".to_string(), |id| format!("#{}", id)),
+    );
+    write_panel_tree(&mut text, root_panel, skip_id, focused, 0);
+    text
+}
+
+// Diagnostic overlay toggled by Shift+F12: outlines of panel visuals, panel tree with
+// ids and the focused panel marked by "*", and event rates. Doesn't take input. The
+// content is updated by the event loop, as the panel can't inspect the tree it's in
+pub(crate) struct DebugPanel {
+    context: FrameworkContext,
+    id: usize,
+    visual: ContainerVisual,
+    outline_visual: SpriteVisual,
+    surface: Option<CompositionDrawingSurface>,
+    root_panel: RibbonPanel,
+    text_handle: TextPanelHandle,
+    outlines: Vec<Outline>,
+}
+
+impl DebugPanel {
+    pub fn new(context: &FrameworkContext) -> windows::Result<Self> {
+        let theme = context.theme()?;
+        let mut background = theme.background.clone();
+        background.A = 0xC0;
+        let text_panel = TextParamsBuilder::default()
+            .color(theme.text)
+            .horizontal_alignment(CanvasHorizontalAlignment::Left)
+            .vertical_alignment(CanvasVerticalAlignment::Top)
+            .font_size(DEBUG_FONT_SIZE)
+            .create_in(context)?;
+        let text_handle = text_panel.handle();
+        let text_column = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel_in(
+                context,
+                BackgroundParamsBuilder::default()
+                    .color(background)
+                    .create_in(context)?,
+            )?
+            .add_panel_in(context, text_panel)?
+            .create_in(context)?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel_with_ratio_in(context, EmptyPanel::new_in(context)?, 1. - DEBUG_TEXT_RATIO)?
+            .add_panel_with_ratio_in(context, text_column, DEBUG_TEXT_RATIO)?
+            .create_in(context)?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        let outline_visual = context.compositor()?.CreateSpriteVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        visual.Children()?.InsertAtTop(outline_visual.clone())?;
+        Ok(Self {
+            context: context.clone(),
+            id: context.next_id()?,
+            visual,
+            outline_visual,
+            surface: None,
+            root_panel,
+            text_handle,
+            outlines: Vec::new(),
+        })
+    }
+
+    pub fn update(&mut self, text: String, outlines: Vec<Outline>) -> windows::Result<()> {
+        self.text_handle.at(&mut self.root_panel)?.set_text(text)?;
+        self.outlines = outlines;
+        self.redraw_outlines()
+    }
+
+    fn resize_surface(&mut self) -> windows::Result<()> {
+        let size = pixel_size(&self.visual.Size()?);
+        if size.X > 0. && size.Y > 0. {
+            let surface = self
+                .context
+                .composition_graphics_device()?
+                .CreateDrawingSurface(
+                    Size {
+                        Width: size.X,
+                        Height: size.Y,
+                    },
+                    DirectXPixelFormat::B8G8R8A8UIntNormalized,
+                    DirectXAlphaMode::Premultiplied,
+                )?;
+            let brush = self.context.compositor()?.CreateSurfaceBrush()?;
+            brush.SetSurface(surface.clone())?;
+            self.surface = Some(surface);
+            self.outline_visual.SetSize(size)?;
+            self.outline_visual.SetBrush(brush)?;
+        }
+        self.redraw_outlines()
+    }
+
+    fn redraw_outlines(&self) -> windows::Result<()> {
+        if let Some(ref surface) = self.surface {
+            let ds = CanvasComposition::CreateDrawingSession(surface)?;
+            ds.Clear(Colors::Transparent()?)?;
+            if self.outlines.is_empty() {
+                return Ok(());
+            }
+            let path_builder = CanvasPathBuilder::new(self.context.canvas_device()?)?;
+            for outline in &self.outlines {
+                let (x, y) = (outline.offset.X, outline.offset.Y);
+                let (w, h) = (outline.size.X, outline.size.Y);
+                path_builder.BeginFigure(Vector2 { X: x, Y: y })?;
+                path_builder.AddLine(Vector2 { X: x + w, Y: y })?;
+                path_builder.AddLine(Vector2 { X: x + w, Y: y + h })?;
+                path_builder.AddLine(Vector2 { X: x, Y: y + h })?;
+                path_builder.EndFigure(CanvasFigureLoop::Closed)?;
+            }
+            let geometry = CanvasGeometry::CreatePath(path_builder)?;
+            ds.DrawGeometryWithColorAndStrokeWidth(
+                geometry,
+                OUTLINE_COLOR,
+                OUTLINE_STROKE_WIDTH * self.context.scale_factor()?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Panel for DebugPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            self.root_panel.find_panel(id)
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)?;
+        self.root_panel.on_init()
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.root_panel.on_close()
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        self.resize_surface()?;
+        self.root_panel.on_resize(size)
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        // Diagnostic panel shouldn't constrain the window
+        Ok(Vector2 { X: 0., Y: 0. })
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        self.root_panel.on_idle()
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        self.root_panel.on_timer(timer_id)
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        self.root_panel.on_focus_changed(focused)
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.surface = None;
+        self.outline_visual
+            .SetBrush(self.context.compositor()?.CreateColorBrush()?)?;
+        self.root_panel.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.resize_surface()?;
+        self.root_panel.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.resize_surface()?;
+        self.root_panel.on_device_lost()
+    }
+
+    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        _button: MouseButton,
+        _state: ElementState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        _input: KeyboardInput,
+        _modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, _input: GamepadInput) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        vec![&self.root_panel]
+    }
+}
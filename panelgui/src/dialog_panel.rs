@@ -0,0 +1,292 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use bindings::Windows::{Foundation::Numerics::Vector2, UI::Composition::ContainerVisual};
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::{
+    background_panel::BackgroundParamsBuilder,
+    button_panel::ButtonParamsBuilder,
+    context::{current_context, FrameworkContext},
+    control::ControlManager,
+    delegate_panel,
+    event_handlers::EventHandlers,
+    gamepad::{GamepadButton, GamepadInput},
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelEvent, PanelHandle},
+    ribbon_panel::RibbonOrientation,
+    ribbon_panel::RibbonPanel,
+    ribbon_panel::RibbonPanelHandle,
+    ribbon_panel::RibbonParamsBuilder,
+    ribbon_panel::{CellSizing, CellTransition, RibbonCellParamsBuilder},
+    text_panel::TextParamsBuilder,
+};
+
+// Dialog answers with value of pressed button, or None when dismissed by Escape without
+// cancel button or removed by someone else
+pub struct DialogPanelHandle<T> {
+    id: usize,
+    _value: PhantomData<T>,
+}
+
+impl<T> Handle for DialogPanelHandle<T> {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> PanelHandle<DialogPanel<T>, Option<T>>
+    for DialogPanelHandle<T>
+{
+}
+
+impl<T: Clone + PartialEq + 'static> DialogPanelHandle<T> {
+    // Waits for the answer and removes dialog from the stack ribbon it was pushed to.
+    // Context current at the call is used until the dialog is closed
+    pub async fn do_modal(self, host: RibbonPanelHandle) -> windows::Result<Option<T>> {
+        let context = current_context()?;
+        context.push_focus_scope(self.id())?;
+        let answer = loop {
+            let mut panel_event = context.expect_panel_event(self.id()).await;
+            if self.is_removed(&panel_event) {
+                context.pop_focus_scope(self.id())?;
+                return Ok(None);
+            }
+            if let Some(answer) = self.extract_event(&mut panel_event) {
+                break answer;
+            }
+        };
+        context.root_panel_with(|root_panel| {
+            host.at(root_panel)?.remove_panel(self.id())?;
+            Ok(())
+        })?;
+        context.pop_focus_scope(self.id())?;
+        Ok(answer)
+    }
+}
+
+// Pushes dialog on top of the stack ribbon and waits for the answer. Dialog takes
+// content_ratio part of the host and blocks input to everything below it
+pub async fn show_dialog<T: Clone + PartialEq + 'static>(
+    host: RibbonPanelHandle,
+    content_ratio: Vector2,
+    dialog: DialogPanel<T>,
+) -> windows::Result<Option<T>> {
+    let context = current_context()?;
+    let handle = dialog.handle();
+    let cell = RibbonCellParamsBuilder::default()
+        .panel(dialog)
+        .content_ratio(content_ratio)
+        .modal(true)
+        .transition(CellTransition::Scale)
+        .create_in(&context)?;
+    context.root_panel_with(|root_panel| host.at(root_panel)?.push_cell(cell))?;
+    handle.do_modal(host).await
+}
+
+// Shows dialog without blocking the caller, so it can be used from panel event
+// handlers. Callback gets the answer after the dialog is removed
+pub fn spawn_dialog<T, F>(
+    host: RibbonPanelHandle,
+    content_ratio: Vector2,
+    dialog: DialogPanel<T>,
+    callback: F,
+) -> windows::Result<()>
+where
+    T: Clone + PartialEq + 'static,
+    F: FnOnce(&mut dyn Panel, Option<T>) -> windows::Result<()> + 'static,
+{
+    let context = current_context()?;
+    context.clone().spawn(async move {
+        let answer = show_dialog(host, content_ratio, dialog).await?;
+        context.root_panel_with(|root_panel| callback(root_panel, answer))
+    })
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct DialogParams<T: Clone + PartialEq + 'static> {
+    #[builder(private, setter(name = "content_private"))]
+    content: Box<dyn Panel>,
+    // Height of content relative to the button row
+    #[builder(default = "{1.5}")]
+    content_weight: f32,
+    #[builder(default = "{Vec::new()}")]
+    buttons: Vec<(Cow<'static, str>, T)>,
+    // Value of the button pressed by Enter when no button is focused
+    #[builder(default = "{None}")]
+    default_button: Option<T>,
+    // Value of the button pressed by Escape, without it Escape dismisses the dialog
+    #[builder(default = "{None}")]
+    cancel_button: Option<T>,
+}
+
+impl<T: Clone + PartialEq + 'static> DialogParamsBuilder<T> {
+    pub fn create(self) -> windows::Result<DialogPanel<T>> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<DialogPanel<T>> {
+        match self.build() {
+            Ok(params) => Ok(DialogPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+    pub fn content(self, panel: impl Panel + 'static) -> Self {
+        let panel: Box<dyn Panel + 'static> = Box::new(panel);
+        self.content_private(panel)
+    }
+    pub fn add_button(mut self, text: impl Into<Cow<'static, str>>, value: T) -> Self {
+        if self.buttons.is_none() {
+            self.buttons = Some(Vec::new());
+        }
+        self.buttons.as_mut().unwrap().push((text.into(), value));
+        self
+    }
+}
+
+pub struct DialogPanel<T> {
+    context: FrameworkContext,
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    event_handlers: EventHandlers<DialogPanel<T>>,
+}
+
+impl<T: Clone + PartialEq + 'static> DialogPanel<T> {
+    pub fn new(context: &FrameworkContext, params: DialogParams<T>) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let background = BackgroundParamsBuilder::default()
+            .dialog(true)
+            .round_corners(true)
+            .shadow(true)
+            .create_in(context)?;
+        let mut ribbon_buttons =
+            RibbonParamsBuilder::default().orientation(RibbonOrientation::Horizontal);
+        let mut control_manager = ControlManager::with_focus_scope(id);
+        let mut event_handlers = EventHandlers::new();
+        for (text, value) in params.buttons {
+            let is_default = params.default_button.as_ref() == Some(&value);
+            let button = ButtonParamsBuilder::default()
+                .accessible_name(Some(text.clone()))
+                .panel(TextParamsBuilder::default().text(text).create_in(context)?)
+                .default_button(is_default)
+                .create_in(context)?;
+            let handle = button.handle();
+            // Buttons are as wide as their text, free space is shared between them
+            ribbon_buttons = ribbon_buttons.add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel(button)
+                    .sizing(CellSizing::Auto)
+                    .create_in(context)?,
+            );
+            control_manager.add_control(handle);
+            if is_default {
+                control_manager.set_default_control(&handle);
+            }
+            if params.cancel_button.as_ref() == Some(&value) {
+                control_manager.set_cancel_control(&handle);
+            }
+            event_handlers = event_handlers.on(&handle, move |panel: &mut Self, _| {
+                panel
+                    .context
+                    .send_panel_event(panel.id, Some(value.clone()))
+            });
+        }
+        let ribbon = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Vertical)
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel_box(params.content)
+                    .ratio(params.content_weight)
+                    .create_in(context)?,
+            )
+            .add_panel_in(context, ribbon_buttons.create_in(context)?)?
+            .create_in(context)?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel_in(context, background)?
+            .add_panel_in(context, ribbon)?
+            .create_in(context)?;
+
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            context: context.clone(),
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            event_handlers,
+        })
+    }
+    pub fn handle(&self) -> DialogPanelHandle<T> {
+        DialogPanelHandle {
+            id: self.id,
+            _value: PhantomData,
+        }
+    }
+    fn cancel(&mut self) -> windows::Result<()> {
+        if !self.control_manager.activate_cancel(&mut self.root_panel)? {
+            self.context.send_panel_event(self.id, None::<T>)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone + PartialEq + 'static> Panel for DialogPanel<T> {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: winit::event::KeyboardInput,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        // Checked before buttons, focused button would only lose focus on Escape
+        if input.state == ElementState::Pressed
+            && input.virtual_keycode == Some(VirtualKeyCode::Escape)
+        {
+            self.cancel()?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if input.button == GamepadButton::B && input.state == ElementState::Pressed {
+            self.cancel()?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        if !self.event_handlers.clone().dispatch(self, panel_event)? {
+            let _ = self
+                .control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+        }
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, on_resize, measure, lifecycle, on_mouse_move, on_mouse_input,
+        on_mouse_wheel, on_cursor_inside_changed, on_modifiers_changed, on_char);
+}
@@ -0,0 +1,587 @@
+use std::any::Any;
+
+use bindings::{
+    Microsoft::Graphics::Canvas::{
+        Text::CanvasHorizontalAlignment, Text::CanvasTextFormat, Text::CanvasTextLayout,
+        Text::CanvasVerticalAlignment, UI::Composition::CanvasComposition,
+    },
+    Windows::{
+        Foundation::Numerics::{Vector2, Vector3},
+        Foundation::{Size, TimeSpan},
+        Graphics::DirectX::DirectXAlphaMode,
+        Graphics::DirectX::DirectXPixelFormat,
+        UI::Composition::{AnimationIterationBehavior, CompositionDrawingSurface},
+        UI::{
+            Color, Colors,
+            Composition::{ContainerVisual, SpriteVisual},
+        },
+    },
+};
+use winit::{
+    event::{ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode},
+    window::CursorIcon,
+};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    control::{Control, ControlHandle},
+    gamepad::{GamepadButton, GamepadInput},
+    globals::{pixel_size, winrt_error},
+    panel::{Handle, Panel, PanelHandle},
+    text_panel::text_min_size,
+};
+
+const CARET_BLINK_PERIOD: TimeSpan = TimeSpan {
+    Duration: 10_000_000,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum EditPanelEvent {
+    Changed(String),
+    Submitted(String),
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct EditParams {
+    #[builder(default = "{String::new()}")]
+    text: String,
+    #[builder(default = "{true}")]
+    enabled: bool,
+    // Maximal number of characters
+    #[builder(default = "{usize::MAX}")]
+    max_length: usize,
+    #[builder(default = "{Colors::Black().unwrap()}")]
+    color: Color,
+    #[builder(default = "{Colors::LightSteelBlue().unwrap()}")]
+    selection_color: Color,
+    #[builder(default = "{2.}")]
+    font_scale: f32,
+}
+
+impl EditParamsBuilder {
+    pub fn create(self) -> windows::Result<EditPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<EditPanel> {
+        match self.build() {
+            Ok(params) => Ok(EditPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct EditPanelHandle(usize);
+
+impl Handle for EditPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<EditPanel, EditPanelEvent> for EditPanelHandle {}
+
+impl ControlHandle for EditPanelHandle {
+    fn as_control<'a>(&self, root_panel: &'a mut dyn Panel) -> Option<&'a mut dyn Control> {
+        self.at(root_panel).ok().map(|p| p as &mut dyn Control)
+    }
+}
+
+pub struct EditPanel {
+    context: FrameworkContext,
+    id: usize,
+    params: EditParams,
+    visual: ContainerVisual,
+    text_visual: SpriteVisual,
+    caret_visual: SpriteVisual,
+    surface: Option<CompositionDrawingSurface>,
+    // Caret and selection anchor positions are in characters
+    caret: usize,
+    anchor: Option<usize>,
+    focused: bool,
+    dragging: bool,
+    mouse_pos: Option<Vector2>,
+}
+
+impl EditPanel {
+    pub fn new(context: &FrameworkContext, params: EditParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let compositor = context.compositor()?;
+        let visual = compositor.CreateContainerVisual()?;
+        let text_visual = compositor.CreateSpriteVisual()?;
+        let caret_visual = compositor.CreateSpriteVisual()?;
+        caret_visual.SetBrush(compositor.CreateColorBrushWithColor(params.color.clone())?)?;
+        caret_visual.SetIsVisible(false)?;
+        visual.Children()?.InsertAtTop(text_visual.clone())?;
+        visual.Children()?.InsertAtTop(caret_visual.clone())?;
+        let caret = params.text.chars().count();
+        Ok(Self {
+            context: context.clone(),
+            id,
+            params,
+            visual,
+            text_visual,
+            caret_visual,
+            surface: None,
+            caret,
+            anchor: None,
+            focused: false,
+            dragging: false,
+            mouse_pos: None,
+        })
+    }
+    pub fn handle(&self) -> EditPanelHandle {
+        EditPanelHandle(self.id)
+    }
+    pub fn text(&self) -> &str {
+        &self.params.text
+    }
+    pub fn set_text<S: Into<String>>(&mut self, text: S) -> windows::Result<()> {
+        self.params.text = text.into();
+        self.caret = self.char_count();
+        self.anchor = None;
+        self.redraw()
+    }
+    pub fn select_all(&mut self) -> windows::Result<()> {
+        self.anchor = Some(0);
+        self.caret = self.char_count();
+        self.redraw()
+    }
+
+    fn char_count(&self) -> usize {
+        self.params.text.chars().count()
+    }
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.params
+            .text
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.params.text.len(), |(i, _)| i)
+    }
+    fn selection(&self) -> Option<(usize, usize)> {
+        match self.anchor {
+            Some(anchor) if anchor < self.caret => Some((anchor, self.caret)),
+            Some(anchor) if anchor > self.caret => Some((self.caret, anchor)),
+            _ => None,
+        }
+    }
+    fn delete_selection(&mut self) -> bool {
+        if let Some((start, end)) = self.selection() {
+            let range = self.byte_index(start)..self.byte_index(end);
+            self.params.text.replace_range(range, "");
+            self.caret = start;
+            self.anchor = None;
+            true
+        } else {
+            self.anchor = None;
+            false
+        }
+    }
+    fn move_caret(&mut self, pos: usize, select: bool) -> windows::Result<()> {
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.caret);
+            }
+        } else {
+            self.anchor = None;
+        }
+        self.caret = pos.min(self.char_count());
+        self.redraw()
+    }
+    fn text_changed(&mut self) -> windows::Result<()> {
+        self.redraw()?;
+        self.context
+            .send_panel_event(self.id, EditPanelEvent::Changed(self.params.text.clone()))
+    }
+    fn insert_char(&mut self, ch: char) -> windows::Result<()> {
+        self.delete_selection();
+        if self.char_count() < self.params.max_length {
+            let index = self.byte_index(self.caret);
+            self.params.text.insert(index, ch);
+            self.caret += 1;
+        }
+        self.text_changed()
+    }
+    fn backspace(&mut self) -> windows::Result<()> {
+        if !self.delete_selection() && self.caret > 0 {
+            let range = self.byte_index(self.caret - 1)..self.byte_index(self.caret);
+            self.params.text.replace_range(range, "");
+            self.caret -= 1;
+        }
+        self.text_changed()
+    }
+    fn delete(&mut self) -> windows::Result<()> {
+        if !self.delete_selection() && self.caret < self.char_count() {
+            let range = self.byte_index(self.caret)..self.byte_index(self.caret + 1);
+            self.params.text.replace_range(range, "");
+        }
+        self.text_changed()
+    }
+
+    fn padding(&self) -> windows::Result<f32> {
+        Ok(self.visual.Size()?.Y / 4.)
+    }
+    fn create_text_layout(&self, size: &Size) -> windows::Result<CanvasTextLayout> {
+        let text_format = CanvasTextFormat::new()?;
+        text_format.SetFontFamily("Arial")?;
+        text_format.SetFontSize(size.Height / self.params.font_scale)?;
+        let text_layout = CanvasTextLayout::Create(
+            self.context.canvas_device()?,
+            self.params.text.clone(),
+            text_format,
+            size.Width,
+            size.Height,
+        )?;
+        text_layout.SetVerticalAlignment(CanvasVerticalAlignment::Center)?;
+        text_layout.SetHorizontalAlignment(CanvasHorizontalAlignment::Left)?;
+        Ok(text_layout)
+    }
+    // Horizontal position of the caret placed before the character with given index
+    fn caret_x(&self, text_layout: &CanvasTextLayout, char_index: usize) -> windows::Result<f32> {
+        if char_index == 0 || self.params.text.is_empty() {
+            return Ok(0.);
+        }
+        // Text layout counts positions in UTF-16 units
+        let utf16_index: usize = self
+            .params
+            .text
+            .chars()
+            .take(char_index)
+            .map(|c| c.len_utf16())
+            .sum();
+        Ok(text_layout
+            .GetCaretPosition(utf16_index as i32 - 1, true)?
+            .X)
+    }
+    fn char_index_by_position(&self, position: &Vector2) -> windows::Result<usize> {
+        if let Some(ref surface) = self.surface {
+            let text_layout = self.create_text_layout(&surface.Size()?)?;
+            let x = position.X - self.padding()?;
+            let mut best = (0, f32::MAX);
+            for i in 0..=self.char_count() {
+                let distance = (self.caret_x(&text_layout, i)? - x).abs();
+                if distance < best.1 {
+                    best = (i, distance);
+                }
+            }
+            Ok(best.0)
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn resize_surface(&mut self) -> windows::Result<()> {
+        let size = self.visual.Size()?;
+        let padding = self.padding()?;
+        let surface_size = pixel_size(&Vector2 {
+            X: size.X - padding * 2.,
+            Y: size.Y,
+        });
+        self.text_visual.SetSize(surface_size.clone())?;
+        self.text_visual.SetOffset(Vector3 {
+            X: padding,
+            Y: 0.,
+            Z: 0.,
+        })?;
+        if surface_size.X > 0. && surface_size.Y > 0. {
+            let surface = self
+                .context
+                .composition_graphics_device()?
+                .CreateDrawingSurface(
+                    Size {
+                        Width: surface_size.X,
+                        Height: surface_size.Y,
+                    },
+                    DirectXPixelFormat::B8G8R8A8UIntNormalized,
+                    DirectXAlphaMode::Premultiplied,
+                )?;
+            let brush = self.context.compositor()?.CreateSurfaceBrush()?;
+            brush.SetSurface(surface.clone())?;
+            self.surface = Some(surface);
+            self.text_visual.SetBrush(brush)?;
+        } else {
+            self.surface = None;
+        }
+        Ok(())
+    }
+
+    fn redraw(&mut self) -> windows::Result<()> {
+        let surface = if let Some(ref surface) = self.surface {
+            surface.clone()
+        } else {
+            return Ok(());
+        };
+        let size = surface.Size()?;
+        let text_layout = self.create_text_layout(&size)?;
+        let caret_x = self.caret_x(&text_layout, self.caret)?;
+        {
+            let ds = CanvasComposition::CreateDrawingSession(&surface)?;
+            ds.Clear(Colors::Transparent()?)?;
+            if let Some((start, end)) = self.selection() {
+                let start_x = self.caret_x(&text_layout, start)?;
+                let end_x = self.caret_x(&text_layout, end)?;
+                ds.FillRectangleAtCoordsWithColor(
+                    start_x,
+                    size.Height / 5.,
+                    end_x - start_x,
+                    size.Height * 3. / 5.,
+                    self.params.selection_color.clone(),
+                )?;
+            }
+            let color = if self.params.enabled {
+                self.params.color.clone()
+            } else {
+                Colors::Gray()?
+            };
+            ds.DrawTextLayoutAtCoordsWithColor(&text_layout, 0., 0., color)?;
+            let underline_color = if self.focused {
+                Colors::Black()?
+            } else {
+                Colors::Gray()?
+            };
+            ds.FillRectangleAtCoordsWithColor(
+                0.,
+                size.Height * 4. / 5.,
+                size.Width,
+                1.,
+                underline_color,
+            )?;
+        }
+        self.caret_visual.SetSize(Vector2 {
+            X: 2.,
+            Y: size.Height * 3. / 5.,
+        })?;
+        self.caret_visual.SetOffset(Vector3 {
+            X: self.padding()? + caret_x,
+            Y: size.Height / 5.,
+            Z: 0.,
+        })?;
+        self.caret_visual
+            .SetIsVisible(self.focused && self.params.enabled)?;
+        self.restart_caret_blink()
+    }
+
+    // Caret is shown immediately after each change and then blinks
+    fn restart_caret_blink(&self) -> windows::Result<()> {
+        let animation = self.context.compositor()?.CreateScalarKeyFrameAnimation()?;
+        animation.InsertKeyFrame(0.0, 1.)?;
+        animation.InsertKeyFrame(0.49, 1.)?;
+        animation.InsertKeyFrame(0.5, 0.)?;
+        animation.InsertKeyFrame(0.99, 0.)?;
+        animation.InsertKeyFrame(1.0, 1.)?;
+        animation.SetDuration(CARET_BLINK_PERIOD)?;
+        animation.SetIterationBehavior(AnimationIterationBehavior::Forever)?;
+        self.caret_visual.StartAnimation("Opacity", animation)
+    }
+}
+
+impl Panel for EditPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            None
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.surface = None;
+        self.text_visual
+            .SetBrush(self.context.compositor()?.CreateColorBrush()?)
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        // Room for a few characters, text is scrolled to the caret
+        let min = text_min_size("", self.params.font_scale, self.context.scale_factor()?);
+        Ok(Vector2 {
+            X: min.Y * 4.,
+            Y: min.Y,
+        })
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        self.resize_surface()?;
+        self.redraw()
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.surface = None;
+        self.text_visual
+            .SetBrush(self.context.compositor()?.CreateColorBrush()?)
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        if self.surface.is_none() {
+            self.resize_surface()?;
+            self.redraw()?;
+        }
+        Ok(())
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.resize_surface()?;
+        self.redraw()
+    }
+
+    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
+        self.mouse_pos = Some(position.clone());
+        let size = self.visual.Size()?;
+        if position.X >= 0. && position.X < size.X && position.Y >= 0. && position.Y < size.Y {
+            self.context.set_cursor(CursorIcon::Text)?;
+        }
+        if self.dragging {
+            let pos = self.char_index_by_position(position)?;
+            self.move_caret(pos, true)?;
+        }
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        // Release may happen outside of the panel, so any mouse input stops dragging
+        self.dragging = false;
+        if !self.params.enabled || button != MouseButton::Left {
+            return Ok(false);
+        }
+        if state == ElementState::Pressed {
+            if let Some(position) = self.mouse_pos.clone() {
+                self.set_focus()?;
+                let pos = self.char_index_by_position(&position)?;
+                self.move_caret(pos, false)?;
+                self.dragging = true;
+            }
+        }
+        Ok(true)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        if !self.focused || !self.params.enabled || input.state != ElementState::Pressed {
+            return Ok(false);
+        }
+        if let Some(code) = input.virtual_keycode {
+            let select = modifiers.shift();
+            match code {
+                VirtualKeyCode::Escape => self.clear_focus()?,
+                VirtualKeyCode::Tab => {
+                    if modifiers.shift() {
+                        self.set_focus_to_prev()?;
+                    } else {
+                        self.set_focus_to_next()?;
+                    }
+                }
+                VirtualKeyCode::Return => self.context.send_panel_event(
+                    self.id,
+                    EditPanelEvent::Submitted(self.params.text.clone()),
+                )?,
+                VirtualKeyCode::Left => {
+                    let pos = match self.selection() {
+                        Some((start, _)) if !select => start,
+                        _ => self.caret.saturating_sub(1),
+                    };
+                    self.move_caret(pos, select)?;
+                }
+                VirtualKeyCode::Right => {
+                    let pos = match self.selection() {
+                        Some((_, end)) if !select => end,
+                        _ => self.caret + 1,
+                    };
+                    self.move_caret(pos, select)?;
+                }
+                VirtualKeyCode::Home => self.move_caret(0, select)?,
+                VirtualKeyCode::End => self.move_caret(self.char_count(), select)?,
+                VirtualKeyCode::Back => self.backspace()?,
+                VirtualKeyCode::Delete => self.delete()?,
+                VirtualKeyCode::A if modifiers.ctrl() => self.select_all()?,
+                _ => return Ok(false),
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn on_char(&mut self, ch: char) -> windows::Result<bool> {
+        if !self.focused || !self.params.enabled {
+            return Ok(false);
+        }
+        // Control characters like backspace are processed as keyboard input
+        if !ch.is_control() {
+            self.insert_char(ch)?;
+        }
+        Ok(true)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if self.focused && self.params.enabled && input.state == ElementState::Pressed {
+            match input.button {
+                GamepadButton::A => {
+                    self.context.send_panel_event(
+                        self.id,
+                        EditPanelEvent::Submitted(self.params.text.clone()),
+                    )?;
+                    return Ok(true);
+                }
+                GamepadButton::B => {
+                    self.clear_focus()?;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Control for EditPanel {
+    fn is_enabled(&self) -> windows::Result<bool> {
+        Ok(self.params.enabled)
+    }
+
+    fn is_focused(&self) -> windows::Result<bool> {
+        Ok(self.focused)
+    }
+
+    fn on_enable(&mut self, enable: bool) -> windows::Result<()> {
+        self.params.enabled = enable;
+        self.redraw()
+    }
+
+    fn on_set_focus(&mut self) -> windows::Result<()> {
+        self.focused = true;
+        self.redraw()
+    }
+
+    fn on_clear_focus(&mut self) -> windows::Result<()> {
+        self.focused = false;
+        self.anchor = None;
+        self.redraw()
+    }
+
+    fn as_panel(&self) -> &dyn Panel {
+        self
+    }
+}
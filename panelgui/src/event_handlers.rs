@@ -0,0 +1,99 @@
+use std::{any::Any, rc::Rc};
+
+use crate::{
+    panel::{PanelEvent, PanelHandle},
+    property::{PropertyChanged, PropertyHandle},
+};
+
+type EventHandler<P> = Box<dyn Fn(&mut P, &mut PanelEvent) -> windows::Result<bool>>;
+
+// Table of typed handlers for events of child panels, replaces chains of
+// extract_event calls in on_panel_event. Handlers are registered when the owner panel
+// is created; cloning is cheap, so the table can be cloned out of the owner before
+// dispatching to it
+pub struct EventHandlers<P> {
+    handlers: Rc<Vec<(usize, EventHandler<P>)>>,
+}
+
+impl<P> Clone for EventHandlers<P> {
+    fn clone(&self) -> Self {
+        Self {
+            handlers: self.handlers.clone(),
+        }
+    }
+}
+
+impl<P: 'static> EventHandlers<P> {
+    pub fn new() -> Self {
+        Self {
+            handlers: Rc::new(Vec::new()),
+        }
+    }
+
+    pub fn on<PanelType, PanelEventType, H, F>(mut self, handle: &H, f: F) -> Self
+    where
+        PanelType: Any,
+        PanelEventType: Any,
+        H: PanelHandle<PanelType, PanelEventType>,
+        F: Fn(&mut P, PanelEventType) -> windows::Result<()> + 'static,
+    {
+        let panel_id = handle.id();
+        let handler: EventHandler<P> = Box::new(move |panel, panel_event| {
+            if let Some(e) = panel_event.extract::<PanelEventType>(panel_id) {
+                f(panel, e)?;
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        });
+        if let Some(handlers) = Rc::get_mut(&mut self.handlers) {
+            handlers.push((panel_id, handler));
+        }
+        self
+    }
+
+    // Calls handler with new values of the property. Properties of the same type share
+    // event type, so the event is taken only if it's sent by the bound property
+    pub fn bind<T, F>(mut self, property: &PropertyHandle<T>, f: F) -> Self
+    where
+        T: Any,
+        F: Fn(&mut P, T) -> windows::Result<()> + 'static,
+    {
+        let owner_id = property.owner_id();
+        let property_id = property.id();
+        let handler: EventHandler<P> = Box::new(move |panel, panel_event| {
+            let bound = panel_event
+                .data
+                .as_ref()
+                .and_then(|data| data.downcast_ref::<PropertyChanged<T>>())
+                .map_or(false, |e| e.property_id == property_id);
+            if bound {
+                if let Some(e) = panel_event.extract::<PropertyChanged<T>>(owner_id) {
+                    f(panel, e.value)?;
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        });
+        if let Some(handlers) = Rc::get_mut(&mut self.handlers) {
+            handlers.push((owner_id, handler));
+        }
+        self
+    }
+
+    // Returns true if event was consumed by one of handlers
+    pub fn dispatch(&self, panel: &mut P, panel_event: &mut PanelEvent) -> windows::Result<bool> {
+        for (panel_id, handler) in self.handlers.iter() {
+            if *panel_id == panel_event.panel_id && handler(panel, panel_event)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl<P: 'static> Default for EventHandlers<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
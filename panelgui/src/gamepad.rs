@@ -0,0 +1,122 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use bindings::Windows::Gaming::Input::{Gamepad, GamepadButtons};
+use winit::event::ElementState;
+
+// Thumbstick deflection which is treated as D-pad press
+const THUMBSTICK_THRESHOLD: f64 = 0.5;
+// Gamepad doesn't generate window events, so it's polled while connected. Connection
+// itself is checked on each pass of the event loop
+const POLL_PERIOD: Duration = Duration::from_millis(16);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GamepadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    X,
+    Y,
+    LeftShoulder,
+    RightShoulder,
+    Menu,
+    View,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GamepadInput {
+    pub button: GamepadButton,
+    pub state: ElementState,
+}
+
+// Polls first connected gamepad and turns changes of its state into button press/release events
+pub(crate) struct GamepadPoller {
+    pressed: HashSet<GamepadButton>,
+    // None when no gamepad is connected
+    next_poll: Option<Instant>,
+}
+
+impl GamepadPoller {
+    pub fn new() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            next_poll: None,
+        }
+    }
+
+    // None if no gamepad is connected
+    fn read_pressed() -> windows::Result<Option<HashSet<GamepadButton>>> {
+        let mut pressed = HashSet::new();
+        let gamepads = Gamepad::Gamepads()?;
+        if gamepads.Size()? == 0 {
+            return Ok(None);
+        }
+        let reading = gamepads.GetAt(0)?.GetCurrentReading()?;
+        let buttons = [
+            (GamepadButtons::DPadUp, GamepadButton::Up),
+            (GamepadButtons::DPadDown, GamepadButton::Down),
+            (GamepadButtons::DPadLeft, GamepadButton::Left),
+            (GamepadButtons::DPadRight, GamepadButton::Right),
+            (GamepadButtons::A, GamepadButton::A),
+            (GamepadButtons::B, GamepadButton::B),
+            (GamepadButtons::X, GamepadButton::X),
+            (GamepadButtons::Y, GamepadButton::Y),
+            (GamepadButtons::LeftShoulder, GamepadButton::LeftShoulder),
+            (GamepadButtons::RightShoulder, GamepadButton::RightShoulder),
+            (GamepadButtons::Menu, GamepadButton::Menu),
+            (GamepadButtons::View, GamepadButton::View),
+        ];
+        for (flag, button) in buttons.iter() {
+            if reading.Buttons.0 & flag.0 != 0 {
+                pressed.insert(*button);
+            }
+        }
+        // Left thumbstick works as D-pad
+        if reading.LeftThumbstickY > THUMBSTICK_THRESHOLD {
+            pressed.insert(GamepadButton::Up);
+        } else if reading.LeftThumbstickY < -THUMBSTICK_THRESHOLD {
+            pressed.insert(GamepadButton::Down);
+        }
+        if reading.LeftThumbstickX > THUMBSTICK_THRESHOLD {
+            pressed.insert(GamepadButton::Right);
+        } else if reading.LeftThumbstickX < -THUMBSTICK_THRESHOLD {
+            pressed.insert(GamepadButton::Left);
+        }
+        Ok(Some(pressed))
+    }
+
+    pub fn poll(&mut self) -> windows::Result<Vec<GamepadInput>> {
+        let now = Instant::now();
+        if self.next_poll.map_or(false, |time| now < time) {
+            return Ok(Vec::new());
+        }
+        let pressed = Self::read_pressed()?;
+        self.next_poll = pressed.as_ref().map(|_| now + POLL_PERIOD);
+        let pressed = pressed.unwrap_or_default();
+        let mut inputs = Vec::new();
+        for button in self.pressed.difference(&pressed) {
+            inputs.push(GamepadInput {
+                button: *button,
+                state: ElementState::Released,
+            });
+        }
+        for button in pressed.difference(&self.pressed) {
+            inputs.push(GamepadInput {
+                button: *button,
+                state: ElementState::Pressed,
+            });
+        }
+        self.pressed = pressed;
+        Ok(inputs)
+    }
+
+    // Event loop should wake up at this time to poll connected gamepad
+    pub fn next_poll_time(&self) -> Option<Instant> {
+        self.next_poll
+    }
+}
@@ -2,38 +2,81 @@ use bindings::Windows::Foundation::Numerics::Vector2;
 use bindings::{
     Microsoft::Graphics::Canvas::{CanvasDevice, UI::Composition::CanvasComposition},
     Windows::{
+        Foundation::TypedEventHandler,
         System::DispatcherQueueController,
-        UI::Composition::{
-            CompositionGraphicsDevice, Compositor, ContainerVisual, Desktop::DesktopWindowTarget,
-        },
+        Win32::UI::WindowsAndMessaging::{IsZoomed, HWND},
+        UI::Composition::{CompositionGraphicsDevice, Compositor, ContainerVisual},
     },
 };
-use futures::executor::{LocalPool, LocalSpawner};
+use futures::{
+    executor::{LocalPool, LocalSpawner},
+    task::LocalSpawnExt,
+};
 use std::{
     any::Any,
-    cell::RefCell,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
 };
 use windows::HRESULT;
 use winit::{
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop, EventLoopProxy},
-    window::{Window, WindowBuilder},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        ElementState, Event, KeyboardInput, ModifiersState, MouseButton, TouchPhase,
+        VirtualKeyCode, WindowEvent,
+    },
+    event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
+    window::{CursorIcon, WindowId},
 };
 
 use crate::{
+    accelerator::{Accelerator, AcceleratorEvent},
+    accessibility::{AccessibleEntry, AccessibleInfo, AccessibleRole},
+    animation::AnimationSettings,
+    backdrop::{apply_dark_mode, WindowBackdrop},
+    context::{current_context, restore_current_context, FrameworkContext},
+    control::ControlEvent,
+    debug_panel::{collect_outlines, debug_text, DebugCounters, DebugPanel},
+    gamepad::GamepadPoller,
     interop::create_dispatcher_queue_controller_for_current_thread,
-    window_target::CompositionDesktopWindowTargetSource,
+    logging::{init_logging, LogPanel},
+    sound::{SoundId, SoundPlayer},
+    theme::Theme,
+    window_chrome::set_chrome_scale_factor,
+    window_host::{WindowHost, WindowParams, WindowParamsBuilder},
 };
 use crate::{
+    message_box_panel::{spawn_message_box, MessageBoxButton, MessageBoxParamsBuilder},
     panel::{Panel, PanelEvent},
     RibbonOrientation, RibbonParamsBuilder,
 };
 
-type RootPanel = crate::ribbon_panel::RibbonPanel;
+pub(crate) type RootPanel = crate::ribbon_panel::RibbonPanel;
+
+// Period of on_idle calls requested by request_idle from on_idle, e.g. for animation
+const IDLE_PERIOD: Duration = Duration::from_millis(10);
+const DEFAULT_RESIZE_DEBOUNCE: Duration = Duration::from_millis(100);
+// Windows also sends emulated mouse messages for touch input, mouse buttons are
+// ignored for this time after touch to avoid processing same input twice
+const TOUCH_MOUSE_SUPPRESS_PERIOD: Duration = Duration::from_millis(500);
+const ERROR_BOX_RATIO: Vector2 = Vector2 { X: 0.8, Y: 0.5 };
+const DEBUG_REFRESH_PERIOD: Duration = Duration::from_millis(500);
+
+// What the event loop does when panel callback or async task returns error
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorPolicy {
+    Exit,
+    // Message box with the error lets user continue or quit
+    Ask,
+}
 
 pub struct Globals {
     _controller: DispatcherQueueController,
@@ -42,40 +85,119 @@ pub struct Globals {
     composition_graphics_device: CompositionGraphicsDevice,
     next_id: Arc<AtomicUsize>,
     event_loop: Option<EventLoop<PanelEvent>>,
-    event_loop_proxy: EventLoopProxy<PanelEvent>,
-    window: Window,
-    root_visual: ContainerVisual,
-    root_panel: Option<RootPanel>,
-    target: Option<DesktopWindowTarget>,
+    // None in headless mode
+    event_loop_proxy: Option<EventLoopProxy<PanelEvent>>,
+    // Windows by winit's id, the main one is created by init_window
+    hosts: HashMap<WindowId, WindowHost>,
+    main_window: WindowId,
+    // Window whose panel tree processes the current event
+    current_window: WindowId,
+    // Requested by open_window and close_window, processed by the event loop
+    pending_windows: Vec<PendingWindow>,
+    closing_windows: Vec<usize>,
     local_pool: Option<LocalPool>,
     local_spawner: LocalSpawner,
+    panel_event_waiters: HashMap<usize, PanelEventWaiter>,
+    // Panel events waiting for TestHarness::dispatch in headless mode
+    headless_events: Option<VecDeque<PanelEvent>>,
+    async_error: Option<windows::Error>,
+    idle_requested: bool,
+    quit_requested: bool,
+    exit_code: i32,
+    // Called when the event loop is finished, after panels are closed
+    shutdown_hook: Option<Box<dyn FnOnce(i32)>>,
+    error_policy: ErrorPolicy,
+    // Called for each error before the policy is applied, e.g. to log it
+    error_hook: Option<Rc<dyn Fn(&windows::Error)>>,
+    // Errors happening while the error message box is open are not shown again
+    error_box_shown: bool,
+    // Panel with recent log lines shown over the window
+    log_panel: Option<usize>,
+    // Diagnostic overlay with panel outlines and tree, refreshed by the event loop
+    debug_panel: Option<usize>,
+    debug_counters: DebugCounters,
+    debug_refresh_time: Option<Instant>,
+    timers: HashMap<usize, Timer>,
+    theme: Theme,
+    theme_changed: bool,
+    animation_settings: AnimationSettings,
+    sound_player: Option<SoundPlayer>,
+    muted: bool,
+    // Surfaces are not re-rendered until window size is unchanged for this time
+    resize_debounce: Duration,
+    last_resize: Option<Instant>,
+    // Set by DeviceLost handler which may be called from another thread
+    device_lost: Arc<AtomicBool>,
+    // Modal panels capturing focus navigation, last one is active
+    focus_scopes: Vec<FocusScope>,
+    focused_control: Option<usize>,
+    // Ordered by panel id, which is the order the UI Automation provider lists them in
+    accessible: BTreeMap<usize, AccessibleEntry>,
+    // Keyboard shortcuts and ids of panels handling them
+    accelerators: HashMap<Accelerator, usize>,
+    // Reset on each mouse move, panels under mouse may change it in on_mouse_move
+    requested_cursor_icon: CursorIcon,
+}
+
+struct FocusScope {
+    owner_id: usize,
+    // Focus to restore when scope is closed
+    saved_focus: Option<usize>,
+}
+
+struct Timer {
+    period: Duration,
+    next: Instant,
+}
+
+#[derive(Default)]
+struct PanelEventWaiter {
+    panel_event: Option<PanelEvent>,
+    waker: Option<Waker>,
+}
+
+struct PendingWindow {
+    id: usize,
+    params: WindowParams,
+    root_panel: RootPanel,
 }
 
 impl Globals {
-    fn new() -> windows::Result<Self> {
+    pub(crate) fn new(params: WindowParams) -> windows::Result<Self> {
         let _controller = create_dispatcher_queue_controller_for_current_thread()?;
         let compositor = Compositor::new()?;
-        let canvas_device = CanvasDevice::GetSharedDevice()?;
+        // Tests don't need a GPU
+        let canvas_device = if params.headless {
+            CanvasDevice::CreateWithForceSoftwareRendererOption(true)?
+        } else {
+            CanvasDevice::GetSharedDevice()?
+        };
+        let device_lost = Arc::new(AtomicBool::new(false));
+        watch_device_lost(&canvas_device, device_lost.clone())?;
         let composition_graphics_device =
             CanvasComposition::CreateCompositionGraphicsDevice(&compositor, &canvas_device)?;
-        let next_id = Arc::new(0.into());
-        let event_loop = EventLoop::<PanelEvent>::with_user_event();
-        let event_loop_proxy = event_loop.create_proxy();
-        let window = WindowBuilder::new()
-            .build(&event_loop)
-            .map_err(|e| winrt_error(e.to_string())())?;
-        let event_loop = Some(event_loop);
-        let target = window.create_window_target(&compositor, false)?;
-        let window_size = window.inner_size();
-        let window_size = Vector2 {
-            X: window_size.width as f32,
-            Y: window_size.height as f32,
+        let next_id: Arc<AtomicUsize> = Arc::new(0.into());
+        let id = next_id.fetch_add(1, Ordering::SeqCst);
+        let (event_loop, event_loop_proxy, main_host) = if params.headless {
+            (
+                None,
+                None,
+                WindowHost::new_headless(id, &params, &compositor)?,
+            )
+        } else {
+            let event_loop = EventLoop::<PanelEvent>::with_user_event();
+            let event_loop_proxy = event_loop.create_proxy();
+            let main_host = WindowHost::new(id, &params, &event_loop, &compositor)?;
+            (Some(event_loop), Some(event_loop_proxy), main_host)
+        };
+        let main_window = main_host.window_id;
+        let mut hosts = HashMap::new();
+        hosts.insert(main_window, main_host);
+        let headless_events = if params.headless {
+            Some(VecDeque::new())
+        } else {
+            None
         };
-        let root_visual = compositor.CreateContainerVisual()?;
-        root_visual.SetSize(window_size)?;
-        target.SetRoot(&root_visual)?;
-        let target = Some(target);
-        let root_panel = None;
         let local_pool = LocalPool::new();
         let local_spawner = local_pool.spawner();
         let local_pool = Some(local_pool);
@@ -87,95 +209,1096 @@ impl Globals {
             next_id,
             event_loop,
             event_loop_proxy,
-            window,
-            target,
-            root_visual,
-            root_panel,
+            hosts,
+            main_window,
+            current_window: main_window,
+            pending_windows: Vec::new(),
+            closing_windows: Vec::new(),
             local_pool,
             local_spawner,
+            panel_event_waiters: HashMap::new(),
+            headless_events,
+            async_error: None,
+            idle_requested: false,
+            quit_requested: false,
+            exit_code: 0,
+            shutdown_hook: None,
+            error_policy: ErrorPolicy::Ask,
+            error_hook: None,
+            error_box_shown: false,
+            log_panel: None,
+            debug_panel: None,
+            debug_counters: DebugCounters::default(),
+            debug_refresh_time: None,
+            timers: HashMap::new(),
+            theme: Theme::light()?,
+            theme_changed: false,
+            animation_settings: AnimationSettings::default(),
+            sound_player: SoundPlayer::new(),
+            muted: false,
+            resize_debounce: DEFAULT_RESIZE_DEBOUNCE,
+            last_resize: None,
+            device_lost,
+            focus_scopes: Vec::new(),
+            focused_control: None,
+            accessible: BTreeMap::new(),
+            accelerators: HashMap::new(),
+            requested_cursor_icon: CursorIcon::Default,
         })
     }
+
+    pub(crate) fn init_main_root(context: &FrameworkContext) -> windows::Result<()> {
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .create_in(context)?;
+        let main_window = context.with(|globals| {
+            globals
+                .host()
+                .root_visual
+                .Children()?
+                .InsertAtTop(root_panel.visual())?;
+            Ok(globals.main_window)
+        })?;
+        context.insert_root(main_window, root_panel);
+        Ok(())
+    }
+
+    // Window of the panel tree being processed, the main one outside of the event loop
+    fn host(&self) -> &WindowHost {
+        self.hosts
+            .get(&self.current_window)
+            .or_else(|| self.hosts.get(&self.main_window))
+            .expect("Main window not found")
+    }
+
+    fn host_mut(&mut self) -> &mut WindowHost {
+        let window_id = if self.hosts.contains_key(&self.current_window) {
+            self.current_window
+        } else {
+            self.main_window
+        };
+        self.hosts
+            .get_mut(&window_id)
+            .expect("Main window not found")
+    }
+
+    // Main window comes first, then secondary ones in order of opening
+    fn window_ids(&self) -> Vec<WindowId> {
+        let mut hosts: Vec<_> = self
+            .hosts
+            .values()
+            .map(|host| (host.id, host.window_id))
+            .collect();
+        hosts.sort_unstable_by_key(|(id, _)| *id);
+        hosts.into_iter().map(|(_, window_id)| window_id).collect()
+    }
+
+    // Shared device is replaced by a new one after it's lost, surfaces created by the old
+    // graphics device are no longer valid
+    fn recreate_device(&mut self) -> windows::Result<()> {
+        let canvas_device = CanvasDevice::GetSharedDevice()?;
+        watch_device_lost(&canvas_device, self.device_lost.clone())?;
+        self.composition_graphics_device =
+            CanvasComposition::CreateCompositionGraphicsDevice(&self.compositor, &canvas_device)?;
+        self.canvas_device = canvas_device;
+        Ok(())
+    }
+
+    // Returns ids of timers which are due and schedules their next ticks. Missed ticks
+    // are skipped, so slow frame doesn't cause a burst of timer calls
+    fn take_expired_timers(&mut self, now: Instant) -> Vec<usize> {
+        let mut expired = Vec::new();
+        for (timer_id, timer) in &mut self.timers {
+            if timer.next <= now {
+                expired.push(*timer_id);
+                timer.next += timer.period;
+                if timer.next <= now {
+                    timer.next = now + timer.period;
+                }
+            }
+        }
+        expired.sort_unstable();
+        expired
+    }
+
+    fn next_timer_time(&self) -> Option<Instant> {
+        self.timers.values().map(|timer| timer.next).min()
+    }
+
+    // Nearest of timer tick, idle call requested by animation in flight and the end of
+    // resizing, when panels render surfaces postponed by is_resizing. None means that
+    // the loop sleeps until next event
+    fn next_wake_time(&self) -> Option<Instant> {
+        let now = Instant::now();
+        let idle_time = if self.idle_requested {
+            Some(now + IDLE_PERIOD)
+        } else {
+            None
+        };
+        let resize_end = self
+            .last_resize
+            .map(|time| time + self.resize_debounce)
+            .filter(|time| *time > now);
+        [idle_time, self.next_timer_time(), resize_end]
+            .iter()
+            .flatten()
+            .min()
+            .copied()
+    }
 }
 
-thread_local! {
-    static GLOBALS: RefCell<Option<Globals>> = RefCell::new(None);
+fn watch_device_lost(
+    canvas_device: &CanvasDevice,
+    device_lost: Arc<AtomicBool>,
+) -> windows::Result<()> {
+    canvas_device.DeviceLost(TypedEventHandler::new(move |_, _| {
+        device_lost.store(true, Ordering::SeqCst);
+        Ok(())
+    }))?;
+    Ok(())
 }
 
+// Drawing fails with device removed error when GPU is reset, Win2D is notified to raise
+// DeviceLost, so the device is recreated on next loop iteration
+fn check_device_lost(error: &windows::Error) -> windows::Result<bool> {
+    let canvas_device = canvas_device();
+    if canvas_device.IsDeviceLost(error.code().0 as i32)? {
+        canvas_device.RaiseDeviceLost()?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+fn recreate_device_if_lost() -> windows::Result<()> {
+    let device_lost =
+        globals_with(|globals| Ok(globals.device_lost.swap(false, Ordering::SeqCst)))?;
+    if device_lost {
+        globals_with(|globals| globals.recreate_device())?;
+        all_roots_with(|root_panel| root_panel.on_device_lost())?;
+    }
+    Ok(())
+}
+
+// Compatibility wrappers over the current FrameworkContext
 pub fn globals_with<F, T>(f: F) -> windows::Result<T>
 where
     F: FnOnce(&mut Globals) -> windows::Result<T>,
 {
-    GLOBALS.with(|globals| {
-        f(globals
-            .borrow_mut()
-            .as_mut()
-            .ok_or_else(winrt_error("Globals not initialized"))?)
-    })
+    current_context()?.with(f)
 }
 
 pub fn globals_with_unwrap<F, T>(f: F) -> T
 where
     F: FnOnce(&mut Globals) -> T,
 {
-    GLOBALS.with(|globals| {
-        f(globals
-            .borrow_mut()
-            .as_mut()
-            .expect("Globals not initialized"))
-    })
+    current_context()
+        .and_then(|context| context.with(|globals| Ok(f(globals))))
+        .unwrap_or_else(|e| panic!("{}", e.message()))
 }
 
 pub fn init_window() -> windows::Result<()> {
-    GLOBALS.with::<_, windows::Result<()>>(|globals| {
-        *globals.borrow_mut() = Some(Globals::new()?);
-        Ok(())
-    })?;
-    let root_panel = RibbonParamsBuilder::default()
-        .orientation(RibbonOrientation::Stack)
-        .create()?;
-    globals_with(|globals| {
+    init_window_with(WindowParamsBuilder::default().create()?)
+}
+
+// Creates the framework context with the main window and makes it current
+pub fn init_window_with(params: WindowParams) -> windows::Result<()> {
+    init_logging();
+    FrameworkContext::new(params)?.make_current();
+    Ok(())
+}
+
+impl FrameworkContext {
+    // Context is current and panel globals like root_visual and scale_factor refer to
+    // the window while its panel tree is processed
+    fn window_root_with<F, T>(&self, window_id: WindowId, f: F) -> windows::Result<T>
+    where
+        F: FnOnce(&mut RootPanel) -> windows::Result<T>,
+    {
+        let root = self
+            .root(window_id)
+            .ok_or_else(winrt_error("Window not found"))?;
+        let previous_window =
+            self.with(|globals| Ok(std::mem::replace(&mut globals.current_window, window_id)))?;
+        let previous_context = self.make_current();
+        let result = match root.try_borrow_mut() {
+            Ok(mut root_panel) => f(&mut root_panel),
+            Err(_) => Err(winrt_error("Root panel is busy")()),
+        };
+        restore_current_context(previous_context);
+        self.with(|globals| {
+            globals.current_window = previous_window;
+            Ok(())
+        })?;
+        result
+    }
+
+    // Gives access to panels of the main window. Fails when called from panel's event
+    // handler
+    pub fn root_panel_with<F, T>(&self, f: F) -> windows::Result<T>
+    where
+        F: FnOnce(&mut dyn Panel) -> windows::Result<T>,
+    {
+        let main_window = self.with(|globals| Ok(globals.main_window))?;
+        self.window_root_with(main_window, |root_panel| f(root_panel))
+    }
+
+    pub fn compositor(&self) -> windows::Result<Compositor> {
+        self.with(|globals| Ok(globals.compositor.clone()))
+    }
+
+    pub fn canvas_device(&self) -> windows::Result<CanvasDevice> {
+        self.with(|globals| Ok(globals.canvas_device.clone()))
+    }
+
+    pub fn composition_graphics_device(&self) -> windows::Result<CompositionGraphicsDevice> {
+        self.with(|globals| Ok(globals.composition_graphics_device.clone()))
+    }
+
+    pub fn theme(&self) -> windows::Result<Theme> {
+        self.with(|globals| Ok(globals.theme.clone()))
+    }
+
+    pub fn next_id(&self) -> windows::Result<usize> {
+        self.with(|globals| Ok(globals.next_id.fetch_add(1, Ordering::SeqCst)))
+    }
+
+    pub fn send_panel_event<T: Any>(&self, panel_id: usize, command: T) -> windows::Result<()> {
+        self.post_panel_event(panel_id, command, false)
+    }
+
+    pub fn broadcast_panel_event<T: Any>(
+        &self,
+        panel_id: usize,
+        command: T,
+    ) -> windows::Result<()> {
+        self.post_panel_event(panel_id, command, true)
+    }
+}
+
+fn window_root_with<F, T>(window_id: WindowId, f: F) -> windows::Result<T>
+where
+    F: FnOnce(&mut RootPanel) -> windows::Result<T>,
+{
+    current_context()?.window_root_with(window_id, f)
+}
+
+fn all_roots_with<F>(mut f: F) -> windows::Result<()>
+where
+    F: FnMut(&mut RootPanel) -> windows::Result<()>,
+{
+    for window_id in globals_with(|globals| Ok(globals.window_ids()))? {
+        window_root_with(window_id, &mut f)?;
+    }
+    Ok(())
+}
+
+fn host_window_id(id: usize) -> Option<WindowId> {
+    globals_with_unwrap(|globals| {
         globals
-            .root_visual
-            .Children()
-            .unwrap()
-            .InsertAtTop(root_panel.visual())?;
-        globals.root_panel = Some(root_panel);
-        Ok(())
+            .hosts
+            .values()
+            .find(|host| host.id == id)
+            .map(|host| host.window_id)
     })
 }
 
+pub(crate) fn root_ribbon_with<F, T>(f: F) -> windows::Result<T>
+where
+    F: FnOnce(&mut RootPanel) -> windows::Result<T>,
+{
+    let main_window = globals_with(|globals| Ok(globals.main_window))?;
+    window_root_with(main_window, f)
+}
+
+// Gives access to panels from async code. Fails when called from panel's event handler
+pub fn root_panel_with<F, T>(f: F) -> windows::Result<T>
+where
+    F: FnOnce(&mut dyn Panel) -> windows::Result<T>,
+{
+    current_context()?.root_panel_with(f)
+}
+
+// Same as root_panel_with for the window opened by open_window
+pub fn window_panel_with<F, T>(window: usize, f: F) -> windows::Result<T>
+where
+    F: FnOnce(&mut dyn Panel) -> windows::Result<T>,
+{
+    let window_id = host_window_id(window).ok_or_else(winrt_error("Window not found"))?;
+    window_root_with(window_id, |root_panel| f(root_panel))
+}
+
+// Sent as broadcast event when window opened by open_window is closed
+#[derive(Copy, Clone, Debug)]
+pub struct WindowClosed {
+    pub window: usize,
+}
+
+// Opens secondary window with its own panel tree, e.g. for detached statistics or
+// debug inspector. Panel events are delivered to trees of all windows. The window is
+// created by the event loop, returned id identifies it in close_window and WindowClosed
+pub fn open_window(params: WindowParams, panel: impl Panel + 'static) -> windows::Result<usize> {
+    current_context()?.open_window(params, panel)
+}
+
+impl FrameworkContext {
+    pub fn open_window(
+        &self,
+        params: WindowParams,
+        panel: impl Panel + 'static,
+    ) -> windows::Result<usize> {
+        let mut root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .create_in(self)?;
+        root_panel.push_cell(
+            crate::ribbon_panel::RibbonCellParamsBuilder::default()
+                .panel(panel)
+                .create_in(self)?,
+        )?;
+        let id = self.next_id()?;
+        self.with(|globals| {
+            globals.pending_windows.push(PendingWindow {
+                id,
+                params,
+                root_panel,
+            });
+            Ok(id)
+        })
+    }
+}
+
+// Closing the main window quits the application
+pub fn close_window(window: usize) {
+    globals_with_unwrap(|globals| globals.closing_windows.push(window))
+}
+
+pub fn main_window() -> usize {
+    globals_with_unwrap(|globals| globals.hosts[&globals.main_window].id)
+}
+
+// Window of the panel tree which processes the current event
+pub fn current_window() -> usize {
+    globals_with_unwrap(|globals| globals.host().id)
+}
+
+fn create_pending_windows(event_loop: &EventLoopWindowTarget<PanelEvent>) -> windows::Result<()> {
+    let pending_windows = globals_with(|globals| Ok(std::mem::take(&mut globals.pending_windows)))?;
+    let compositor = compositor();
+    for pending in pending_windows {
+        let host = WindowHost::new(pending.id, &pending.params, event_loop, &compositor)?;
+        let window_id = host.window_id;
+        let size = host.size();
+        host.root_visual
+            .Children()?
+            .InsertAtTop(pending.root_panel.visual())?;
+        globals_with_unwrap(|globals| globals.hosts.insert(window_id, host));
+        current_context()?.insert_root(window_id, pending.root_panel);
+        window_root_with(window_id, |root_panel| {
+            root_panel.on_init()?;
+            root_panel.on_resize(&size)
+        })?;
+    }
+    Ok(())
+}
+
+fn destroy_window(window_id: WindowId) -> windows::Result<()> {
+    window_root_with(window_id, |root_panel| root_panel.on_close())?;
+    current_context()?.remove_root(window_id);
+    // Window is destroyed when its host is dropped
+    if let Some(host) = globals_with_unwrap(|globals| globals.hosts.remove(&window_id)) {
+        broadcast_panel_event(host.id, WindowClosed { window: host.id })?;
+    }
+    Ok(())
+}
+
+fn close_requested_windows() -> windows::Result<()> {
+    let (closing_windows, main_window) = globals_with(|globals| {
+        Ok((
+            std::mem::take(&mut globals.closing_windows),
+            globals.main_window,
+        ))
+    })?;
+    for window in closing_windows {
+        match host_window_id(window) {
+            Some(window_id) if window_id == main_window => quit(),
+            Some(window_id) => destroy_window(window_id)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+// Event is delivered to the sender panel and its owners
 pub fn send_panel_event<T: Any>(panel_id: usize, command: T) -> windows::Result<()> {
-    globals_with(|globals| {
+    current_context()?.send_panel_event(panel_id, command)
+}
+
+// Event is delivered to every panel in the tree, use for application-wide notifications
+pub fn broadcast_panel_event<T: Any>(panel_id: usize, command: T) -> windows::Result<()> {
+    current_context()?.broadcast_panel_event(panel_id, command)
+}
+
+impl FrameworkContext {
+    fn post_panel_event<T: Any>(
+        &self,
+        panel_id: usize,
+        command: T,
+        broadcast: bool,
+    ) -> windows::Result<()> {
+        tracing::trace!(
+            panel_id,
+            broadcast,
+            event_type = std::any::type_name::<T>(),
+            "panel event posted"
+        );
+        let panel_event = PanelEvent {
+            panel_id,
+            data: Some(Box::new(command)),
+            broadcast,
+        };
+        self.with(|globals| {
+            if let Some(headless_events) = &mut globals.headless_events {
+                headless_events.push_back(panel_event);
+                Ok(())
+            } else {
+                globals
+                    .event_loop_proxy
+                    .as_ref()
+                    .ok_or_else(winrt_error("Event loop is not available"))?
+                    .send_event(panel_event)
+                    .map_err(|e| winrt_error(e)())
+            }
+        })
+    }
+}
+
+pub(crate) fn take_headless_events() -> VecDeque<PanelEvent> {
+    globals_with_unwrap(|globals| {
         globals
-            .event_loop_proxy
-            .send_event(PanelEvent {
-                panel_id,
-                data: Some(Box::new(command)),
-            })
-            .map_err(|e| winrt_error(e)())
+            .headless_events
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
     })
 }
 
+pub(crate) fn take_local_pool() -> Option<LocalPool> {
+    globals_with_unwrap(|globals| globals.local_pool.take())
+}
+
+pub(crate) fn take_async_error() -> Option<windows::Error> {
+    globals_with_unwrap(|globals| globals.async_error.take())
+}
+
+// Event loop sleeps until next event unless some panel needs on_idle calls for
+// animation or polling. Such panel should request it again on each on_idle call.
+// Requests made while processing input or events are served by on_idle of the same
+// pass of the loop and don't cause extra wakeups
+pub fn request_idle() {
+    unwrap_context(|context| context.request_idle())
+}
+
+// Starts periodic on_timer(timer_id) calls for the whole panel tree. Panels usually use
+// own id as timer id. Setting existing timer restarts it with new period
+pub fn set_timer(timer_id: usize, period: Duration) {
+    unwrap_context(|context| context.set_timer(timer_id, period))
+}
+
+pub fn kill_timer(timer_id: usize) {
+    unwrap_context(|context| context.kill_timer(timer_id))
+}
+
+impl FrameworkContext {
+    pub fn request_idle(&self) -> windows::Result<()> {
+        self.with(|globals| {
+            globals.idle_requested = true;
+            Ok(())
+        })
+    }
+
+    pub fn set_timer(&self, timer_id: usize, period: Duration) -> windows::Result<()> {
+        self.with(|globals| {
+            globals.timers.insert(
+                timer_id,
+                Timer {
+                    period,
+                    next: Instant::now() + period,
+                },
+            );
+            Ok(())
+        })
+    }
+
+    pub fn kill_timer(&self, timer_id: usize) -> windows::Result<()> {
+        self.with(|globals| {
+            globals.timers.remove(&timer_id);
+            Ok(())
+        })
+    }
+}
+
+// Closes the window after the current event is processed
+pub fn quit() {
+    request_exit(0)
+}
+
+// Like quit, but the process exits with given code. Panels are closed with on_close
+// and pending async tasks are run before the shutdown hook is called
+pub fn request_exit(exit_code: i32) {
+    globals_with_unwrap(|globals| {
+        globals.quit_requested = true;
+        globals.exit_code = exit_code;
+    })
+}
+
+pub fn set_error_policy(error_policy: ErrorPolicy) {
+    globals_with_unwrap(|globals| globals.error_policy = error_policy)
+}
+
+pub fn set_error_hook(hook: impl Fn(&windows::Error) + 'static) {
+    globals_with_unwrap(|globals| globals.error_hook = Some(Rc::new(hook)))
+}
+
+// Passes error to the hook and applies error policy. Returns false if the event loop
+// should exit
+fn report_error(error: &windows::Error) -> windows::Result<bool> {
+    let (error_hook, error_policy, error_box_shown) = globals_with(|globals| {
+        Ok((
+            globals.error_hook.clone(),
+            globals.error_policy,
+            globals.error_box_shown,
+        ))
+    })?;
+    if let Some(error_hook) = error_hook {
+        error_hook(error);
+    }
+    if error_policy == ErrorPolicy::Exit {
+        return Ok(false);
+    }
+    if error_box_shown {
+        return Ok(true);
+    }
+    let message_box = MessageBoxParamsBuilder::default()
+        .message(format!(
+            "Error 0x{:08X}\n{}",
+            error.code().0,
+            error.message()
+        ))
+        .button_flags(MessageBoxButton::Ok | MessageBoxButton::Cancel)
+        .ok_text("Continue")
+        .cancel_text("Quit")
+        .default_button(MessageBoxButton::Ok)
+        .create()?;
+    let host = root_ribbon_with(|root_panel| Ok(root_panel.handle()))?;
+    spawn_message_box(host, ERROR_BOX_RATIO, message_box, |_, answer| {
+        globals_with_unwrap(|globals| globals.error_box_shown = false);
+        if answer != MessageBoxButton::Ok {
+            request_exit(1);
+        }
+        Ok(())
+    })?;
+    globals_with_unwrap(|globals| globals.error_box_shown = true);
+    Ok(true)
+}
+
+// Hook gets the exit code, it's the place to save application state not owned by panels
+pub fn set_shutdown_hook(hook: impl FnOnce(i32) + 'static) {
+    globals_with_unwrap(|globals| globals.shutdown_hook = Some(Box::new(hook)))
+}
+
 pub fn spawner() -> LocalSpawner {
-    globals_with_unwrap(|globals| globals.local_spawner.clone())
+    unwrap_context(|context| context.spawner())
+}
+
+// Runs future in the event loop thread. Error returned by future stops the event loop
+pub fn spawn<F>(future: F) -> windows::Result<()>
+where
+    F: Future<Output = windows::Result<()>> + 'static,
+{
+    current_context()?.spawn(future)
+}
+
+// Resolves with the next event sent to the panel. While someone waits, events for this
+// panel are not passed to the panel tree
+pub fn expect_panel_event(panel_id: usize) -> ExpectPanelEvent {
+    unwrap_context(|context| Ok(context.expect_panel_event(panel_id)))
+}
+
+impl FrameworkContext {
+    pub fn spawner(&self) -> windows::Result<LocalSpawner> {
+        self.with(|globals| Ok(globals.local_spawner.clone()))
+    }
+
+    // Future keeps the context, its error is stored even if other context is current
+    // when it completes
+    pub fn spawn<F>(&self, future: F) -> windows::Result<()>
+    where
+        F: Future<Output = windows::Result<()>> + 'static,
+    {
+        let context = self.clone();
+        self.spawner()?
+            .spawn_local(async move {
+                if let Err(e) = future.await {
+                    context.try_with(|globals| globals.async_error = Some(e));
+                }
+            })
+            .map_err(|e| winrt_error(e)())
+    }
+
+    pub fn expect_panel_event(&self, panel_id: usize) -> ExpectPanelEvent {
+        ExpectPanelEvent {
+            context: self.clone(),
+            panel_id,
+        }
+    }
+}
+
+pub struct ExpectPanelEvent {
+    context: FrameworkContext,
+    panel_id: usize,
+}
+
+impl Future for ExpectPanelEvent {
+    type Output = PanelEvent;
+    // Polled again later if globals are busy
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let panel_id = self.panel_id;
+        self.context
+            .try_with(|globals| {
+                let waiter = globals.panel_event_waiters.entry(panel_id).or_default();
+                if let Some(panel_event) = waiter.panel_event.take() {
+                    globals.panel_event_waiters.remove(&panel_id);
+                    Poll::Ready(panel_event)
+                } else {
+                    waiter.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            })
+            .unwrap_or_else(|| {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+    }
+}
+
+impl Drop for ExpectPanelEvent {
+    fn drop(&mut self) {
+        let panel_id = self.panel_id;
+        self.context
+            .try_with(|globals| globals.panel_event_waiters.remove(&panel_id));
+    }
 }
 
-// pub fn spawn(func: impl FnOnce(root: &RefCell<>) -> F) where F: Future<Output = windows::Result<()>>
-// {
-//     spawner().
-// }
+// Passes event to the future waiting for it, if any
+pub(crate) fn deliver_panel_event(panel_event: &mut PanelEvent) -> windows::Result<bool> {
+    globals_with(|globals| {
+        if let Some(waiter) = globals.panel_event_waiters.get_mut(&panel_event.panel_id) {
+            if waiter.panel_event.is_none() {
+                waiter.panel_event = Some(PanelEvent {
+                    panel_id: panel_event.panel_id,
+                    data: panel_event.data.take(),
+                    broadcast: panel_event.broadcast,
+                });
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    })
+}
 
 pub fn compositor() -> Compositor {
-    globals_with_unwrap(|globals| globals.compositor.clone())
+    unwrap_context(|context| context.compositor())
 }
 pub fn canvas_device() -> CanvasDevice {
-    globals_with_unwrap(|globals| globals.canvas_device.clone())
+    unwrap_context(|context| context.canvas_device())
 }
 pub fn composition_graphics_device() -> CompositionGraphicsDevice {
-    globals_with_unwrap(|globals| globals.composition_graphics_device.clone())
+    unwrap_context(|context| context.composition_graphics_device())
+}
+pub fn theme() -> Theme {
+    unwrap_context(|context| context.theme())
+}
+
+// Panels read the theme when drawing, so whole tree is redrawn on the next window redraw
+pub fn set_theme(theme: Theme) {
+    globals_with_unwrap(|globals| {
+        globals.theme = theme;
+        globals.theme_changed = true;
+        for window in globals
+            .hosts
+            .values()
+            .filter_map(|host| host.window.as_ref())
+        {
+            window.request_redraw();
+        }
+    })
+}
+pub fn animation_settings() -> AnimationSettings {
+    unwrap_context(|context| context.animation_settings())
+}
+pub fn set_animation_settings(animation_settings: AnimationSettings) {
+    globals_with_unwrap(|globals| globals.animation_settings = animation_settings)
+}
+// Zero duration re-renders surfaces on each resize
+pub fn set_resize_debounce(resize_debounce: Duration) {
+    globals_with_unwrap(|globals| globals.resize_debounce = resize_debounce)
+}
+// True while window is being resized, panels should only lay out their visuals and
+// postpone rendering of surfaces until it's false
+pub fn is_resizing() -> bool {
+    unwrap_context(|context| context.is_resizing())
+}
+// Does nothing when muted or when there is no audio device
+pub fn play_sound(sound_id: SoundId) {
+    unwrap_context(|context| context.play_sound(sound_id))
+}
+pub fn set_muted(muted: bool) {
+    globals_with_unwrap(|globals| globals.muted = muted)
+}
+// Starts focus scope owned by modal panel: only its ControlManager navigates focus until
+// the scope is closed
+pub fn push_focus_scope(owner_id: usize) {
+    unwrap_context(|context| context.push_focus_scope(owner_id))
+}
+// Closes focus scope and returns focus to the control focused when it was pushed
+pub fn pop_focus_scope(owner_id: usize) -> windows::Result<()> {
+    current_context()?.pop_focus_scope(owner_id)
+}
+pub fn set_accessible(
+    panel_id: usize,
+    visual: &ContainerVisual,
+    role: AccessibleRole,
+    name: impl Into<Cow<'static, str>>,
+) {
+    unwrap_context(|context| context.set_accessible(panel_id, visual, role, name))
+}
+pub fn remove_accessible(panel_id: usize) {
+    unwrap_context(|context| context.remove_accessible(panel_id))
+}
+pub fn accessible_info(panel_id: usize) -> Option<AccessibleInfo> {
+    globals_with_unwrap(|globals| {
+        globals
+            .accessible
+            .get(&panel_id)
+            .map(|entry| entry.info.clone())
+    })
+}
+// Called by UI Automation provider from the window procedure, so it fails instead of
+// panicking when globals are borrowed
+pub(crate) fn accessible_entries(hwnd: HWND) -> windows::Result<Vec<(usize, AccessibleEntry)>> {
+    globals_with(|globals| {
+        Ok(globals
+            .accessible
+            .iter()
+            .filter(|(_, entry)| entry.hwnd.0 == hwnd.0)
+            .map(|(panel_id, entry)| (*panel_id, entry.clone()))
+            .collect())
+    })
+}
+// Fails if the accelerator is already registered by another panel
+pub fn register_accelerator(panel_id: usize, accelerator: Accelerator) -> windows::Result<()> {
+    current_context()?.register_accelerator(panel_id, accelerator)
+}
+pub fn unregister_accelerators(panel_id: usize) {
+    unwrap_context(|context| context.unregister_accelerators(panel_id))
+}
+// Shortcut of the panel to show in its tooltip or label
+pub fn accelerator_for(panel_id: usize) -> Option<Accelerator> {
+    globals_with_unwrap(|globals| {
+        globals
+            .accelerators
+            .iter()
+            .find(|(_, id)| **id == panel_id)
+            .map(|(accelerator, _)| *accelerator)
+    })
+}
+// Accelerators are disabled while modal dialog is open: its keys belong to the dialog
+pub(crate) fn process_accelerator(
+    input: KeyboardInput,
+    modifiers: ModifiersState,
+) -> windows::Result<bool> {
+    if input.state != ElementState::Pressed {
+        return Ok(false);
+    }
+    let key = match input.virtual_keycode {
+        Some(key) => key,
+        None => return Ok(false),
+    };
+    let accelerator = Accelerator::new(key, modifiers);
+    let panel_id = globals_with_unwrap(|globals| {
+        if globals.focus_scopes.is_empty() {
+            globals.accelerators.get(&accelerator).copied()
+        } else {
+            None
+        }
+    });
+    if let Some(panel_id) = panel_id {
+        send_panel_event(panel_id, AcceleratorEvent(accelerator))?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+// Called by panel from on_mouse_move when mouse is over it, cursor returns to default
+// on the next move unless requested again
+pub fn set_cursor(cursor_icon: CursorIcon) {
+    unwrap_context(|context| context.set_cursor(cursor_icon))
+}
+fn apply_cursor() {
+    globals_with_unwrap(|globals| {
+        let cursor_icon =
+            std::mem::replace(&mut globals.requested_cursor_icon, CursorIcon::Default);
+        let host = globals.host_mut();
+        if cursor_icon != host.cursor_icon {
+            host.cursor_icon = cursor_icon;
+            if let Some(window) = &host.window {
+                window.set_cursor_icon(cursor_icon);
+            }
+        }
+    })
+}
+pub(crate) fn active_focus_scope() -> Option<usize> {
+    globals_with_unwrap(|globals| globals.focus_scopes.last().map(|scope| scope.owner_id))
+}
+pub(crate) fn set_focused_control(panel_id: Option<usize>) {
+    globals_with_unwrap(|globals| globals.focused_control = panel_id)
+}
+// Visuals are sized in physical pixels, fixed sizes like stroke widths should be multiplied by this
+pub fn scale_factor() -> f32 {
+    unwrap_context(|context| context.scale_factor())
+}
+// Drawing surfaces are rounded up to whole pixels to avoid stretching their content
+pub(crate) fn pixel_size(size: &Vector2) -> Vector2 {
+    Vector2 {
+        X: size.X.ceil(),
+        Y: size.Y.ceil(),
+    }
+}
+// Position of the window's outer frame in physical pixels, None if not available
+pub fn window_position() -> Option<Vector2> {
+    globals_with_unwrap(|globals| {
+        globals
+            .host()
+            .window
+            .as_ref()?
+            .outer_position()
+            .ok()
+            .map(|position| Vector2 {
+                X: position.x as f32,
+                Y: position.y as f32,
+            })
+    })
+}
+pub fn minimize_window() {
+    globals_with_unwrap(|globals| {
+        if let Some(window) = &globals.host().window {
+            window.set_minimized(true)
+        }
+    })
+}
+pub fn is_window_maximized() -> bool {
+    unsafe { IsZoomed(window_hwnd()).as_bool() }
+}
+// Maximizes the window or restores maximized one
+pub fn toggle_maximize_window() {
+    let maximized = is_window_maximized();
+    globals_with_unwrap(|globals| {
+        if let Some(window) = &globals.host().window {
+            window.set_maximized(!maximized)
+        }
+    })
+}
+// Backdrop requested in WindowParams if the system supports it, otherwise None
+pub fn window_backdrop() -> WindowBackdrop {
+    unwrap_context(|context| context.window_backdrop())
+}
+// Dark frame and backdrop tint, should follow the theme. Theme colors don't say if
+// it's dark, so it's set separately. Applies to all windows
+pub fn set_window_dark_mode(dark_mode: bool) {
+    let hwnds: Vec<_> = globals_with_unwrap(|globals| {
+        globals
+            .hosts
+            .values()
+            .filter(|host| !host.headless)
+            .map(|host| host.hwnd())
+            .collect()
+    });
+    let mut applied = true;
+    for hwnd in hwnds {
+        applied &= apply_dark_mode(hwnd, dark_mode);
+    }
+    if !applied {
+        tracing::warn!(dark_mode, "window dark mode is not supported");
+    }
+}
+pub(crate) fn window_hwnd() -> HWND {
+    globals_with_unwrap(|globals| globals.host().hwnd())
+}
+// Parent of the root panel's visual, its origin is the top left corner of client area
+pub(crate) fn root_visual() -> ContainerVisual {
+    globals_with_unwrap(|globals| globals.host().root_visual.clone())
+}
+// Screen position and size of the window's client area, None in headless mode or if
+// the window is minimized
+pub(crate) fn window_client_rect() -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    globals_with_unwrap(|globals| {
+        let host = globals.host();
+        if host.headless || host.suspended {
+            return None;
+        }
+        let window = host.window.as_ref()?;
+        let position = window.inner_position().ok()?;
+        Some((position, window.inner_size()))
+    })
+}
+// Size of the window's client area in physical pixels
+pub fn window_size() -> Vector2 {
+    globals_with_unwrap(|globals| globals.host().size())
 }
 pub fn get_next_id() -> usize {
-    globals_with_unwrap(|globals| globals.next_id.fetch_add(1, Ordering::SeqCst))
+    unwrap_context(|context| context.next_id())
+}
+
+// Per-window values refer to the window whose panel tree processes the current event
+impl FrameworkContext {
+    pub fn animation_settings(&self) -> windows::Result<AnimationSettings> {
+        self.with(|globals| Ok(globals.animation_settings.clone()))
+    }
+
+    pub fn is_resizing(&self) -> windows::Result<bool> {
+        self.with(|globals| {
+            Ok(globals
+                .last_resize
+                .map_or(false, |t| t.elapsed() < globals.resize_debounce))
+        })
+    }
+
+    pub fn play_sound(&self, sound_id: SoundId) -> windows::Result<()> {
+        self.with(|globals| {
+            if let Some(sound_player) = &globals.sound_player {
+                if !globals.muted {
+                    sound_player.play(sound_id)
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub fn push_focus_scope(&self, owner_id: usize) -> windows::Result<()> {
+        self.with(|globals| {
+            let saved_focus = globals.focused_control.take();
+            globals.focus_scopes.push(FocusScope {
+                owner_id,
+                saved_focus,
+            });
+            Ok(())
+        })
+    }
+
+    pub fn pop_focus_scope(&self, owner_id: usize) -> windows::Result<()> {
+        let restore = self.with(|globals| {
+            let index = match globals
+                .focus_scopes
+                .iter()
+                .rposition(|scope| scope.owner_id == owner_id)
+            {
+                Some(index) => index,
+                None => return Ok(None),
+            };
+            let scope = globals.focus_scopes.remove(index);
+            if index == globals.focus_scopes.len() {
+                globals.focused_control = scope.saved_focus;
+                Ok(scope.saved_focus)
+            } else {
+                // Scope above keeps its own focus, pass saved one to it
+                globals.focus_scopes[index].saved_focus = scope.saved_focus;
+                Ok(None)
+            }
+        })?;
+        if let Some(panel_id) = restore {
+            self.send_panel_event(panel_id, ControlEvent::FocusSet)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_accessible(
+        &self,
+        panel_id: usize,
+        visual: &ContainerVisual,
+        role: AccessibleRole,
+        name: impl Into<Cow<'static, str>>,
+    ) -> windows::Result<()> {
+        let name = name.into();
+        self.with(|globals| {
+            let host = globals.host();
+            let entry = AccessibleEntry {
+                info: AccessibleInfo { role, name },
+                hwnd: host.hwnd(),
+                visual: visual.clone(),
+                root_visual: host.root_visual.clone(),
+            };
+            globals.accessible.insert(panel_id, entry);
+            Ok(())
+        })
+    }
+
+    pub fn remove_accessible(&self, panel_id: usize) -> windows::Result<()> {
+        self.with(|globals| {
+            globals.accessible.remove(&panel_id);
+            Ok(())
+        })
+    }
+
+    pub fn register_accelerator(
+        &self,
+        panel_id: usize,
+        accelerator: Accelerator,
+    ) -> windows::Result<()> {
+        self.with(|globals| match globals.accelerators.get(&accelerator) {
+            Some(owner_id) if *owner_id != panel_id => Err(winrt_error(format!(
+                "Accelerator {} is already registered by panel {}",
+                accelerator, owner_id
+            ))()),
+            _ => {
+                globals.accelerators.insert(accelerator, panel_id);
+                Ok(())
+            }
+        })
+    }
+
+    pub fn unregister_accelerators(&self, panel_id: usize) -> windows::Result<()> {
+        self.with(|globals| {
+            globals.accelerators.retain(|_, id| *id != panel_id);
+            Ok(())
+        })
+    }
+
+    pub fn set_cursor(&self, cursor_icon: CursorIcon) -> windows::Result<()> {
+        self.with(|globals| {
+            globals.requested_cursor_icon = cursor_icon;
+            Ok(())
+        })
+    }
+
+    pub fn scale_factor(&self) -> windows::Result<f32> {
+        self.with(|globals| Ok(globals.host().scale_factor as f32))
+    }
+
+    pub fn window_backdrop(&self) -> windows::Result<WindowBackdrop> {
+        self.with(|globals| Ok(globals.host().backdrop))
+    }
+}
+// Wrappers keep signatures from before the context, they panic when it's not available
+fn unwrap_context<F, T>(f: F) -> T
+where
+    F: FnOnce(&FrameworkContext) -> windows::Result<T>,
+{
+    current_context()
+        .and_then(|context| f(&context))
+        .unwrap_or_else(|e| panic!("{}", e.message()))
 }
 
 pub fn winrt_error<T: std::fmt::Display + 'static>(e: T) -> impl FnOnce() -> windows::Error {
@@ -185,83 +1308,444 @@ pub fn winrt_error<T: std::fmt::Display + 'static>(e: T) -> impl FnOnce() -> win
     }
 }
 
+// Window is not allowed to be smaller than the panels need, it's checked on each loop
+// iteration because panels may be added or removed at any time
+fn update_min_size(root_panel: &RootPanel) -> windows::Result<()> {
+    let content_size = root_panel.measure()?;
+    globals_with(|globals| {
+        let host = globals.host_mut();
+        let min_size = Vector2 {
+            X: content_size.X.max(host.min_size.X).ceil(),
+            Y: content_size.Y.max(host.min_size.Y).ceil(),
+        };
+        if host.applied_min_size.as_ref() != Some(&min_size) {
+            if let Some(window) = &host.window {
+                window.set_min_inner_size(Some(PhysicalSize::new(
+                    min_size.X as u32,
+                    min_size.Y as u32,
+                )));
+            }
+            host.applied_min_size = Some(min_size);
+        }
+        Ok(())
+    })
+}
+
+fn is_log_panel_toggle(input: &KeyboardInput, modifiers: ModifiersState) -> bool {
+    input.state == ElementState::Pressed
+        && input.virtual_keycode == Some(VirtualKeyCode::F12)
+        && !modifiers.shift()
+}
+
+fn is_debug_panel_toggle(input: &KeyboardInput, modifiers: ModifiersState) -> bool {
+    input.state == ElementState::Pressed
+        && input.virtual_keycode == Some(VirtualKeyCode::F12)
+        && modifiers.shift()
+}
+
+// Log panel is pushed on top of everything and doesn't take input
+fn toggle_log_panel(context: &FrameworkContext, root_panel: &mut RootPanel) -> windows::Result<()> {
+    if let Some(panel_id) = globals_with(|globals| Ok(globals.log_panel.take()))? {
+        root_panel.remove_panel(panel_id)?;
+    } else {
+        let log_panel = LogPanel::new(context)?;
+        let panel_id = log_panel.id();
+        root_panel.push_cell(
+            crate::ribbon_panel::RibbonCellParamsBuilder::default()
+                .panel(log_panel)
+                .input_transparent(true)
+                .create_in(context)?,
+        )?;
+        globals_with_unwrap(|globals| globals.log_panel = Some(panel_id));
+    }
+    Ok(())
+}
+
+// Debug panel is pushed on top like the log panel, its timer keeps the event loop
+// waking up to refresh it
+fn toggle_debug_panel(
+    context: &FrameworkContext,
+    root_panel: &mut RootPanel,
+) -> windows::Result<()> {
+    if let Some(panel_id) = globals_with(|globals| Ok(globals.debug_panel.take()))? {
+        context.kill_timer(panel_id)?;
+        root_panel.remove_panel(panel_id)?;
+    } else {
+        let debug_panel = DebugPanel::new(context)?;
+        let panel_id = debug_panel.id();
+        root_panel.push_cell(
+            crate::ribbon_panel::RibbonCellParamsBuilder::default()
+                .panel(debug_panel)
+                .input_transparent(true)
+                .create_in(context)?,
+        )?;
+        globals_with_unwrap(|globals| {
+            globals.debug_panel = Some(panel_id);
+            globals.debug_counters = DebugCounters::default();
+            globals.debug_refresh_time = Some(Instant::now());
+        });
+        context.set_timer(panel_id, DEBUG_REFRESH_PERIOD)?;
+    }
+    Ok(())
+}
+
+// Snapshot of the tree is taken here, as the debug panel is a part of it
+fn refresh_debug_panel(root_panel: &mut RootPanel) -> windows::Result<()> {
+    let now = Instant::now();
+    let refresh = globals_with(|globals| {
+        Ok(match (globals.debug_panel, globals.debug_refresh_time) {
+            (Some(panel_id), Some(time)) if now - time >= DEBUG_REFRESH_PERIOD => {
+                globals.debug_refresh_time = Some(now);
+                Some((
+                    panel_id,
+                    std::mem::take(&mut globals.debug_counters),
+                    globals.focused_control,
+                    now - time,
+                ))
+            }
+            _ => None,
+        })
+    })?;
+    if let Some((panel_id, counters, focused, period)) = refresh {
+        let mut outlines = Vec::new();
+        collect_outlines(root_panel, &root_panel.visual(), panel_id, &mut outlines)?;
+        let text = debug_text(root_panel, panel_id, focused, &counters, period);
+        if let Some(debug_panel) = root_panel
+            .find_panel(panel_id)
+            .and_then(|panel| panel.downcast_mut::<DebugPanel>())
+        {
+            debug_panel.update(text, outlines)?;
+        }
+    }
+    Ok(())
+}
+
+fn drop_window_targets() {
+    globals_with_unwrap(|globals| {
+        for host in globals.hosts.values_mut() {
+            drop(host.target.take());
+        }
+    })
+}
+
+// Secondary windows are closed before the main one
+fn close_all_windows() -> windows::Result<()> {
+    let (window_ids, main_window) =
+        globals_with(|globals| Ok((globals.window_ids(), globals.main_window)))?;
+    for window_id in window_ids {
+        if window_id != main_window {
+            destroy_window(window_id)?;
+        }
+    }
+    window_root_with(main_window, |root_panel| root_panel.on_close())?;
+    drop_window_targets();
+    Ok(())
+}
+
+// Input and layout of one window, goes to its root panel
+fn process_window_event(
+    context: &FrameworkContext,
+    root_panel: &mut RootPanel,
+    event: &WindowEvent,
+    is_main_window: bool,
+) -> windows::Result<()> {
+    match event {
+        WindowEvent::Resized(size) => {
+            // Minimized window gets zero size, panels are not resized to it
+            let minimized = size.width == 0 || size.height == 0;
+            let was_suspended = globals_with(|globals| {
+                Ok(std::mem::replace(
+                    &mut globals.host_mut().suspended,
+                    minimized,
+                ))
+            })?;
+            if minimized {
+                if !was_suspended {
+                    root_panel.on_suspend()?;
+                }
+            } else {
+                let size = Vector2 {
+                    X: size.width as f32,
+                    Y: size.height as f32,
+                };
+                globals_with(|globals| {
+                    globals.last_resize = Some(Instant::now());
+                    Ok(())
+                })?;
+                root_visual().SetSize(&size)?;
+                root_panel.on_resize(&size)?;
+                if was_suspended {
+                    root_panel.on_resume()?;
+                }
+            }
+        }
+        WindowEvent::ScaleFactorChanged {
+            scale_factor,
+            new_inner_size,
+        } => {
+            globals_with_unwrap(|globals| globals.host_mut().scale_factor = *scale_factor);
+            set_chrome_scale_factor(*scale_factor as f32);
+            let size = Vector2 {
+                X: new_inner_size.width as f32,
+                Y: new_inner_size.height as f32,
+            };
+            // Resize is forced even if size is the same to re-render text surfaces
+            root_visual().SetSize(&size)?;
+            root_panel.on_resize(&size)?;
+        }
+        WindowEvent::Focused(focused) => {
+            root_panel.on_focus_changed(*focused)?;
+        }
+        WindowEvent::ModifiersChanged(state) => {
+            globals_with_unwrap(|globals| globals.host_mut().modifiers = *state);
+            root_panel.on_modifiers_changed(*state)?;
+        }
+        WindowEvent::KeyboardInput { input, .. } => {
+            tracing::debug!(
+                key = ?input.virtual_keycode,
+                state = ?input.state,
+                "keyboard input"
+            );
+            let modifiers = globals_with_unwrap(|globals| globals.host().modifiers);
+            // Diagnostic panels live in the main window
+            if is_main_window && is_log_panel_toggle(input, modifiers) {
+                toggle_log_panel(context, root_panel)?;
+            } else if is_main_window && is_debug_panel_toggle(input, modifiers) {
+                toggle_debug_panel(context, root_panel)?;
+            } else if !process_accelerator(*input, modifiers)? {
+                let _ = root_panel.on_keyboard_input(*input, modifiers)?;
+            }
+        }
+        WindowEvent::ReceivedCharacter(ch) => {
+            let _ = root_panel.on_char(*ch)?;
+        }
+        WindowEvent::CursorMoved { position, .. } => {
+            let position = Vector2 {
+                X: position.x as f32,
+                Y: position.y as f32,
+            };
+            root_panel.on_mouse_move(&position)?;
+            apply_cursor();
+        }
+        WindowEvent::CursorEntered { .. } => {
+            root_panel.on_cursor_inside_changed(true)?;
+        }
+        // Move outside of the window resets hover state of panels
+        WindowEvent::CursorLeft { .. } => {
+            root_panel.on_mouse_move(&Vector2 { X: -1., Y: -1. })?;
+            root_panel.on_cursor_inside_changed(false)?;
+            apply_cursor();
+        }
+        WindowEvent::MouseWheel { delta, .. } => {
+            let _ = root_panel.on_mouse_wheel(*delta)?;
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+            tracing::debug!(?button, ?state, "mouse input");
+            let touch_active = globals_with_unwrap(|globals| {
+                let host = globals.host();
+                host.primary_touch.is_some()
+                    || host
+                        .last_touch_time
+                        .map_or(false, |t| t.elapsed() < TOUCH_MOUSE_SUPPRESS_PERIOD)
+            });
+            if !touch_active {
+                let _ = root_panel.on_mouse_input(*button, *state)?;
+            }
+        }
+        WindowEvent::Touch(touch) => {
+            // Only the first finger is tracked, it's translated to left mouse button
+            let position = Vector2 {
+                X: touch.location.x as f32,
+                Y: touch.location.y as f32,
+            };
+            let primary_touch = globals_with_unwrap(|globals| globals.host().primary_touch);
+            match touch.phase {
+                TouchPhase::Started => {
+                    if primary_touch.is_none() {
+                        globals_with_unwrap(|globals| {
+                            globals.host_mut().primary_touch = Some(touch.id)
+                        });
+                        root_panel.on_mouse_move(&position)?;
+                        let _ =
+                            root_panel.on_mouse_input(MouseButton::Left, ElementState::Pressed)?;
+                    }
+                }
+                TouchPhase::Moved => {
+                    if primary_touch == Some(touch.id) {
+                        root_panel.on_mouse_move(&position)?;
+                    }
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    if primary_touch == Some(touch.id) {
+                        globals_with_unwrap(|globals| {
+                            let host = globals.host_mut();
+                            host.primary_touch = None;
+                            host.last_touch_time = Some(Instant::now());
+                        });
+                        // Lifted finger doesn't hover anything, and
+                        // cancelled touch is released outside to not click
+                        let outside = Vector2 { X: -1., Y: -1. };
+                        if touch.phase == TouchPhase::Ended {
+                            root_panel.on_mouse_move(&position)?;
+                        } else {
+                            root_panel.on_mouse_move(&outside)?;
+                        }
+                        let _ =
+                            root_panel.on_mouse_input(MouseButton::Left, ElementState::Released)?;
+                        root_panel.on_mouse_move(&outside)?;
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 pub fn run(panel: impl Panel + 'static) -> ! {
-    let event_loop =
-        globals_with_unwrap(|globals| globals.event_loop.take().expect("Unexpected second run"));
-    let mut root_panel =
-        globals_with_unwrap(|globals| globals.root_panel.take().expect("Unexpected second run"));
+    let event_loop = globals_with_unwrap(|globals| {
+        globals
+            .event_loop
+            .take()
+            .expect("Unexpected second run or run in headless mode")
+    });
     let mut local_pool =
         globals_with_unwrap(|globals| globals.local_pool.take().expect("Unexpected second run"));
+    let context = current_context().expect("Framework context not initialized");
 
-    let root_visual = globals_with_unwrap(|globals| globals.root_visual.clone());
-
-    root_panel
-        .push_cell(
+    root_ribbon_with(|root_panel| {
+        root_panel.push_cell(
             crate::ribbon_panel::RibbonCellParamsBuilder::default()
                 .panel(panel)
-                .create()
-                .expect("Error:"),
-        )
-        .expect("Error:");
-    root_panel.on_init().expect("Error:");
+                .create_in(&context)?,
+        )?;
+        root_panel.on_init()
+    })
+    .expect("Error:");
+
+    let mut gamepad_poller = GamepadPoller::new();
 
-    event_loop.run(move |mut evt, _, control_flow| {
+    event_loop.run(move |mut evt, event_loop, control_flow| {
+        if let Event::LoopDestroyed = evt {
+            let (shutdown_hook, exit_code) =
+                globals_with_unwrap(|globals| (globals.shutdown_hook.take(), globals.exit_code));
+            if let Some(shutdown_hook) = shutdown_hook {
+                shutdown_hook(exit_code);
+            }
+            std::process::exit(exit_code);
+        }
+        globals_with_unwrap(|globals| globals.debug_counters.count(&evt));
         // just to allow '?' usage
         let mut run = || -> windows::Result<()> {
-            local_pool.run_until_stalled();
-            *control_flow = ControlFlow::Wait;
+            let main_window = globals_with(|globals| Ok(globals.main_window))?;
             match &mut evt {
-                Event::WindowEvent { event, window_id } => match event {
-                    WindowEvent::Resized(size) => {
-                        let size = Vector2 {
-                            X: size.width as f32,
-                            Y: size.height as f32,
-                        };
-                        root_visual.SetSize(&size)?;
-                        root_panel.on_resize(&size)?;
-                    }
-                    WindowEvent::CloseRequested => {
-                        if *window_id == globals_with(|globals| Ok(globals.window.id()))? {
-                            // TODO: notify panels
-                            *control_flow = ControlFlow::Exit;
-                            globals_with(|globals| {
-                                drop(globals.target.take());
-                                Ok(())
-                            })?;
-                        }
+                // Events still may come for the window being destroyed
+                Event::WindowEvent { window_id, .. } if !context.has_root(*window_id) => {}
+                Event::WindowEvent {
+                    event: WindowEvent::CloseRequested,
+                    window_id,
+                } => {
+                    if *window_id == main_window {
+                        close_all_windows()?;
+                        *control_flow = ControlFlow::Exit;
+                    } else {
+                        destroy_window(*window_id)?;
                     }
-                    WindowEvent::KeyboardInput { input, .. } => {
-                        let _ = root_panel.on_keyboard_input(*input)?;
-                    }
-                    WindowEvent::CursorMoved { position, .. } => {
-                        let position = Vector2 {
-                            X: position.x as f32,
-                            Y: position.y as f32,
-                        };
-                        root_panel.on_mouse_move(&position)?;
-                    }
-                    WindowEvent::MouseInput { state, button, .. } => {
-                        let _ = root_panel.on_mouse_input(*button, *state)?;
+                }
+                Event::WindowEvent { event, window_id } => {
+                    let is_main_window = *window_id == main_window;
+                    window_root_with(*window_id, |root_panel| {
+                        process_window_event(&context, root_panel, event, is_main_window)
+                    })?;
+                }
+                Event::RedrawRequested(_) => {
+                    let theme_changed =
+                        globals_with(|globals| Ok(std::mem::take(&mut globals.theme_changed)))?;
+                    if theme_changed {
+                        all_roots_with(|root_panel| root_panel.on_resize(&root_visual().Size()?))?;
                     }
-                    _ => {}
-                },
+                }
                 Event::MainEventsCleared => {
-                    root_panel.on_idle()?;
+                    let quit_requested =
+                        globals_with(|globals| Ok(std::mem::take(&mut globals.quit_requested)))?;
+                    if quit_requested && *control_flow != ControlFlow::Exit {
+                        close_all_windows()?;
+                        *control_flow = ControlFlow::Exit;
+                        return Ok(());
+                    }
+                    close_requested_windows()?;
+                    create_pending_windows(event_loop)?;
+                    window_root_with(main_window, |root_panel| {
+                        for input in gamepad_poller.poll()? {
+                            let _ = root_panel.on_gamepad_input(input)?;
+                        }
+                        Ok(())
+                    })?;
+                    let now = Instant::now();
+                    let expired_timers =
+                        globals_with(|globals| Ok(globals.take_expired_timers(now)))?;
+                    all_roots_with(|root_panel| {
+                        for timer_id in &expired_timers {
+                            root_panel.on_timer(*timer_id)?;
+                        }
+                        Ok(())
+                    })?;
+                    window_root_with(main_window, refresh_debug_panel)?;
+                    // Requests made so far are served by this on_idle, only ones made from
+                    // on_idle itself need another pass
+                    globals_with_unwrap(|globals| globals.idle_requested = false);
+                    // Panels render pending changes here, after all updates of this frame
+                    all_roots_with(|root_panel| root_panel.on_idle())?;
+                    recreate_device_if_lost()?;
+                    all_roots_with(|root_panel| update_min_size(root_panel))?;
                 }
                 Event::UserEvent(ref mut panel_event) => {
-                    root_panel.on_panel_event(panel_event)?;
+                    let span = tracing::debug_span!(
+                        "panel_event",
+                        panel_id = panel_event.panel_id,
+                        broadcast = panel_event.broadcast
+                    );
+                    let _enter = span.enter();
+                    if !deliver_panel_event(panel_event)? {
+                        all_roots_with(|root_panel| root_panel.on_panel_event(panel_event))?;
+                    }
                 }
                 _ => {}
             }
+            // Continue async tasks woken by processed event
+            local_pool.run_until_stalled();
+            if let Some(e) = globals_with(|globals| Ok(globals.async_error.take()))? {
+                return Err(e);
+            }
+            // Scheduled after async tasks, as they may request idle too
+            if let Event::MainEventsCleared = evt {
+                if *control_flow != ControlFlow::Exit {
+                    let wake_time = globals_with(|globals| Ok(globals.next_wake_time()))?;
+                    let wake_time = match (wake_time, gamepad_poller.next_poll_time()) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+                    *control_flow = match wake_time {
+                        Some(time) => ControlFlow::WaitUntil(time),
+                        None => ControlFlow::Wait,
+                    };
+                }
+            }
             Ok(())
         };
         if let Err(e) = run() {
-            dbg!(&e);
-            globals_with(|globals| {
-                drop(globals.target.take());
-                Ok(())
-            })
-            .unwrap();
+            if check_device_lost(&e).unwrap_or(false) {
+                // Wake up immediately to recreate the device
+                *control_flow = ControlFlow::Poll;
+                return;
+            }
+            tracing::error!(code = e.code().0, message = %e.message(), "panel callback failed");
+            // Error in the error reporting itself is not reported again
+            if report_error(&e).unwrap_or(false) {
+                return;
+            }
+            drop_window_targets();
+            globals_with_unwrap(|globals| globals.exit_code = 1);
             *control_flow = ControlFlow::Exit;
         }
     });
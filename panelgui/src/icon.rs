@@ -0,0 +1,198 @@
+use std::f32::consts::PI;
+
+use bindings::{
+    Microsoft::Graphics::Canvas::{
+        CanvasDrawingSession,
+        Geometry::{CanvasFigureLoop, CanvasGeometry, CanvasPathBuilder},
+    },
+    Windows::{Foundation::Numerics::Vector2, UI::Color},
+};
+
+use crate::globals::canvas_device;
+
+// Vector icons drawn with Win2D paths, for places where font glyphs may be missing
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IconId {
+    Menu,
+    Undo,
+    Restart,
+    Play,
+    Close,
+    Share,
+    Minimize,
+    Maximize,
+    // Returns maximized window to its previous size
+    Restore,
+}
+
+// Icon occupies this part of the smaller side of the area
+const ICON_SIZE_RATIO: f32 = 0.6;
+// Relative to icon size
+const ICON_STROKE_RATIO: f32 = 0.08;
+
+impl IconId {
+    // Used as accessible name of icon-only buttons
+    pub fn name(&self) -> &'static str {
+        match self {
+            IconId::Menu => "Menu",
+            IconId::Undo => "Undo",
+            IconId::Restart => "Restart",
+            IconId::Play => "Play",
+            IconId::Close => "Close",
+            IconId::Share => "Share",
+            IconId::Minimize => "Minimize",
+            IconId::Maximize => "Maximize",
+            IconId::Restore => "Restore",
+        }
+    }
+
+    fn figures(&self) -> &'static [Figure] {
+        match self {
+            IconId::Menu => &MENU,
+            IconId::Undo => &UNDO,
+            IconId::Restart => &RESTART,
+            IconId::Play => &PLAY,
+            IconId::Close => &CLOSE,
+            IconId::Share => &SHARE,
+            IconId::Minimize => &MINIMIZE,
+            IconId::Maximize => &MAXIMIZE,
+            IconId::Restore => &RESTORE,
+        }
+    }
+}
+
+// Figures are described in the unit square: polyline optionally continued by an arc
+struct Figure {
+    points: &'static [(f32, f32)],
+    arc: Option<Arc>,
+    closed: bool,
+}
+
+// Angles are in radians, clockwise from the positive X axis
+struct Arc {
+    center: (f32, f32),
+    radius: f32,
+    start: f32,
+    sweep: f32,
+}
+
+const fn line(points: &'static [(f32, f32)]) -> Figure {
+    Figure {
+        points,
+        arc: None,
+        closed: false,
+    }
+}
+
+const MENU: [Figure; 3] = [
+    line(&[(0.15, 0.25), (0.85, 0.25)]),
+    line(&[(0.15, 0.5), (0.85, 0.5)]),
+    line(&[(0.15, 0.75), (0.85, 0.75)]),
+];
+const UNDO: [Figure; 3] = [
+    Figure {
+        points: &[(0.2, 0.4), (0.6, 0.4)],
+        arc: Some(Arc {
+            center: (0.6, 0.6),
+            radius: 0.2,
+            start: -PI / 2.,
+            sweep: PI,
+        }),
+        closed: false,
+    },
+    line(&[(0.6, 0.8), (0.35, 0.8)]),
+    line(&[(0.35, 0.25), (0.2, 0.4), (0.35, 0.55)]),
+];
+const RESTART: [Figure; 2] = [
+    Figure {
+        points: &[(0.5, 0.2)],
+        arc: Some(Arc {
+            center: (0.5, 0.5),
+            radius: 0.3,
+            start: -PI / 2.,
+            sweep: -1.75 * PI,
+        }),
+        closed: false,
+    },
+    line(&[(0.38, 0.08), (0.5, 0.2), (0.38, 0.32)]),
+];
+const PLAY: [Figure; 1] = [Figure {
+    points: &[(0.3, 0.2), (0.8, 0.5), (0.3, 0.8)],
+    arc: None,
+    closed: true,
+}];
+const CLOSE: [Figure; 2] = [
+    line(&[(0.2, 0.2), (0.8, 0.8)]),
+    line(&[(0.8, 0.2), (0.2, 0.8)]),
+];
+// Arrow going up out of an open box
+const SHARE: [Figure; 3] = [
+    line(&[(0.25, 0.45), (0.25, 0.85), (0.75, 0.85), (0.75, 0.45)]),
+    line(&[(0.5, 0.65), (0.5, 0.12)]),
+    line(&[(0.33, 0.29), (0.5, 0.12), (0.67, 0.29)]),
+];
+const MINIMIZE: [Figure; 1] = [line(&[(0.25, 0.5), (0.75, 0.5)])];
+const MAXIMIZE: [Figure; 1] = [Figure {
+    points: &[(0.25, 0.25), (0.75, 0.25), (0.75, 0.75), (0.25, 0.75)],
+    arc: None,
+    closed: true,
+}];
+// Two overlapping windows
+const RESTORE: [Figure; 2] = [
+    Figure {
+        points: &[(0.25, 0.38), (0.62, 0.38), (0.62, 0.75), (0.25, 0.75)],
+        arc: None,
+        closed: true,
+    },
+    line(&[
+        (0.38, 0.38),
+        (0.38, 0.25),
+        (0.75, 0.25),
+        (0.75, 0.62),
+        (0.62, 0.62),
+    ]),
+];
+
+// Draws icon centered in the area of given size
+pub(crate) fn draw_icon(
+    ds: &CanvasDrawingSession,
+    icon: IconId,
+    size: Vector2,
+    color: Color,
+) -> windows::Result<()> {
+    let side = size.X.min(size.Y) * ICON_SIZE_RATIO;
+    let offset = Vector2 {
+        X: (size.X - side) / 2.,
+        Y: (size.Y - side) / 2.,
+    };
+    let point = |(x, y): (f32, f32)| Vector2 {
+        X: offset.X + x * side,
+        Y: offset.Y + y * side,
+    };
+    let path_builder = CanvasPathBuilder::new(canvas_device())?;
+    for figure in icon.figures() {
+        let mut points = figure.points.iter();
+        if let Some(first) = points.next() {
+            path_builder.BeginFigure(point(*first))?;
+        }
+        for p in points {
+            path_builder.AddLine(point(*p))?;
+        }
+        if let Some(arc) = &figure.arc {
+            path_builder.AddArcAroundEllipse(
+                point(arc.center),
+                arc.radius * side,
+                arc.radius * side,
+                arc.start,
+                arc.sweep,
+            )?;
+        }
+        path_builder.EndFigure(if figure.closed {
+            CanvasFigureLoop::Closed
+        } else {
+            CanvasFigureLoop::Open
+        })?;
+    }
+    let geometry = CanvasGeometry::CreatePath(path_builder)?;
+    ds.DrawGeometryWithColorAndStrokeWidth(geometry, color, side * ICON_STROKE_RATIO)
+}
@@ -1,34 +1,144 @@
 #[macro_use]
 extern crate derive_builder;
 
+mod accelerator;
+mod accessibility;
+mod animation;
+mod backdrop;
 mod background_panel;
+mod button_content_panel;
 mod button_panel;
+mod capture;
+mod confetti_panel;
+mod context;
 mod control;
+mod debug_panel;
+mod dialog_panel;
+mod edit_panel;
+mod event_handlers;
+mod gamepad;
 mod globals;
+mod icon;
 mod interop;
+mod list_panel;
+mod logging;
+mod macros;
 mod message_box_panel;
 mod numerics;
+mod overlay_panel;
 mod panel;
+mod property;
 mod ribbon_panel;
+mod score_panel;
+mod screen_manager;
+mod shadow;
+mod slider_panel;
+mod sound;
+mod style;
+mod test_harness;
 mod text_panel;
+mod theme;
+mod title_bar_panel;
+mod toast_panel;
+mod toggle_panel;
+mod window_chrome;
+mod window_host;
 mod window_target;
 
 pub use globals::{
-    canvas_device, composition_graphics_device, compositor, get_next_id, init_window, run,
-    send_panel_event, spawner, winrt_error,
+    accelerator_for, accessible_info, animation_settings, broadcast_panel_event, canvas_device,
+    close_window, composition_graphics_device, compositor, current_window, expect_panel_event,
+    get_next_id, init_window, init_window_with, is_resizing, is_window_maximized, kill_timer,
+    main_window, minimize_window, open_window, play_sound, pop_focus_scope, push_focus_scope, quit,
+    register_accelerator, remove_accessible, request_exit, request_idle, root_panel_with, run,
+    scale_factor, send_panel_event, set_accessible, set_animation_settings, set_cursor,
+    set_error_hook, set_error_policy, set_muted, set_resize_debounce, set_shutdown_hook, set_theme,
+    set_timer, set_window_dark_mode, spawn, spawner, theme, toggle_maximize_window,
+    unregister_accelerators, window_backdrop, window_panel_with, window_position, window_size,
+    winrt_error, ErrorPolicy, ExpectPanelEvent, WindowClosed, WindowParams, WindowParamsBuilder,
 };
 
-pub use panel::{EmptyPanel, Handle, Panel, PanelEvent, PanelHandle};
+pub use accelerator::{Accelerator, AcceleratorEvent};
+
+pub use accessibility::{AccessibleInfo, AccessibleRole};
+
+pub use animation::AnimationSettings;
+
+pub use backdrop::WindowBackdrop;
+
+pub use panel::{EmptyPanel, Handle, Panel, PanelEvent, PanelHandle, PanelRemoved};
 
 pub use control::{Control, ControlManager};
 
+pub use dialog_panel::{
+    show_dialog, spawn_dialog, DialogPanel, DialogPanelHandle, DialogParamsBuilder,
+};
+
+pub use edit_panel::{EditPanelEvent, EditPanelHandle, EditParamsBuilder};
+
+pub use event_handlers::EventHandlers;
+
+pub use property::{Property, PropertyChanged, PropertyHandle};
+
+pub use gamepad::{GamepadButton, GamepadInput};
+
+pub use icon::IconId;
+
+pub use logging::recent_log_lines;
+
 pub use background_panel::BackgroundParamsBuilder;
 
+pub use button_content_panel::{ButtonContentPanel, ButtonContentParamsBuilder, ButtonLayout};
+
 pub use button_panel::{ButtonPanelEvent, ButtonPanelHandle, ButtonParamsBuilder};
 
-pub use message_box_panel::{MessageBoxButton, MessageBoxPanelHandle, MessageBoxParamsBuilder};
+pub use capture::{
+    capture_visuals, capture_window, capture_window_to_png, relative_offset, WindowImage,
+};
+
+pub use confetti_panel::{ConfettiPanel, ConfettiPanelHandle, ConfettiParamsBuilder};
+pub use context::FrameworkContext;
+
+pub use list_panel::{ListPanelEvent, ListPanelHandle, ListParamsBuilder};
+
+pub use message_box_panel::{
+    show_message_box, spawn_message_box, MessageBoxButton, MessageBoxPanel, MessageBoxPanelHandle,
+    MessageBoxParamsBuilder,
+};
+
+pub use overlay_panel::{
+    OverlayAnchor, OverlayItem, OverlayItemParamsBuilder, OverlayPanel, OverlayPanelHandle,
+    OverlayParamsBuilder, OverlayPosition, OverlaySize,
+};
 
 pub use ribbon_panel::{
-    RibbonCellParamsBuilder, RibbonOrientation, RibbonPanel, RibbonPanelHandle, RibbonParamsBuilder,
+    CellAlignment, CellLimit, CellSizing, CellTransition, CellTransitionFinished,
+    RibbonCellParamsBuilder, RibbonOrientation, RibbonPanel, RibbonPanelHandle,
+    RibbonParamsBuilder,
 };
+pub use score_panel::{ScoreFormatter, ScorePanelHandle, ScoreParamsBuilder};
+pub use screen_manager::{
+    ScreenManager, ScreenManagerHandle, ScreenManagerParamsBuilder, ScreenTransition,
+};
+pub use shadow::{create_rounded_mask, create_shadow_visual};
+pub use slider_panel::{SliderPanelEvent, SliderPanelHandle, SliderParamsBuilder};
+pub use sound::SoundId;
+pub use style::{ControlStyle, DropShadowStyle, StyleState};
+pub use test_harness::TestHarness;
 pub use text_panel::{TextPanelHandle, TextParamsBuilder};
+pub use theme::{is_system_dark_mode, Theme};
+pub use title_bar_panel::{TitleBarPanel, TitleBarPanelHandle, TitleBarParamsBuilder};
+pub use toast_panel::show_toast;
+pub use toggle_panel::{TogglePanelEvent, TogglePanelHandle, ToggleParamsBuilder};
+pub use window_chrome::{HitTestKind, HitTestRegion};
+pub use window_host::{WindowParams, WindowParamsBuilder};
+
+// Paths used by exported macros, so they expand the same in crates with other imports
+#[doc(hidden)]
+pub mod __private {
+    pub use bindings::Windows::Foundation::Numerics::Vector2;
+    pub use windows;
+    pub use winit::event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    };
+}
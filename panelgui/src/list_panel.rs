@@ -0,0 +1,488 @@
+use std::{any::Any, borrow::Cow};
+
+use bindings::Windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::{
+        Color, Colors,
+        Composition::{ContainerVisual, SpriteVisual},
+    },
+};
+use winit::event::{
+    ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    control::{Control, ControlHandle},
+    gamepad::{GamepadButton, GamepadInput},
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelHandle},
+    text_panel::{TextPanel, TextParamsBuilder},
+};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ListPanelEvent {
+    Selected(usize),
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct ListParams {
+    #[builder(default = "{Vec::new()}")]
+    items: Vec<Cow<'static, str>>,
+    // Index of highlighted item
+    #[builder(default = "{0}")]
+    current: usize,
+    // Number of items fitting the panel height, the rest is scrolled
+    #[builder(default = "{5}")]
+    visible_items: usize,
+    #[builder(default = "{true}")]
+    enabled: bool,
+    #[builder(default = "{Colors::LightSteelBlue().unwrap()}")]
+    highlight_color: Color,
+}
+
+impl ListParamsBuilder {
+    pub fn create(self) -> windows::Result<ListPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<ListPanel> {
+        match self.build() {
+            Ok(params) => Ok(ListPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct ListPanelHandle(usize);
+
+impl Handle for ListPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<ListPanel, ListPanelEvent> for ListPanelHandle {}
+
+impl ControlHandle for ListPanelHandle {
+    fn as_control<'a>(&self, root_panel: &'a mut dyn Panel) -> Option<&'a mut dyn Control> {
+        self.at(root_panel).ok().map(|p| p as &mut dyn Control)
+    }
+}
+
+pub struct ListPanel {
+    context: FrameworkContext,
+    id: usize,
+    params: ListParams,
+    visual: ContainerVisual,
+    items_visual: ContainerVisual,
+    highlight_visual: SpriteVisual,
+    item_panels: Vec<TextPanel>,
+    // Index of first visible item
+    scroll: usize,
+    focused: bool,
+    mouse_pos: Option<Vector2>,
+}
+
+impl ListPanel {
+    pub fn new(context: &FrameworkContext, params: ListParams) -> windows::Result<Self> {
+        if params.visible_items == 0 {
+            return Err(winrt_error("List must have at least one visible item")());
+        }
+        let id = context.next_id()?;
+        let compositor = context.compositor()?;
+        let visual = compositor.CreateContainerVisual()?;
+        // Hide items scrolled out of the panel
+        visual.SetClip(compositor.CreateInsetClip()?)?;
+        let items_visual = compositor.CreateContainerVisual()?;
+        let highlight_visual = compositor.CreateSpriteVisual()?;
+        visual.Children()?.InsertAtTop(items_visual.clone())?;
+        let mut panel = Self {
+            context: context.clone(),
+            id,
+            params,
+            visual,
+            items_visual,
+            highlight_visual,
+            item_panels: Vec::new(),
+            scroll: 0,
+            focused: false,
+            mouse_pos: None,
+        };
+        panel.create_item_panels()?;
+        Ok(panel)
+    }
+    pub fn handle(&self) -> ListPanelHandle {
+        ListPanelHandle(self.id)
+    }
+    pub fn items(&self) -> &[Cow<'static, str>] {
+        &self.params.items
+    }
+    pub fn set_items(&mut self, items: Vec<Cow<'static, str>>) -> windows::Result<()> {
+        for item_panel in &mut self.item_panels {
+            item_panel.on_close()?;
+        }
+        self.params.items = items;
+        self.create_item_panels()?;
+        self.on_resize(&self.visual.Size()?)
+    }
+    // Index of the item at the top of the panel
+    pub fn first_visible(&self) -> usize {
+        self.scroll
+    }
+    pub fn current(&self) -> usize {
+        self.params.current
+    }
+    pub fn set_current(&mut self, current: usize) -> windows::Result<()> {
+        if !self.params.items.is_empty() {
+            self.params.current = current.min(self.params.items.len() - 1);
+            self.ensure_visible(self.params.current);
+        }
+        self.redraw()
+    }
+
+    fn create_item_panels(&mut self) -> windows::Result<()> {
+        self.items_visual.Children()?.RemoveAll()?;
+        self.items_visual
+            .Children()?
+            .InsertAtBottom(self.highlight_visual.clone())?;
+        self.item_panels.clear();
+        for item in &self.params.items {
+            let item_panel = TextParamsBuilder::default()
+                .text(item.clone())
+                .enabled(self.params.enabled)
+                .create_in(&self.context)?;
+            self.items_visual
+                .Children()?
+                .InsertAtTop(item_panel.visual())?;
+            self.item_panels.push(item_panel);
+        }
+        if self.params.current >= self.params.items.len() {
+            self.params.current = self.params.items.len().saturating_sub(1);
+        }
+        self.scroll = 0;
+        self.ensure_visible(self.params.current);
+        Ok(())
+    }
+    fn item_height(&self) -> windows::Result<f32> {
+        Ok(self.visual.Size()?.Y / self.params.visible_items as f32)
+    }
+    fn ensure_visible(&mut self, index: usize) {
+        if index < self.scroll {
+            self.scroll = index;
+        } else if index >= self.scroll + self.params.visible_items {
+            self.scroll = index + 1 - self.params.visible_items;
+        }
+    }
+    // Moves the view only, current item may go out of it
+    fn scroll_by(&mut self, lines: isize) -> windows::Result<()> {
+        let max_scroll = self
+            .params
+            .items
+            .len()
+            .saturating_sub(self.params.visible_items);
+        let scroll = (self.scroll as isize + lines).max(0) as usize;
+        self.scroll = scroll.min(max_scroll);
+        self.redraw()
+    }
+    fn item_by_position(&self, position: &Vector2) -> windows::Result<Option<usize>> {
+        let item_height = self.item_height()?;
+        if item_height <= 0. || position.Y < 0. {
+            return Ok(None);
+        }
+        let index = self.scroll + (position.Y / item_height) as usize;
+        if index < self.params.items.len() {
+            Ok(Some(index))
+        } else {
+            Ok(None)
+        }
+    }
+    fn move_current(&mut self, current: usize) -> windows::Result<()> {
+        if !self.params.items.is_empty() {
+            self.params.current = current.min(self.params.items.len() - 1);
+            self.ensure_visible(self.params.current);
+            self.redraw()?;
+        }
+        Ok(())
+    }
+    fn select(&mut self) -> windows::Result<()> {
+        if self.params.enabled && self.params.current < self.params.items.len() {
+            self.context
+                .send_panel_event(self.id, ListPanelEvent::Selected(self.params.current))?;
+        }
+        Ok(())
+    }
+    fn redraw(&mut self) -> windows::Result<()> {
+        let size = self.visual.Size()?;
+        let item_height = self.item_height()?;
+        self.items_visual.SetOffset(Vector3 {
+            X: 0.,
+            Y: -(self.scroll as f32) * item_height,
+            Z: 0.,
+        })?;
+        let highlight_color = if !self.params.enabled {
+            Colors::LightGray()?
+        } else if self.focused {
+            self.params.highlight_color.clone()
+        } else {
+            // Keep position visible without focus, but less prominent
+            let mut color = self.params.highlight_color.clone();
+            color.A /= 2;
+            color
+        };
+        self.highlight_visual.SetBrush(
+            self.context
+                .compositor()?
+                .CreateColorBrushWithColor(highlight_color)?,
+        )?;
+        self.highlight_visual.SetSize(Vector2 {
+            X: size.X,
+            Y: item_height,
+        })?;
+        self.highlight_visual.SetOffset(Vector3 {
+            X: 0.,
+            Y: self.params.current as f32 * item_height,
+            Z: 0.,
+        })?;
+        self.highlight_visual
+            .SetIsVisible(!self.params.items.is_empty())?;
+        Ok(())
+    }
+}
+
+impl Panel for ListPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            for item_panel in &mut self.item_panels {
+                if let Some(panel) = item_panel.find_panel(id) {
+                    return Some(panel);
+                }
+            }
+            None
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        for item_panel in &mut self.item_panels {
+            item_panel.on_close()?;
+        }
+        Ok(())
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        let mut size = Vector2 { X: 0., Y: 0. };
+        for item_panel in &self.item_panels {
+            let item = item_panel.measure()?;
+            size.X = size.X.max(item.X);
+            size.Y = size.Y.max(item.Y);
+        }
+        size.Y *= self.params.visible_items as f32;
+        Ok(size)
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        let item_height = self.item_height()?;
+        let item_size = Vector2 {
+            X: size.X,
+            Y: item_height,
+        };
+        self.items_visual.SetSize(Vector2 {
+            X: size.X,
+            Y: item_height * self.item_panels.len() as f32,
+        })?;
+        for (i, item_panel) in self.item_panels.iter_mut().enumerate() {
+            item_panel.visual().SetOffset(Vector3 {
+                X: 0.,
+                Y: i as f32 * item_height,
+                Z: 0.,
+            })?;
+            item_panel.visual().SetSize(item_size.clone())?;
+            item_panel.on_resize(&item_size)?;
+        }
+        self.redraw()
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        for item_panel in &mut self.item_panels {
+            item_panel.on_idle()?;
+        }
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        for item_panel in &mut self.item_panels {
+            item_panel.on_suspend()?;
+        }
+        Ok(())
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        for item_panel in &mut self.item_panels {
+            item_panel.on_resume()?;
+        }
+        Ok(())
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        for item_panel in &mut self.item_panels {
+            item_panel.on_device_lost()?;
+        }
+        Ok(())
+    }
+
+    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
+        self.mouse_pos = Some(position.clone());
+        if self.params.enabled {
+            if let Some(index) = self.item_by_position(position)? {
+                if index != self.params.current {
+                    self.move_current(index)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        if !self.params.enabled || button != MouseButton::Left {
+            return Ok(false);
+        }
+        if state == ElementState::Pressed {
+            if let Some(position) = self.mouse_pos.clone() {
+                self.set_focus()?;
+                if let Some(index) = self.item_by_position(&position)? {
+                    self.move_current(index)?;
+                    self.select()?;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        if self.focused && self.params.enabled && input.state == ElementState::Pressed {
+            if let Some(code) = input.virtual_keycode {
+                let current = self.params.current;
+                let page = self.params.visible_items;
+                match code {
+                    VirtualKeyCode::Escape => self.clear_focus()?,
+                    VirtualKeyCode::Tab => {
+                        if modifiers.shift() {
+                            self.set_focus_to_prev()?;
+                        } else {
+                            self.set_focus_to_next()?;
+                        }
+                    }
+                    VirtualKeyCode::Up => self.move_current(current.saturating_sub(1))?,
+                    VirtualKeyCode::Down => self.move_current(current + 1)?,
+                    VirtualKeyCode::PageUp => self.move_current(current.saturating_sub(page))?,
+                    VirtualKeyCode::PageDown => self.move_current(current + page)?,
+                    VirtualKeyCode::Home => self.move_current(0)?,
+                    VirtualKeyCode::End => self.move_current(usize::MAX)?,
+                    VirtualKeyCode::Return | VirtualKeyCode::Space => self.select()?,
+                    _ => return Ok(false),
+                }
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if self.focused && self.params.enabled && input.state == ElementState::Pressed {
+            let current = self.params.current;
+            match input.button {
+                GamepadButton::Up => self.move_current(current.saturating_sub(1))?,
+                GamepadButton::Down => self.move_current(current + 1)?,
+                GamepadButton::A => self.select()?,
+                GamepadButton::B => self.clear_focus()?,
+                _ => return Ok(false),
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn on_mouse_wheel(&mut self, delta: MouseScrollDelta) -> windows::Result<bool> {
+        // Positive delta scrolls up, towards the first item
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => -y.round() as isize,
+            MouseScrollDelta::PixelDelta(position) => {
+                let item_height = self.item_height()?;
+                if item_height <= 0. {
+                    0
+                } else {
+                    (-position.y as f32 / item_height).round() as isize
+                }
+            }
+        };
+        if lines == 0 {
+            return Ok(false);
+        }
+        self.scroll_by(lines)?;
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        self.item_panels.iter().map(|p| p as &dyn Panel).collect()
+    }
+}
+
+impl Control for ListPanel {
+    fn is_enabled(&self) -> windows::Result<bool> {
+        Ok(self.params.enabled)
+    }
+
+    fn is_focused(&self) -> windows::Result<bool> {
+        Ok(self.focused)
+    }
+
+    fn on_enable(&mut self, enable: bool) -> windows::Result<()> {
+        self.params.enabled = enable;
+        for item_panel in &mut self.item_panels {
+            item_panel.on_enable(enable)?;
+        }
+        self.redraw()
+    }
+
+    fn on_set_focus(&mut self) -> windows::Result<()> {
+        self.focused = true;
+        self.redraw()
+    }
+
+    fn on_clear_focus(&mut self) -> windows::Result<()> {
+        self.focused = false;
+        self.redraw()
+    }
+
+    fn as_panel(&self) -> &dyn Panel {
+        self
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt::{self, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use lazy_static::lazy_static;
+
+use bindings::Microsoft::Graphics::Canvas::Text::{
+    CanvasHorizontalAlignment, CanvasVerticalAlignment,
+};
+use bindings::Windows::{Foundation::Numerics::Vector2, UI::Composition::ContainerVisual};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    fmt as tracing_fmt,
+    layer::{Context, Layer},
+    prelude::*,
+    EnvFilter,
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton};
+
+use crate::{
+    background_panel::BackgroundParamsBuilder,
+    context::FrameworkContext,
+    gamepad::GamepadInput,
+    panel::{Panel, PanelEvent, PanelHandle},
+    ribbon_panel::{RibbonOrientation, RibbonPanel, RibbonParamsBuilder},
+    text_panel::{TextPanelHandle, TextParamsBuilder},
+};
+
+// Number of lines kept for the log panel
+const RECENT_LINES_LIMIT: usize = 50;
+const LOG_FONT_SIZE: f32 = 12.;
+// Used when RUST_LOG is not set
+const DEFAULT_FILTER: &str = "warn";
+
+lazy_static! {
+    static ref RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+}
+// Incremented on each new line, so the log panel redraws only when log is changed
+static RECENT_LINES_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+// Installs subscriber printing events to stderr and keeping recent lines for the log
+// panel. Does nothing if the application has already installed its own subscriber
+pub(crate) fn init_logging() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_fmt::layer().with_writer(std::io::stderr))
+        .with(RecentLinesLayer)
+        .try_init();
+}
+
+pub fn recent_log_lines() -> Vec<String> {
+    match RECENT_LINES.lock() {
+        Ok(lines) => lines.iter().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+struct RecentLinesLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLinesLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = format!("{} {}:", metadata.level(), metadata.target());
+        event.record(&mut LineVisitor(&mut line));
+        if let Ok(mut lines) = RECENT_LINES.lock() {
+            if lines.len() == RECENT_LINES_LIMIT {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+            RECENT_LINES_GENERATION.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let _ = if field.name() == "message" {
+            write!(self.0, " {:?}", value)
+        } else {
+            write!(self.0, " {}={:?}", field.name(), value)
+        };
+    }
+}
+
+// Shows recent log lines over the window, toggled by F12. Doesn't take input
+pub(crate) struct LogPanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    text_handle: TextPanelHandle,
+    generation: Option<usize>,
+}
+
+impl LogPanel {
+    pub fn new(context: &FrameworkContext) -> windows::Result<Self> {
+        let theme = context.theme()?;
+        let mut background = theme.background.clone();
+        background.A = 0xC0;
+        let text_panel = TextParamsBuilder::default()
+            .color(theme.text)
+            .horizontal_alignment(CanvasHorizontalAlignment::Left)
+            .vertical_alignment(CanvasVerticalAlignment::Bottom)
+            .font_size(LOG_FONT_SIZE)
+            .create_in(context)?;
+        let text_handle = text_panel.handle();
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel_in(
+                context,
+                BackgroundParamsBuilder::default()
+                    .color(background)
+                    .create_in(context)?,
+            )?
+            .add_panel_in(context, text_panel)?
+            .create_in(context)?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id: context.next_id()?,
+            visual,
+            root_panel,
+            text_handle,
+            generation: None,
+        })
+    }
+}
+
+impl Panel for LogPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            self.root_panel.find_panel(id)
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)?;
+        self.root_panel.on_init()
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.root_panel.on_close()
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        self.root_panel.on_resize(size)
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        // Diagnostic panel shouldn't constrain the window
+        Ok(Vector2 { X: 0., Y: 0. })
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        let generation = RECENT_LINES_GENERATION.load(Ordering::SeqCst);
+        if self.generation != Some(generation) {
+            self.generation = Some(generation);
+            let text = recent_log_lines().join("\n");
+            self.text_handle.at(&mut self.root_panel)?.set_text(text)?;
+        }
+        self.root_panel.on_idle()
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        self.root_panel.on_timer(timer_id)
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        self.root_panel.on_focus_changed(focused)
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.root_panel.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.root_panel.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.root_panel.on_device_lost()
+    }
+
+    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        _button: MouseButton,
+        _state: ElementState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        _input: KeyboardInput,
+        _modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, _input: GamepadInput) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        vec![&self.root_panel]
+    }
+}
@@ -0,0 +1,222 @@
+// Declarative construction of ribbon trees. Evaluates to RibbonPanel and passes errors
+// out with `?`, so it's used in functions returning windows::Result:
+//
+//     let (field_handle, board_handle);
+//     let panel = ribbon!(Vertical(spacing: 4.) {
+//         header_panel,
+//         ribbon!(Stack => board_handle {
+//             field_panel => field_handle { ratio: 4., margin: 2. },
+//         }),
+//     });
+//
+// Ribbon setters are listed in parentheses after the orientation. Panel followed by
+// `=> name` assigns its handle to the variable `name` declared before, braces after
+// the arrow contain setters of RibbonCellParamsBuilder for the panel's cell
+#[macro_export]
+macro_rules! ribbon {
+    ($orientation:ident $(=> $handle:ident)? $(($($param:ident : $value:expr),* $(,)?))? {
+        $($panel:expr $(=> $($cell_handle:ident)? $({ $($cell_param:ident : $cell_value:expr),* $(,)? })?)?),* $(,)?
+    }) => {{
+        let ribbon = $crate::RibbonParamsBuilder::default()
+            .orientation($crate::RibbonOrientation::$orientation)
+            $($(.$param($value))*)?;
+        $(
+            let panel = $panel;
+            $($($cell_handle = panel.handle();)?)?
+            let ribbon = ribbon.add_cell(
+                $crate::RibbonCellParamsBuilder::default()
+                    .panel(panel)
+                    $($($(.$cell_param($cell_value))*)?)?
+                    .create()?,
+            );
+        )*
+        let ribbon = ribbon.create()?;
+        $($handle = ribbon.handle();)?
+        ribbon
+    }};
+}
+
+// Forwards Panel methods to the panel stored in the given field, for panels built around
+// an inner root panel. Invoked inside of `impl Panel`, next to the panel's own id, visual
+// and as_any_mut and the handlers it overrides:
+//
+//     impl Panel for MainPanel {
+//         ...
+//         fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+//             self.root_panel.on_panel_event(panel_event)?;
+//             ...
+//         }
+//         delegate_panel!(root_panel: tree, layout, lifecycle, input);
+//     }
+//
+// Methods are listed by name or by group: `tree` is find_panel and children, `layout` is
+// on_resize, measure and desired_size, `lifecycle` is calls passed to the whole tree and `input` is
+// mouse, keyboard and gamepad handlers. Delegated on_resize sizes the panel's own visual
+#[macro_export]
+macro_rules! delegate_panel {
+    ($field:ident : $($method:ident),* $(,)?) => {
+        $($crate::delegate_panel!(@method $field $method);)*
+    };
+    (@method $field:ident tree) => {
+        $crate::delegate_panel!($field: find_panel, children);
+    };
+    (@method $field:ident layout) => {
+        $crate::delegate_panel!($field: on_resize, measure, desired_size);
+    };
+    (@method $field:ident lifecycle) => {
+        $crate::delegate_panel!($field: on_init, on_close, on_idle, on_timer, on_focus_changed,
+            on_suspend, on_resume, on_device_lost);
+    };
+    (@method $field:ident input) => {
+        $crate::delegate_panel!($field: on_mouse_move, on_mouse_input, on_mouse_wheel,
+            on_cursor_inside_changed, on_keyboard_input, on_modifiers_changed, on_char,
+            on_gamepad_input);
+    };
+    (@method $field:ident find_panel) => {
+        fn find_panel(&mut self, id: usize) -> Option<&mut dyn std::any::Any> {
+            if id == $crate::Panel::id(self) {
+                Some($crate::Panel::as_any_mut(self))
+            } else {
+                $crate::Panel::find_panel(&mut self.$field, id)
+            }
+        }
+    };
+    (@method $field:ident children) => {
+        fn children(&self) -> Vec<&dyn $crate::Panel> {
+            vec![&self.$field]
+        }
+    };
+    (@method $field:ident on_resize) => {
+        fn on_resize(
+            &mut self,
+            size: &$crate::__private::Vector2,
+        ) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::visual(self).SetSize(size)?;
+            $crate::Panel::on_resize(&mut self.$field, size)
+        }
+    };
+    (@method $field:ident measure) => {
+        fn measure(&self) -> $crate::__private::windows::Result<$crate::__private::Vector2> {
+            $crate::Panel::measure(&self.$field)
+        }
+    };
+    (@method $field:ident desired_size) => {
+        fn desired_size(
+            &self,
+            available: &$crate::__private::Vector2,
+        ) -> $crate::__private::windows::Result<$crate::__private::Vector2> {
+            $crate::Panel::desired_size(&self.$field, available)
+        }
+    };
+    (@method $field:ident on_init) => {
+        fn on_init(&mut self) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_init(&mut self.$field)
+        }
+    };
+    (@method $field:ident on_close) => {
+        fn on_close(&mut self) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_close(&mut self.$field)
+        }
+    };
+    (@method $field:ident on_idle) => {
+        fn on_idle(&mut self) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_idle(&mut self.$field)
+        }
+    };
+    (@method $field:ident on_timer) => {
+        fn on_timer(&mut self, timer_id: usize) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_timer(&mut self.$field, timer_id)
+        }
+    };
+    (@method $field:ident on_focus_changed) => {
+        fn on_focus_changed(&mut self, focused: bool) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_focus_changed(&mut self.$field, focused)
+        }
+    };
+    (@method $field:ident on_suspend) => {
+        fn on_suspend(&mut self) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_suspend(&mut self.$field)
+        }
+    };
+    (@method $field:ident on_resume) => {
+        fn on_resume(&mut self) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_resume(&mut self.$field)
+        }
+    };
+    (@method $field:ident on_device_lost) => {
+        fn on_device_lost(&mut self) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_device_lost(&mut self.$field)
+        }
+    };
+    (@method $field:ident on_mouse_move) => {
+        fn on_mouse_move(
+            &mut self,
+            position: &$crate::__private::Vector2,
+        ) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_mouse_move(&mut self.$field, position)
+        }
+    };
+    (@method $field:ident on_mouse_input) => {
+        fn on_mouse_input(
+            &mut self,
+            button: $crate::__private::MouseButton,
+            state: $crate::__private::ElementState,
+        ) -> $crate::__private::windows::Result<bool> {
+            $crate::Panel::on_mouse_input(&mut self.$field, button, state)
+        }
+    };
+    (@method $field:ident on_mouse_wheel) => {
+        fn on_mouse_wheel(
+            &mut self,
+            delta: $crate::__private::MouseScrollDelta,
+        ) -> $crate::__private::windows::Result<bool> {
+            $crate::Panel::on_mouse_wheel(&mut self.$field, delta)
+        }
+    };
+    (@method $field:ident on_cursor_inside_changed) => {
+        fn on_cursor_inside_changed(
+            &mut self,
+            inside: bool,
+        ) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_cursor_inside_changed(&mut self.$field, inside)
+        }
+    };
+    (@method $field:ident on_keyboard_input) => {
+        fn on_keyboard_input(
+            &mut self,
+            input: $crate::__private::KeyboardInput,
+            modifiers: $crate::__private::ModifiersState,
+        ) -> $crate::__private::windows::Result<bool> {
+            $crate::Panel::on_keyboard_input(&mut self.$field, input, modifiers)
+        }
+    };
+    (@method $field:ident on_modifiers_changed) => {
+        fn on_modifiers_changed(
+            &mut self,
+            modifiers: $crate::__private::ModifiersState,
+        ) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_modifiers_changed(&mut self.$field, modifiers)
+        }
+    };
+    (@method $field:ident on_char) => {
+        fn on_char(&mut self, ch: char) -> $crate::__private::windows::Result<bool> {
+            $crate::Panel::on_char(&mut self.$field, ch)
+        }
+    };
+    (@method $field:ident on_gamepad_input) => {
+        fn on_gamepad_input(
+            &mut self,
+            input: $crate::GamepadInput,
+        ) -> $crate::__private::windows::Result<bool> {
+            $crate::Panel::on_gamepad_input(&mut self.$field, input)
+        }
+    };
+    (@method $field:ident on_panel_event) => {
+        fn on_panel_event(
+            &mut self,
+            panel_event: &mut $crate::PanelEvent,
+        ) -> $crate::__private::windows::Result<()> {
+            $crate::Panel::on_panel_event(&mut self.$field, panel_event)
+        }
+    };
+}
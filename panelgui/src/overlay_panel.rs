@@ -0,0 +1,487 @@
+use std::any::Any;
+
+use bindings::Windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::Composition::ContainerVisual,
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::winrt_error,
+    numerics::FromVector2,
+    panel::{Handle, Panel, PanelEvent, PanelHandle, PanelRemoved},
+};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum OverlayAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl OverlayAnchor {
+    // Relative position of anchor point: 0 - left/top, 0.5 - center, 1 - right/bottom
+    fn ratio(&self) -> Vector2 {
+        let (x, y) = match self {
+            OverlayAnchor::TopLeft => (0., 0.),
+            OverlayAnchor::Top => (0.5, 0.),
+            OverlayAnchor::TopRight => (1., 0.),
+            OverlayAnchor::Left => (0., 0.5),
+            OverlayAnchor::Center => (0.5, 0.5),
+            OverlayAnchor::Right => (1., 0.5),
+            OverlayAnchor::BottomLeft => (0., 1.),
+            OverlayAnchor::Bottom => (0.5, 1.),
+            OverlayAnchor::BottomRight => (1., 1.),
+        };
+        Vector2 { X: x, Y: y }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum OverlayPosition {
+    // Offset of the top-left corner in pixels
+    Absolute(Vector2),
+    // Item is aligned to anchor point of the overlay, margin is the distance
+    // from the overlay edges (ignored on centered axis)
+    Anchor(OverlayAnchor, Vector2),
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum OverlaySize {
+    Absolute(Vector2),
+    // Part of the overlay size
+    Ratio(Vector2),
+}
+
+pub struct OverlayItem {
+    panel: Box<dyn Panel>,
+    container: ContainerVisual,
+    position: OverlayPosition,
+    size: OverlaySize,
+}
+
+impl OverlayItem {
+    pub fn new(context: &FrameworkContext, params: OverlayItemParams) -> windows::Result<Self> {
+        let container = context.compositor()?.CreateContainerVisual()?;
+        container.Children()?.InsertAtTop(params.panel.visual())?;
+        Ok(Self {
+            panel: params.panel,
+            container,
+            position: params.position,
+            size: params.size,
+        })
+    }
+    pub fn panel(&self) -> &dyn Panel {
+        &*self.panel
+    }
+    fn layout(&self, overlay_size: &Vector2) -> (Vector3, Vector2) {
+        let size = match &self.size {
+            OverlaySize::Absolute(size) => size.clone(),
+            OverlaySize::Ratio(ratio) => overlay_size.clone() * ratio.clone(),
+        };
+        let offset = match &self.position {
+            OverlayPosition::Absolute(offset) => offset.clone(),
+            OverlayPosition::Anchor(anchor, margin) => {
+                let ratio = anchor.ratio();
+                let place = |overlay: f32, item: f32, ratio: f32, margin: f32| {
+                    // Margin pushes the item from the edge towards the center
+                    let margin = margin * (1. - 2. * ratio);
+                    (overlay - item) * ratio + margin
+                };
+                Vector2 {
+                    X: place(overlay_size.X, size.X, ratio.X, margin.X),
+                    Y: place(overlay_size.Y, size.Y, ratio.Y, margin.Y),
+                }
+            }
+        };
+        (Vector3::from_vector2(offset, 0.), size)
+    }
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct OverlayItemParams {
+    #[builder(private, setter(name = "panel_private"))]
+    panel: Box<dyn Panel>,
+    #[builder(default = "{OverlayPosition::Absolute(Vector2 { X: 0., Y: 0. })}")]
+    position: OverlayPosition,
+    #[builder(default = "{OverlaySize::Ratio(Vector2 { X: 1., Y: 1. })}")]
+    size: OverlaySize,
+}
+
+impl OverlayItemParamsBuilder {
+    pub fn create(self) -> windows::Result<OverlayItem> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<OverlayItem> {
+        match self.build() {
+            Ok(params) => Ok(OverlayItem::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+    pub fn panel(self, panel: impl Panel + 'static) -> Self {
+        let panel: Box<dyn Panel + 'static> = Box::new(panel);
+        self.panel_private(panel)
+    }
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct OverlayParams {
+    #[builder(default = "{Vec::new()}")]
+    items: Vec<OverlayItem>,
+}
+
+impl OverlayParamsBuilder {
+    pub fn create(self) -> windows::Result<OverlayPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<OverlayPanel> {
+        match self.build() {
+            Ok(params) => Ok(OverlayPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+    pub fn add_item(mut self, item: OverlayItem) -> Self {
+        if self.items.is_none() {
+            self.items = Some(Vec::new());
+        }
+        self.items.as_mut().unwrap().push(item);
+        self
+    }
+    pub fn add_panel(self, panel: impl Panel + 'static) -> windows::Result<Self> {
+        self.add_panel_in(&current_context()?, panel)
+    }
+    pub fn add_panel_in(
+        self,
+        context: &FrameworkContext,
+        panel: impl Panel + 'static,
+    ) -> windows::Result<Self> {
+        Ok(self.add_item(
+            OverlayItemParamsBuilder::default()
+                .panel(panel)
+                .create_in(context)?,
+        ))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct OverlayPanelHandle(usize);
+
+impl Handle for OverlayPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<OverlayPanel> for OverlayPanelHandle {}
+
+pub struct OverlayPanel {
+    context: FrameworkContext,
+    id: usize,
+    params: OverlayParams,
+    visual: ContainerVisual,
+    mouse_position: Option<Vector2>,
+}
+
+impl OverlayPanel {
+    pub fn new(context: &FrameworkContext, params: OverlayParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        for item in &params.items {
+            visual.Children()?.InsertAtTop(item.container.clone())?;
+        }
+        Ok(Self {
+            context: context.clone(),
+            id,
+            params,
+            visual,
+            mouse_position: None,
+        })
+    }
+    pub fn handle(&self) -> OverlayPanelHandle {
+        OverlayPanelHandle(self.id)
+    }
+    // Added item is placed above all others
+    pub fn push_item(&mut self, mut item: OverlayItem) -> windows::Result<()> {
+        self.visual
+            .Children()?
+            .InsertAtTop(item.container.clone())?;
+        Self::resize_item(&mut item, &self.visual.Size()?)?;
+        item.panel.on_init()?;
+        self.params.items.push(item);
+        Ok(())
+    }
+    pub fn remove_panel(&mut self, panel_id: usize) -> windows::Result<OverlayItem> {
+        if let Some(index) = self
+            .params
+            .items
+            .iter()
+            .position(|item| item.panel.id() == panel_id)
+        {
+            let mut item = self.params.items.remove(index);
+            item.panel.on_close()?;
+            self.visual.Children()?.Remove(&item.container)?;
+            self.context
+                .broadcast_panel_event(item.panel.id(), PanelRemoved)?;
+            Ok(item)
+        } else {
+            Err(winrt_error("Panel not found in overlay")())
+        }
+    }
+    pub fn set_position(
+        &mut self,
+        panel_id: usize,
+        position: OverlayPosition,
+    ) -> windows::Result<()> {
+        let size = self.visual.Size()?;
+        let item = self.get_item(panel_id)?;
+        item.position = position;
+        Self::resize_item(item, &size)
+    }
+    pub fn set_size(&mut self, panel_id: usize, item_size: OverlaySize) -> windows::Result<()> {
+        let size = self.visual.Size()?;
+        let item = self.get_item(panel_id)?;
+        item.size = item_size;
+        Self::resize_item(item, &size)
+    }
+    fn get_item(&mut self, panel_id: usize) -> windows::Result<&mut OverlayItem> {
+        self.params
+            .items
+            .iter_mut()
+            .find(|item| item.panel.id() == panel_id)
+            .ok_or_else(winrt_error("Panel not found in overlay"))
+    }
+    fn resize_item(item: &mut OverlayItem, overlay_size: &Vector2) -> windows::Result<()> {
+        let (offset, size) = item.layout(overlay_size);
+        item.container.SetOffset(offset)?;
+        item.container.SetSize(size.clone())?;
+        item.panel.on_resize(&size)
+    }
+    fn get_item_by_mouse_position<'a>(
+        &'a mut self,
+        position: &Vector2,
+    ) -> windows::Result<Option<(Vector2, &'a mut OverlayItem)>> {
+        // Unlike Stack ribbon, mouse passes through to lower items outside of upper ones
+        for item in self.params.items.iter_mut().rev() {
+            if !item.panel.is_visible()? {
+                continue;
+            }
+            let offset = item.container.Offset()?;
+            let size = item.container.Size()?;
+            let position = Vector2 {
+                X: position.X - offset.X,
+                Y: position.Y - offset.Y,
+            };
+            if position.X >= 0. && position.X < size.X && position.Y >= 0. && position.Y < size.Y {
+                return Ok(Some((position, item)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Panel for OverlayPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            for item in &mut self.params.items {
+                if let Some(panel) = item.panel.find_panel(id) {
+                    return Some(panel);
+                }
+            }
+            None
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)?;
+        for item in &mut self.params.items {
+            item.panel.on_init()?;
+        }
+        Ok(())
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_close()?;
+        }
+        Ok(())
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        // Only items sized relative to overlay constrain it
+        let mut size = Vector2 { X: 0., Y: 0. };
+        for item in &self.params.items {
+            if let OverlaySize::Ratio(ratio) = &item.size {
+                let item_size = item.panel.measure()?;
+                if ratio.X > 0. {
+                    size.X = size.X.max(item_size.X / ratio.X);
+                }
+                if ratio.Y > 0. {
+                    size.Y = size.Y.max(item_size.Y / ratio.Y);
+                }
+            }
+        }
+        Ok(size)
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        for item in &mut self.params.items {
+            Self::resize_item(item, size)?;
+        }
+        Ok(())
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_idle()?;
+        }
+        Ok(())
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_timer(timer_id)?;
+        }
+        Ok(())
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_focus_changed(focused)?;
+        }
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_suspend()?;
+        }
+        Ok(())
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_resume()?;
+        }
+        Ok(())
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_device_lost()?;
+        }
+        Ok(())
+    }
+
+    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
+        self.mouse_position = Some(position.clone());
+        if let Some((position, item)) = self.get_item_by_mouse_position(position)? {
+            item.panel.on_mouse_move(&position)?;
+        }
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        if let Some(position) = self.mouse_position.clone() {
+            if let Some((_, item)) = self.get_item_by_mouse_position(&position)? {
+                return item.panel.on_mouse_input(button, state);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        for item in self.params.items.iter_mut().rev() {
+            if item.panel.on_keyboard_input(input, modifiers)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_char(&mut self, ch: char) -> windows::Result<bool> {
+        for item in self.params.items.iter_mut().rev() {
+            if item.panel.on_char(ch)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        for item in self.params.items.iter_mut().rev() {
+            if item.panel.on_gamepad_input(input)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            if panel_event.is_routed_to(&mut *item.panel) {
+                item.panel.on_panel_event(panel_event)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_mouse_wheel(&mut self, delta: MouseScrollDelta) -> windows::Result<bool> {
+        if let Some(position) = self.mouse_position.clone() {
+            if let Some((_, item)) = self.get_item_by_mouse_position(&position)? {
+                return item.panel.on_mouse_wheel(delta);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_cursor_inside_changed(&mut self, inside: bool) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_cursor_inside_changed(inside)?;
+        }
+        Ok(())
+    }
+
+    fn on_modifiers_changed(&mut self, modifiers: ModifiersState) -> windows::Result<()> {
+        for item in &mut self.params.items {
+            item.panel.on_modifiers_changed(modifiers)?;
+        }
+        Ok(())
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        self.params.items.iter().map(|item| &*item.panel).collect()
+    }
+}
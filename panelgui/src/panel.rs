@@ -2,31 +2,190 @@ use std::any::Any;
 
 use bindings::Windows::Foundation::Numerics::Vector2;
 use bindings::Windows::UI::Composition::ContainerVisual;
-use winit::event::{ElementState, KeyboardInput, MouseButton};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta};
 
-use crate::globals::{compositor, get_next_id, winrt_error};
+use crate::{
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::winrt_error,
+};
 
 pub struct PanelEvent {
     pub panel_id: usize,
     pub data: Option<Box<dyn Any>>,
+    // Delivered to all panels instead of the sender and its owners only
+    pub broadcast: bool,
 }
+
+impl PanelEvent {
+    // Composite panels pass events only to children for which this is true, so event
+    // follows the path from the root to its sender
+    pub fn is_routed_to(&self, panel: &mut dyn Panel) -> bool {
+        self.data.is_some() && (self.broadcast || panel.find_panel(self.panel_id).is_some())
+    }
+
+    // Takes event data out if it was sent by given panel and has expected type
+    pub fn extract<T: Any>(&mut self, panel_id: usize) -> Option<T> {
+        if self.panel_id != panel_id {
+            return None;
+        }
+        match self.data.take()?.downcast::<T>() {
+            Ok(e) => Some(*e),
+            Err(data) => {
+                self.data = Some(data);
+                None
+            }
+        }
+    }
+}
+
+// Only identity methods are required. Other methods default to what a leaf panel
+// without input needs: nothing is done and input is not handled. Composite panels must
+// pass tree-wide calls like on_idle or on_resize to their children themselves
 pub trait Panel {
     fn id(&self) -> usize;
     fn visual(&self) -> ContainerVisual;
     fn as_any_mut(&mut self) -> &mut dyn Any;
-    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any>;
-    fn on_init(&mut self) -> windows::Result<()>;
-    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()>;
-    fn on_idle(&mut self) -> windows::Result<()>;
-    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()>;
-    fn on_mouse_input(&mut self, button: MouseButton, state: ElementState)
-        -> windows::Result<bool>;
-    fn on_keyboard_input(&mut self, input: KeyboardInput) -> windows::Result<bool>;
-    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()>;
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            None
+        }
+    }
+    fn on_init(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+    // Called before panel is removed from the tree or the window is closed
+    fn on_close(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual().SetSize(size)
+    }
+    // Minimal size at which panel content is still usable, in physical pixels
+    fn measure(&self) -> windows::Result<Vector2> {
+        Ok(Vector2 { X: 0., Y: 0. })
+    }
+    // Size the content takes when space allows, e.g. text at the font filling the height.
+    // Used by auto sized ribbon cells, the available size is in physical pixels
+    fn desired_size(&self, _available: &Vector2) -> windows::Result<Vector2> {
+        self.measure()
+    }
+    fn on_idle(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+    // Called for the whole tree when timer started by set_timer ticks
+    fn on_timer(&mut self, _timer_id: usize) -> windows::Result<()> {
+        Ok(())
+    }
+    // Called for the whole tree when the window gains or loses keyboard focus
+    fn on_focus_changed(&mut self, _focused: bool) -> windows::Result<()> {
+        Ok(())
+    }
+    // Called for the whole tree when the window is minimized, panels should release
+    // drawing surfaces and recreate them in on_resume
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+    fn on_resume(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+    // Called for the whole tree after the lost graphics device was recreated, content of
+    // all drawing surfaces is gone and they must be recreated
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        Ok(())
+    }
+    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
+        Ok(())
+    }
+    // Input methods return true if the input is handled and shouldn't go further
+    fn on_mouse_input(
+        &mut self,
+        _button: MouseButton,
+        _state: ElementState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+    fn on_keyboard_input(
+        &mut self,
+        _input: KeyboardInput,
+        _modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+    fn on_gamepad_input(&mut self, _input: GamepadInput) -> windows::Result<bool> {
+        Ok(false)
+    }
+    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
+        Ok(())
+    }
+    // Sent to the panel under mouse cursor like on_mouse_input
+    fn on_mouse_wheel(&mut self, _delta: MouseScrollDelta) -> windows::Result<bool> {
+        Ok(false)
+    }
+    // Called for the whole tree when mouse cursor enters or leaves the window
+    fn on_cursor_inside_changed(&mut self, _inside: bool) -> windows::Result<()> {
+        Ok(())
+    }
+    // Called for the whole tree when modifier keys are pressed or released, without
+    // waiting for the next key input
+    fn on_modifiers_changed(&mut self, _modifiers: ModifiersState) -> windows::Result<()> {
+        Ok(())
+    }
+    // Hidden panel keeps its state and place in the tree, but is not drawn and doesn't
+    // receive mouse input
+    fn set_visible(&mut self, visible: bool) -> windows::Result<()> {
+        self.visual().SetIsVisible(visible)
+    }
+    fn is_visible(&self) -> windows::Result<bool> {
+        self.visual().IsVisible()
+    }
+    fn set_opacity(&mut self, opacity: f32) -> windows::Result<()> {
+        self.visual().SetOpacity(opacity)
+    }
+    // Moves panel visual above its siblings. Cells of ribbon are ordered by the ribbon
+    // itself, use RibbonPanel::bring_cell_to_front for them
+    fn bring_to_front(&mut self) -> windows::Result<()> {
+        let visual = self.visual();
+        let children = visual.Parent()?.Children()?;
+        children.Remove(&visual)?;
+        children.InsertAtTop(visual)
+    }
+    // Marks layout of the panel as outdated. Containers arrange their children once
+    // in next on_idle instead of on each change, others are just resized again
+    fn request_layout(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Size()?)
+    }
+    // Shown in the panel tree of the debug overlay
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+    // Panels owned by this one, composite panels should list them for the debug overlay
+    fn children(&self) -> Vec<&dyn Panel> {
+        Vec::new()
+    }
 }
 
+// Broadcasted on behalf of the panel removed from ribbon or overlay, so owners can
+// drop stale handles. Not sent for children of removed panel
+pub struct PanelRemoved;
+
 pub trait Handle {
     fn id(&self) -> usize;
+    fn is_alive(&self, root_panel: &mut dyn Panel) -> bool {
+        root_panel.find_panel(self.id()).is_some()
+    }
+    fn is_removed(&self, panel_event: &PanelEvent) -> bool {
+        panel_event.panel_id == self.id()
+            && panel_event
+                .data
+                .as_ref()
+                .map_or(false, |data| data.is::<PanelRemoved>())
+    }
 }
 
 pub trait PanelHandle<PanelType: Any, PanelEventType: Any = ()>: Handle {
@@ -39,21 +198,7 @@ pub trait PanelHandle<PanelType: Any, PanelEventType: Any = ()>: Handle {
         Err(winrt_error("Can't find panel")())
     }
     fn extract_event(&self, panel_event: &mut PanelEvent) -> Option<PanelEventType> {
-        if panel_event.panel_id == self.id() {
-            if let Some(data) = panel_event.data.take() {
-                match data.downcast::<PanelEventType>() {
-                    Ok(e) => Some(*e),
-                    Err(data) => {
-                        panel_event.data = Some(data);
-                        None
-                    }
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+        panel_event.extract(self.id())
     }
 }
 
@@ -64,8 +209,11 @@ pub struct EmptyPanel {
 
 impl EmptyPanel {
     pub fn new() -> windows::Result<Self> {
-        let visual = compositor().CreateContainerVisual()?;
-        let id = get_next_id();
+        Self::new_in(&current_context()?)
+    }
+    pub fn new_in(context: &FrameworkContext) -> windows::Result<Self> {
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        let id = context.next_id()?;
         Ok(Self { id, visual })
     }
 }
@@ -80,44 +228,4 @@ impl Panel for EmptyPanel {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
-
-    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
-        if id == self.id() {
-            Some(self.as_any_mut())
-        } else {
-            None
-        }
-    }
-
-    fn on_init(&mut self) -> windows::Result<()> {
-        Ok(())
-    }
-
-    fn on_resize(&mut self, _size: &Vector2) -> windows::Result<()> {
-        Ok(())
-    }
-
-    fn on_idle(&mut self) -> windows::Result<()> {
-        Ok(())
-    }
-
-    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
-        Ok(())
-    }
-
-    fn on_mouse_input(
-        &mut self,
-        _button: MouseButton,
-        _state: ElementState,
-    ) -> windows::Result<bool> {
-        Ok(false)
-    }
-
-    fn on_keyboard_input(&mut self, _input: KeyboardInput) -> windows::Result<bool> {
-        Ok(false)
-    }
-
-    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
-        Ok(())
-    }
 }
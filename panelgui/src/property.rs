@@ -0,0 +1,83 @@
+use std::{any::Any, marker::PhantomData};
+
+use crate::globals::{get_next_id, send_panel_event};
+
+// Observable value of a panel. Changing it sends PropertyChanged on behalf of the owner
+// panel, so owners bind their children to it with EventHandlers::bind instead of
+// polling the panel after each update
+pub struct Property<T> {
+    owner_id: usize,
+    id: usize,
+    value: T,
+}
+
+// Sent on behalf of the owner panel with the new value of the property
+pub struct PropertyChanged<T> {
+    pub property_id: usize,
+    pub value: T,
+}
+
+impl<T: Any + Clone + PartialEq> Property<T> {
+    pub fn new(owner_id: usize, value: T) -> Self {
+        Self {
+            owner_id,
+            id: get_next_id(),
+            value,
+        }
+    }
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+    pub fn set(&mut self, value: T) -> windows::Result<()> {
+        if value != self.value {
+            self.value = value;
+            self.notify()?;
+        }
+        Ok(())
+    }
+    // Sends current value unconditionally, e.g. from owner's on_init to update bound
+    // panels to the initial state
+    pub fn notify(&self) -> windows::Result<()> {
+        send_panel_event(
+            self.owner_id,
+            PropertyChanged {
+                property_id: self.id,
+                value: self.value.clone(),
+            },
+        )
+    }
+    pub fn handle(&self) -> PropertyHandle<T> {
+        PropertyHandle {
+            owner_id: self.owner_id,
+            id: self.id,
+            phantom: PhantomData,
+        }
+    }
+}
+
+pub struct PropertyHandle<T> {
+    owner_id: usize,
+    id: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T> PropertyHandle<T> {
+    pub fn owner_id(&self) -> usize {
+        self.owner_id
+    }
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<T> Clone for PropertyHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            owner_id: self.owner_id,
+            id: self.id,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Copy for PropertyHandle<T> {}
@@ -1,13 +1,21 @@
-use std::any::Any;
+use std::{any::Any, borrow::Cow};
 
 use bindings::Windows::{
-    Foundation::Numerics::{Vector2, Vector3},
-    UI::Composition::ContainerVisual,
+    Foundation::{
+        Numerics::{Vector2, Vector3},
+        TypedEventHandler,
+    },
+    UI::Composition::{
+        CompositionBatchTypes, ContainerVisual, ScalarKeyFrameAnimation, SpriteVisual,
+        VisualCollection,
+    },
 };
 
 use crate::{
-    globals::{compositor, get_next_id, winrt_error},
-    panel::{EmptyPanel, Handle, Panel, PanelEvent, PanelHandle},
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::{send_panel_event, winrt_error},
+    panel::{EmptyPanel, Handle, Panel, PanelEvent, PanelHandle, PanelRemoved},
 };
 
 #[derive(PartialEq, Copy, Clone)]
@@ -16,11 +24,104 @@ pub enum RibbonOrientation {
     Vertical,
     Stack,
 }
+// Placement of cell content which is smaller than its slot
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum CellAlignment {
+    Start,
+    Center,
+    End,
+    // Content takes whole slot ignoring content_ratio and max_size
+    Stretch,
+}
+
+impl CellAlignment {
+    // Returns position and length of content in the slot
+    fn place(self, slot_pos: f32, slot_len: f32, len: f32) -> (f32, f32) {
+        match self {
+            CellAlignment::Start => (slot_pos, len),
+            CellAlignment::Center => (slot_pos + (slot_len - len) / 2., len),
+            CellAlignment::End => (slot_pos + slot_len - len, len),
+            CellAlignment::Stretch => (slot_pos, slot_len),
+        }
+    }
+}
+
+// How the length of the cell along Horizontal or Vertical ribbon is chosen. Min and max
+// sizes apply in all cases, Stack ribbon ignores it
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum CellSizing {
+    // Cell shares space left by other cells with the rest of ratio cells
+    Ratio,
+    // Percent of the ribbon length without padding and spacing
+    Percent(f32),
+    // Length desired by the cell panel
+    Auto,
+}
+
+// Animation of the cell pushed to or removed from Stack ribbon. Other orientations
+// ignore it
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum CellTransition {
+    None,
+    Fade,
+    // Content grows from the center of the cell and shrinks back to it
+    Scale,
+    // Content comes from the bottom edge of the ribbon and leaves to it
+    Slide,
+}
+
+const SCALE_TRANSITION_FROM: f32 = 0.8;
+
+// Sent by the ribbon when transition of the cell is over. For exit transition it means
+// that cell's visuals are gone from the ribbon. Sent also when there was nothing to
+// animate, so owners may rely on it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CellTransitionFinished {
+    pub panel_id: usize,
+    pub exit: bool,
+}
+
+// Sent by completed animation batch to the ribbon itself
+struct TransitionBatchCompleted {
+    panel_id: usize,
+    exit: bool,
+}
+
+struct TransitionBatchHandle(usize);
+
+impl Handle for TransitionBatchHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<RibbonPanel, TransitionBatchCompleted> for TransitionBatchHandle {}
+
+// Visuals of the removed cell kept until its exit transition ends
+struct LeavingCell {
+    panel_id: usize,
+    container: ContainerVisual,
+    backdrop: Option<SpriteVisual>,
+}
+
 pub struct RibbonCell {
+    context: FrameworkContext,
     panel: Box<dyn Panel>,
+    name: Option<Cow<'static, str>>,
     container: ContainerVisual,
     limit: CellLimit,
     content_ratio: Vector2,
+    aspect_ratio: Option<f32>,
+    margin: f32,
+    horizontal_alignment: CellAlignment,
+    vertical_alignment: CellAlignment,
+    input_transparent: bool,
+    modal: bool,
+    // Covers whole Stack ribbon beneath modal cell
+    backdrop: Option<SpriteVisual>,
+    transition: CellTransition,
+    // Enter transition starts on first layout, when the cell size is known
+    entering: bool,
 }
 
 impl Default for RibbonCell {
@@ -33,25 +134,266 @@ impl Default for RibbonCell {
 }
 
 impl RibbonCell {
-    pub fn new(params: RibbonCellParams) -> windows::Result<Self> {
-        let container = compositor().CreateContainerVisual()?;
+    pub fn new(context: &FrameworkContext, params: RibbonCellParams) -> windows::Result<Self> {
+        let compositor = context.compositor()?;
+        let container = compositor.CreateContainerVisual()?;
         container
             .Children()?
             .InsertAtTop(params.panel.visual().clone())?;
+        if params.clip_content {
+            // Zero insets clip to the container bounds, so the clip follows its size
+            container.SetClip(compositor.CreateInsetClip()?)?;
+        }
+        let backdrop = if params.modal {
+            Some(compositor.CreateSpriteVisual()?)
+        } else {
+            None
+        };
         Ok(Self {
+            context: context.clone(),
             panel: params.panel,
+            name: params.name,
             container,
             limit: CellLimit {
                 ratio: params.ratio,
                 min_size: params.min_size,
                 max_size: params.max_size,
+                sizing: params.sizing,
             },
             content_ratio: params.content_ratio,
+            aspect_ratio: params.aspect_ratio,
+            margin: params.margin,
+            horizontal_alignment: params.horizontal_alignment,
+            vertical_alignment: params.vertical_alignment,
+            input_transparent: params.input_transparent,
+            modal: params.modal,
+            backdrop,
+            transition: params.transition,
+            entering: false,
         })
     }
     pub fn panel(&self) -> &dyn Panel {
         &*self.panel
     }
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    // Places container into the cell's slot according to content and aspect ratios and alignment,
+    // leaving margin around it. Content is smaller than the slot also when the slot
+    // includes the space left by cells limited by max_size
+    fn place_container(
+        &self,
+        slot_offset: &Vector2,
+        slot_size: &Vector2,
+        content_size: &Vector2,
+    ) -> windows::Result<()> {
+        let mut content_size = content_size.clone() * self.content_ratio.clone();
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            if content_size.X > content_size.Y * aspect_ratio {
+                content_size.X = content_size.Y * aspect_ratio;
+            } else {
+                content_size.Y = content_size.X / aspect_ratio;
+            }
+        }
+        let (x, width) =
+            self.horizontal_alignment
+                .place(slot_offset.X, slot_size.X, content_size.X);
+        let (y, height) = self
+            .vertical_alignment
+            .place(slot_offset.Y, slot_size.Y, content_size.Y);
+        let margin = (self.margin * self.context.scale_factor()?)
+            .min(width / 2.)
+            .min(height / 2.);
+        self.container.SetSize(Vector2 {
+            X: width - margin * 2.,
+            Y: height - margin * 2.,
+        })?;
+        self.container.SetOffset(Vector3 {
+            X: x + margin,
+            Y: y + margin,
+            Z: 0.,
+        })
+    }
+    // Percent and auto sized cells are fixed ones for the layout pass. Available is the
+    // ribbon length left for cells, across is the ribbon width
+    fn layout_limit(&self, hor: bool, available: f32, across: f32) -> windows::Result<CellLimit> {
+        let size = match self.limit.sizing {
+            CellSizing::Ratio => return Ok(self.limit),
+            CellSizing::Percent(percent) => available * percent / 100.,
+            CellSizing::Auto => {
+                let (along_ratio, across_ratio) = if hor {
+                    (self.content_ratio.X, self.content_ratio.Y)
+                } else {
+                    (self.content_ratio.Y, self.content_ratio.X)
+                };
+                let margin = self.margin * self.context.scale_factor()? * 2.;
+                let along = (available * along_ratio - margin).max(0.);
+                let across = (across * across_ratio - margin).max(0.);
+                let bounds = if hor {
+                    Vector2 {
+                        X: along,
+                        Y: across,
+                    }
+                } else {
+                    Vector2 {
+                        X: across,
+                        Y: along,
+                    }
+                };
+                let desired = self.panel.desired_size(&bounds)?;
+                let desired = if hor { desired.X } else { desired.Y };
+                (desired + margin) / along_ratio
+            }
+        };
+        let mut size = size.max(self.limit.min_size);
+        if let Some(max_size) = self.limit.max_size {
+            size = size.min(max_size);
+        }
+        let mut limit = self.limit;
+        limit.set_size(size);
+        Ok(limit)
+    }
+    // Hidden panels are skipped by input like input transparent ones
+    fn accepts_input(&self) -> bool {
+        !self.input_transparent && self.panel.is_visible().unwrap_or(true)
+    }
+    pub(crate) fn container(&self) -> &ContainerVisual {
+        &self.container
+    }
+    // Inserts backdrop and container below visuals of the next cell or at top
+    fn insert_visuals(
+        &self,
+        children: &VisualCollection,
+        next: Option<&RibbonCell>,
+    ) -> windows::Result<()> {
+        match next {
+            Some(RibbonCell {
+                backdrop: Some(next_backdrop),
+                ..
+            }) => {
+                if let Some(backdrop) = &self.backdrop {
+                    children.InsertBelow(backdrop.clone(), next_backdrop)?;
+                }
+                children.InsertBelow(self.container.clone(), next_backdrop)?;
+            }
+            Some(next) => {
+                if let Some(backdrop) = &self.backdrop {
+                    children.InsertBelow(backdrop.clone(), &next.container)?;
+                }
+                children.InsertBelow(self.container.clone(), &next.container)?;
+            }
+            None => {
+                if let Some(backdrop) = &self.backdrop {
+                    children.InsertAtTop(backdrop.clone())?;
+                }
+                children.InsertAtTop(self.container.clone())?;
+            }
+        }
+        Ok(())
+    }
+    // Animates cell from hidden state on enter and to it on exit. Batch completion is
+    // reported to the ribbon. Returns false if animations are off and nothing started
+    fn start_transition(
+        &self,
+        ribbon_id: usize,
+        ribbon_size: &Vector2,
+        exit: bool,
+    ) -> windows::Result<bool> {
+        let settings = self.context.animation_settings()?;
+        if self.transition == CellTransition::None || !settings.enabled {
+            return Ok(false);
+        }
+        let compositor = self.context.compositor()?;
+        let (from, to) = if exit { (1., 0.) } else { (0., 1.) };
+        let batch = compositor.CreateScopedBatch(CompositionBatchTypes::Animation)?;
+        // Final values are set too, they stay if animation is interrupted
+        let fade = || -> windows::Result<ScalarKeyFrameAnimation> {
+            let animation = compositor.CreateScalarKeyFrameAnimation()?;
+            animation.InsertKeyFrame(0.0, from)?;
+            animation.InsertKeyFrameWithEasingFunction(1.0, to, settings.easing_function()?)?;
+            animation.SetDuration(settings.time_span())?;
+            Ok(animation)
+        };
+        if let Some(backdrop) = &self.backdrop {
+            backdrop.SetOpacity(to)?;
+            backdrop.StartAnimation("Opacity", fade()?)?;
+        }
+        match self.transition {
+            CellTransition::None => {}
+            CellTransition::Fade => {
+                self.container.SetOpacity(to)?;
+                self.container.StartAnimation("Opacity", fade()?)?;
+            }
+            CellTransition::Scale => {
+                let size = self.container.Size()?;
+                self.container.SetCenterPoint(Vector3 {
+                    X: size.X / 2.,
+                    Y: size.Y / 2.,
+                    Z: 0.,
+                })?;
+                let scale = |value: f32| Vector3 {
+                    X: value,
+                    Y: value,
+                    Z: 1.,
+                };
+                let (from_scale, to_scale) = if exit {
+                    (1., SCALE_TRANSITION_FROM)
+                } else {
+                    (SCALE_TRANSITION_FROM, 1.)
+                };
+                let animation = compositor.CreateVector3KeyFrameAnimation()?;
+                animation.InsertKeyFrame(0.0, scale(from_scale))?;
+                animation.InsertKeyFrameWithEasingFunction(
+                    1.0,
+                    scale(to_scale),
+                    settings.easing_function()?,
+                )?;
+                animation.SetDuration(settings.time_span())?;
+                self.container.SetScale(scale(to_scale))?;
+                self.container.SetOpacity(to)?;
+                self.container.StartAnimation("Scale", animation)?;
+                self.container.StartAnimation("Opacity", fade()?)?;
+            }
+            CellTransition::Slide => {
+                // Panel visual is moved inside of the container, so layout changes of the
+                // container during transition are not overridden by animation
+                let below = Vector3 {
+                    X: 0.,
+                    Y: ribbon_size.Y - self.container.Offset()?.Y,
+                    Z: 0.,
+                };
+                let zero = Vector3 {
+                    X: 0.,
+                    Y: 0.,
+                    Z: 0.,
+                };
+                let (from_offset, to_offset) = if exit { (zero, below) } else { (below, zero) };
+                let animation = compositor.CreateVector3KeyFrameAnimation()?;
+                animation.InsertKeyFrame(0.0, from_offset)?;
+                animation.InsertKeyFrameWithEasingFunction(
+                    1.0,
+                    to_offset.clone(),
+                    settings.easing_function()?,
+                )?;
+                animation.SetDuration(settings.time_span())?;
+                let visual = self.panel.visual();
+                visual.SetOffset(to_offset.clone())?;
+                visual.StartAnimation("Offset", animation)?;
+            }
+        }
+        batch.End()?;
+        let panel_id = self.panel.id();
+        batch.Completed(TypedEventHandler::new(move |_, _| {
+            send_panel_event(ribbon_id, TransitionBatchCompleted { panel_id, exit })
+        }))?;
+        Ok(true)
+    }
+    fn remove_visuals(&self, children: &VisualCollection) -> windows::Result<()> {
+        if let Some(backdrop) = &self.backdrop {
+            children.Remove(backdrop)?;
+        }
+        children.Remove(&self.container)
+    }
 }
 
 #[derive(Builder)]
@@ -59,20 +401,52 @@ impl RibbonCell {
 pub struct RibbonCellParams {
     #[builder(private, setter(name = "panel_private"))]
     panel: Box<dyn Panel>,
+    // Lets the owner find the cell without keeping its index
+    #[builder(default = "{None}")]
+    name: Option<Cow<'static, str>>,
     #[builder(default = "{1.0}")]
     ratio: f32,
     #[builder(default = "{0.0}")]
     min_size: f32,
     #[builder(default = "{None}")]
     max_size: Option<f32>,
+    #[builder(default = "{CellSizing::Ratio}")]
+    sizing: CellSizing,
     #[builder(default = "{Vector2 { X: 1.0, Y: 1.0 }}")]
     content_ratio: Vector2,
+    // Width to height ratio kept by the content, which is shrunk to fit into the cell and
+    // placed according to alignment
+    #[builder(default = "{None}")]
+    aspect_ratio: Option<f32>,
+    // Space around the panel inside of the cell, in logical pixels
+    #[builder(default = "{0.}")]
+    margin: f32,
+    #[builder(default = "{CellAlignment::Center}")]
+    horizontal_alignment: CellAlignment,
+    #[builder(default = "{CellAlignment::Center}")]
+    vertical_alignment: CellAlignment,
+    // Cell doesn't receive input, it goes to cells below even in Stack orientation
+    #[builder(default = "{false}")]
+    input_transparent: bool,
+    // In Stack orientation mouse outside of the cell doesn't reach cells below it.
+    // Otherwise only the cell's own area blocks them
+    #[builder(default = "{false}")]
+    modal: bool,
+    // Played when the cell is pushed to or removed from Stack ribbon
+    #[builder(default = "{CellTransition::None}")]
+    transition: CellTransition,
+    // Panel content outside of the cell, e.g. moving during animation, is not drawn
+    #[builder(default = "{false}")]
+    clip_content: bool,
 }
 
 impl RibbonCellParamsBuilder {
     pub fn create(self) -> windows::Result<RibbonCell> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<RibbonCell> {
         match self.build() {
-            Ok(params) => Ok(RibbonCell::new(params)?),
+            Ok(params) => Ok(RibbonCell::new(context, params)?),
             Err(e) => Err(winrt_error(e)()),
         }
     }
@@ -80,6 +454,9 @@ impl RibbonCellParamsBuilder {
         let panel: Box<dyn Panel + 'static> = Box::new(panel);
         self.panel_private(panel)
     }
+    pub(crate) fn panel_box(self, panel: Box<dyn Panel>) -> Self {
+        self.panel_private(panel)
+    }
 }
 
 #[derive(Builder)]
@@ -89,12 +466,21 @@ pub struct RibbonParams {
     orientation: RibbonOrientation,
     #[builder(default = "{Vec::new()}")]
     cells: Vec<RibbonCell>,
+    // Gap between cells in Horizontal and Vertical orientations, in logical pixels
+    #[builder(default = "{0.}")]
+    spacing: f32,
+    // Space between ribbon edges and its cells, in logical pixels
+    #[builder(default = "{0.}")]
+    padding: f32,
 }
 
 impl RibbonParamsBuilder {
     pub fn create(self) -> windows::Result<RibbonPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<RibbonPanel> {
         match self.build() {
-            Ok(settings) => Ok(RibbonPanel::new(settings)?),
+            Ok(settings) => Ok(RibbonPanel::new(context, settings)?),
             Err(e) => Err(winrt_error(e)()),
         }
     }
@@ -106,34 +492,64 @@ impl RibbonParamsBuilder {
         self
     }
     pub fn add_panel(self, panel: impl Panel + 'static) -> windows::Result<Self> {
-        Ok(self.add_cell(RibbonCellParamsBuilder::default().panel(panel).create()?))
+        self.add_panel_in(&current_context()?, panel)
+    }
+    pub fn add_panel_in(
+        self,
+        context: &FrameworkContext,
+        panel: impl Panel + 'static,
+    ) -> windows::Result<Self> {
+        Ok(self.add_cell(
+            RibbonCellParamsBuilder::default()
+                .panel(panel)
+                .create_in(context)?,
+        ))
     }
     pub fn add_panel_with_ratio(
         self,
         panel: impl Panel + 'static,
         ratio: f32,
+    ) -> windows::Result<Self> {
+        self.add_panel_with_ratio_in(&current_context()?, panel, ratio)
+    }
+    pub fn add_panel_with_ratio_in(
+        self,
+        context: &FrameworkContext,
+        panel: impl Panel + 'static,
+        ratio: f32,
     ) -> windows::Result<Self> {
         Ok(self.add_cell(
             RibbonCellParamsBuilder::default()
                 .panel(panel)
                 .ratio(ratio)
-                .create()?,
+                .create_in(context)?,
         ))
     }
 }
 
 pub struct RibbonPanel {
+    context: FrameworkContext,
     handle: RibbonPanelHandle,
     params: RibbonParams,
     visual: ContainerVisual,
     mouse_position: Option<Vector2>,
+    // Panel which accepted mouse button press gets release and moves until then
+    mouse_capture: Option<usize>,
+    // Panel which received last mouse move, it gets one more when mouse leaves it
+    mouse_hover: Option<usize>,
+    // Cells are arranged in on_idle, so series of changes causes single layout pass
+    layout_pending: bool,
+    // Removed cells which are still playing exit transition
+    leaving: Vec<LeavingCell>,
 }
 #[derive(Copy, Clone, PartialEq)]
 pub struct RibbonPanelHandle(usize);
 
 impl RibbonPanelHandle {
-    fn new() -> Self {
-        Self { 0: get_next_id() }
+    fn new(context: &FrameworkContext) -> windows::Result<Self> {
+        Ok(Self {
+            0: context.next_id()?,
+        })
     }
 }
 
@@ -143,13 +559,14 @@ impl Handle for RibbonPanelHandle {
     }
 }
 
-impl PanelHandle<RibbonPanel> for RibbonPanelHandle {}
+impl PanelHandle<RibbonPanel, CellTransitionFinished> for RibbonPanelHandle {}
 
 #[derive(Copy, Clone, Debug)]
 pub struct CellLimit {
     pub ratio: f32,
     pub min_size: f32,
     pub max_size: Option<f32>,
+    pub sizing: CellSizing,
 }
 
 impl CellLimit {
@@ -165,86 +582,115 @@ impl Default for CellLimit {
             ratio: 1.,
             min_size: 0.,
             max_size: None,
+            sizing: CellSizing::Ratio,
         }
     }
 }
 
-fn adjust_cells(limits: Vec<CellLimit>, mut target: f32) -> Vec<f32> {
-    //dbg!(&target);
-    let mut lock = Vec::with_capacity(limits.len());
-    let mut result = Vec::with_capacity(limits.len());
-    lock.resize(limits.len(), false);
-    result.resize(limits.len(), 0.);
-
-    let mut sum_ratio = limits
+// Splits target length between cells proportionally to their ratios, keeping each size
+// within its limits. Cells which hit a limit are fixed at it and the rest of the length
+// is split again between the others, so every pass fixes at least one cell. When even
+// min sizes don't fit, cells get their min sizes and overflow the target. Negative or
+// NaN values are treated as zero, max size less than min size as min size
+fn adjust_cells(limits: Vec<CellLimit>, target: f32) -> Vec<f32> {
+    let valid = |v: f32| if v > 0. { v } else { 0. };
+    let bounds = limits
         .iter()
         .map(|c| {
-            assert!(c.ratio > 0.);
-            c.ratio
+            let min = valid(c.min_size);
+            let max = c
+                .max_size
+                .filter(|v| !v.is_nan())
+                .map_or(f32::INFINITY, |v| v.max(min));
+            (valid(c.ratio), min, max)
         })
-        .sum::<f32>();
+        .collect::<Vec<_>>();
+    let target = valid(target);
+    let mut result = bounds.iter().map(|&(_, min, _)| min).collect::<Vec<_>>();
+    let mut fixed = vec![false; bounds.len()];
     loop {
-        let mut new_target = target;
-        let mut all_lock = true;
-        for i in 0..limits.len() {
-            if !lock[i] {
-                let mut share = target * limits[i].ratio / sum_ratio;
-                if share <= limits[i].min_size {
-                    share = limits[i].min_size;
-                    lock[i] = true;
-                }
-                if let Some(max_size) = limits[i].max_size {
-                    if share > max_size {
-                        share = max_size;
-                        lock[i] = true;
-                    }
-                }
-                if lock[i] {
-                    new_target -= share;
-                    sum_ratio -= limits[i].ratio;
-                    lock[i] = true;
+        let (mut rest, mut sum_ratio) = (target, 0.);
+        for i in 0..bounds.len() {
+            if fixed[i] {
+                rest -= result[i];
+            } else {
+                sum_ratio += bounds[i].0;
+            }
+        }
+        if sum_ratio <= 0. {
+            // Cells without ratio don't grow, they keep min size
+            break;
+        }
+        let share = rest.max(0.) / sum_ratio;
+        let free = |ratio: f32| if ratio > 0. { share * ratio } else { 0. };
+        // Positive error means that limits took more than the free split gave
+        let mut error = 0.;
+        for i in 0..bounds.len() {
+            if !fixed[i] {
+                let (ratio, min, max) = bounds[i];
+                let free = free(ratio);
+                result[i] = free.max(min).min(max);
+                error += result[i] - free;
+            }
+        }
+        let mut changed = false;
+        for i in 0..bounds.len() {
+            if !fixed[i] {
+                let (ratio, min, max) = bounds[i];
+                let free = free(ratio);
+                let hit = if error > 0. {
+                    result[i] == min && free < min
+                } else if error < 0. {
+                    result[i] == max && free > max
                 } else {
-                    all_lock = false;
+                    false
+                };
+                if hit {
+                    fixed[i] = true;
+                    changed = true;
                 }
-                result[i] = share;
             }
         }
-        if all_lock || new_target == target {
+        if !changed {
             break;
         }
-        target = if new_target > 0. { new_target } else { 0. };
     }
-    //dbg!(&result);
     result
 }
 
 impl RibbonPanel {
-    pub fn new(params: RibbonParams) -> windows::Result<Self> {
-        let handle = RibbonPanelHandle::new();
-        let visual = compositor().CreateContainerVisual()?;
+    pub fn new(context: &FrameworkContext, params: RibbonParams) -> windows::Result<Self> {
+        let handle = RibbonPanelHandle::new(context)?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
         for p in &params.cells {
-            visual.Children()?.InsertAtTop(p.container.clone())?;
+            p.insert_visuals(&visual.Children()?, None)?;
         }
         Ok(Self {
+            context: context.clone(),
             handle,
             params,
             visual,
             mouse_position: None,
+            mouse_capture: None,
+            mouse_hover: None,
+            layout_pending: false,
+            leaving: Vec::new(),
         })
     }
     pub fn handle(&self) -> RibbonPanelHandle {
         self.handle.clone()
     }
-    pub fn set_cell_at(&mut self, index: usize, cell: RibbonCell) -> windows::Result<()> {
-        if index >= self.params.cells.len() {
-            return Err(winrt_error("Bad cell index")());
+    pub fn cell_count(&self) -> usize {
+        self.params.cells.len()
+    }
+    pub(crate) fn cell_panel(&self, index: usize) -> Option<&dyn Panel> {
+        self.params.cells.get(index).map(|cell| &*cell.panel)
+    }
+    pub(crate) fn cell_panel_mut(&mut self, index: usize) -> Option<&mut dyn Panel> {
+        match self.params.cells.get_mut(index) {
+            Some(cell) => Some(&mut *cell.panel),
+            None => None,
         }
-        self.visual
-            .Children()?
-            .InsertAtTop(cell.container.clone())?;
-        self.params.cells.insert(index, cell);
-        self.resize_cells()?;
-        Ok(())
     }
     pub fn get_cell_limit(&self, index: usize) -> windows::Result<CellLimit> {
         if let Some(cell) = self.params.cells.get(index) {
@@ -256,91 +702,276 @@ impl RibbonPanel {
     pub fn set_cell_limit(&mut self, index: usize, limit: CellLimit) -> windows::Result<()> {
         if let Some(cell) = self.params.cells.get_mut(index) {
             cell.limit = limit;
-            self.resize_cells()?;
-            Ok(())
+            self.request_layout()
+        } else {
+            Err(winrt_error("Wrong cell index")())
+        }
+    }
+    // Index of the cell holding the panel, indexes shift when cells are inserted or removed
+    pub fn cell_index_of(&self, handle: &impl Handle) -> Option<usize> {
+        self.params
+            .cells
+            .iter()
+            .position(|c| c.panel.id() == handle.id())
+    }
+    pub fn cell_index_named(&self, name: &str) -> Option<usize> {
+        self.params
+            .cells
+            .iter()
+            .position(|c| c.name.as_deref() == Some(name))
+    }
+    pub fn get_cell_limit_of(&self, handle: &impl Handle) -> windows::Result<CellLimit> {
+        self.get_cell_limit(
+            self.cell_index_of(handle)
+                .ok_or_else(winrt_error("Cell not found in ribbon"))?,
+        )
+    }
+    pub fn set_cell_limit_of(
+        &mut self,
+        handle: &impl Handle,
+        limit: CellLimit,
+    ) -> windows::Result<()> {
+        self.set_cell_limit(
+            self.cell_index_of(handle)
+                .ok_or_else(winrt_error("Cell not found in ribbon"))?,
+            limit,
+        )
+    }
+    pub fn get_cell_limit_named(&self, name: &str) -> windows::Result<CellLimit> {
+        self.get_cell_limit(
+            self.cell_index_named(name)
+                .ok_or_else(winrt_error("Cell not found in ribbon"))?,
+        )
+    }
+    pub fn set_cell_limit_named(&mut self, name: &str, limit: CellLimit) -> windows::Result<()> {
+        self.set_cell_limit(
+            self.cell_index_named(name)
+                .ok_or_else(winrt_error("Cell not found in ribbon"))?,
+            limit,
+        )
+    }
+    // Content of the cell keeps this width to height ratio, None fills the whole slot
+    pub fn set_cell_aspect_ratio(
+        &mut self,
+        index: usize,
+        aspect_ratio: Option<f32>,
+    ) -> windows::Result<()> {
+        if let Some(cell) = self.params.cells.get_mut(index) {
+            cell.aspect_ratio = aspect_ratio;
+            self.request_layout()
         } else {
             Err(winrt_error("Wrong cell index")())
         }
     }
+    pub fn set_cell_aspect_ratio_of(
+        &mut self,
+        handle: &impl Handle,
+        aspect_ratio: Option<f32>,
+    ) -> windows::Result<()> {
+        self.set_cell_aspect_ratio(
+            self.cell_index_of(handle)
+                .ok_or_else(winrt_error("Cell not found in ribbon"))?,
+            aspect_ratio,
+        )
+    }
     /*    pub fn get_mut_cell_at<'a>(
         &'a mut self,
         index: usize,
     ) -> windows::Result<Option<&'a mut RibbonCell>> {
         Ok(self.params.cells.get_mut(index))
     }*/
-    pub fn push_cell(&mut self, cell: RibbonCell) -> windows::Result<()> {
-        self.visual
-            .Children()?
-            .InsertAtTop(cell.container.clone())?;
+    pub fn insert_cell(&mut self, index: usize, cell: RibbonCell) -> windows::Result<()> {
+        // Keep visuals order same as cells order, it matters for Stack orientation
+        if index > self.params.cells.len() {
+            return Err(winrt_error("Bad cell index")());
+        }
+        let mut cell = cell;
+        cell.entering = self.params.orientation == RibbonOrientation::Stack
+            && cell.transition != CellTransition::None;
+        cell.insert_visuals(&self.visual.Children()?, self.params.cells.get(index))?;
+        self.params.cells.insert(index, cell);
+        self.request_layout()
+    }
+    pub fn remove_cell(&mut self, index: usize) -> windows::Result<RibbonCell> {
+        if index >= self.params.cells.len() {
+            return Err(winrt_error("Bad cell index")());
+        }
+        let mut cell = self.params.cells.remove(index);
+        cell.panel.on_close()?;
+        // Visuals of animated cell stay in place until the transition ends, though the
+        // panel is already out of the tree
+        let animated = self.params.orientation == RibbonOrientation::Stack
+            && !cell.entering
+            && cell.start_transition(self.handle.id(), &self.visual.Size()?, true)?;
+        if animated {
+            self.leaving.push(LeavingCell {
+                panel_id: cell.panel.id(),
+                container: cell.container.clone(),
+                backdrop: cell.backdrop.clone(),
+            });
+        } else {
+            cell.remove_visuals(&self.visual.Children()?)?;
+            if self.params.orientation == RibbonOrientation::Stack
+                && cell.transition != CellTransition::None
+            {
+                self.context.send_panel_event(
+                    self.handle.id(),
+                    CellTransitionFinished {
+                        panel_id: cell.panel.id(),
+                        exit: true,
+                    },
+                )?;
+            }
+        }
+        self.request_layout()?;
+        self.context
+            .broadcast_panel_event(cell.panel.id(), PanelRemoved)?;
+        Ok(cell)
+    }
+    pub fn replace_cell(&mut self, index: usize, cell: RibbonCell) -> windows::Result<RibbonCell> {
+        let old_cell = self.remove_cell(index)?;
+        self.insert_cell(index, cell)?;
+        Ok(old_cell)
+    }
+    pub fn remove_panel(&mut self, panel_id: usize) -> windows::Result<RibbonCell> {
+        if let Some(index) = self
+            .params
+            .cells
+            .iter()
+            .position(|c| c.panel.id() == panel_id)
+        {
+            self.remove_cell(index)
+        } else {
+            Err(winrt_error("Panel not found in ribbon")())
+        }
+    }
+    // Moves cell to the top of Stack ribbon without reinitializing its panel
+    pub fn bring_cell_to_front(&mut self, index: usize) -> windows::Result<()> {
+        if self.params.orientation != RibbonOrientation::Stack {
+            return Err(winrt_error("Only cells of Stack ribbon overlap")());
+        }
+        if index >= self.params.cells.len() {
+            return Err(winrt_error("Bad cell index")());
+        }
+        let children = self.visual.Children()?;
+        let cell = self.params.cells.remove(index);
+        cell.remove_visuals(&children)?;
+        cell.insert_visuals(&children, None)?;
         self.params.cells.push(cell);
-        self.resize_cells()?;
         Ok(())
     }
+    pub fn push_cell(&mut self, cell: RibbonCell) -> windows::Result<()> {
+        self.insert_cell(self.params.cells.len(), cell)
+    }
     pub fn pop_cell(&mut self) -> windows::Result<RibbonCell> {
-        if let Some(cell) = self.params.cells.pop() {
-            self.visual.Children()?.Remove(&cell.container)?;
-            self.resize_cells()?;
-            Ok(cell)
-        } else {
+        if self.params.cells.is_empty() {
             Err(winrt_error("Ribbon is empty")())
+        } else {
+            self.remove_cell(self.params.cells.len() - 1)
         }
     }
     pub fn set_len(&mut self, new_len: usize) -> windows::Result<()> {
-        self.params.cells.resize_with(new_len, Default::default);
+        while self.params.cells.len() > new_len {
+            self.pop_cell()?;
+        }
+        while self.params.cells.len() < new_len {
+            let cell = RibbonCellParamsBuilder::default()
+                .panel(EmptyPanel::new_in(&self.context)?)
+                .create_in(&self.context)?;
+            self.push_cell(cell)?;
+        }
         Ok(())
     }
     fn resize_cells(&mut self) -> windows::Result<()> {
+        let span = tracing::trace_span!("layout", panel_id = self.handle.id());
+        let _enter = span.enter();
         let size = self.visual.Size()?;
+        let padding = self.params.padding * self.context.scale_factor()?;
+        let inner = Vector2 {
+            X: (size.X - padding * 2.).max(0.),
+            Y: (size.Y - padding * 2.).max(0.),
+        };
         if self.params.orientation == RibbonOrientation::Stack {
-            for cell in &self.params.cells {
-                let content_size = size.clone() * cell.content_ratio.clone();
-                let content_offset = Vector3 {
-                    X: (size.X - content_size.X) / 2.,
-                    Y: (size.Y - content_size.Y) / 2.,
-                    Z: 0.,
-                };
-                cell.container.SetSize(&content_size)?;
-                cell.container.SetOffset(&content_offset)?;
+            let offset = Vector2 {
+                X: padding,
+                Y: padding,
+            };
+            for cell in &mut self.params.cells {
+                cell.place_container(&offset, &inner, &inner)?;
+                if std::mem::take(&mut cell.entering)
+                    && !cell.start_transition(self.handle.id(), &size, false)?
+                {
+                    self.context.send_panel_event(
+                        self.handle.id(),
+                        CellTransitionFinished {
+                            panel_id: cell.panel.id(),
+                            exit: false,
+                        },
+                    )?;
+                }
+                if let Some(backdrop) = &cell.backdrop {
+                    backdrop.SetSize(&size)?;
+                    // Theme may be changed, resize is called for it
+                    backdrop.SetBrush(
+                        self.context
+                            .compositor()?
+                            .CreateColorBrushWithColor(self.context.theme()?.modal_backdrop)?,
+                    )?;
+                }
             }
         } else {
+            let hor = self.params.orientation == RibbonOrientation::Horizontal;
+            let spacing = self.params.spacing * self.context.scale_factor()?;
+            let gaps = self.params.cells.len().saturating_sub(1) as f32 * spacing;
+            let (target, across) = if hor {
+                (inner.X, inner.Y)
+            } else {
+                (inner.Y, inner.X)
+            };
+            let available = (target - gaps).max(0.);
             let limits = self
                 .params
                 .cells
                 .iter()
-                .map(|c| c.limit)
-                .collect::<Vec<_>>();
-            let hor = self.params.orientation == RibbonOrientation::Horizontal;
-            let target = if hor { size.X } else { size.Y };
-            let sizes = adjust_cells(limits, target);
-            let mut pos: f32 = 0.;
+                .map(|c| c.layout_limit(hor, available, across))
+                .collect::<windows::Result<Vec<_>>>()?;
+            let sizes = adjust_cells(limits, available);
+            // Space not taken by cells limited by max_size is shared between their slots
+            let slack = if sizes.is_empty() {
+                0.
+            } else {
+                (available - sizes.iter().sum::<f32>()).max(0.) / sizes.len() as f32
+            };
+            let mut pos: f32 = padding;
             for i in 0..self.params.cells.len() {
-                let size = if hor {
-                    Vector2 {
-                        X: sizes[i],
-                        Y: size.Y,
-                    }
+                let slot = sizes[i] + slack;
+                let (offset, slot_size, content_size) = if hor {
+                    (
+                        Vector2 { X: pos, Y: padding },
+                        Vector2 {
+                            X: slot,
+                            Y: inner.Y,
+                        },
+                        Vector2 {
+                            X: sizes[i],
+                            Y: inner.Y,
+                        },
+                    )
                 } else {
-                    Vector2 {
-                        X: size.X,
-                        Y: sizes[i],
-                    }
+                    (
+                        Vector2 { X: padding, Y: pos },
+                        Vector2 {
+                            X: inner.X,
+                            Y: slot,
+                        },
+                        Vector2 {
+                            X: inner.X,
+                            Y: sizes[i],
+                        },
+                    )
                 };
-                let cell = &mut self.params.cells[i];
-                cell.container.SetSize(&size)?;
-                cell.container.SetOffset(if hor {
-                    Vector3 {
-                        X: pos,
-                        Y: 0.,
-                        Z: 0.,
-                    }
-                } else {
-                    Vector3 {
-                        X: 0.,
-                        Y: pos,
-                        Z: 0.,
-                    }
-                })?;
-                pos += sizes[i];
+                self.params.cells[i].place_container(&offset, &slot_size, &content_size)?;
+                pos += slot + spacing;
             }
         }
         for p in &mut self.params.cells {
@@ -348,12 +979,15 @@ impl RibbonPanel {
         }
         Ok(())
     }
-    fn get_cell_by_mouse_position<'a>(
-        &'a mut self,
-        position: &Vector2,
-    ) -> windows::Result<Option<(Vector2, &'a mut RibbonCell)>> {
-        // Scan in reverse order and exit immediately on topmost cell when in Stack mode
-        for p in &mut self.params.cells.iter_mut().rev() {
+    // Returns index of the cell under the position and the position relative to it. In
+    // Stack orientation cells are scanned from top to bottom until a cell containing the
+    // position or a modal cell is met
+    fn hit_test_cell(&self, position: &Vector2) -> windows::Result<Option<(usize, Vector2)>> {
+        for (index, p) in self.params.cells.iter().enumerate().rev() {
+            if !p.accepts_input() {
+                continue;
+            }
+            // Container is offset inside of the cell slot by alignment and margins
             let offset = p.container.Offset()?;
             let size = p.container.Size()?;
             let position = Vector2 {
@@ -361,14 +995,52 @@ impl RibbonPanel {
                 Y: position.Y - offset.Y,
             };
             if position.X >= 0. && position.X < size.X && position.Y >= 0. && position.Y < size.Y {
-                return Ok(Some((position, p)));
+                return Ok(Some((index, position)));
             }
-            if self.params.orientation == RibbonOrientation::Stack {
+            if self.params.orientation == RibbonOrientation::Stack && p.modal {
                 return Ok(None);
             }
         }
         Ok(None)
     }
+    // Id of the cell panel which receives mouse input at the position
+    pub fn hit_test(&self, position: &Vector2) -> windows::Result<Option<usize>> {
+        Ok(self
+            .hit_test_cell(position)?
+            .map(|(index, _)| self.params.cells[index].panel.id()))
+    }
+    fn get_cell_by_mouse_position<'a>(
+        &'a mut self,
+        position: &Vector2,
+    ) -> windows::Result<Option<(Vector2, &'a mut RibbonCell)>> {
+        if let Some((index, position)) = self.hit_test_cell(position)? {
+            Ok(Some((position, &mut self.params.cells[index])))
+        } else {
+            Ok(None)
+        }
+    }
+    // Unlike get_cell_by_mouse_position returns cell even if position is outside of it
+    fn get_cell_by_panel_id<'a>(
+        &'a mut self,
+        panel_id: usize,
+        position: &Vector2,
+    ) -> windows::Result<Option<(Vector2, &'a mut RibbonCell)>> {
+        if let Some(p) = self
+            .params
+            .cells
+            .iter_mut()
+            .find(|c| c.panel.id() == panel_id)
+        {
+            let offset = p.container.Offset()?;
+            let position = Vector2 {
+                X: position.X - offset.X,
+                Y: position.Y - offset.Y,
+            };
+            Ok(Some((position, p)))
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 impl Panel for RibbonPanel {
@@ -379,23 +1051,211 @@ impl Panel for RibbonPanel {
         self.visual.clone()
     }
 
+    fn measure(&self) -> windows::Result<Vector2> {
+        let scale_factor = self.context.scale_factor()?;
+        // Cell margins and content ratios are included into sizes of their panels
+        let cells = self
+            .params
+            .cells
+            .iter()
+            .map(|c| {
+                let margin = c.margin * scale_factor * 2.;
+                let min = c.panel.measure()?;
+                Ok((
+                    c,
+                    Vector2 {
+                        X: (min.X + margin) / c.content_ratio.X,
+                        Y: (min.Y + margin) / c.content_ratio.Y,
+                    },
+                ))
+            })
+            .collect::<windows::Result<Vec<_>>>()?;
+        let padding = self.params.padding * scale_factor * 2.;
+        let mut size = Vector2 { X: 0., Y: 0. };
+        if self.params.orientation == RibbonOrientation::Stack {
+            for (_, min) in cells {
+                size.X = size.X.max(min.X);
+                size.Y = size.Y.max(min.Y);
+            }
+            return Ok(Vector2 {
+                X: size.X + padding,
+                Y: size.Y + padding,
+            });
+        }
+        let hor = self.params.orientation == RibbonOrientation::Horizontal;
+        let gaps = cells.len().saturating_sub(1) as f32 * self.params.spacing * scale_factor;
+        // Cells with max size and auto sized ones are fixed, the rest share the remaining
+        // space by ratio. Percent cells need the ribbon long enough for their share
+        let flex_ratio = cells
+            .iter()
+            .filter(|(c, _)| c.limit.max_size.is_none() && c.limit.sizing == CellSizing::Ratio)
+            .map(|(c, _)| c.limit.ratio)
+            .sum::<f32>();
+        let mut fixed = 0.;
+        let mut flex = 0.;
+        let mut by_percent: f32 = 0.;
+        let mut sum_percent = 0.;
+        for (cell, min) in cells {
+            let (along, across) = if hor { (min.X, min.Y) } else { (min.Y, min.X) };
+            let along = along.max(cell.limit.min_size);
+            match (cell.limit.sizing, cell.limit.max_size) {
+                (CellSizing::Percent(percent), _) if percent > 0. => {
+                    by_percent = by_percent.max(along * 100. / percent);
+                    sum_percent += percent;
+                }
+                (CellSizing::Percent(_), _) => {}
+                (_, Some(max_size)) => fixed += along.min(max_size),
+                (CellSizing::Auto, None) => fixed += along,
+                (CellSizing::Ratio, None) => {
+                    flex = f32::max(flex, along * flex_ratio / cell.limit.ratio);
+                }
+            }
+            if hor {
+                size.Y = size.Y.max(across);
+            } else {
+                size.X = size.X.max(across);
+            }
+        }
+        let rest = if sum_percent < 100. {
+            (fixed + flex) * 100. / (100. - sum_percent)
+        } else {
+            fixed + flex
+        };
+        let along = rest.max(by_percent) + gaps;
+        if hor {
+            size.X = along;
+        } else {
+            size.Y = along;
+        }
+        Ok(Vector2 {
+            X: size.X + padding,
+            Y: size.Y + padding,
+        })
+    }
+
+    // Cells are laid out one after another with their desired sizes, Stack takes the
+    // largest one. Never less than measure
+    fn desired_size(&self, available: &Vector2) -> windows::Result<Vector2> {
+        let scale_factor = self.context.scale_factor()?;
+        let padding = self.params.padding * scale_factor * 2.;
+        let inner = Vector2 {
+            X: (available.X - padding).max(0.),
+            Y: (available.Y - padding).max(0.),
+        };
+        let hor = self.params.orientation == RibbonOrientation::Horizontal;
+        let stack = self.params.orientation == RibbonOrientation::Stack;
+        let mut size = Vector2 { X: 0., Y: 0. };
+        for cell in &self.params.cells {
+            let margin = cell.margin * scale_factor * 2.;
+            let bounds = Vector2 {
+                X: (inner.X * cell.content_ratio.X - margin).max(0.),
+                Y: (inner.Y * cell.content_ratio.Y - margin).max(0.),
+            };
+            let desired = cell.panel.desired_size(&bounds)?;
+            let desired = Vector2 {
+                X: (desired.X + margin) / cell.content_ratio.X,
+                Y: (desired.Y + margin) / cell.content_ratio.Y,
+            };
+            if stack {
+                size.X = size.X.max(desired.X);
+                size.Y = size.Y.max(desired.Y);
+            } else if hor {
+                size.X += desired.X;
+                size.Y = size.Y.max(desired.Y);
+            } else {
+                size.X = size.X.max(desired.X);
+                size.Y += desired.Y;
+            }
+        }
+        if !stack {
+            let gaps = self.params.cells.len().saturating_sub(1) as f32
+                * self.params.spacing
+                * scale_factor;
+            if hor {
+                size.X += gaps;
+            } else {
+                size.Y += gaps;
+            }
+        }
+        let min = self.measure()?;
+        Ok(Vector2 {
+            X: (size.X + padding).max(min.X),
+            Y: (size.Y + padding).max(min.Y),
+        })
+    }
+
     fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
         self.visual.SetSize(size)?;
-        self.resize_cells()?;
-        Ok(())
+        self.request_layout()
     }
 
     fn on_idle(&mut self) -> windows::Result<()> {
+        // Parent is arranged before on_idle of its children, so they get new sizes
+        // and arrange themselves in the same pass
+        if std::mem::take(&mut self.layout_pending) {
+            self.resize_cells()?;
+        }
         for p in &mut self.params.cells {
             p.panel.on_idle()?;
         }
         Ok(())
     }
 
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        for p in &mut self.params.cells {
+            p.panel.on_timer(timer_id)?;
+        }
+        Ok(())
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        for p in &mut self.params.cells {
+            p.panel.on_focus_changed(focused)?;
+        }
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        for p in &mut self.params.cells {
+            p.panel.on_suspend()?;
+        }
+        Ok(())
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        for p in &mut self.params.cells {
+            p.panel.on_resume()?;
+        }
+        Ok(())
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        for p in &mut self.params.cells {
+            p.panel.on_device_lost()?;
+        }
+        Ok(())
+    }
+
     fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
         self.mouse_position = Some(position.clone());
-        if let Some((position, cell)) = self.get_cell_by_mouse_position(position)? {
-            cell.panel.on_mouse_move(&position)?;
+        let target = match self.mouse_capture {
+            Some(panel_id) => Some(panel_id),
+            None => self
+                .get_cell_by_mouse_position(position)?
+                .map(|(_, cell)| cell.panel.id()),
+        };
+        if let Some(panel_id) = self.mouse_hover {
+            if Some(panel_id) != target {
+                if let Some((position, cell)) = self.get_cell_by_panel_id(panel_id, position)? {
+                    cell.panel.on_mouse_move(&position)?;
+                }
+            }
+        }
+        self.mouse_hover = target;
+        if let Some(panel_id) = target {
+            if let Some((position, cell)) = self.get_cell_by_panel_id(panel_id, position)? {
+                cell.panel.on_mouse_move(&position)?;
+            }
         }
         Ok(())
     }
@@ -406,8 +1266,20 @@ impl Panel for RibbonPanel {
         state: winit::event::ElementState,
     ) -> windows::Result<bool> {
         if let Some(position) = self.mouse_position.clone() {
+            if state == winit::event::ElementState::Released {
+                if let Some(panel_id) = self.mouse_capture.take() {
+                    if let Some((_, cell)) = self.get_cell_by_panel_id(panel_id, &position)? {
+                        return cell.panel.on_mouse_input(button, state);
+                    }
+                }
+            }
             if let Some((_, cell)) = self.get_cell_by_mouse_position(&position)? {
-                return cell.panel.on_mouse_input(button, state);
+                let panel_id = cell.panel.id();
+                let handled = cell.panel.on_mouse_input(button, state)?;
+                if handled && state == winit::event::ElementState::Pressed {
+                    self.mouse_capture = Some(panel_id);
+                }
+                return Ok(handled);
             }
         }
         Ok(false)
@@ -428,12 +1300,63 @@ impl Panel for RibbonPanel {
         }
     }
 
-    fn on_keyboard_input(&mut self, input: winit::event::KeyboardInput) -> windows::Result<bool> {
-        for p in &mut self.params.cells.iter_mut().rev() {
+    fn on_keyboard_input(
+        &mut self,
+        input: winit::event::KeyboardInput,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        for p in self
+            .params
+            .cells
+            .iter_mut()
+            .rev()
+            .filter(|c| c.accepts_input())
+        {
+            if self.params.orientation == RibbonOrientation::Stack {
+                // Modal cell doesn't let input through to its owner
+                return Ok(p.panel.on_keyboard_input(input, modifiers)? || p.modal);
+            } else {
+                if p.panel.on_keyboard_input(input, modifiers)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_char(&mut self, ch: char) -> windows::Result<bool> {
+        for p in self
+            .params
+            .cells
+            .iter_mut()
+            .rev()
+            .filter(|c| c.accepts_input())
+        {
             if self.params.orientation == RibbonOrientation::Stack {
-                return p.panel.on_keyboard_input(input);
+                // Modal cell doesn't let input through to its owner
+                return Ok(p.panel.on_char(ch)? || p.modal);
             } else {
-                if p.panel.on_keyboard_input(input)? {
+                if p.panel.on_char(ch)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        for p in self
+            .params
+            .cells
+            .iter_mut()
+            .rev()
+            .filter(|c| c.accepts_input())
+        {
+            if self.params.orientation == RibbonOrientation::Stack {
+                // Modal cell doesn't let input through to its owner
+                return Ok(p.panel.on_gamepad_input(input)? || p.modal);
+            } else {
+                if p.panel.on_gamepad_input(input)? {
                     return Ok(true);
                 }
             }
@@ -442,17 +1365,252 @@ impl Panel for RibbonPanel {
     }
 
     fn on_init(&mut self) -> windows::Result<()> {
-        self.on_resize(&self.visual().Parent()?.Size()?)?;
+        // Children are initialized with their actual sizes
+        self.visual.SetSize(self.visual().Parent()?.Size()?)?;
+        self.layout_pending = false;
+        self.resize_cells()?;
         for p in &mut self.params.cells {
             p.panel.on_init()?;
         }
         Ok(())
     }
 
+    fn on_close(&mut self) -> windows::Result<()> {
+        for p in &mut self.params.cells {
+            p.panel.on_close()?;
+        }
+        Ok(())
+    }
+
     fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        if let Some(TransitionBatchCompleted { panel_id, exit }) =
+            TransitionBatchHandle(self.handle.id()).extract_event(panel_event)
+        {
+            if exit {
+                let children = self.visual.Children()?;
+                for cell in self.leaving.iter().filter(|c| c.panel_id == panel_id) {
+                    if let Some(backdrop) = &cell.backdrop {
+                        children.Remove(backdrop)?;
+                    }
+                    children.Remove(&cell.container)?;
+                }
+                self.leaving.retain(|c| c.panel_id != panel_id);
+            }
+            self.context
+                .send_panel_event(self.handle.id(), CellTransitionFinished { panel_id, exit })?;
+        }
+        for p in &mut self.params.cells {
+            if panel_event.is_routed_to(&mut *p.panel) {
+                p.panel.on_panel_event(panel_event)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_mouse_wheel(&mut self, delta: winit::event::MouseScrollDelta) -> windows::Result<bool> {
+        if let Some(position) = self.mouse_position.clone() {
+            if let Some((_, cell)) = self.get_cell_by_mouse_position(&position)? {
+                return cell.panel.on_mouse_wheel(delta);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_cursor_inside_changed(&mut self, inside: bool) -> windows::Result<()> {
+        for p in &mut self.params.cells {
+            p.panel.on_cursor_inside_changed(inside)?;
+        }
+        Ok(())
+    }
+
+    fn on_modifiers_changed(
+        &mut self,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<()> {
         for p in &mut self.params.cells {
-            p.panel.on_panel_event(panel_event)?;
+            p.panel.on_modifiers_changed(modifiers)?;
         }
         Ok(())
     }
+
+    fn request_layout(&mut self) -> windows::Result<()> {
+        self.layout_pending = true;
+        // Changes made inside of on_idle are arranged on the next frame
+        self.context.request_idle()
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        self.params.cells.iter().map(|c| &*c.panel).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CellLimit;
+
+    pub fn limit(ratio: f32, min_size: f32, max_size: Option<f32>) -> CellLimit {
+        CellLimit {
+            ratio,
+            min_size,
+            max_size,
+            ..CellLimit::default()
+        }
+    }
+
+    pub fn assert_near(a: f32, b: f32) {
+        assert!(
+            (a - b).abs() <= 1e-3 * a.abs().max(b.abs()).max(1.),
+            "{} != {}",
+            a,
+            b
+        );
+    }
+
+    // Xorshift generator, so failing cases are reproduced by the seed
+    pub struct Random(pub u64);
+
+    impl Random {
+        pub fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        pub fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+        pub fn size(&mut self) -> f32 {
+            (self.below(1000) as f32) / 2.
+        }
+        pub fn limits(&mut self) -> Vec<CellLimit> {
+            (0..self.below(8))
+                .map(|_| {
+                    let ratio = (self.below(5) as f32) / 2.;
+                    let min_size = if self.below(2) == 0 { 0. } else { self.size() };
+                    let max_size = match self.below(3) {
+                        0 => None,
+                        _ => Some(min_size + self.size()),
+                    };
+                    limit(ratio, min_size, max_size)
+                })
+                .collect()
+        }
+    }
+}
+
+#[test]
+fn adjust_cells_splits_by_ratio() {
+    use tests::{assert_near, limit};
+    let sizes = adjust_cells(vec![limit(1., 0., None), limit(3., 0., None)], 100.);
+    assert_near(sizes[0], 25.);
+    assert_near(sizes[1], 75.);
+}
+
+#[test]
+fn adjust_cells_gives_rest_to_unlimited() {
+    use tests::{assert_near, limit};
+    let sizes = adjust_cells(
+        vec![
+            limit(1., 0., Some(10.)),
+            limit(1., 50., None),
+            limit(2., 0., None),
+        ],
+        100.,
+    );
+    assert_near(sizes[0], 10.);
+    assert_near(sizes[1], 50.);
+    assert_near(sizes[2], 40.);
+}
+
+#[test]
+fn adjust_cells_min_sizes_overflow() {
+    use tests::{assert_near, limit};
+    let sizes = adjust_cells(vec![limit(1., 60., None), limit(1., 70., None)], 100.);
+    assert_near(sizes[0], 60.);
+    assert_near(sizes[1], 70.);
+}
+
+#[test]
+fn adjust_cells_degenerate_input() {
+    use tests::limit;
+    assert!(adjust_cells(Vec::new(), 100.).is_empty());
+    let sizes = adjust_cells(
+        vec![
+            limit(0., 10., None),
+            limit(-1., -5., Some(-10.)),
+            limit(f32::NAN, f32::NAN, Some(f32::NAN)),
+            limit(1., 20., Some(5.)),
+        ],
+        f32::NAN,
+    );
+    assert_eq!(sizes, vec![10., 0., 0., 20.]);
+    let sizes = adjust_cells(vec![limit(0., 0., None), limit(0., 0., None)], 100.);
+    assert_eq!(sizes, vec![0., 0.]);
+}
+
+// Sizes are within limits, fill the target when limits allow, and cells not stopped
+// by a limit get the same share per ratio, which is more than stopped at max got
+// and less than stopped at min got
+#[test]
+fn adjust_cells_properties() {
+    use tests::{assert_near, Random};
+    for seed in 1..2000 {
+        let mut random = Random(seed);
+        let limits = random.limits();
+        let target = random.size() * 4.;
+        let sizes = adjust_cells(limits.clone(), target);
+        assert_eq!(sizes.len(), limits.len(), "seed {}", seed);
+        let sum_min = limits.iter().map(|c| c.min_size).sum::<f32>();
+        let growing = limits.iter().filter(|c| c.ratio > 0.).collect::<Vec<_>>();
+        let sum_max = growing
+            .iter()
+            .map(|c| c.max_size.unwrap_or(f32::INFINITY))
+            .sum::<f32>()
+            + limits
+                .iter()
+                .filter(|c| c.ratio <= 0.)
+                .map(|c| c.min_size)
+                .sum::<f32>();
+        for (size, c) in sizes.iter().zip(&limits) {
+            assert!(*size >= c.min_size, "seed {}", seed);
+            if let Some(max_size) = c.max_size {
+                assert!(*size <= max_size, "seed {}", seed);
+            }
+        }
+        let sum = sizes.iter().sum::<f32>();
+        if sum_min >= target {
+            for (size, c) in sizes.iter().zip(&limits) {
+                assert_near(*size, c.min_size);
+            }
+        } else if sum_max >= target {
+            assert_near(sum, target);
+        } else {
+            assert_near(sum, sum_max);
+        }
+        let free = sizes
+            .iter()
+            .zip(&limits)
+            .filter(|(size, c)| {
+                c.ratio > 0. && **size > c.min_size && c.max_size.iter().all(|m| **size < *m)
+            })
+            .map(|(size, c)| size / c.ratio)
+            .collect::<Vec<_>>();
+        if let Some(share) = free.first() {
+            for s in &free {
+                assert_near(*s, *share);
+            }
+            for (size, c) in sizes.iter().zip(&limits) {
+                // Cell with equal min and max sizes is fixed whatever the share is
+                if c.ratio <= 0. || Some(c.min_size) == c.max_size {
+                    continue;
+                }
+                if Some(*size) == c.max_size {
+                    assert!(size / c.ratio <= share * 1.001, "seed {}", seed);
+                }
+                if *size == c.min_size {
+                    assert!(size / c.ratio >= share * 0.999, "seed {}", seed);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,309 @@
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use bindings::Windows::{
+    Foundation::{
+        Numerics::{Vector2, Vector3},
+        TimeSpan,
+    },
+    UI::{Color, Colors, Composition::ContainerVisual},
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton};
+
+use crate::{
+    accessibility::AccessibleRole,
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelEvent, PanelHandle},
+    text_panel::{TextPanel, TextParamsBuilder},
+};
+
+#[derive(Copy, Clone)]
+pub struct ScorePanelHandle {
+    id: usize,
+}
+
+impl Handle for ScorePanelHandle {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl PanelHandle<ScorePanel> for ScorePanelHandle {}
+
+// Makes displayed text of the score and its increments
+pub type ScoreFormatter = Rc<dyn Fn(u32) -> String>;
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct ScoreParams {
+    #[builder(default = "{0}")]
+    score: u32,
+    // Theme text color is used when not set
+    #[builder(default = "{None}")]
+    color: Option<Color>,
+    #[builder(default = "{Colors::DarkGreen().unwrap()}")]
+    increment_color: Color,
+    #[builder(default = "{2.}")]
+    font_scale: f32,
+    #[builder(default = "{Duration::from_millis(500)}")]
+    count_duration: Duration,
+    #[builder(default = "{Duration::from_millis(1000)}")]
+    increment_duration: Duration,
+    #[builder(default = "{Rc::new(|score: u32| score.to_string())}")]
+    formatter: ScoreFormatter,
+}
+
+impl ScoreParamsBuilder {
+    pub fn create(self) -> windows::Result<ScorePanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<ScorePanel> {
+        match self.build() {
+            Ok(params) => Ok(ScorePanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+}
+
+pub struct ScorePanel {
+    context: FrameworkContext,
+    id: usize,
+    params: ScoreParams,
+    visual: ContainerVisual,
+    text_panel: TextPanel,
+    increment_panel: TextPanel,
+    displayed_score: u32,
+    count_from: u32,
+    count_start: Option<Instant>,
+}
+
+impl ScorePanel {
+    pub fn new(context: &FrameworkContext, params: ScoreParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        let text_panel = TextParamsBuilder::default()
+            .text((params.formatter)(params.score))
+            .color(params.color)
+            .font_scale(params.font_scale)
+            .create_in(context)?;
+        let increment_panel = TextParamsBuilder::default()
+            .color(params.increment_color)
+            .font_scale(params.font_scale * 1.5)
+            .create_in(context)?;
+        increment_panel.visual().SetOpacity(0.)?;
+        visual.Children()?.InsertAtTop(text_panel.visual())?;
+        visual.Children()?.InsertAtTop(increment_panel.visual())?;
+        let displayed_score = params.score;
+        Ok(Self {
+            context: context.clone(),
+            id,
+            params,
+            visual,
+            text_panel,
+            increment_panel,
+            displayed_score,
+            count_from: displayed_score,
+            count_start: None,
+        })
+    }
+    pub fn handle(&self) -> ScorePanelHandle {
+        ScorePanelHandle { id: self.id }
+    }
+    pub fn score(&self) -> u32 {
+        self.params.score
+    }
+    // Increasing score is counted up and shown as floating "+N", decreasing is set instantly
+    pub fn set_score(&mut self, score: u32) -> windows::Result<()> {
+        if score > self.params.score {
+            self.show_increment(score - self.params.score)?;
+            self.count_from = self.displayed_score;
+            self.count_start = Some(Instant::now());
+            self.params.score = score;
+        } else if score != self.params.score || self.count_start.is_some() {
+            self.params.score = score;
+            self.count_start = None;
+            self.set_displayed_score(score)?;
+        }
+        // Final score is announced, not intermediate counted values
+        self.context.set_accessible(
+            self.id,
+            &self.visual,
+            AccessibleRole::Text,
+            score.to_string(),
+        )
+    }
+    pub fn set_formatter(&mut self, formatter: ScoreFormatter) -> windows::Result<()> {
+        self.params.formatter = formatter;
+        self.text_panel
+            .set_text((self.params.formatter)(self.displayed_score))
+    }
+    fn set_displayed_score(&mut self, score: u32) -> windows::Result<()> {
+        if self.displayed_score != score {
+            self.displayed_score = score;
+            self.text_panel.set_text((self.params.formatter)(score))?;
+        }
+        Ok(())
+    }
+    fn show_increment(&mut self, increment: u32) -> windows::Result<()> {
+        self.increment_panel
+            .set_text(format!("+{}", (self.params.formatter)(increment)))?;
+        let visual = self.increment_panel.visual();
+        let duration = TimeSpan {
+            Duration: (self.params.increment_duration.as_nanos() / 100) as i64,
+        };
+        let height = self.visual.Size()?.Y;
+
+        let compositor = self.context.compositor()?;
+        let offset_animation = compositor.CreateVector3KeyFrameAnimation()?;
+        offset_animation.InsertKeyFrame(
+            0.0,
+            Vector3 {
+                X: 0.,
+                Y: 0.,
+                Z: 0.,
+            },
+        )?;
+        offset_animation.InsertKeyFrame(
+            1.0,
+            Vector3 {
+                X: 0.,
+                Y: -height / 2.,
+                Z: 0.,
+            },
+        )?;
+        offset_animation.SetDuration(&duration)?;
+
+        let opacity_animation = compositor.CreateScalarKeyFrameAnimation()?;
+        opacity_animation.InsertKeyFrame(0.0, 1.)?;
+        opacity_animation.InsertKeyFrame(1.0, 0.)?;
+        opacity_animation.SetDuration(&duration)?;
+
+        visual.StartAnimation("Offset", offset_animation)?;
+        visual.StartAnimation("Opacity", opacity_animation)?;
+        Ok(())
+    }
+}
+
+impl Panel for ScorePanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn std::any::Any> {
+        if id == self.id {
+            Some(self.as_any_mut())
+        } else {
+            None
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.context.set_accessible(
+            self.id,
+            &self.visual,
+            AccessibleRole::Text,
+            self.params.score.to_string(),
+        )?;
+        self.on_resize(&self.visual().Parent()?.Size()?)
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.context.remove_accessible(self.id)?;
+        self.count_start = None;
+        self.text_panel.on_close()?;
+        self.increment_panel.on_close()
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        self.text_panel.measure()
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size)?;
+        self.text_panel.on_resize(size)?;
+        self.increment_panel.on_resize(size)
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        if let Some(start) = self.count_start {
+            let t = start.elapsed().as_secs_f32() / self.params.count_duration.as_secs_f32();
+            if t >= 1. {
+                self.count_start = None;
+                self.set_displayed_score(self.params.score)?;
+            } else {
+                let delta = (self.params.score - self.count_from) as f32 * t;
+                self.set_displayed_score(self.count_from + delta as u32)?;
+                self.context.request_idle()?;
+            }
+        }
+        self.text_panel.on_idle()?;
+        self.increment_panel.on_idle()
+    }
+
+    fn on_timer(&mut self, _timer_id: usize) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_focus_changed(&mut self, _focused: bool) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.text_panel.on_suspend()?;
+        self.increment_panel.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.text_panel.on_resume()?;
+        self.increment_panel.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.text_panel.on_device_lost()?;
+        self.increment_panel.on_device_lost()
+    }
+
+    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        _button: MouseButton,
+        _state: ElementState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        _input: KeyboardInput,
+        _modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, _input: GamepadInput) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
+        Ok(())
+    }
+}
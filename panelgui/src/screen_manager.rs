@@ -0,0 +1,341 @@
+use std::any::Any;
+
+use bindings::Windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::Composition::ContainerVisual,
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelEvent, PanelHandle},
+    ribbon_panel::{
+        RibbonCell, RibbonCellParamsBuilder, RibbonOrientation, RibbonPanel, RibbonParamsBuilder,
+    },
+};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ScreenTransition {
+    None,
+    Fade,
+    // Pushed screen comes from the right, popped one leaves to the right
+    Slide,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct ScreenManagerParams {
+    #[builder(private, setter(name = "screen_private"))]
+    screen: Box<dyn Panel>,
+}
+
+impl ScreenManagerParamsBuilder {
+    pub fn create(self) -> windows::Result<ScreenManager> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<ScreenManager> {
+        match self.build() {
+            Ok(params) => Ok(ScreenManager::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+    pub fn screen(self, screen: impl Panel + 'static) -> Self {
+        let screen: Box<dyn Panel + 'static> = Box::new(screen);
+        self.screen_private(screen)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct ScreenManagerHandle(usize);
+
+impl Handle for ScreenManagerHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<ScreenManager> for ScreenManagerHandle {}
+
+// Stack of screens where only the top one is visible and receives input
+pub struct ScreenManager {
+    context: FrameworkContext,
+    id: usize,
+    visual: ContainerVisual,
+    ribbon: RibbonPanel,
+    // Popped screens are already closed, their visuals are kept until the transition ends
+    leaving: Vec<RibbonCell>,
+}
+
+impl ScreenManager {
+    pub fn new(context: &FrameworkContext, params: ScreenManagerParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let ribbon = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel_box(params.screen)
+                    .create_in(context)?,
+            )
+            .create_in(context)?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(ribbon.visual())?;
+        Ok(Self {
+            context: context.clone(),
+            id,
+            visual,
+            ribbon,
+            leaving: Vec::new(),
+        })
+    }
+    pub fn handle(&self) -> ScreenManagerHandle {
+        ScreenManagerHandle(self.id)
+    }
+    pub fn screen_count(&self) -> usize {
+        self.ribbon.cell_count()
+    }
+    pub fn push_screen(
+        &mut self,
+        cell: RibbonCell,
+        transition: ScreenTransition,
+    ) -> windows::Result<()> {
+        self.finish_transition()?;
+        let prev = self.top_visual()?;
+        self.ribbon.push_cell(cell)?;
+        let top = self.ribbon.cell_count() - 1;
+        if let Some(panel) = self.ribbon.cell_panel_mut(top) {
+            panel.on_init()?;
+        }
+        let width = self.visual.Size()?.X;
+        let top = self.top_visual()?;
+        self.animate(&top, transition, width, 0., 0., 1.)?;
+        self.animate(&prev, transition, 0., -width, 1., 0.)?;
+        self.start_transition(transition)
+    }
+    pub fn pop_screen(&mut self, transition: ScreenTransition) -> windows::Result<()> {
+        if self.ribbon.cell_count() < 2 {
+            return Err(winrt_error("Can't pop the last screen")());
+        }
+        self.finish_transition()?;
+        let cell = self.ribbon.pop_cell()?;
+        self.visual
+            .Children()?
+            .InsertAtTop(cell.container().clone())?;
+        let top = self.top_visual()?;
+        top.SetIsVisible(true)?;
+        let width = self.visual.Size()?.X;
+        self.animate(&top, transition, -width, 0., 0., 1.)?;
+        self.animate(&cell.panel().visual(), transition, 0., width, 1., 0.)?;
+        self.leaving.push(cell);
+        self.start_transition(transition)
+    }
+    fn top_visual(&mut self) -> windows::Result<ContainerVisual> {
+        let top = self.ribbon.cell_count() - 1;
+        match self.ribbon.cell_panel_mut(top) {
+            Some(panel) => Ok(panel.visual()),
+            None => Err(winrt_error("No screens")()),
+        }
+    }
+    // Slide moves the visual horizontally, fade changes its opacity
+    fn animate(
+        &self,
+        visual: &ContainerVisual,
+        transition: ScreenTransition,
+        from_x: f32,
+        to_x: f32,
+        from_opacity: f32,
+        to_opacity: f32,
+    ) -> windows::Result<()> {
+        let settings = self.context.animation_settings()?;
+        if transition == ScreenTransition::None || !settings.enabled {
+            return Ok(());
+        }
+        let compositor = self.context.compositor()?;
+        if transition == ScreenTransition::Slide {
+            let animation = compositor.CreateVector3KeyFrameAnimation()?;
+            animation.InsertKeyFrame(
+                0.0,
+                Vector3 {
+                    X: from_x,
+                    Y: 0.,
+                    Z: 0.,
+                },
+            )?;
+            animation.InsertKeyFrameWithEasingFunction(
+                1.0,
+                Vector3 {
+                    X: to_x,
+                    Y: 0.,
+                    Z: 0.,
+                },
+                settings.easing_function()?,
+            )?;
+            animation.SetDuration(settings.time_span())?;
+            visual.StartAnimation("Offset", animation)
+        } else {
+            let animation = compositor.CreateScalarKeyFrameAnimation()?;
+            animation.InsertKeyFrame(0.0, from_opacity)?;
+            animation.InsertKeyFrameWithEasingFunction(
+                1.0,
+                to_opacity,
+                settings.easing_function()?,
+            )?;
+            animation.SetDuration(settings.time_span())?;
+            visual.StartAnimation("Opacity", animation)
+        }
+    }
+    fn start_transition(&mut self, transition: ScreenTransition) -> windows::Result<()> {
+        let settings = self.context.animation_settings()?;
+        if transition == ScreenTransition::None || !settings.enabled {
+            self.finish_transition()
+        } else {
+            self.context.set_timer(self.id, settings.duration)
+        }
+    }
+    // Drops visuals of popped screens and hides all screens except the top one
+    fn finish_transition(&mut self) -> windows::Result<()> {
+        self.context.kill_timer(self.id)?;
+        for cell in self.leaving.drain(..) {
+            self.visual.Children()?.Remove(cell.container())?;
+        }
+        let count = self.ribbon.cell_count();
+        for index in 0..count {
+            if let Some(panel) = self.ribbon.cell_panel_mut(index) {
+                let visual = panel.visual();
+                visual.StopAnimation("Offset")?;
+                visual.StopAnimation("Opacity")?;
+                visual.SetOffset(Vector3 {
+                    X: 0.,
+                    Y: 0.,
+                    Z: 0.,
+                })?;
+                visual.SetOpacity(1.)?;
+                visual.SetIsVisible(index + 1 == count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Panel for ScreenManager {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id {
+            Some(self.as_any_mut())
+        } else {
+            self.ribbon.find_panel(id)
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)?;
+        self.ribbon.on_init()?;
+        self.finish_transition()
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.finish_transition()?;
+        self.ribbon.on_close()
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        // Hidden screens don't constrain the window
+        match self.ribbon.cell_panel(self.ribbon.cell_count() - 1) {
+            Some(panel) => panel.measure(),
+            None => Ok(Vector2 { X: 0., Y: 0. }),
+        }
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size)?;
+        self.ribbon.on_resize(size)
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        self.ribbon.on_idle()
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        self.ribbon.on_timer(timer_id)?;
+        if timer_id == self.id {
+            self.finish_transition()?;
+        }
+        Ok(())
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        self.ribbon.on_focus_changed(focused)
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.ribbon.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.ribbon.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.ribbon.on_device_lost()
+    }
+
+    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
+        self.ribbon.on_mouse_move(position)
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        self.ribbon.on_mouse_input(button, state)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        self.ribbon.on_keyboard_input(input, modifiers)
+    }
+
+    fn on_char(&mut self, ch: char) -> windows::Result<bool> {
+        self.ribbon.on_char(ch)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        self.ribbon.on_gamepad_input(input)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.ribbon.on_panel_event(panel_event)
+    }
+
+    fn on_mouse_wheel(&mut self, delta: MouseScrollDelta) -> windows::Result<bool> {
+        self.ribbon.on_mouse_wheel(delta)
+    }
+
+    fn on_cursor_inside_changed(&mut self, inside: bool) -> windows::Result<()> {
+        self.ribbon.on_cursor_inside_changed(inside)
+    }
+
+    fn on_modifiers_changed(&mut self, modifiers: ModifiersState) -> windows::Result<()> {
+        self.ribbon.on_modifiers_changed(modifiers)
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        vec![&self.ribbon]
+    }
+}
@@ -0,0 +1,77 @@
+use bindings::{
+    Microsoft::Graphics::Canvas::UI::Composition::CanvasComposition,
+    Windows::{
+        Foundation::{
+            Numerics::{Vector2, Vector3},
+            Size,
+        },
+        Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        UI::{
+            Colors,
+            Composition::{CompositionSurfaceBrush, SpriteVisual},
+        },
+    },
+};
+
+use crate::{
+    globals::{composition_graphics_device, compositor, pixel_size},
+    style::DropShadowStyle,
+};
+
+// Brush with opaque rounded rectangle filling the whole surface. It's stretched to the
+// visual it's applied to, so it may be drawn once for visuals of the same proportions
+pub fn create_rounded_mask(
+    size: &Vector2,
+    corner_radius: f32,
+) -> windows::Result<CompositionSurfaceBrush> {
+    let size = pixel_size(size);
+    let surface = composition_graphics_device().CreateDrawingSurface(
+        Size {
+            Width: size.X.max(1.),
+            Height: size.Y.max(1.),
+        },
+        DirectXPixelFormat::B8G8R8A8UIntNormalized,
+        DirectXAlphaMode::Premultiplied,
+    )?;
+    {
+        let ds = CanvasComposition::CreateDrawingSession(&surface)?;
+        ds.Clear(Colors::Transparent()?)?;
+        ds.FillRoundedRectangleAtCoordsWithColor(
+            0.,
+            0.,
+            size.X,
+            size.Y,
+            corner_radius,
+            corner_radius,
+            Colors::Black()?,
+        )?;
+    }
+    let brush = compositor().CreateSurfaceBrush()?;
+    brush.SetSurface(surface)?;
+    Ok(brush)
+}
+
+// Visual which draws nothing but the shadow of the mask shape. It should be placed
+// under the content it belongs to. Scale converts logical pixels of the style to units
+// of the visual
+pub fn create_shadow_visual(
+    size: &Vector2,
+    mask: &CompositionSurfaceBrush,
+    style: &DropShadowStyle,
+    scale: f32,
+) -> windows::Result<SpriteVisual> {
+    let shadow = compositor().CreateDropShadow()?;
+    shadow.SetMask(mask)?;
+    shadow.SetColor(style.color.clone())?;
+    shadow.SetOpacity(style.opacity)?;
+    shadow.SetBlurRadius(style.blur_radius * scale)?;
+    shadow.SetOffset(Vector3 {
+        X: style.offset.X * scale,
+        Y: style.offset.Y * scale,
+        Z: 0.,
+    })?;
+    let visual = compositor().CreateSpriteVisual()?;
+    visual.SetSize(size.clone())?;
+    visual.SetShadow(shadow)?;
+    Ok(visual)
+}
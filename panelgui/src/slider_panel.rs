@@ -0,0 +1,432 @@
+use std::any::Any;
+
+use bindings::Windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color, Colors,
+        Composition::{CompositionShape, ContainerVisual, ShapeVisual},
+    },
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    control::{Control, ControlHandle},
+    gamepad::{GamepadButton, GamepadInput},
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelHandle},
+    style::StyleState,
+};
+
+// In logical pixels
+const MIN_SLIDER_HEIGHT: f32 = 16.;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SliderPanelEvent {
+    ValueChanged(f32),
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct SliderParams {
+    #[builder(default = "{0.}")]
+    min: f32,
+    #[builder(default = "{1.}")]
+    max: f32,
+    #[builder(default = "{0.}")]
+    value: f32,
+    // Value is rounded to multiple of step, step is also used for keyboard adjustment
+    #[builder(default = "{0.1}")]
+    step: f32,
+    #[builder(default = "{true}")]
+    enabled: bool,
+    // Non-interactive slider has no thumb and works as progress bar
+    #[builder(default = "{true}")]
+    interactive: bool,
+    #[builder(default = "{Colors::LightGray().unwrap()}")]
+    track_color: Color,
+    #[builder(default = "{Colors::SteelBlue().unwrap()}")]
+    fill_color: Color,
+    #[builder(default = "{Colors::White().unwrap()}")]
+    thumb_color: Color,
+}
+
+impl SliderParamsBuilder {
+    pub fn create(self) -> windows::Result<SliderPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<SliderPanel> {
+        match self.build() {
+            Ok(params) => Ok(SliderPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct SliderPanelHandle(usize);
+
+impl Handle for SliderPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<SliderPanel, SliderPanelEvent> for SliderPanelHandle {}
+
+impl ControlHandle for SliderPanelHandle {
+    fn as_control<'a>(&self, root_panel: &'a mut dyn Panel) -> Option<&'a mut dyn Control> {
+        self.at(root_panel).ok().map(|p| p as &mut dyn Control)
+    }
+}
+
+pub struct SliderPanel {
+    context: FrameworkContext,
+    id: usize,
+    params: SliderParams,
+    visual: ContainerVisual,
+    shape_visual: ShapeVisual,
+    focused: bool,
+    dragging: bool,
+    mouse_pos: Option<Vector2>,
+}
+
+impl SliderPanel {
+    pub fn new(context: &FrameworkContext, params: SliderParams) -> windows::Result<Self> {
+        if params.max <= params.min {
+            return Err(winrt_error("Slider max must be greater than min")());
+        }
+        let id = context.next_id()?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        let shape_visual = context.compositor()?.CreateShapeVisual()?;
+        visual.Children()?.InsertAtTop(shape_visual.clone())?;
+        let mut panel = Self {
+            context: context.clone(),
+            id,
+            params,
+            visual,
+            shape_visual,
+            focused: false,
+            dragging: false,
+            mouse_pos: None,
+        };
+        panel.params.value = panel.adjust_value(panel.params.value);
+        Ok(panel)
+    }
+    pub fn handle(&self) -> SliderPanelHandle {
+        SliderPanelHandle(self.id)
+    }
+    pub fn value(&self) -> f32 {
+        self.params.value
+    }
+    pub fn set_value(&mut self, value: f32) -> windows::Result<()> {
+        self.params.value = self.adjust_value(value);
+        self.redraw()
+    }
+    fn adjust_value(&self, value: f32) -> f32 {
+        let mut value = value;
+        if self.params.step > 0. {
+            let steps = ((value - self.params.min) / self.params.step).round();
+            value = self.params.min + steps * self.params.step;
+        }
+        value.max(self.params.min).min(self.params.max)
+    }
+    fn change_value(&mut self, value: f32) -> windows::Result<()> {
+        let value = self.adjust_value(value);
+        if value != self.params.value {
+            self.params.value = value;
+            self.redraw()?;
+            self.context
+                .send_panel_event(self.id, SliderPanelEvent::ValueChanged(value))?;
+        }
+        Ok(())
+    }
+    fn is_interactive(&self) -> bool {
+        self.params.enabled && self.params.interactive
+    }
+    fn thumb_size(&self, size: &Vector2) -> f32 {
+        if self.params.interactive {
+            (size.Y * 0.6).min(size.X / 4.)
+        } else {
+            0.
+        }
+    }
+    fn value_by_position(&self, position: &Vector2) -> windows::Result<f32> {
+        let size = self.visual.Size()?;
+        let thumb_size = self.thumb_size(&size);
+        let track_length = size.X - thumb_size;
+        if track_length <= 0. {
+            return Ok(self.params.value);
+        }
+        let t = ((position.X - thumb_size / 2.) / track_length)
+            .max(0.)
+            .min(1.);
+        Ok(self.params.min + t * (self.params.max - self.params.min))
+    }
+    fn create_rect(
+        &self,
+        offset: Vector2,
+        size: Vector2,
+        radius: f32,
+        color: Color,
+        stroke: Option<Color>,
+    ) -> windows::Result<CompositionShape> {
+        let compositor = self.context.compositor()?;
+        let geometry = compositor.CreateRoundedRectangleGeometry()?;
+        geometry.SetSize(size)?;
+        geometry.SetCornerRadius(Vector2 {
+            X: radius,
+            Y: radius,
+        })?;
+        let rect = compositor.CreateSpriteShapeWithGeometry(geometry)?;
+        rect.SetFillBrush(compositor.CreateColorBrushWithColor(color)?)?;
+        if let Some(stroke) = stroke {
+            rect.SetStrokeBrush(compositor.CreateColorBrushWithColor(stroke)?)?;
+            rect.SetStrokeThickness(self.context.scale_factor()?)?;
+        }
+        rect.SetOffset(offset)?;
+        Ok(rect.into())
+    }
+    fn redraw(&mut self) -> windows::Result<()> {
+        let size = self.visual.Size()?;
+        self.shape_visual.SetSize(size.clone())?;
+        self.shape_visual.Shapes()?.Clear()?;
+        if size.X <= 0. || size.Y <= 0. {
+            return Ok(());
+        }
+        let thumb_size = self.thumb_size(&size);
+        let track_height = if self.params.interactive {
+            size.Y / 5.
+        } else {
+            size.Y / 2.
+        };
+        let track_length = size.X - thumb_size;
+        let t = (self.params.value - self.params.min) / (self.params.max - self.params.min);
+        let track_offset = Vector2 {
+            X: thumb_size / 2.,
+            Y: (size.Y - track_height) / 2.,
+        };
+        let (track_color, fill_color) = if self.params.enabled {
+            (
+                self.params.track_color.clone(),
+                self.params.fill_color.clone(),
+            )
+        } else {
+            (
+                self.params.track_color.clone(),
+                self.context.theme()?.disabled_text,
+            )
+        };
+        self.shape_visual.Shapes()?.Append(self.create_rect(
+            track_offset.clone(),
+            Vector2 {
+                X: track_length,
+                Y: track_height,
+            },
+            track_height / 2.,
+            track_color,
+            None,
+        )?)?;
+        self.shape_visual.Shapes()?.Append(self.create_rect(
+            track_offset,
+            Vector2 {
+                X: track_length * t,
+                Y: track_height,
+            },
+            track_height / 2.,
+            fill_color,
+            None,
+        )?)?;
+        if self.params.interactive {
+            let stroke = self
+                .context
+                .theme()?
+                .control_style(StyleState::new(
+                    self.params.enabled,
+                    self.focused,
+                    false,
+                    false,
+                ))
+                .stroke;
+            self.shape_visual.Shapes()?.Append(self.create_rect(
+                Vector2 {
+                    X: track_length * t,
+                    Y: (size.Y - thumb_size) / 2.,
+                },
+                Vector2 {
+                    X: thumb_size,
+                    Y: thumb_size,
+                },
+                thumb_size / 2.,
+                self.params.thumb_color.clone(),
+                Some(stroke),
+            )?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Panel for SliderPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            None
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.dragging = false;
+        Ok(())
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        let size = MIN_SLIDER_HEIGHT * self.context.scale_factor()?;
+        Ok(Vector2 {
+            X: size * 4.,
+            Y: size,
+        })
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        self.redraw()
+    }
+
+    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
+        self.mouse_pos = Some(position.clone());
+        if self.dragging {
+            self.change_value(self.value_by_position(position)?)?;
+        }
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        // Release may happen outside of the panel, so any mouse input stops dragging
+        self.dragging = false;
+        if !self.is_interactive() || button != MouseButton::Left {
+            return Ok(false);
+        }
+        if state == ElementState::Pressed {
+            if let Some(position) = self.mouse_pos.clone() {
+                self.set_focus()?;
+                self.dragging = true;
+                self.change_value(self.value_by_position(&position)?)?;
+            }
+        }
+        Ok(true)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        if self.focused && self.is_interactive() && input.state == ElementState::Pressed {
+            if let Some(code) = input.virtual_keycode {
+                match code {
+                    VirtualKeyCode::Escape => {
+                        self.clear_focus()?;
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Tab => {
+                        if modifiers.shift() {
+                            self.set_focus_to_prev()?;
+                        } else {
+                            self.set_focus_to_next()?;
+                        }
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Left | VirtualKeyCode::Down => {
+                        self.change_value(self.params.value - self.params.step)?;
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Right | VirtualKeyCode::Up => {
+                        self.change_value(self.params.value + self.params.step)?;
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Home => {
+                        self.change_value(self.params.min)?;
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::End => {
+                        self.change_value(self.params.max)?;
+                        return Ok(true);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if self.focused && self.is_interactive() && input.state == ElementState::Pressed {
+            match input.button {
+                GamepadButton::Left => {
+                    self.change_value(self.params.value - self.params.step)?;
+                    return Ok(true);
+                }
+                GamepadButton::Right => {
+                    self.change_value(self.params.value + self.params.step)?;
+                    return Ok(true);
+                }
+                GamepadButton::B => {
+                    self.clear_focus()?;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Control for SliderPanel {
+    fn is_enabled(&self) -> windows::Result<bool> {
+        Ok(self.is_interactive())
+    }
+
+    fn is_focused(&self) -> windows::Result<bool> {
+        Ok(self.focused)
+    }
+
+    fn on_enable(&mut self, enable: bool) -> windows::Result<()> {
+        self.params.enabled = enable;
+        self.redraw()
+    }
+
+    fn on_set_focus(&mut self) -> windows::Result<()> {
+        self.focused = true;
+        self.redraw()
+    }
+
+    fn on_clear_focus(&mut self) -> windows::Result<()> {
+        self.focused = false;
+        self.redraw()
+    }
+
+    fn as_panel(&self) -> &dyn Panel {
+        self
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use rodio::{source::SineWave, OutputStream, OutputStreamHandle, Source};
+
+const VOLUME: f32 = 0.15;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SoundId {
+    Merge,
+    InvalidMove,
+    ButtonPress,
+    GameOver,
+}
+
+// Sounds are synthesized from sine tones, so no audio files are needed
+pub(crate) struct SoundPlayer {
+    // Playback stops when the stream is dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+}
+
+fn tone(freq: u32, millis: u64) -> impl Source<Item = f32> + Send + 'static {
+    SineWave::new(freq)
+        .take_duration(Duration::from_millis(millis))
+        .amplify(VOLUME)
+}
+
+fn delayed_tone(freq: u32, millis: u64, delay: u64) -> impl Source<Item = f32> + Send + 'static {
+    tone(freq, millis).delay(Duration::from_millis(delay))
+}
+
+impl SoundPlayer {
+    // None if there is no audio output device
+    pub fn new() -> Option<Self> {
+        let (stream, handle) = OutputStream::try_default().ok()?;
+        Some(Self {
+            _stream: stream,
+            handle,
+        })
+    }
+
+    // Playback errors are ignored, sound is not essential for the application
+    pub fn play(&self, sound_id: SoundId) {
+        let _ = match sound_id {
+            SoundId::Merge => self
+                .handle
+                .play_raw(tone(660, 50).mix(delayed_tone(990, 50, 50))),
+            SoundId::InvalidMove => self.handle.play_raw(tone(110, 80)),
+            SoundId::ButtonPress => self.handle.play_raw(tone(1760, 15)),
+            SoundId::GameOver => self.handle.play_raw(
+                tone(523, 150)
+                    .mix(delayed_tone(392, 150, 150))
+                    .mix(delayed_tone(262, 300, 300)),
+            ),
+        };
+    }
+}
@@ -0,0 +1,45 @@
+use bindings::Windows::{Foundation::Numerics::Vector2, UI::Color};
+
+// Visual state of a control, derived from its input state so that all controls
+// agree on which state wins, e.g. disabled control doesn't look focused
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StyleState {
+    Normal,
+    Hovered,
+    Pressed,
+    Focused,
+    Disabled,
+}
+
+impl StyleState {
+    pub fn new(enabled: bool, focused: bool, hovered: bool, pressed: bool) -> Self {
+        if !enabled {
+            StyleState::Disabled
+        } else if pressed && hovered {
+            StyleState::Pressed
+        } else if focused {
+            StyleState::Focused
+        } else if hovered {
+            StyleState::Hovered
+        } else {
+            StyleState::Normal
+        }
+    }
+}
+
+// Colors of control background for given state, stroke width is in logical pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlStyle {
+    pub fill: Color,
+    pub stroke: Color,
+    pub stroke_width: f32,
+}
+
+// Shadow cast by raised elements like buttons and dialogs, sizes are in logical pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct DropShadowStyle {
+    pub color: Color,
+    pub opacity: f32,
+    pub blur_radius: f32,
+    pub offset: Vector2,
+}
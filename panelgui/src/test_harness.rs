@@ -0,0 +1,194 @@
+use std::{any::Any, collections::VecDeque};
+
+use bindings::Windows::Foundation::Numerics::Vector2;
+use futures::executor::LocalPool;
+use winit::event::{
+    ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::{
+        deliver_panel_event, init_window_with, process_accelerator, root_ribbon_with,
+        take_async_error, take_headless_events, take_local_pool, winrt_error,
+    },
+    panel::{Panel, PanelEvent},
+    ribbon_panel::RibbonCellParamsBuilder,
+    window_host::WindowParamsBuilder,
+};
+
+// Drives panels without the event loop, so they can be tested with cargo test. No window
+// is created and drawing uses the software device, input is injected by calling methods
+// below, and panel events are queued until dispatch() delivers them to the tree.
+// Tested panels are created in the harness context and then placed by set_panel().
+// Context is per thread, so each test should create its own harness
+pub struct TestHarness {
+    context: FrameworkContext,
+    local_pool: LocalPool,
+    modifiers: ModifiersState,
+    // Events posted by panels and not yet delivered
+    events: VecDeque<PanelEvent>,
+}
+
+impl TestHarness {
+    pub fn new(size: Vector2) -> windows::Result<Self> {
+        init_window_with(
+            WindowParamsBuilder::default()
+                .size(size)
+                .headless(true)
+                .create()?,
+        )?;
+        let context = current_context()?;
+        let local_pool =
+            take_local_pool().ok_or_else(winrt_error("Local pool is already taken"))?;
+        Ok(Self {
+            context,
+            local_pool,
+            modifiers: ModifiersState::empty(),
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn context(&self) -> &FrameworkContext {
+        &self.context
+    }
+
+    // Puts the tested panel into the window and initializes the tree. Called once,
+    // before any input is injected
+    pub fn set_panel(&mut self, panel: impl Panel + 'static) -> windows::Result<()> {
+        let cell = RibbonCellParamsBuilder::default()
+            .panel(panel)
+            .create_in(&self.context)?;
+        root_ribbon_with(|root_panel| {
+            root_panel.push_cell(cell)?;
+            root_panel.on_init()
+        })?;
+        self.idle()
+    }
+
+    // Gives access to panels, e.g. to find them by handles and check their state
+    pub fn with_panel<F, T>(&mut self, f: F) -> windows::Result<T>
+    where
+        F: FnOnce(&mut dyn Panel) -> windows::Result<T>,
+    {
+        root_ribbon_with(|root_panel| f(root_panel))
+    }
+
+    pub fn resize(&mut self, size: Vector2) -> windows::Result<()> {
+        root_ribbon_with(|root_panel| {
+            root_panel.visual().Parent()?.SetSize(&size)?;
+            root_panel.on_resize(&size)
+        })?;
+        self.idle()
+    }
+
+    // Runs pending layout and rendering like the event loop does after processing events
+    pub fn idle(&mut self) -> windows::Result<()> {
+        root_ribbon_with(|root_panel| root_panel.on_idle())?;
+        self.run_tasks()
+    }
+
+    pub fn mouse_move(&mut self, position: Vector2) -> windows::Result<()> {
+        root_ribbon_with(|root_panel| root_panel.on_mouse_move(&position))
+    }
+
+    pub fn mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        root_ribbon_with(|root_panel| root_panel.on_mouse_input(button, state))
+    }
+
+    pub fn mouse_wheel(&mut self, delta: MouseScrollDelta) -> windows::Result<bool> {
+        root_ribbon_with(|root_panel| root_panel.on_mouse_wheel(delta))
+    }
+
+    // Moves mouse to the position and presses and releases left button there
+    pub fn click(&mut self, position: Vector2) -> windows::Result<()> {
+        self.mouse_move(position)?;
+        self.mouse_input(MouseButton::Left, ElementState::Pressed)?;
+        self.mouse_input(MouseButton::Left, ElementState::Released)?;
+        Ok(())
+    }
+
+    // Modifiers apply to following key presses
+    pub fn set_modifiers(&mut self, modifiers: ModifiersState) -> windows::Result<()> {
+        self.modifiers = modifiers;
+        root_ribbon_with(|root_panel| root_panel.on_modifiers_changed(modifiers))
+    }
+
+    // Registered accelerators are checked first, as in the event loop
+    pub fn key_input(&mut self, key: VirtualKeyCode, state: ElementState) -> windows::Result<bool> {
+        #[allow(deprecated)]
+        let input = KeyboardInput {
+            scancode: 0,
+            state,
+            virtual_keycode: Some(key),
+            modifiers: self.modifiers,
+        };
+        if process_accelerator(input, self.modifiers)? {
+            return Ok(true);
+        }
+        let modifiers = self.modifiers;
+        root_ribbon_with(|root_panel| root_panel.on_keyboard_input(input, modifiers))
+    }
+
+    pub fn key(&mut self, key: VirtualKeyCode) -> windows::Result<()> {
+        self.key_input(key, ElementState::Pressed)?;
+        self.key_input(key, ElementState::Released)?;
+        Ok(())
+    }
+
+    pub fn char(&mut self, ch: char) -> windows::Result<bool> {
+        root_ribbon_with(|root_panel| root_panel.on_char(ch))
+    }
+
+    pub fn gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        root_ribbon_with(|root_panel| root_panel.on_gamepad_input(input))
+    }
+
+    // Undelivered events posted by panels, in order. They are removed from the queue
+    pub fn take_events(&mut self) -> Vec<PanelEvent> {
+        self.collect_events();
+        self.events.drain(..).collect()
+    }
+
+    // Removes the first undelivered event of given type sent by the panel
+    pub fn take_event<T: Any>(&mut self, panel_id: usize) -> Option<T> {
+        self.collect_events();
+        let (index, data) = self
+            .events
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, e)| e.extract::<T>(panel_id).map(|data| (index, data)))?;
+        self.events.remove(index);
+        Some(data)
+    }
+
+    // Delivers events queued so far to the tree, then runs idle. Events posted by
+    // handlers stay queued, so they can be checked or delivered by the next call
+    pub fn dispatch(&mut self) -> windows::Result<()> {
+        self.collect_events();
+        for mut panel_event in std::mem::take(&mut self.events) {
+            if !deliver_panel_event(&mut panel_event)? {
+                root_ribbon_with(|root_panel| root_panel.on_panel_event(&mut panel_event))?;
+            }
+            self.run_tasks()?;
+        }
+        self.idle()
+    }
+
+    fn collect_events(&mut self) {
+        self.events.extend(take_headless_events());
+    }
+
+    fn run_tasks(&mut self) -> windows::Result<()> {
+        self.local_pool.run_until_stalled();
+        match take_async_error() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
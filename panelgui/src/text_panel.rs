@@ -3,7 +3,8 @@ use std::borrow::Cow;
 use bindings::{
     Microsoft::Graphics::Canvas::{
         Text::CanvasHorizontalAlignment, Text::CanvasTextFormat, Text::CanvasTextLayout,
-        Text::CanvasVerticalAlignment, UI::Composition::CanvasComposition,
+        Text::CanvasTrimmingGranularity, Text::CanvasTrimmingSign, Text::CanvasVerticalAlignment,
+        Text::CanvasWordWrapping, UI::Composition::CanvasComposition,
     },
     Windows::{
         Foundation::Numerics::Vector2,
@@ -14,13 +15,17 @@ use bindings::{
         UI::{
             Color, Colors,
             Composition::{ContainerVisual, SpriteVisual},
+            Text::FontWeight,
         },
     },
 };
 
 use crate::{
+    context::{current_context, FrameworkContext},
     control::{Control, ControlHandle},
-    globals::{canvas_device, composition_graphics_device, compositor, get_next_id, winrt_error},
+    gamepad::GamepadInput,
+    globals::{pixel_size, winrt_error},
+    icon::{draw_icon, IconId},
     panel::{Handle, Panel, PanelEvent, PanelHandle},
 };
 
@@ -50,64 +55,216 @@ pub struct TextParams {
     text: Cow<'static, str>,
     #[builder(default = "{true}")]
     enabled: bool,
-    #[builder(default = "{Colors::Black().unwrap()}")]
-    color: Color,
+    // Theme text color is used when not set
+    #[builder(default = "{None}")]
+    color: Option<Color>,
     #[builder(default = "{2.}")]
     font_scale: f32,
+    // Shorter text, e.g. an icon, shown when the text would be too small to read
+    #[builder(default = "{None}", setter(strip_option))]
+    compact_text: Option<Cow<'static, str>>,
+    // Drawn instead of the text when it would be too small to read, or when text is empty
+    #[builder(default = "{None}", setter(strip_option))]
+    icon: Option<IconId>,
+    #[builder(default = "{CanvasHorizontalAlignment::Center}")]
+    horizontal_alignment: CanvasHorizontalAlignment,
+    #[builder(default = "{CanvasVerticalAlignment::Center}")]
+    vertical_alignment: CanvasVerticalAlignment,
+    // Long lines are broken at word boundaries, otherwise text lines are kept as is
+    #[builder(default = "{false}")]
+    wrap: bool,
+    // Font size in logical pixels. When not set font size is derived from panel height
+    // and font_scale, which fits single line text only
+    #[builder(default = "{None}", setter(strip_option))]
+    font_size: Option<f32>,
+    // Text not fitting the panel is cut with ellipsis
+    #[builder(default = "{false}")]
+    ellipsis: bool,
+    // Text contains inline formatting: **bold** and [#RRGGBB]colored[/] spans
+    #[builder(default = "{false}")]
+    markup: bool,
+}
+
+// Styled run of text, in UTF-16 code units as expected by CanvasTextLayout
+#[derive(Clone, Debug, PartialEq)]
+struct TextSpan {
+    start: i32,
+    len: i32,
+    bold: bool,
+    color: Option<Color>,
+}
+
+fn parse_color(hex: &str) -> Option<Color> {
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color {
+        A: 255,
+        R: (value >> 16) as u8,
+        G: (value >> 8) as u8,
+        B: value as u8,
+    })
+}
+
+// Splits markup into plain text and styled spans. Unrecognized tags are kept as text
+fn parse_markup(markup: &str) -> (String, Vec<TextSpan>) {
+    let mut text = String::new();
+    let mut spans: Vec<TextSpan> = Vec::new();
+    let mut bold = false;
+    let mut color = None;
+    let mut pos = 0;
+    let mut rest = markup;
+    while let Some(ch) = rest.chars().next() {
+        let (bold_next, color_next, skip) = if rest.starts_with("**") {
+            (!bold, color, 2)
+        } else if rest.starts_with("[/]") {
+            (bold, None, 3)
+        } else if let Some(c) = rest
+            .strip_prefix("[#")
+            .and_then(|r| r.get(..7))
+            .filter(|r| r.ends_with(']'))
+            .and_then(|r| parse_color(&r[..6]))
+        {
+            (bold, Some(c), 9)
+        } else {
+            text.push(ch);
+            let len = ch.len_utf16() as i32;
+            if bold || color.is_some() {
+                match spans.last_mut() {
+                    Some(span)
+                        if span.start + span.len == pos
+                            && span.bold == bold
+                            && span.color == color =>
+                    {
+                        span.len += len
+                    }
+                    _ => spans.push(TextSpan {
+                        start: pos,
+                        len,
+                        bold,
+                        color: color.clone(),
+                    }),
+                }
+            }
+            pos += len;
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        };
+        bold = bold_next;
+        color = color_next;
+        rest = &rest[skip..];
+    }
+    (text, spans)
+}
+
+// Text smaller than this is hard to read, in logical pixels
+const MIN_FONT_SIZE: f32 = 12.;
+// Average glyph width relative to font size, to estimate text width without layout
+const CHAR_WIDTH_RATIO: f32 = 0.6;
+
+pub(crate) fn text_min_size(text: &str, font_scale: f32, scale_factor: f32) -> Vector2 {
+    let font_size = MIN_FONT_SIZE * scale_factor;
+    Vector2 {
+        X: text.chars().count() as f32 * font_size * CHAR_WIDTH_RATIO,
+        Y: font_size * font_scale,
+    }
 }
 
 impl TextParamsBuilder {
     pub fn create(self) -> windows::Result<TextPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<TextPanel> {
         match self.build() {
-            Ok(settings) => Ok(TextPanel::new(settings)?),
+            Ok(settings) => Ok(TextPanel::new(context, settings)?),
             Err(e) => Err(winrt_error(e)()),
         }
     }
 }
 
 pub struct TextPanel {
+    context: FrameworkContext,
     id: usize,
     params: TextParams,
     surface: Option<CompositionDrawingSurface>,
     visual: SpriteVisual,
+    // Changes are rendered in on_idle, so a burst of updates is drawn once per frame
+    resize_pending: bool,
+    redraw_pending: bool,
 }
 
 impl TextPanel {
-    pub fn new(params: TextParams) -> windows::Result<Self> {
-        let id = get_next_id();
-        let visual = compositor().CreateSpriteVisual()?;
+    pub fn new(context: &FrameworkContext, params: TextParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let visual = context.compositor()?.CreateSpriteVisual()?;
         Ok(Self {
+            context: context.clone(),
             id,
             params,
             surface: None,
             visual,
+            resize_pending: false,
+            redraw_pending: false,
         })
     }
     pub fn handle(&self) -> TextPanelHandle {
         TextPanelHandle { id: self.id }
     }
     pub fn set_text<S: Into<Cow<'static, str>>>(&mut self, text: S) -> windows::Result<()> {
-        self.params.text = text.into();
-        self.redraw_text()
+        let text = text.into();
+        if text != self.params.text {
+            self.params.text = text;
+            self.invalidate(false)?;
+        }
+        Ok(())
+    }
+    pub fn set_icon(&mut self, icon: Option<IconId>) -> windows::Result<()> {
+        if icon != self.params.icon {
+            self.params.icon = icon;
+            self.invalidate(false)?;
+        }
+        Ok(())
     }
     pub fn set_text_color(&mut self, color: Color) -> windows::Result<()> {
-        self.params.color = color;
-        self.redraw_text()
+        if self.params.color.as_ref() != Some(&color) {
+            self.params.color = Some(color);
+            self.invalidate(false)?;
+        }
+        Ok(())
+    }
+
+    fn invalidate(&mut self, resize: bool) -> windows::Result<()> {
+        self.resize_pending |= resize;
+        self.redraw_pending = true;
+        self.context.request_idle()
+    }
+
+    // Drawing surface is recreated on next resize or resume
+    fn release_surface(&mut self) -> windows::Result<()> {
+        self.surface = None;
+        self.resize_pending = false;
+        self.redraw_pending = false;
+        self.visual
+            .SetBrush(self.context.compositor()?.CreateColorBrush()?)
     }
 
     fn resize_surface(&mut self) -> windows::Result<()> {
         let size = self.visual.Size()?;
         if size.X > 0. && size.Y > 0. {
-            let surface = composition_graphics_device().CreateDrawingSurface(
-                Size {
-                    Width: size.X,
-                    Height: size.Y,
-                },
-                DirectXPixelFormat::B8G8R8A8UIntNormalized,
-                DirectXAlphaMode::Premultiplied,
-            )?;
+            let surface = self
+                .context
+                .composition_graphics_device()?
+                .CreateDrawingSurface(
+                    Size {
+                        Width: size.X,
+                        Height: size.Y,
+                    },
+                    DirectXPixelFormat::B8G8R8A8UIntNormalized,
+                    DirectXAlphaMode::Premultiplied,
+                )?;
 
-            let brush = compositor().CreateSurfaceBrush()?;
+            let brush = self.context.compositor()?.CreateSurfaceBrush()?;
             brush.SetSurface(surface.clone())?;
             self.surface = Some(surface);
             self.visual.SetBrush(brush)?;
@@ -115,31 +272,112 @@ impl TextPanel {
         Ok(())
     }
 
+    // Text without markup tags
+    fn plain_text(&self, text: &Cow<'static, str>) -> Cow<'static, str> {
+        if self.params.markup {
+            Cow::Owned(parse_markup(text).0)
+        } else {
+            text.clone()
+        }
+    }
+
+    fn base_font_size(&self, height: f32) -> windows::Result<f32> {
+        Ok(match self.params.font_size {
+            Some(font_size) => font_size * self.context.scale_factor()?,
+            None => height / self.params.font_scale,
+        })
+    }
+
+    // With compact text or icon set the text is shrunk to fit the width, and replaced by
+    // them when it becomes unreadable. None means that the icon should be drawn
+    fn fit_text(
+        &self,
+        width: f32,
+        height: f32,
+    ) -> windows::Result<Option<(Cow<'static, str>, f32)>> {
+        let font_size = self.base_font_size(height)?;
+        if self.params.icon.is_some() && self.params.text.is_empty() {
+            return Ok(None);
+        }
+        if self.params.compact_text.is_none() && self.params.icon.is_none() {
+            return Ok(Some((self.params.text.clone(), font_size)));
+        }
+        let chars = self.params.text.chars().count().max(1) as f32;
+        let fit_size = font_size.min(width / (chars * CHAR_WIDTH_RATIO));
+        if fit_size < MIN_FONT_SIZE * self.context.scale_factor()? {
+            Ok(match (&self.params.icon, &self.params.compact_text) {
+                (Some(_), _) => None,
+                (None, compact_text) => compact_text.clone().map(|text| (text, font_size)),
+            })
+        } else {
+            Ok(Some((self.params.text.clone(), fit_size)))
+        }
+    }
+
     fn redraw_text(&self) -> windows::Result<()> {
+        let span = tracing::trace_span!("redraw_text", panel_id = self.id);
+        let _enter = span.enter();
         if let Some(ref surface) = self.surface {
             let ds = CanvasComposition::CreateDrawingSession(surface)?;
             ds.Clear(Colors::Transparent()?)?;
 
             let size = surface.Size()?;
+            let theme = self.context.theme()?;
+            let color = if self.params.enabled {
+                self.params.color.clone().unwrap_or(theme.text)
+            } else {
+                theme.disabled_text
+            };
+            let (text, font_size) = match self.fit_text(size.Width, size.Height)? {
+                Some(fitted) => fitted,
+                None => {
+                    if let Some(icon) = self.params.icon {
+                        let size = Vector2 {
+                            X: size.Width,
+                            Y: size.Height,
+                        };
+                        draw_icon(&ds, icon, size, color)?;
+                    }
+                    return Ok(());
+                }
+            };
             let text_format = CanvasTextFormat::new()?;
-            text_format.SetFontFamily("Arial")?;
-            text_format.SetFontSize(size.Height / self.params.font_scale)?;
-            let text: String = self.params.text.clone().into();
+            text_format.SetFontFamily(theme.font_family.as_ref())?;
+            text_format.SetFontSize(font_size)?;
+            text_format.SetWordWrapping(if self.params.wrap {
+                CanvasWordWrapping::Wrap
+            } else {
+                CanvasWordWrapping::NoWrap
+            })?;
+            if self.params.ellipsis {
+                text_format.SetTrimmingGranularity(CanvasTrimmingGranularity::Character)?;
+                text_format.SetTrimmingSign(CanvasTrimmingSign::Ellipsis)?;
+            }
+            let (text, spans) = if self.params.markup {
+                parse_markup(&text)
+            } else {
+                (text.into(), Vec::new())
+            };
             let text_layout = CanvasTextLayout::Create(
-                canvas_device(),
+                self.context.canvas_device()?,
                 text,
                 text_format,
                 size.Width,
                 size.Height,
             )?;
-            text_layout.SetVerticalAlignment(CanvasVerticalAlignment::Center)?;
-            text_layout.SetHorizontalAlignment(CanvasHorizontalAlignment::Center)?;
-            let color = if self.params.enabled {
-                self.params.color.clone()
-            } else {
-                Colors::Gray()?
-            };
-
+            text_layout.SetVerticalAlignment(self.params.vertical_alignment)?;
+            text_layout.SetHorizontalAlignment(self.params.horizontal_alignment)?;
+            for span in spans {
+                if span.bold {
+                    text_layout.SetFontWeight(span.start, span.len, FontWeight { Weight: 700 })?;
+                }
+                match span.color {
+                    Some(color) if self.params.enabled => {
+                        text_layout.SetColor(span.start, span.len, color)?
+                    }
+                    _ => {}
+                }
+            }
             ds.DrawTextLayoutAtCoordsWithColor(text_layout, 0., 0., color)
         } else {
             Ok(())
@@ -155,16 +393,108 @@ impl Panel for TextPanel {
         self.visual.clone().into()
     }
 
+    fn measure(&self) -> windows::Result<Vector2> {
+        if self.params.icon.is_some() {
+            let height = text_min_size("", self.params.font_scale, self.context.scale_factor()?).Y;
+            return Ok(Vector2 {
+                X: height,
+                Y: height,
+            });
+        }
+        let text = self.plain_text(
+            self.params
+                .compact_text
+                .as_ref()
+                .unwrap_or(&self.params.text),
+        );
+        let text = text.as_ref();
+        if self.params.font_size.is_none() && !text.contains('\n') {
+            return Ok(text_min_size(
+                text,
+                self.params.font_scale,
+                self.context.scale_factor()?,
+            ));
+        }
+        // Multi-line text needs space for every line, wrapped text for its longest word
+        let font_size = self
+            .params
+            .font_size
+            .unwrap_or(MIN_FONT_SIZE)
+            .max(MIN_FONT_SIZE)
+            * self.context.scale_factor()?;
+        let chars = if self.params.wrap {
+            text.split_whitespace().map(|w| w.chars().count()).max()
+        } else {
+            text.lines().map(|l| l.chars().count()).max()
+        };
+        let lines = text.lines().count().max(1);
+        Ok(Vector2 {
+            X: chars.unwrap_or(0) as f32 * font_size * CHAR_WIDTH_RATIO,
+            Y: lines as f32 * font_size * self.params.font_scale,
+        })
+    }
+
+    // Full text at the font filling the available height
+    fn desired_size(&self, available: &Vector2) -> windows::Result<Vector2> {
+        let min = self.measure()?;
+        if self.params.icon.is_some() && self.params.text.is_empty() {
+            return Ok(Vector2 {
+                X: available.Y.max(min.X),
+                Y: available.Y.max(min.Y),
+            });
+        }
+        let text = self.plain_text(&self.params.text);
+        let lines = text.lines().count().max(1);
+        let chars = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let font_size = self.base_font_size(available.Y / lines as f32)?;
+        Ok(Vector2 {
+            X: (chars as f32 * font_size * CHAR_WIDTH_RATIO).max(min.X),
+            Y: (lines as f32 * font_size * self.params.font_scale).max(min.Y),
+        })
+    }
+
     fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
-        self.visual.SetSize(size)?;
-        self.resize_surface()?;
-        self.redraw_text()?;
+        self.visual.SetSize(pixel_size(size))?;
+        self.invalidate(true)?;
         Ok(())
     }
 
     fn on_idle(&mut self) -> windows::Result<()> {
+        if self.resize_pending && self.surface.is_some() && self.context.is_resizing()? {
+            // Old surface is stretched until resizing stops, the event loop wakes up then
+            return Ok(());
+        }
+        if std::mem::take(&mut self.resize_pending) {
+            self.resize_surface()?;
+        }
+        if std::mem::take(&mut self.redraw_pending) {
+            self.redraw_text()?;
+        }
         Ok(())
     }
+
+    fn on_timer(&mut self, _timer_id: usize) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_focus_changed(&mut self, _focused: bool) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.release_surface()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.invalidate(true)?;
+        Ok(())
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.invalidate(true)?;
+        Ok(())
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -181,6 +511,10 @@ impl Panel for TextPanel {
         self.on_resize(&self.visual().Parent()?.Size()?)
     }
 
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.release_surface()
+    }
+
     fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
         Ok(())
     }
@@ -193,7 +527,19 @@ impl Panel for TextPanel {
         Ok(false)
     }
 
-    fn on_keyboard_input(&mut self, _input: winit::event::KeyboardInput) -> windows::Result<bool> {
+    fn on_keyboard_input(
+        &mut self,
+        _input: winit::event::KeyboardInput,
+        _modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, _input: GamepadInput) -> windows::Result<bool> {
         Ok(false)
     }
 
@@ -205,7 +551,8 @@ impl Panel for TextPanel {
 impl Control for TextPanel {
     fn on_enable(&mut self, enable: bool) -> windows::Result<()> {
         self.params.enabled = enable;
-        self.redraw_text()
+        self.invalidate(false)?;
+        Ok(())
     }
 
     fn on_set_focus(&mut self) -> windows::Result<()> {
@@ -0,0 +1,139 @@
+use std::borrow::Cow;
+
+use bindings::Windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color, Colors,
+        ViewManagement::{UIColorType, UISettings},
+    },
+};
+
+use crate::style::{ControlStyle, DropShadowStyle, StyleState};
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub background: Color,
+    pub dialog_background: Color,
+    pub button_fill: Color,
+    pub button_hover_fill: Color,
+    pub button_pressed_fill: Color,
+    pub button_stroke: Color,
+    pub button_disabled_fill: Color,
+    pub button_disabled_stroke: Color,
+    pub focus_ring: Color,
+    // Border of the button activated by Enter in dialogs
+    pub default_ring: Color,
+    // Dims content below modal dialogs
+    pub modal_backdrop: Color,
+    pub text: Color,
+    pub disabled_text: Color,
+    pub font_family: Cow<'static, str>,
+    // Corner radius of buttons, dialogs and game tiles relative to their smaller side
+    pub corner_radius: f32,
+    // Shadow under buttons, dialogs and game tiles, None for flat look
+    pub shadow: Option<DropShadowStyle>,
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color {
+        A: 255,
+        R: r,
+        G: g,
+        B: b,
+    }
+}
+
+impl Theme {
+    pub fn light() -> windows::Result<Self> {
+        Ok(Self {
+            background: Colors::White()?,
+            dialog_background: Colors::Wheat()?,
+            button_fill: Colors::White()?,
+            button_hover_fill: rgb(0xe8, 0xe8, 0xe8),
+            button_pressed_fill: rgb(0xd0, 0xd0, 0xd0),
+            button_stroke: rgb(0xc8, 0xc8, 0xc8),
+            button_disabled_fill: rgb(0xf0, 0xf0, 0xf0),
+            button_disabled_stroke: rgb(0xe0, 0xe0, 0xe0),
+            focus_ring: Colors::Black()?,
+            default_ring: rgb(0x3a, 0x7b, 0xd5),
+            modal_backdrop: Color {
+                A: 0x60,
+                R: 0,
+                G: 0,
+                B: 0,
+            },
+            text: Colors::Black()?,
+            disabled_text: Colors::Gray()?,
+            font_family: "Arial".into(),
+            corner_radius: 0.05,
+            shadow: Some(DropShadowStyle {
+                color: Colors::Black()?,
+                opacity: 0.3,
+                blur_radius: 6.,
+                offset: Vector2 { X: 0., Y: 2. },
+            }),
+        })
+    }
+    pub fn dark() -> windows::Result<Self> {
+        Ok(Self {
+            background: rgb(0x20, 0x20, 0x20),
+            dialog_background: rgb(0x3c, 0x3c, 0x46),
+            button_fill: rgb(0x50, 0x50, 0x5a),
+            button_hover_fill: rgb(0x60, 0x60, 0x6a),
+            button_pressed_fill: rgb(0x40, 0x40, 0x48),
+            button_stroke: rgb(0x50, 0x50, 0x5a),
+            button_disabled_fill: rgb(0x34, 0x34, 0x3a),
+            button_disabled_stroke: rgb(0x40, 0x40, 0x48),
+            focus_ring: Colors::White()?,
+            default_ring: rgb(0x60, 0x9c, 0xe8),
+            modal_backdrop: Color {
+                A: 0x90,
+                R: 0,
+                G: 0,
+                B: 0,
+            },
+            text: Colors::WhiteSmoke()?,
+            disabled_text: Colors::DimGray()?,
+            font_family: "Arial".into(),
+            corner_radius: 0.05,
+            shadow: Some(DropShadowStyle {
+                color: Colors::Black()?,
+                opacity: 0.6,
+                blur_radius: 8.,
+                offset: Vector2 { X: 0., Y: 3. },
+            }),
+        })
+    }
+    pub fn control_style(&self, state: StyleState) -> ControlStyle {
+        let (fill, stroke, stroke_width) = match state {
+            StyleState::Normal => (self.button_fill.clone(), self.button_stroke.clone(), 1.),
+            StyleState::Hovered => (
+                self.button_hover_fill.clone(),
+                self.button_stroke.clone(),
+                1.,
+            ),
+            StyleState::Pressed => (
+                self.button_pressed_fill.clone(),
+                self.focus_ring.clone(),
+                1.,
+            ),
+            StyleState::Focused => (self.button_fill.clone(), self.focus_ring.clone(), 2.),
+            StyleState::Disabled => (
+                self.button_disabled_fill.clone(),
+                self.button_disabled_stroke.clone(),
+                1.,
+            ),
+        };
+        ControlStyle {
+            fill,
+            stroke,
+            stroke_width,
+        }
+    }
+}
+
+// Windows doesn't expose dark mode flag directly, but dark mode has dark background color
+pub fn is_system_dark_mode() -> windows::Result<bool> {
+    let background = UISettings::new()?.GetColorValue(UIColorType::Background)?;
+    Ok((background.R as u32 + background.G as u32 + background.B as u32) < 3 * 128)
+}
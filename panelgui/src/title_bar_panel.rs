@@ -0,0 +1,276 @@
+use std::{any::Any, borrow::Cow};
+
+use bindings::{
+    Microsoft::Graphics::Canvas::Text::CanvasHorizontalAlignment,
+    Windows::{Foundation::Numerics::Vector2, UI::Composition::ContainerVisual},
+};
+
+use crate::{
+    background_panel::BackgroundParamsBuilder,
+    button_panel::{ButtonPanelHandle, ButtonParamsBuilder},
+    capture::visual_offset,
+    context::{current_context, FrameworkContext},
+    delegate_panel,
+    event_handlers::EventHandlers,
+    globals::{
+        is_window_maximized, minimize_window, quit, root_visual, toggle_maximize_window,
+        winrt_error,
+    },
+    icon::IconId,
+    panel::{Handle, Panel, PanelEvent, PanelHandle},
+    ribbon_panel::{
+        CellLimit, RibbonCellParamsBuilder, RibbonOrientation, RibbonPanel, RibbonPanelHandle,
+        RibbonParamsBuilder,
+    },
+    text_panel::{TextPanelHandle, TextParamsBuilder},
+    window_chrome::{set_hit_test_regions, HitTestKind, HitTestRegion},
+};
+
+// Space before the title, in logical pixels
+const TITLE_MARGIN: f32 = 8.;
+
+#[derive(Copy, Clone)]
+pub struct TitleBarPanelHandle {
+    id: usize,
+}
+
+impl Handle for TitleBarPanelHandle {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl PanelHandle<TitleBarPanel> for TitleBarPanelHandle {}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct TitleBarParams {
+    #[builder(default = "{\"\".into()}")]
+    title: Cow<'static, str>,
+    // Width of caption buttons relative to the title bar height
+    #[builder(default = "{1.5}")]
+    button_ratio: f32,
+    #[builder(default = "{true}")]
+    minimize_button: bool,
+    // Hide it for windows which are not resizable
+    #[builder(default = "{true}")]
+    maximize_button: bool,
+}
+
+impl TitleBarParamsBuilder {
+    pub fn create(self) -> windows::Result<TitleBarPanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<TitleBarPanel> {
+        match self.build() {
+            Ok(params) => Ok(TitleBarPanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+}
+
+// Title and caption buttons for the window created without decorations. The panel
+// reports its area as the caption, so the window is moved by dragging it and
+// maximized by double click, while the buttons get mouse input as usual
+pub struct TitleBarPanel {
+    id: usize,
+    params: TitleBarParams,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    row_handle: RibbonPanelHandle,
+    title_handle: TextPanelHandle,
+    button_handles: Vec<ButtonPanelHandle>,
+    maximize_icon_handle: Option<TextPanelHandle>,
+    event_handlers: EventHandlers<TitleBarPanel>,
+    // Last reported to the window, regions are updated only when layout changes them
+    regions: Vec<HitTestRegion>,
+}
+
+impl TitleBarPanel {
+    pub fn new(context: &FrameworkContext, params: TitleBarParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let title = TextParamsBuilder::default()
+            .text(params.title.clone())
+            .horizontal_alignment(CanvasHorizontalAlignment::Left)
+            .create_in(context)?;
+        let title_handle = title.handle();
+        let mut row = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel(title)
+                    .margin(TITLE_MARGIN)
+                    .create_in(context)?,
+            );
+        let mut button_handles = Vec::new();
+        let mut maximize_icon_handle = None;
+        let mut event_handlers = EventHandlers::new();
+        if params.minimize_button {
+            let button = ButtonParamsBuilder::default()
+                .accessible_name(Some(IconId::Minimize.name().into()))
+                .panel(
+                    TextParamsBuilder::default()
+                        .icon(IconId::Minimize)
+                        .create_in(context)?,
+                )
+                .create_in(context)?;
+            event_handlers = event_handlers.on(&button.handle(), |_: &mut Self, _| {
+                minimize_window();
+                Ok(())
+            });
+            button_handles.push(button.handle());
+            row = row.add_panel_in(context, button)?;
+        }
+        if params.maximize_button {
+            // Icon is switched between Maximize and Restore with the window state
+            let icon = TextParamsBuilder::default()
+                .icon(IconId::Maximize)
+                .create_in(context)?;
+            maximize_icon_handle = Some(icon.handle());
+            let button = ButtonParamsBuilder::default()
+                .accessible_name(Some(IconId::Maximize.name().into()))
+                .panel(icon)
+                .create_in(context)?;
+            event_handlers = event_handlers.on(&button.handle(), |_: &mut Self, _| {
+                toggle_maximize_window();
+                Ok(())
+            });
+            button_handles.push(button.handle());
+            row = row.add_panel_in(context, button)?;
+        }
+        let button = ButtonParamsBuilder::default()
+            .accessible_name(Some(IconId::Close.name().into()))
+            .panel(
+                TextParamsBuilder::default()
+                    .icon(IconId::Close)
+                    .create_in(context)?,
+            )
+            .create_in(context)?;
+        event_handlers = event_handlers.on(&button.handle(), |_: &mut Self, _| {
+            quit();
+            Ok(())
+        });
+        button_handles.push(button.handle());
+        let row = row.add_panel_in(context, button)?.create_in(context)?;
+        let row_handle = row.handle();
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel_in(
+                context,
+                BackgroundParamsBuilder::default()
+                    .transparent_to_backdrop(true)
+                    .create_in(context)?,
+            )?
+            .add_panel_in(context, row)?
+            .create_in(context)?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id,
+            params,
+            visual,
+            root_panel,
+            row_handle,
+            title_handle,
+            button_handles,
+            maximize_icon_handle,
+            event_handlers,
+            regions: Vec::new(),
+        })
+    }
+
+    pub fn handle(&self) -> TitleBarPanelHandle {
+        TitleBarPanelHandle { id: self.id }
+    }
+
+    pub fn set_title(&mut self, title: impl Into<Cow<'static, str>>) -> windows::Result<()> {
+        let title = title.into();
+        self.params.title = title.clone();
+        self.title_handle.at(&mut self.root_panel)?.set_text(title)
+    }
+
+    // Caption is the whole panel except the buttons
+    fn update_regions(&mut self) -> windows::Result<()> {
+        let origin = root_visual();
+        let mut regions = Vec::new();
+        if let Some(offset) = visual_offset(&self.visual, &origin)? {
+            regions.push(HitTestRegion {
+                kind: HitTestKind::Caption,
+                offset,
+                size: self.visual.Size()?,
+            });
+            for handle in &self.button_handles {
+                let visual = handle.at(&mut self.root_panel)?.visual();
+                if let Some(offset) = visual_offset(&visual, &origin)? {
+                    regions.push(HitTestRegion {
+                        kind: HitTestKind::Client,
+                        offset,
+                        size: visual.Size()?,
+                    });
+                }
+            }
+        }
+        if regions != self.regions {
+            set_hit_test_regions(self.id, regions.clone());
+            self.regions = regions;
+        }
+        Ok(())
+    }
+}
+
+impl Panel for TitleBarPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        set_hit_test_regions(self.id, Vec::new());
+        self.regions.clear();
+        self.root_panel.on_close()
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        // Buttons are square-ish whatever the window width is
+        let button_width = size.Y * self.params.button_ratio;
+        let row = self.row_handle.at(&mut self.root_panel)?;
+        let mut limit = CellLimit::default();
+        limit.set_size(button_width);
+        for handle in &self.button_handles {
+            row.set_cell_limit_of(handle, limit)?;
+        }
+        // Window is resized when it's maximized or restored
+        if let Some(handle) = self.maximize_icon_handle {
+            let icon = if is_window_maximized() {
+                IconId::Restore
+            } else {
+                IconId::Maximize
+            };
+            handle.at(&mut self.root_panel)?.set_icon(Some(icon))?;
+        }
+        self.root_panel.on_resize(size)
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        self.root_panel.on_idle()?;
+        // Layout of the tree is done by now, including the place of the title bar
+        self.update_regions()
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        self.event_handlers.clone().dispatch(self, panel_event)?;
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, measure, desired_size, on_init, on_timer, on_focus_changed, on_suspend,
+        on_resume, on_device_lost, input);
+}
@@ -0,0 +1,220 @@
+use std::{any::Any, borrow::Cow, time::Duration};
+
+use bindings::Windows::{
+    Foundation::{Numerics::Vector2, TimeSpan},
+    UI::Composition::ContainerVisual,
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton};
+
+use crate::{
+    background_panel::BackgroundParamsBuilder,
+    context::{current_context, FrameworkContext},
+    gamepad::GamepadInput,
+    globals::root_ribbon_with,
+    overlay_panel::{
+        OverlayAnchor, OverlayItemParamsBuilder, OverlayPanel, OverlayParamsBuilder,
+        OverlayPosition, OverlaySize,
+    },
+    panel::{Panel, PanelEvent},
+    ribbon_panel::{RibbonCellParamsBuilder, RibbonOrientation, RibbonParamsBuilder},
+    text_panel::TextParamsBuilder,
+};
+
+const TOAST_FADE_DURATION: Duration = Duration::from_millis(250);
+const TOAST_SIZE: Vector2 = Vector2 { X: 0.6, Y: 0.08 };
+// Distance from the bottom of the window in pixels
+const TOAST_MARGIN: Vector2 = Vector2 { X: 0., Y: 40. };
+
+struct ToastPanel {
+    context: FrameworkContext,
+    id: usize,
+    visual: ContainerVisual,
+    overlay: OverlayPanel,
+    duration: Duration,
+    fading_out: bool,
+}
+
+impl ToastPanel {
+    fn new(
+        context: &FrameworkContext,
+        text: Cow<'static, str>,
+        duration: Duration,
+    ) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let theme = context.theme()?;
+        // Toast uses inverted colors to stand out over any content
+        let background = BackgroundParamsBuilder::default()
+            .color(theme.text.clone())
+            .round_corners(true)
+            .create_in(context)?;
+        let text = TextParamsBuilder::default()
+            .text(text)
+            .color(theme.background)
+            .create_in(context)?;
+        let toast_box = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel_in(context, background)?
+            .add_panel_in(context, text)?
+            .create_in(context)?;
+        let overlay = OverlayParamsBuilder::default()
+            .add_item(
+                OverlayItemParamsBuilder::default()
+                    .panel(toast_box)
+                    .position(OverlayPosition::Anchor(OverlayAnchor::Bottom, TOAST_MARGIN))
+                    .size(OverlaySize::Ratio(TOAST_SIZE))
+                    .create_in(context)?,
+            )
+            .create_in(context)?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(overlay.visual())?;
+        let panel = Self {
+            context: context.clone(),
+            id,
+            visual,
+            overlay,
+            duration,
+            fading_out: false,
+        };
+        panel.fade(0., 1.)?;
+        // Timer drives the toast lifetime: first tick starts fading out, second removes it
+        context.set_timer(id, duration)?;
+        Ok(panel)
+    }
+    fn fade(&self, from: f32, to: f32) -> windows::Result<()> {
+        let animation = self.context.compositor()?.CreateScalarKeyFrameAnimation()?;
+        animation.InsertKeyFrame(0.0, from)?;
+        animation.InsertKeyFrame(1.0, to)?;
+        animation.SetDuration(TimeSpan {
+            Duration: (TOAST_FADE_DURATION.as_nanos() / 100) as i64,
+        })?;
+        self.visual.SetOpacity(to)?;
+        self.visual.StartAnimation("Opacity", animation)
+    }
+}
+
+impl Panel for ToastPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            self.overlay.find_panel(id)
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)?;
+        self.overlay.on_init()
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.context.kill_timer(self.id)?;
+        self.overlay.on_close()
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        // Toast is transient, it shouldn't constrain the window
+        Ok(Vector2 { X: 0., Y: 0. })
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        self.overlay.on_resize(size)
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        self.overlay.on_idle()
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        if timer_id != self.id {
+            return self.overlay.on_timer(timer_id);
+        }
+        if self.fading_out {
+            // Panel can't remove itself from the tree while it's processing the event
+            self.context.kill_timer(self.id)?;
+            let id = self.id;
+            self.context
+                .spawn(async move { root_ribbon_with(|root| root.remove_panel(id).map(|_| ())) })
+        } else {
+            self.fading_out = true;
+            self.context.set_timer(self.id, TOAST_FADE_DURATION)?;
+            self.fade(1., 0.)
+        }
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        self.overlay.on_focus_changed(focused)
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.overlay.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.overlay.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.overlay.on_device_lost()
+    }
+
+    fn on_mouse_move(&mut self, _position: &Vector2) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        _button: MouseButton,
+        _state: ElementState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        _input: KeyboardInput,
+        _modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, _input: GamepadInput) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        vec![&self.overlay]
+    }
+}
+
+// Shows text over the window for given time. The toast doesn't take input, so it may be
+// shown at any moment, including from panel event handlers
+pub fn show_toast<S: Into<Cow<'static, str>>>(text: S, duration: Duration) -> windows::Result<()> {
+    let context = current_context()?;
+    let toast = ToastPanel::new(&context, text.into(), duration)?;
+    let cell = RibbonCellParamsBuilder::default()
+        .panel(toast)
+        .input_transparent(true)
+        .create_in(&context)?;
+    context.spawn(async move { root_ribbon_with(|root| root.push_cell(cell)) })
+}
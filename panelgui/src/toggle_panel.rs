@@ -0,0 +1,374 @@
+use std::{any::Any, borrow::Cow};
+
+use bindings::Windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::{
+        Color, Colors,
+        Composition::{CompositionShape, ContainerVisual, ShapeVisual},
+    },
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::{
+    context::{current_context, FrameworkContext},
+    control::{Control, ControlHandle},
+    gamepad::{GamepadButton, GamepadInput},
+    globals::winrt_error,
+    panel::{Handle, Panel, PanelHandle},
+    style::StyleState,
+    text_panel::{TextPanel, TextParamsBuilder},
+};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TogglePanelEvent {
+    Toggled(bool),
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", setter(into))]
+pub struct ToggleParams {
+    #[builder(default = "{false}")]
+    checked: bool,
+    #[builder(default = "{true}")]
+    enabled: bool,
+    #[builder(default = "{\"\".into()}")]
+    label: Cow<'static, str>,
+    #[builder(default = "{Colors::SteelBlue().unwrap()}")]
+    check_color: Color,
+}
+
+impl ToggleParamsBuilder {
+    pub fn create(self) -> windows::Result<TogglePanel> {
+        self.create_in(&current_context()?)
+    }
+    pub fn create_in(self, context: &FrameworkContext) -> windows::Result<TogglePanel> {
+        match self.build() {
+            Ok(params) => Ok(TogglePanel::new(context, params)?),
+            Err(e) => Err(winrt_error(e)()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct TogglePanelHandle(usize);
+
+impl Handle for TogglePanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<TogglePanel, TogglePanelEvent> for TogglePanelHandle {}
+
+impl ControlHandle for TogglePanelHandle {
+    fn as_control<'a>(&self, root_panel: &'a mut dyn Panel) -> Option<&'a mut dyn Control> {
+        self.at(root_panel).ok().map(|p| p as &mut dyn Control)
+    }
+}
+
+pub struct TogglePanel {
+    context: FrameworkContext,
+    id: usize,
+    params: ToggleParams,
+    visual: ContainerVisual,
+    box_visual: ShapeVisual,
+    label_panel: TextPanel,
+    focused: bool,
+}
+
+impl TogglePanel {
+    pub fn new(context: &FrameworkContext, params: ToggleParams) -> windows::Result<Self> {
+        let id = context.next_id()?;
+        let visual = context.compositor()?.CreateContainerVisual()?;
+        let box_visual = context.compositor()?.CreateShapeVisual()?;
+        let label_panel = TextParamsBuilder::default()
+            .text(params.label.clone())
+            .enabled(params.enabled)
+            .create_in(context)?;
+        visual.Children()?.InsertAtTop(box_visual.clone())?;
+        visual.Children()?.InsertAtTop(label_panel.visual())?;
+        Ok(Self {
+            context: context.clone(),
+            id,
+            params,
+            visual,
+            box_visual,
+            label_panel,
+            focused: false,
+        })
+    }
+    pub fn handle(&self) -> TogglePanelHandle {
+        TogglePanelHandle(self.id)
+    }
+    pub fn is_checked(&self) -> bool {
+        self.params.checked
+    }
+    pub fn set_checked(&mut self, checked: bool) -> windows::Result<()> {
+        self.params.checked = checked;
+        self.redraw_box()
+    }
+    fn toggle(&mut self) -> windows::Result<()> {
+        if self.params.enabled {
+            self.params.checked = !self.params.checked;
+            self.redraw_box()?;
+            self.context
+                .send_panel_event(self.id, TogglePanelEvent::Toggled(self.params.checked))?;
+        }
+        Ok(())
+    }
+    fn create_rect(
+        &self,
+        offset: f32,
+        size: f32,
+        fill: Color,
+        stroke: Option<(Color, f32)>,
+    ) -> windows::Result<CompositionShape> {
+        let compositor = self.context.compositor()?;
+        let geometry = compositor.CreateRoundedRectangleGeometry()?;
+        geometry.SetSize(Vector2 { X: size, Y: size })?;
+        geometry.SetCornerRadius(Vector2 {
+            X: size / 8.,
+            Y: size / 8.,
+        })?;
+        let rect = compositor.CreateSpriteShapeWithGeometry(geometry)?;
+        rect.SetFillBrush(compositor.CreateColorBrushWithColor(fill)?)?;
+        if let Some((color, thickness)) = stroke {
+            rect.SetStrokeBrush(compositor.CreateColorBrushWithColor(color)?)?;
+            rect.SetStrokeThickness(thickness)?;
+        }
+        rect.SetOffset(Vector2 {
+            X: offset,
+            Y: offset,
+        })?;
+        Ok(rect.into())
+    }
+    fn redraw_box(&mut self) -> windows::Result<()> {
+        let size = self.box_visual.Size()?;
+        self.box_visual.Shapes()?.Clear()?;
+        let box_size = size.X.min(size.Y) / 2.;
+        if box_size <= 0. {
+            return Ok(());
+        }
+        let box_offset = (size.X - box_size) / 2.;
+        let style = self.context.theme()?.control_style(StyleState::new(
+            self.params.enabled,
+            self.focused,
+            false,
+            false,
+        ));
+        let stroke_thickness = style.stroke_width * self.context.scale_factor()?;
+        self.box_visual.Shapes()?.Append(self.create_rect(
+            box_offset,
+            box_size,
+            style.fill,
+            Some((style.stroke, stroke_thickness)),
+        )?)?;
+        if self.params.checked {
+            let check_color = if self.params.enabled {
+                self.params.check_color.clone()
+            } else {
+                self.context.theme()?.disabled_text
+            };
+            let check_size = box_size / 2.;
+            self.box_visual.Shapes()?.Append(self.create_rect(
+                box_offset + (box_size - check_size) / 2.,
+                check_size,
+                check_color,
+                None,
+            )?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Panel for TogglePanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
+        if id == self.id() {
+            Some(self.as_any_mut())
+        } else {
+            self.label_panel.find_panel(id)
+        }
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        self.label_panel.on_close()
+    }
+
+    fn measure(&self) -> windows::Result<Vector2> {
+        // Box is a square of label height
+        let label = self.label_panel.measure()?;
+        if self.params.label.is_empty() {
+            Ok(Vector2 {
+                X: label.Y,
+                Y: label.Y,
+            })
+        } else {
+            Ok(Vector2 {
+                X: label.Y + label.X,
+                Y: label.Y,
+            })
+        }
+    }
+
+    fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
+        self.visual.SetSize(size.clone())?;
+        // Box is square at the left side, label takes the rest
+        let box_size = if self.params.label.is_empty() {
+            size.X
+        } else {
+            size.Y.min(size.X)
+        };
+        self.box_visual.SetSize(Vector2 {
+            X: box_size,
+            Y: size.Y,
+        })?;
+        let label_size = Vector2 {
+            X: size.X - box_size,
+            Y: size.Y,
+        };
+        self.label_panel.visual().SetOffset(Vector3 {
+            X: box_size,
+            Y: 0.,
+            Z: 0.,
+        })?;
+        self.label_panel.visual().SetSize(label_size.clone())?;
+        self.label_panel.on_resize(&label_size)?;
+        self.redraw_box()
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        self.label_panel.on_idle()
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.label_panel.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.label_panel.on_resume()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.label_panel.on_device_lost()
+    }
+
+    fn on_mouse_input(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+    ) -> windows::Result<bool> {
+        if self.params.enabled && button == MouseButton::Left && state == ElementState::Pressed {
+            self.set_focus()?;
+            self.toggle()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        if self.focused && self.params.enabled && input.state == ElementState::Pressed {
+            if let Some(code) = input.virtual_keycode {
+                match code {
+                    VirtualKeyCode::Escape => {
+                        self.clear_focus()?;
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Tab => {
+                        if modifiers.shift() {
+                            self.set_focus_to_prev()?;
+                        } else {
+                            self.set_focus_to_next()?;
+                        }
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Left | VirtualKeyCode::Up => {
+                        self.set_focus_to_prev()?;
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Right | VirtualKeyCode::Down => {
+                        self.set_focus_to_next()?;
+                        return Ok(true);
+                    }
+                    VirtualKeyCode::Space | VirtualKeyCode::Return => {
+                        self.toggle()?;
+                        return Ok(true);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if self.focused && self.params.enabled && input.state == ElementState::Pressed {
+            match input.button {
+                GamepadButton::A => {
+                    self.toggle()?;
+                    return Ok(true);
+                }
+                GamepadButton::B => {
+                    self.clear_focus()?;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn Panel> {
+        vec![&self.label_panel]
+    }
+}
+
+impl Control for TogglePanel {
+    fn is_enabled(&self) -> windows::Result<bool> {
+        Ok(self.params.enabled)
+    }
+
+    fn is_focused(&self) -> windows::Result<bool> {
+        Ok(self.focused)
+    }
+
+    fn on_enable(&mut self, enable: bool) -> windows::Result<()> {
+        self.params.enabled = enable;
+        self.label_panel.on_enable(enable)?;
+        self.redraw_box()
+    }
+
+    fn on_set_focus(&mut self) -> windows::Result<()> {
+        self.focused = true;
+        self.redraw_box()
+    }
+
+    fn on_clear_focus(&mut self) -> windows::Result<()> {
+        self.focused = false;
+        self.redraw_box()
+    }
+
+    fn as_panel(&self) -> &dyn Panel {
+        self
+    }
+}
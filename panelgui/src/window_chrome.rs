@@ -0,0 +1,209 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use bindings::Windows::{
+    Foundation::Numerics::Vector2,
+    Win32::{
+        Graphics::Gdi::ScreenToClient,
+        UI::{
+            DisplayDevices::{POINT, RECT},
+            Shell::{DefSubclassProc, SetWindowSubclass},
+            WindowsAndMessaging::{GetClientRect, IsZoomed, HWND, LPARAM, LRESULT, WPARAM},
+        },
+    },
+};
+
+use crate::{
+    accessibility::{disconnect_providers, get_object},
+    globals::winrt_error,
+};
+
+const WM_DESTROY: u32 = 0x0002;
+const WM_GETOBJECT: u32 = 0x003D;
+const WM_NCHITTEST: u32 = 0x0084;
+const HTCLIENT: isize = 1;
+const HTCAPTION: isize = 2;
+const HTLEFT: isize = 10;
+const HTRIGHT: isize = 11;
+const HTTOP: isize = 12;
+const HTTOPLEFT: isize = 13;
+const HTTOPRIGHT: isize = 14;
+const HTBOTTOM: isize = 15;
+const HTBOTTOMLEFT: isize = 16;
+const HTBOTTOMRIGHT: isize = 17;
+// Any unique value identifies the subclass of the window
+const CHROME_SUBCLASS_ID: usize = 2048;
+// Subclass data of the window without decorations
+const CUSTOM_CHROME: usize = 1;
+// Width of the invisible resize border of window without decorations, logical pixels
+const RESIZE_BORDER: f32 = 6.;
+
+// How the window treats mouse over part of custom chrome
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum HitTestKind {
+    // Dragging moves the window, double click maximizes it, right click opens system menu
+    Caption,
+    // Input goes to panels as usual, e.g. for caption buttons inside of the caption area
+    Client,
+}
+
+// Rectangle in physical pixels relative to the client area
+#[derive(Clone, Debug, PartialEq)]
+pub struct HitTestRegion {
+    pub kind: HitTestKind,
+    pub offset: Vector2,
+    pub size: Vector2,
+}
+
+impl HitTestRegion {
+    fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.offset.X
+            && x < self.offset.X + self.size.X
+            && y >= self.offset.Y
+            && y < self.offset.Y + self.size.Y
+    }
+}
+
+#[derive(Default)]
+struct ChromeState {
+    // Regions reported by panels, keyed by panel id
+    regions: HashMap<usize, Vec<HitTestRegion>>,
+    resizable: bool,
+    scale_factor: f32,
+}
+
+thread_local! {
+    // Kept apart from globals: window procedure is called while globals may be borrowed
+    static CHROME: RefCell<ChromeState> = RefCell::new(ChromeState::default());
+}
+
+// Replaces regions previously set by the panel, empty list removes them
+pub(crate) fn set_hit_test_regions(panel_id: usize, regions: Vec<HitTestRegion>) {
+    CHROME.with(|chrome| {
+        let mut chrome = chrome.borrow_mut();
+        if regions.is_empty() {
+            chrome.regions.remove(&panel_id);
+        } else {
+            chrome.regions.insert(panel_id, regions);
+        }
+    })
+}
+
+pub(crate) fn set_chrome_scale_factor(scale_factor: f32) {
+    CHROME.with(|chrome| chrome.borrow_mut().scale_factor = scale_factor)
+}
+
+// Subclass answers WM_GETOBJECT with the UI Automation provider. Window without
+// decorations has no caption and borders, so it's also moved and resized by answering
+// WM_NCHITTEST from the regions reported by panels
+pub(crate) fn install_window_subclass(
+    hwnd: HWND,
+    custom_chrome: bool,
+    resizable: bool,
+    scale_factor: f32,
+) -> windows::Result<()> {
+    if custom_chrome {
+        CHROME.with(|chrome| {
+            let mut chrome = chrome.borrow_mut();
+            chrome.resizable = resizable;
+            chrome.scale_factor = scale_factor;
+        });
+    }
+    let data = if custom_chrome { CUSTOM_CHROME } else { 0 };
+    let installed =
+        unsafe { SetWindowSubclass(hwnd, Some(chrome_subclass_proc), CHROME_SUBCLASS_ID, data) };
+    if installed.as_bool() {
+        Ok(())
+    } else {
+        Err(winrt_error("Failed to install window subclass")())
+    }
+}
+
+unsafe extern "system" fn chrome_subclass_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    data: usize,
+) -> LRESULT {
+    match message {
+        WM_GETOBJECT => {
+            if let Some(result) = get_object(hwnd, wparam, lparam) {
+                return result;
+            }
+        }
+        WM_DESTROY => disconnect_providers(hwnd),
+        _ => {}
+    }
+    let result = DefSubclassProc(hwnd, message, wparam, lparam);
+    if data != CUSTOM_CHROME || message != WM_NCHITTEST || result.0 != HTCLIENT {
+        return result;
+    }
+    // Screen coordinates are packed as signed 16 bit values
+    let mut point = POINT {
+        x: (lparam.0 & 0xFFFF) as i16 as i32,
+        y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+    };
+    if !ScreenToClient(hwnd, &mut point).as_bool() {
+        return result;
+    }
+    let maximized = IsZoomed(hwnd).as_bool();
+    match hit_test(hwnd, point.x as f32, point.y as f32, maximized) {
+        Some(hit) => LRESULT(hit),
+        None => result,
+    }
+}
+
+fn hit_test(hwnd: HWND, x: f32, y: f32, maximized: bool) -> Option<isize> {
+    let size = client_size(hwnd)?;
+    CHROME.with(|chrome| {
+        let chrome = chrome.try_borrow().ok()?;
+        if chrome.resizable && !maximized {
+            let border = RESIZE_BORDER * chrome.scale_factor;
+            let left = x < border;
+            let right = x >= size.X - border;
+            let top = y < border;
+            let bottom = y >= size.Y - border;
+            let hit = match (left, right, top, bottom) {
+                (true, _, true, _) => Some(HTTOPLEFT),
+                (_, true, true, _) => Some(HTTOPRIGHT),
+                (true, _, _, true) => Some(HTBOTTOMLEFT),
+                (_, true, _, true) => Some(HTBOTTOMRIGHT),
+                (true, _, _, _) => Some(HTLEFT),
+                (_, true, _, _) => Some(HTRIGHT),
+                (_, _, true, _) => Some(HTTOP),
+                (_, _, _, true) => Some(HTBOTTOM),
+                _ => None,
+            };
+            if hit.is_some() {
+                return hit;
+            }
+        }
+        let regions = chrome.regions.values().flatten();
+        // Client regions are holes in caption ones
+        let mut caption = false;
+        for region in regions.filter(|r| r.contains(x, y)) {
+            match region.kind {
+                HitTestKind::Client => return None,
+                HitTestKind::Caption => caption = true,
+            }
+        }
+        if caption {
+            Some(HTCAPTION)
+        } else {
+            None
+        }
+    })
+}
+
+fn client_size(hwnd: HWND) -> Option<Vector2> {
+    let mut rect = RECT::default();
+    if unsafe { GetClientRect(hwnd, &mut rect) }.as_bool() {
+        Some(Vector2 {
+            X: (rect.right - rect.left) as f32,
+            Y: (rect.bottom - rect.top) as f32,
+        })
+    } else {
+        None
+    }
+}
@@ -0,0 +1,218 @@
+use bindings::Windows::{
+    Foundation::Numerics::Vector2,
+    Win32::UI::WindowsAndMessaging::HWND,
+    UI::Composition::{Compositor, ContainerVisual, Desktop::DesktopWindowTarget},
+};
+use std::time::Instant;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::ModifiersState,
+    event_loop::EventLoopWindowTarget,
+    platform::windows::WindowExtWindows,
+    window::{CursorIcon, Icon, Window, WindowBuilder, WindowId},
+};
+
+use crate::{
+    backdrop::{apply_backdrop, WindowBackdrop},
+    globals::winrt_error,
+    panel::PanelEvent,
+    window_chrome::install_window_subclass,
+    window_target::CompositionDesktopWindowTargetSource,
+};
+
+#[derive(Builder, Clone)]
+#[builder(pattern = "owned", setter(into))]
+pub struct WindowParams {
+    #[builder(default = "{String::new()}")]
+    title: String,
+    #[builder(default = "{None}")]
+    icon: Option<Icon>,
+    // Sizes and position are in physical pixels, None means system default
+    #[builder(default = "{None}")]
+    size: Option<Vector2>,
+    #[builder(default = "{None}")]
+    min_size: Option<Vector2>,
+    #[builder(default = "{None}")]
+    position: Option<Vector2>,
+    #[builder(default = "{true}")]
+    resizable: bool,
+    #[builder(default = "{false}")]
+    maximized: bool,
+    // No window and no event loop are created, panels are composed into a visual which
+    // isn't attached to any target and panel events are queued. Used by TestHarness
+    #[builder(default = "{false}")]
+    pub(crate) headless: bool,
+    // Falls back to opaque background when the system doesn't support it
+    #[builder(default = "{WindowBackdrop::None}")]
+    backdrop: WindowBackdrop,
+    // Without decorations the window has no system title bar and borders, panels draw
+    // them and report caption area with TitleBarPanel
+    #[builder(default = "{true}")]
+    decorations: bool,
+}
+
+impl WindowParamsBuilder {
+    pub fn create(self) -> windows::Result<WindowParams> {
+        self.build().map_err(|e| winrt_error(e)())
+    }
+}
+
+impl WindowParams {
+    fn window_builder(&self) -> WindowBuilder {
+        let mut builder = WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_window_icon(self.icon.clone())
+            .with_resizable(self.resizable)
+            .with_maximized(self.maximized)
+            .with_decorations(self.decorations)
+            // Window is shown after it's moved to the requested position
+            .with_visible(false);
+        if let Some(size) = &self.size {
+            builder = builder.with_inner_size(PhysicalSize::new(size.X as u32, size.Y as u32));
+        }
+        if let Some(min_size) = &self.min_size {
+            builder = builder
+                .with_min_inner_size(PhysicalSize::new(min_size.X as u32, min_size.Y as u32));
+        }
+        builder
+    }
+}
+
+// Window with its composition target and the visual of its root panel. Root panels are
+// kept apart from globals, hosts are found by winit's WindowId on window events and by
+// own id in the application code
+pub(crate) struct WindowHost {
+    pub(crate) id: usize,
+    pub(crate) window_id: WindowId,
+    // None in headless mode
+    pub(crate) window: Option<Window>,
+    pub(crate) root_visual: ContainerVisual,
+    pub(crate) target: Option<DesktopWindowTarget>,
+    pub(crate) headless: bool,
+    // Backdrop actually applied to the window
+    pub(crate) backdrop: WindowBackdrop,
+    // Ratio of physical pixels to logical ones for the monitor the window is on
+    pub(crate) scale_factor: f64,
+    // Minimal window size from WindowParams, content measure can only enlarge it
+    pub(crate) min_size: Vector2,
+    pub(crate) applied_min_size: Option<Vector2>,
+    // Window is minimized, panel content is released
+    pub(crate) suspended: bool,
+    pub(crate) cursor_icon: CursorIcon,
+    pub(crate) modifiers: ModifiersState,
+    // Touch id of the finger which is translated to mouse events
+    pub(crate) primary_touch: Option<u64>,
+    pub(crate) last_touch_time: Option<Instant>,
+}
+
+impl WindowHost {
+    pub(crate) fn new(
+        id: usize,
+        params: &WindowParams,
+        event_loop: &EventLoopWindowTarget<PanelEvent>,
+        compositor: &Compositor,
+    ) -> windows::Result<Self> {
+        let window = params
+            .window_builder()
+            .build(event_loop)
+            .map_err(|e| winrt_error(e.to_string())())?;
+        let window_id = window.id();
+        if let Some(position) = &params.position {
+            window.set_outer_position(PhysicalPosition::new(position.X as i32, position.Y as i32));
+        }
+        window.set_visible(true);
+        let scale_factor = window.scale_factor();
+        let min_size = params.min_size.clone().unwrap_or(Vector2 { X: 0., Y: 0. });
+        let window_size = window.inner_size();
+        let root_visual = compositor.CreateContainerVisual()?;
+        root_visual.SetSize(Vector2 {
+            X: window_size.width as f32,
+            Y: window_size.height as f32,
+        })?;
+        let target = window.create_window_target(compositor, false)?;
+        target.SetRoot(&root_visual)?;
+        let hwnd = HWND(window.hwnd() as isize);
+        install_window_subclass(
+            hwnd,
+            !params.decorations,
+            params.resizable,
+            scale_factor as f32,
+        )?;
+        let backdrop = if params.backdrop == WindowBackdrop::None {
+            WindowBackdrop::None
+        } else if apply_backdrop(hwnd, params.backdrop) {
+            params.backdrop
+        } else {
+            tracing::warn!(backdrop = ?params.backdrop, "window backdrop is not supported");
+            WindowBackdrop::None
+        };
+        Ok(Self {
+            id,
+            window_id,
+            window: Some(window),
+            root_visual,
+            target: Some(target),
+            headless: false,
+            backdrop,
+            scale_factor,
+            min_size,
+            applied_min_size: None,
+            suspended: false,
+            cursor_icon: CursorIcon::Default,
+            modifiers: ModifiersState::empty(),
+            primary_touch: None,
+            last_touch_time: None,
+        })
+    }
+
+    // Root visual without window, its size is the size of the window
+    pub(crate) fn new_headless(
+        id: usize,
+        params: &WindowParams,
+        compositor: &Compositor,
+    ) -> windows::Result<Self> {
+        let size = params.size.clone().unwrap_or(Vector2 { X: 0., Y: 0. });
+        let root_visual = compositor.CreateContainerVisual()?;
+        root_visual.SetSize(&size)?;
+        Ok(Self {
+            id,
+            // Nothing else identifies the host, there is only one window in headless mode
+            window_id: unsafe { WindowId::dummy() },
+            window: None,
+            root_visual,
+            target: None,
+            headless: true,
+            backdrop: WindowBackdrop::None,
+            scale_factor: 1.,
+            min_size: params.min_size.clone().unwrap_or(Vector2 { X: 0., Y: 0. }),
+            applied_min_size: None,
+            suspended: false,
+            cursor_icon: CursorIcon::Default,
+            modifiers: ModifiersState::empty(),
+            primary_touch: None,
+            last_touch_time: None,
+        })
+    }
+
+    // Null window in headless mode
+    pub(crate) fn hwnd(&self) -> HWND {
+        HWND(
+            self.window
+                .as_ref()
+                .map_or(0, |window| window.hwnd() as isize),
+        )
+    }
+
+    pub(crate) fn size(&self) -> Vector2 {
+        match &self.window {
+            Some(window) => {
+                let size = window.inner_size();
+                Vector2 {
+                    X: size.width as f32,
+                    Y: size.height as f32,
+                }
+            }
+            None => self.root_visual.Size().unwrap_or(Vector2 { X: 0., Y: 0. }),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::borrow::Cow;
+
+use bindings::Windows::Foundation::Numerics::Vector2;
+use panelgui::{
+    accessible_info, Accelerator, AccessibleRole, ButtonPanelEvent, ButtonParamsBuilder, Handle,
+    ListPanelHandle, ListParamsBuilder, Panel, PanelHandle, RibbonOrientation, RibbonParamsBuilder,
+    TestHarness, TextParamsBuilder,
+};
+use winit::event::{ModifiersState, MouseScrollDelta, VirtualKeyCode};
+
+const SIZE: Vector2 = Vector2 { X: 200., Y: 100. };
+const CENTER: Vector2 = Vector2 { X: 100., Y: 50. };
+
+#[test]
+fn button_click_sends_pressed() -> windows::Result<()> {
+    let mut harness = TestHarness::new(SIZE)?;
+    let button = ButtonParamsBuilder::default()
+        .text("Ok")?
+        .create_in(harness.context())?;
+    let button_id = button.handle().id();
+    harness.set_panel(button)?;
+    harness.click(CENTER)?;
+    assert!(harness.take_event::<ButtonPanelEvent>(button_id) == Some(ButtonPanelEvent::Pressed));
+    assert!(harness.take_events().is_empty());
+    Ok(())
+}
+
+#[test]
+fn button_accelerator_sends_pressed() -> windows::Result<()> {
+    let mut harness = TestHarness::new(SIZE)?;
+    let button = ButtonParamsBuilder::default()
+        .text("Undo")?
+        .accelerator(Some(Accelerator::ctrl(VirtualKeyCode::Z)))
+        .create_in(harness.context())?;
+    let button_id = button.handle().id();
+    harness.set_panel(button)?;
+    harness.set_modifiers(ModifiersState::CTRL)?;
+    harness.key(VirtualKeyCode::Z)?;
+    // Accelerator is delivered to the button, which sends Pressed in turn
+    harness.dispatch()?;
+    assert!(harness.take_event::<ButtonPanelEvent>(button_id) == Some(ButtonPanelEvent::Pressed));
+    Ok(())
+}
+
+#[test]
+fn button_registers_accessible_name() -> windows::Result<()> {
+    let mut harness = TestHarness::new(SIZE)?;
+    // Compat constructors use the context made current by the harness
+    let button = ButtonParamsBuilder::default().text("Ok")?.create()?;
+    let button_id = button.handle().id();
+    harness.set_panel(button)?;
+    let info = accessible_info(button_id).expect("Button is not accessible");
+    assert_eq!(info.role, AccessibleRole::Button);
+    assert_eq!(info.name, "Ok");
+    Ok(())
+}
+
+fn first_visible(
+    harness: &mut TestHarness,
+    list_handle: ListPanelHandle,
+) -> windows::Result<usize> {
+    harness.with_panel(|root| Ok(list_handle.at(root)?.first_visible()))
+}
+
+#[test]
+fn list_mouse_wheel_scrolls_view() -> windows::Result<()> {
+    let mut harness = TestHarness::new(SIZE)?;
+    let items = (0..10)
+        .map(|i| Cow::from(i.to_string()))
+        .collect::<Vec<_>>();
+    let list = ListParamsBuilder::default()
+        .items(items)
+        .visible_items(4usize)
+        .create_in(harness.context())?;
+    let list_handle = list.handle();
+    harness.set_panel(list)?;
+    assert!(harness.mouse_wheel(MouseScrollDelta::LineDelta(0., -3.))?);
+    assert_eq!(first_visible(&mut harness, list_handle)?, 3);
+    // View stops at the last page and at the first item
+    harness.mouse_wheel(MouseScrollDelta::LineDelta(0., -10.))?;
+    assert_eq!(first_visible(&mut harness, list_handle)?, 6);
+    harness.mouse_wheel(MouseScrollDelta::LineDelta(0., 10.))?;
+    assert_eq!(first_visible(&mut harness, list_handle)?, 0);
+    // Wheel doesn't move the current item
+    let current = harness.with_panel(|root| Ok(list_handle.at(root)?.current()))?;
+    assert_eq!(current, 0);
+    Ok(())
+}
+
+#[test]
+fn ribbon_cell_aspect_ratio_can_change() -> windows::Result<()> {
+    let mut harness = TestHarness::new(SIZE)?;
+    let context = harness.context().clone();
+    let content = TextParamsBuilder::default()
+        .text("2048")
+        .create_in(&context)?;
+    let content_visual = content.visual();
+    let content_handle = content.handle();
+    let ribbon = RibbonParamsBuilder::default()
+        .orientation(RibbonOrientation::Stack)
+        .add_panel_in(&context, content)?
+        .create_in(&context)?;
+    let ribbon_handle = ribbon.handle();
+    harness.set_panel(ribbon)?;
+    assert_eq!(content_visual.Size()?, SIZE);
+    harness.with_panel(|root| ribbon_handle.at(root)?.set_cell_aspect_ratio(0, Some(1.)))?;
+    harness.idle()?;
+    assert_eq!(content_visual.Size()?, Vector2 { X: 100., Y: 100. });
+    // Cell is found by its panel as well
+    harness.with_panel(|root| {
+        ribbon_handle
+            .at(root)?
+            .set_cell_aspect_ratio_of(&content_handle, None)
+    })?;
+    harness.idle()?;
+    assert_eq!(content_visual.Size()?, SIZE);
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use std::borrow::Cow;
+
+use bindings::Windows::UI::Composition::ContainerVisual;
+use panelgui::{
+    compositor, delegate_panel, get_next_id, send_panel_event, BackgroundParamsBuilder,
+    ButtonPanelEvent, ButtonPanelHandle, ButtonParamsBuilder, ControlManager, GamepadButton,
+    GamepadInput, Handle, ListParamsBuilder, Panel, PanelEvent, PanelHandle, RibbonOrientation,
+    RibbonPanel, RibbonParamsBuilder, TextParamsBuilder,
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode};
+
+// Long lines of the reference and licenses are wrapped to this number of characters
+const LINE_WIDTH: usize = 40;
+const VISIBLE_LINES: usize = 12;
+
+const CONTROLS: &str = "Controls
+Arrows, WASD or swipe: move tiles
+Backspace, Ctrl+Z: undo
+Y: redo
+R, Ctrl+N: new game
+H: hint
+P: pause
+Esc: menu
+F1: about
+F2: screenshot
+Gamepad: D-pad moves tiles, X undo, Y redo, Menu new game, B back";
+
+const LICENSE: &str = include_str!("../LICENSE.txt");
+const THIRD_PARTY_LICENSES: &str = include_str!("../THIRD_PARTY_LICENSES.txt");
+
+#[derive(PartialEq)]
+pub enum AboutPanelEvent {
+    Closed,
+}
+
+pub struct AboutPanelHandle(usize);
+
+impl Handle for AboutPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<AboutPanel, AboutPanelEvent> for AboutPanelHandle {}
+
+// Version, controls reference and licenses. The text is shown in a list, so it can
+// be scrolled with wheel, keyboard and gamepad
+pub struct AboutPanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    close_handle: ButtonPanelHandle,
+}
+
+// Splits text to lines of at most `width` characters at spaces, empty lines are kept
+fn wrap_lines(text: &str, width: usize) -> Vec<Cow<'static, str>> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line).into());
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line.into());
+    }
+    lines
+}
+
+impl AboutPanel {
+    pub fn new() -> windows::Result<Self> {
+        let id = get_next_id();
+        let background = BackgroundParamsBuilder::default()
+            .dialog(true)
+            .round_corners(true)
+            .create()?;
+        let title = TextParamsBuilder::default()
+            .text("2048")
+            .font_scale(1.5)
+            .create()?;
+        let version = TextParamsBuilder::default()
+            .text(format!("Version {}", env!("CARGO_PKG_VERSION")))
+            .create()?;
+        let mut lines = wrap_lines(CONTROLS, LINE_WIDTH);
+        lines.push("".into());
+        lines.extend(wrap_lines(LICENSE, LINE_WIDTH));
+        lines.push("".into());
+        lines.extend(wrap_lines(THIRD_PARTY_LICENSES, LINE_WIDTH));
+        let text = ListParamsBuilder::default()
+            .items(lines)
+            .visible_items(VISIBLE_LINES)
+            .create()?;
+        let text_handle = text.handle();
+        let close = ButtonParamsBuilder::default().text("Close")?.create()?;
+        let close_handle = close.handle();
+        let mut control_manager = ControlManager::new();
+        control_manager.add_control(text_handle);
+        control_manager.add_control(close_handle);
+
+        let ribbon = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Vertical)
+            .add_panel(title)?
+            .add_panel(version)?
+            .add_panel_with_ratio(text, 6.)?
+            .add_panel(close)?
+            .create()?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel(background)?
+            .add_panel(ribbon)?
+            .create()?;
+
+        let visual = compositor().CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            close_handle,
+        })
+    }
+
+    pub fn handle(&self) -> AboutPanelHandle {
+        AboutPanelHandle(self.id)
+    }
+}
+
+impl Panel for AboutPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        if input.state == ElementState::Pressed
+            && input.virtual_keycode == Some(VirtualKeyCode::Escape)
+        {
+            send_panel_event(self.id, AboutPanelEvent::Closed)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if input.button == GamepadButton::B && input.state == ElementState::Pressed {
+            send_panel_event(self.id, AboutPanelEvent::Closed)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        if self.close_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            send_panel_event(self.id, AboutPanelEvent::Closed)?;
+        } else {
+            let _ = self
+                .control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+        }
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, layout, lifecycle, on_mouse_move, on_mouse_input,
+        on_mouse_wheel, on_cursor_inside_changed, on_modifiers_changed, on_char);
+}
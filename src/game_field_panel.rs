@@ -1,9 +1,15 @@
 use lazy_static::lazy_static;
 use panelgui::{
-    canvas_device, composition_graphics_device, compositor, get_next_id, send_panel_event, Handle,
-    Panel, PanelEvent, PanelHandle,
+    animation_settings, canvas_device, composition_graphics_device, compositor,
+    create_rounded_mask, create_shadow_visual, get_next_id, is_resizing, kill_timer, play_sound,
+    remove_accessible, scale_factor, send_panel_event, set_accessible, set_timer, show_toast,
+    theme, winrt_error, AccessibleRole, AnimationSettings, GamepadButton, GamepadInput, Handle,
+    Panel, PanelEvent, PanelHandle, Property, PropertyHandle, SoundId,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
 };
-use std::collections::HashMap;
 
 use bindings::{
     Microsoft::Graphics::Canvas::{
@@ -16,41 +22,100 @@ use bindings::{
     Windows::{
         Foundation::{
             Numerics::{Vector2, Vector3},
-            Size,
+            Size, TimeSpan, TypedEventHandler,
+        },
+        Graphics::{
+            DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+            SizeInt32,
         },
-        Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
         UI::{
             Color, ColorHelper, Colors,
             Composition::{
-                CompositionBorderMode, CompositionGraphicsDevice, CompositionShape, Compositor,
-                ContainerVisual, ShapeVisual, Visual,
+                AnimationDelayBehavior, CompositionBatchTypes, CompositionBorderMode,
+                CompositionDrawingSurface, CompositionGraphicsDevice, CompositionShape,
+                CompositionSurfaceBrush, Compositor, ContainerVisual, ShapeVisual, SpriteVisual,
+                Visual,
             },
         },
     },
 };
 use float_ord::FloatOrd;
-use model::field::{Field, Origin, Side};
-use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode};
+use model::{
+    ai,
+    field::{Field, Origin, ResizeMove, Side, BLOCKER_LEVEL},
+    format::NumberFormat,
+    game::Game,
+    game_mode::GameMode,
+    merge_rule::MergeRuleKind,
+    spawn::SpawnPolicy,
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode};
 
 lazy_static! {
     static ref TILE_RECT_SIZE: Vector2 = Vector2 { X: 512., Y: 512. };
     static ref TILE_SIZE: Vector2 = &*TILE_RECT_SIZE / 1.1;
-    static ref TILE_CORNER_RADIUS: Vector2 = Vector2 {
-        X: TILE_SIZE.X / 20.,
-        Y: TILE_SIZE.Y / 20.,
-    };
     static ref TILE_OFFSET: Vector2 = (&*TILE_RECT_SIZE - &*TILE_SIZE) / 2.;
     static ref GAME_BOARD_MARGIN: Vector2 = Vector2 { X: 100.0, Y: 100.0 };
     static ref MIN_DRAG_MOUSE_MOVE: FloatOrd<f32> = FloatOrd(5.);
 }
 
+// Drag longer than this part of the panel size is recognized as swipe
+const SWIPE_GESTURE_DISTANCE_RATIO: f32 = 0.1;
+
+use crate::high_scores;
+
+const SAVE_FILE_NAME: &str = "game.txt";
+const AUTO_PLAY_PERIOD: Duration = Duration::from_millis(250);
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+// Swipes made during animation above this limit are dropped
+const MAX_QUEUED_SWIPES: usize = 4;
+// Period of remaining time updates in timed modes
+const CLOCK_PERIOD: Duration = Duration::from_secs(1);
+// Longer pauses between moves are counted as this time, so the game left open
+// doesn't spoil the speed
+const MAX_MOVE_GAP: Duration = Duration::from_secs(10);
+// Maximal scale of merged tile during its "pop" animation
+const MERGE_POP_SCALE: f32 = 1.2;
+// Smallest tile size in logical pixels at which numbers are still readable
+const MIN_TILE_SIZE: f32 = 32.;
+// Board is scaled to the window, so tile shadow is sized as for a tile of this size in
+// logical pixels and scales with the board
+const SHADOW_REFERENCE_TILE_SIZE: f32 = 100.;
+// TimeSpan is measured in 100ns units
+const HINT_DURATION: TimeSpan = TimeSpan {
+    Duration: 15_000_000,
+};
+const SCORE_POPUP_DURATION: TimeSpan = TimeSpan {
+    Duration: 8_000_000,
+};
+
 #[derive(PartialEq)]
 pub enum GameFieldPanelEvent {
     Changed,
     UndoRequested,
+    RedoRequested,
     ResetRequested,
+    GameOver,
+    Won,
+    // Score of finished game became the best one
+    NewBestScore,
+    AnimationFinished,
+    TimeLeftChanged,
 }
 
+// Sent by completed animation batch to the panel itself
+struct AnimationBatchCompleted(usize);
+
+struct AnimationBatchHandle(usize);
+
+impl Handle for AnimationBatchHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<GameFieldPanel, AnimationBatchCompleted> for AnimationBatchHandle {}
+
 pub struct GameFieldPanel {
     id: usize,
     compositor: Compositor,
@@ -62,10 +127,53 @@ pub struct GameFieldPanel {
     removed_tiles: Vec<Visual>,
     tile_shapes: HashMap<u32, CompositionShape>,
     tile_text_layouts: HashMap<u32, CanvasTextLayout>,
-    field: Field,
-    score: u32,
+    // Rendered tile numbers keyed by level and dark mode, surface and brush are shared by
+    // all tiles of the same level, so switching theme back doesn't render them again
+    tile_text_surfaces: HashMap<(u32, bool), (CompositionDrawingSurface, CompositionSurfaceBrush)>,
+    // Same for all tiles, None until first tile is created or if theme has no shadow
+    tile_shadow_mask: Option<CompositionSurfaceBrush>,
+    // Tile text is rendered at board scale, so it's not stretched when board is fit to window
+    text_scale: f32,
+    // Current scale of the board visual, text is re-rendered to it when resizing stops
+    board_scale: f32,
+    game: Game,
+    board_size: usize,
+    challenge: bool,
+    merge_rule: MergeRuleKind,
+    spawn_policy: SpawnPolicy,
+    hint_visual: Option<Visual>,
+    // "+N" floating over the tiles merged by the last swipe
+    score_popup_visual: Option<Visual>,
+    auto_play: bool,
+    dark_mode: bool,
+    // Tile labels of big values can be grouped or shortened
+    number_format: NumberFormat,
+    // Number of the last started animation batch
+    animation_batch: usize,
+    animating: bool,
+    // Swipes made while tiles are moving, they are applied when animation finishes
+    swipe_queue: VecDeque<Side>,
+    // Play queued swipes without animation except the last one
+    fast_forward: bool,
+    skip_animations: bool,
+    game_mode: GameMode,
+    clock_timer_id: usize,
+    // Time is over when this moment is reached, clock starts with the first move
+    deadline: Option<Instant>,
+    timed_out: bool,
+    // Game is recorded once, even if it's undone after game over and finished again
+    score_recorded: bool,
+    // Paused game ignores swipes, the clock is stopped keeping the remaining time
+    paused: bool,
+    paused_time_left: Option<Duration>,
+    // Play time is counted from the previous move, None before the first move of the session
+    last_move: Option<Instant>,
     mouse_pos: Option<Vector2>,
     mouse_pressed_pos: Option<Vector2>,
+    score: Property<u32>,
+    can_undo: Property<bool>,
+    // Width to height ratio of the board with its margin, follows the field size
+    aspect_ratio: Property<f32>,
 }
 
 #[derive(Copy, Clone)]
@@ -88,6 +196,14 @@ impl Panel for GameFieldPanel {
     fn visual(&self) -> ContainerVisual {
         self.root.clone()
     }
+    fn measure(&self) -> windows::Result<Vector2> {
+        let tile_size = MIN_TILE_SIZE * scale_factor();
+        Ok(Vector2 {
+            X: self.field().width() as f32 * tile_size,
+            Y: self.field().height() as f32 * tile_size,
+        })
+    }
+
     fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
         self.visual().SetSize(size.clone())?;
         self.scale_game_board()
@@ -95,7 +211,11 @@ impl Panel for GameFieldPanel {
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
-    fn on_keyboard_input(&mut self, input: KeyboardInput) -> windows::Result<bool> {
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        _modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
         if input.state == ElementState::Pressed {
             if let Some(side) = match input.virtual_keycode {
                 Some(VirtualKeyCode::Left) => Some(Side::Left),
@@ -113,6 +233,9 @@ impl Panel for GameFieldPanel {
             } else if input.virtual_keycode == Some(VirtualKeyCode::Back) {
                 send_panel_event(self.id, GameFieldPanelEvent::UndoRequested)?;
                 return Ok(true);
+            } else if input.virtual_keycode == Some(VirtualKeyCode::Y) {
+                send_panel_event(self.id, GameFieldPanelEvent::RedoRequested)?;
+                return Ok(true);
             } else if input.virtual_keycode == Some(VirtualKeyCode::R) {
                 send_panel_event(self.id, GameFieldPanelEvent::ResetRequested)?;
                 return Ok(true);
@@ -121,8 +244,50 @@ impl Panel for GameFieldPanel {
         Ok(false)
     }
 
+    fn on_char(&mut self, _ch: char) -> windows::Result<bool> {
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if input.state == ElementState::Pressed {
+            if let Some(side) = match input.button {
+                GamepadButton::Left => Some(Side::Left),
+                GamepadButton::Right => Some(Side::Right),
+                GamepadButton::Up => Some(Side::Up),
+                GamepadButton::Down => Some(Side::Down),
+                _ => None,
+            } {
+                self.swipe(side)?;
+                return Ok(true);
+            } else if input.button == GamepadButton::X {
+                send_panel_event(self.id, GameFieldPanelEvent::UndoRequested)?;
+                return Ok(true);
+            } else if input.button == GamepadButton::Y {
+                send_panel_event(self.id, GameFieldPanelEvent::RedoRequested)?;
+                return Ok(true);
+            } else if input.button == GamepadButton::Menu {
+                send_panel_event(self.id, GameFieldPanelEvent::ResetRequested)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
         self.mouse_pos = Some(position.clone());
+        // Swipe gesture is recognized as soon as the pointer moved far enough,
+        // without waiting for release
+        if let Some(ref prev_position) = self.mouse_pressed_pos {
+            let dx = position.X - prev_position.X;
+            let dy = position.Y - prev_position.Y;
+            let size = self.visual().Size()?;
+            let swipe_distance =
+                std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 * SWIPE_GESTURE_DISTANCE_RATIO;
+            if std::cmp::max(FloatOrd(dx.abs()), FloatOrd(dy.abs())).0 >= swipe_distance {
+                self.mouse_pressed_pos = None;
+                self.swipe(Self::side_by_direction(dx, dy))?;
+            }
+        }
         Ok(())
     }
 
@@ -132,7 +297,7 @@ impl Panel for GameFieldPanel {
         state: ElementState,
     ) -> windows::Result<bool> {
         let position = if let Some(ref posiition) = self.mouse_pos {
-            posiition
+            posiition.clone()
         } else {
             return Ok(false);
         };
@@ -141,35 +306,22 @@ impl Panel for GameFieldPanel {
         }
 
         if state == ElementState::Pressed {
-            self.mouse_pressed_pos = Some(position.clone());
+            self.mouse_pressed_pos = Some(position);
         } else if state == ElementState::Released {
             if let Some(prev_position) = self.mouse_pressed_pos.take() {
                 let mut dx = position.X - prev_position.X;
                 let mut dy = position.Y - prev_position.Y;
-                let mut dx_abs = FloatOrd(dx.abs());
-                let mut dy_abs = FloatOrd(dy.abs());
-                if dx_abs < *MIN_DRAG_MOUSE_MOVE && dy_abs < *MIN_DRAG_MOUSE_MOVE {
+                if FloatOrd(dx.abs()) < *MIN_DRAG_MOUSE_MOVE
+                    && FloatOrd(dy.abs()) < *MIN_DRAG_MOUSE_MOVE
+                {
+                    // Click or tap: swipe towards clicked side of the board
                     let size = self.visual().Size()?;
                     let cx = size.X / 2.;
                     let cy = size.Y / 2.;
                     dx = position.X - cx;
                     dy = position.Y - cy;
-                    dx_abs = FloatOrd(dx.abs());
-                    dy_abs = FloatOrd(dy.abs());
-                }
-                if dx_abs > dy_abs {
-                    if dx.is_sign_positive() {
-                        self.swipe(Side::Right)?;
-                    } else {
-                        self.swipe(Side::Left)?;
-                    }
-                } else {
-                    if dy.is_sign_positive() {
-                        self.swipe(Side::Down)?;
-                    } else {
-                        self.swipe(Side::Up)?;
-                    }
                 }
+                self.swipe(Self::side_by_direction(dx, dy))?;
             }
         }
         Ok(true)
@@ -184,20 +336,98 @@ impl Panel for GameFieldPanel {
     }
 
     fn on_init(&mut self) -> windows::Result<()> {
+        set_accessible(self.id, &self.root, AccessibleRole::Grid, "Game board");
+        self.score.notify()?;
+        self.can_undo.notify()?;
+        self.aspect_ratio.notify()?;
         self.init_board()
     }
 
+    fn on_close(&mut self) -> windows::Result<()> {
+        remove_accessible(self.id);
+        self.stop_auto_play();
+        self.stop_clock();
+        self.save_game();
+        self.game_board_container.Children()?.RemoveAll()?;
+        self.game_board_tiles.clear();
+        self.removed_tiles.clear();
+        self.tile_shapes.clear();
+        self.tile_text_layouts.clear();
+        self.tile_text_surfaces.clear();
+        self.tile_shadow_mask = None;
+        self.hint_visual = None;
+        Ok(())
+    }
+
     fn on_idle(&mut self) -> windows::Result<()> {
+        // Event loop wakes up when resizing stops
+        if is_resizing() {
+            Ok(())
+        } else {
+            self.set_text_scale(self.board_scale)
+        }
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        if timer_id == self.id && self.auto_play {
+            if let Some(side) = ai::best_move(self.field()) {
+                self.swipe(side)?;
+            } else {
+                self.set_auto_play(false)?;
+            }
+        } else if timer_id == self.clock_timer_id {
+            if self.time_left() == Some(Duration::from_secs(0)) {
+                self.time_out()?;
+            }
+            send_panel_event(self.id, GameFieldPanelEvent::TimeLeftChanged)?;
+        }
         Ok(())
     }
 
-    fn on_panel_event(&mut self, _panel_event: &mut PanelEvent) -> windows::Result<()> {
+    fn on_focus_changed(&mut self, _focused: bool) -> windows::Result<()> {
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> windows::Result<()> {
+        self.release_tile_texts()
+    }
+
+    fn on_resume(&mut self) -> windows::Result<()> {
+        self.redraw_tile_texts()
+    }
+
+    fn on_device_lost(&mut self) -> windows::Result<()> {
+        self.canvas_device = canvas_device();
+        self.composition_graphics_device = composition_graphics_device();
+        self.tile_text_layouts.clear();
+        self.tile_text_surfaces.clear();
+        self.tile_shadow_mask = None;
+        self.init_board()
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        if let Some(AnimationBatchCompleted(batch)) =
+            AnimationBatchHandle(self.id).extract_event(panel_event)
+        {
+            // Earlier batches may complete after the new one started
+            if batch == self.animation_batch && self.animating {
+                self.animating = false;
+                send_panel_event(self.id, GameFieldPanelEvent::AnimationFinished)?;
+                self.drain_swipe_queue()?;
+            }
+        }
         Ok(())
     }
 }
 
 impl GameFieldPanel {
-    pub fn new() -> windows::Result<Self> {
+    pub fn new(
+        board_size: usize,
+        game_mode: GameMode,
+        challenge: bool,
+        merge_rule: MergeRuleKind,
+        spawn_policy: SpawnPolicy,
+    ) -> windows::Result<Self> {
         let compositor = compositor().clone();
         let root = compositor.CreateSpriteVisual()?;
         root.SetOffset(Vector3 {
@@ -222,10 +452,15 @@ impl GameFieldPanel {
         //    Array2::from_shape_vec((4, 3), vec![2, 4, 4, 2, 2, 4, 0, 2, 2, 0, 0, 2]).unwrap();
         //let mut field = Field::from_array(array);
 
-        let (field, score) = Self::reset_field_and_score();
+        let game = Self::load_game()
+            .unwrap_or_else(|| Self::new_game(board_size, challenge, merge_rule, spawn_policy));
+        let id = get_next_id();
+        let score = Property::new(id, game.score());
+        let can_undo = Property::new(id, game.can_undo());
+        let aspect_ratio = Property::new(id, Self::board_aspect_ratio(game.field()));
 
         Ok(Self {
-            id: get_next_id(),
+            id,
             compositor,
             canvas_device: canvas_device().clone(),
             composition_graphics_device: composition_graphics_device().clone(),
@@ -235,10 +470,38 @@ impl GameFieldPanel {
             removed_tiles: Vec::new(),
             tile_shapes: HashMap::new(),
             tile_text_layouts: HashMap::new(),
-            field,
-            score,
+            tile_text_surfaces: HashMap::new(),
+            tile_shadow_mask: None,
+            text_scale: 1.,
+            board_scale: 1.,
+            game,
+            board_size,
+            challenge,
+            merge_rule,
+            spawn_policy,
+            hint_visual: None,
+            score_popup_visual: None,
+            auto_play: false,
+            dark_mode: false,
+            number_format: NumberFormat::default(),
+            animation_batch: 0,
+            animating: false,
+            swipe_queue: VecDeque::new(),
+            fast_forward: false,
+            skip_animations: false,
+            game_mode,
+            clock_timer_id: get_next_id(),
+            deadline: None,
+            timed_out: false,
+            score_recorded: false,
+            paused: false,
+            paused_time_left: None,
+            last_move: None,
             mouse_pos: None,
             mouse_pressed_pos: None,
+            score,
+            can_undo,
+            aspect_ratio,
         })
     }
 
@@ -246,55 +509,565 @@ impl GameFieldPanel {
         GameFieldHandle { id: self.id }
     }
 
+    fn side_by_direction(dx: f32, dy: f32) -> Side {
+        if dx.abs() > dy.abs() {
+            if dx.is_sign_positive() {
+                Side::Right
+            } else {
+                Side::Left
+            }
+        } else if dy.is_sign_positive() {
+            Side::Down
+        } else {
+            Side::Up
+        }
+    }
+
     pub fn get_score(&self) -> u32 {
-        self.score
+        self.game.score()
+    }
+
+    pub fn get_moves(&self) -> u32 {
+        self.game.moves()
     }
 
+    pub fn get_moves_per_minute(&self) -> Option<f32> {
+        self.game.moves_per_minute()
+    }
+
+    pub fn get_longest_chain(&self) -> u32 {
+        self.game.longest_chain()
+    }
+
+    pub fn score_property(&self) -> PropertyHandle<u32> {
+        self.score.handle()
+    }
+
+    pub fn can_undo_property(&self) -> PropertyHandle<bool> {
+        self.can_undo.handle()
+    }
+
+    pub fn get_aspect_ratio(&self) -> f32 {
+        *self.aspect_ratio.get()
+    }
+
+    pub fn aspect_ratio_property(&self) -> PropertyHandle<f32> {
+        self.aspect_ratio.handle()
+    }
+
+    // Updates bound properties before owner handles the change
+    fn notify_changed(&mut self) -> windows::Result<()> {
+        self.score.set(self.game.score())?;
+        self.can_undo.set(self.can_undo())?;
+        send_panel_event(self.id, GameFieldPanelEvent::Changed)
+    }
+
+    fn field(&self) -> &Field {
+        self.game.field()
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animating
+    }
+
+    pub fn set_fast_forward(&mut self, fast_forward: bool) {
+        self.fast_forward = fast_forward;
+    }
+
+    pub fn set_number_format(&mut self, number_format: NumberFormat) -> windows::Result<()> {
+        if number_format != self.number_format {
+            self.number_format = number_format;
+            self.tile_text_layouts.clear();
+            self.redraw_tile_texts()?;
+        }
+        Ok(())
+    }
+
+    // Swipe during animation is queued to avoid overlapping tile animations
     pub fn swipe(&mut self, side: Side) -> windows::Result<()> {
-        if self.field.can_swipe(side) {
-            self.score += self.field.swipe(side);
-            self.field.append_tile();
-            self.field.append_tile();
+        if self.animating {
+            if self.swipe_queue.len() < MAX_QUEUED_SWIPES {
+                self.swipe_queue.push_back(side);
+            }
+            Ok(())
+        } else {
+            self.do_swipe(side)
+        }
+    }
+
+    fn drain_swipe_queue(&mut self) -> windows::Result<()> {
+        while let Some(side) = self.swipe_queue.pop_front() {
+            self.skip_animations = self.fast_forward && !self.swipe_queue.is_empty();
+            let result = self.do_swipe(side);
+            self.skip_animations = false;
+            result?;
+            // Swipe which doesn't move tiles starts no animation, so continue with the next one
+            if self.animating {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn do_swipe(&mut self, side: Side) -> windows::Result<()> {
+        if self.timed_out || self.paused {
+            return Ok(());
+        }
+        if let Some(result) = self.game.swipe(side) {
+            if result.merges > 0 {
+                play_sound(SoundId::Merge);
+            }
+            self.start_clock();
+            self.count_play_time();
             self.animate_board()?;
-            send_panel_event(self.id, GameFieldPanelEvent::Changed)?;
+            self.show_score_popup(result.score)?;
+            self.save_game();
+            self.notify_changed()?;
+            if self.game.has_won() && !self.game.is_win_announced() {
+                self.stop_auto_play();
+                self.swipe_queue.clear();
+                send_panel_event(self.id, GameFieldPanelEvent::Won)?;
+            } else if self.field().is_game_over() {
+                self.stop_auto_play();
+                self.stop_clock();
+                self.swipe_queue.clear();
+                self.record_score()?;
+                play_sound(SoundId::GameOver);
+                send_panel_event(self.id, GameFieldPanelEvent::GameOver)?;
+            }
+        } else {
+            play_sound(SoundId::InvalidMove);
         }
         Ok(())
     }
 
+    fn count_play_time(&mut self) {
+        let now = Instant::now();
+        if let Some(last_move) = self.last_move.replace(now) {
+            self.game.add_play_time((now - last_move).min(MAX_MOVE_GAP));
+        }
+    }
+
     pub fn undo(&mut self) -> windows::Result<()> {
-        if self.field.can_undo() {
-            self.score -= self.field.undo();
+        if !self.timed_out && self.game.undo() {
+            self.animate_board()?;
+            self.game.hold_all(); // origins are used for animation only
+            self.save_game();
+            self.notify_changed()?;
+        }
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> windows::Result<()> {
+        if !self.timed_out && self.game.redo() {
             self.animate_board()?;
-            self.field.hold_all(); // do not allow undo undo
-            send_panel_event(self.id, GameFieldPanelEvent::Changed)?;
+            self.save_game();
+            self.notify_changed()?;
         }
         Ok(())
     }
 
-    fn reset_field_and_score() -> (Field, u32) {
-        let mut field = Field::new(4, 4);
-        field.append_tile();
-        field.append_tile();
-        field.hold_all();
-        (field, 0)
+    fn new_game(
+        board_size: usize,
+        challenge: bool,
+        merge_rule: MergeRuleKind,
+        spawn_policy: SpawnPolicy,
+    ) -> Game {
+        let mut game = Game::with_rule(board_size, board_size, merge_rule, challenge);
+        game.set_spawn_policy(spawn_policy);
+        game
+    }
+
+    // Saved game which is not over yet can be continued
+    pub fn has_saved_game() -> bool {
+        matches!(Self::load_game(), Some(game) if !game.field().is_game_over())
+    }
+
+    fn load_game() -> Option<Game> {
+        crate::storage::load(SAVE_FILE_NAME).and_then(|s| Game::deserialize(&s).ok())
+    }
+
+    // Failed autosave doesn't stop the game, the next move tries again
+    fn save_game(&self) {
+        if let Err(e) = crate::storage::save(SAVE_FILE_NAME, &self.game.serialize()) {
+            tracing::warn!(error = %e, "failed to save game");
+        }
+    }
+
+    fn record_score(&mut self) -> windows::Result<()> {
+        if self.score_recorded {
+            return Ok(());
+        }
+        self.score_recorded = true;
+        let pos = high_scores::record(
+            self.game.score(),
+            self.field().width(),
+            self.field().height(),
+            self.game_mode,
+        )
+        .map_err(|e| winrt_error(e)())?;
+        if pos == Some(0) {
+            show_toast("New best score!", TOAST_DURATION)?;
+            send_panel_event(self.id, GameFieldPanelEvent::NewBestScore)?;
+        }
+        Ok(())
     }
 
     pub fn reset(&mut self) -> windows::Result<()> {
-        let (field, score) = Self::reset_field_and_score();
-        self.field = field;
-        self.score = score;
-        self.animate_board()?;
-        send_panel_event(self.id, GameFieldPanelEvent::Changed)?;
+        // finished games are recorded on game over
+        if !self.field().is_game_over() && !self.timed_out {
+            self.record_score()?;
+        }
+        self.stop_clock();
+        self.timed_out = false;
+        self.score_recorded = false;
+        self.last_move = None;
+        let resized =
+            self.field().width() != self.board_size || self.field().height() != self.board_size;
+        let rule_changed = self.field().rule() != self.merge_rule;
+        self.game = Self::new_game(
+            self.board_size,
+            self.challenge,
+            self.merge_rule,
+            self.spawn_policy,
+        );
+        if rule_changed {
+            // same levels are labeled differently under other rule
+            self.tile_text_layouts.clear();
+            self.tile_text_surfaces.clear();
+            self.init_board()?;
+        } else if resized {
+            self.init_board()?;
+        } else {
+            self.animate_board()?;
+        }
+        self.save_game();
+        self.notify_changed()?;
+        Ok(())
+    }
+
+    // Board size change keeps the game going with its tiles migrated to the new board.
+    // New game starts if the game is finished or the tiles don't fit
+    pub fn set_board_size(&mut self, board_size: usize) -> windows::Result<()> {
+        if board_size == self.board_size {
+            return Ok(());
+        }
+        self.board_size = board_size;
+        if self.field().is_game_over() || self.timed_out {
+            return self.reset();
+        }
+        match self.game.resize(board_size, board_size) {
+            Ok(moves) => {
+                self.migrate_board(&moves)?;
+                self.save_game();
+                self.notify_changed()
+            }
+            Err(e) => {
+                show_toast(format!("New game started: {}", e), TOAST_DURATION)?;
+                self.reset()
+            }
+        }
+    }
+
+    // Challenge mode change takes effect from the next game
+    pub fn set_challenge(&mut self, challenge: bool) {
+        self.challenge = challenge;
+    }
+
+    // Spawn policy change takes effect from the next game, the current one keeps its own
+    pub fn set_spawn_policy(&mut self, spawn_policy: SpawnPolicy) {
+        self.spawn_policy = spawn_policy;
+    }
+
+    // Merge rule change starts new game
+    pub fn set_merge_rule(&mut self, merge_rule: MergeRuleKind) -> windows::Result<()> {
+        if merge_rule != self.merge_rule {
+            self.merge_rule = merge_rule;
+            self.reset()?;
+        }
+        Ok(())
+    }
+
+    // Game mode change starts new game, the current one is recorded in its mode's table
+    pub fn set_game_mode(&mut self, game_mode: GameMode) -> windows::Result<()> {
+        if game_mode != self.game_mode {
+            self.reset()?;
+            self.game_mode = game_mode;
+            send_panel_event(self.id, GameFieldPanelEvent::TimeLeftChanged)?;
+        }
         Ok(())
     }
 
+    // None in classic mode. Full time limit is returned until the clock starts
+    pub fn time_left(&self) -> Option<Duration> {
+        let time_limit = self.game_mode.time_limit()?;
+        if self.timed_out {
+            Some(Duration::from_secs(0))
+        } else if let Some(time_left) = self.paused_time_left {
+            Some(time_left)
+        } else if let Some(deadline) = self.deadline {
+            Some(deadline.saturating_duration_since(Instant::now()))
+        } else {
+            Some(time_limit)
+        }
+    }
+
+    // Called after each successful move
+    fn start_clock(&mut self) {
+        if let Some(time_limit) = self.game_mode.time_limit() {
+            if self.deadline.is_none() || self.game_mode == GameMode::MoveCountdown {
+                self.deadline = Some(Instant::now() + time_limit);
+                // Ticks are aligned to the deadline to show whole seconds
+                set_timer(self.clock_timer_id, CLOCK_PERIOD);
+            }
+        }
+    }
+
+    fn stop_clock(&mut self) {
+        self.deadline = None;
+        self.paused_time_left = None;
+        kill_timer(self.clock_timer_id);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) -> windows::Result<()> {
+        if paused == self.paused {
+            return Ok(());
+        }
+        self.paused = paused;
+        if paused {
+            self.stop_auto_play();
+            self.swipe_queue.clear();
+            if let Some(deadline) = self.deadline {
+                self.stop_clock();
+                self.paused_time_left = Some(deadline.saturating_duration_since(Instant::now()));
+            }
+        } else if let Some(time_left) = self.paused_time_left.take() {
+            self.deadline = Some(Instant::now() + time_left);
+            set_timer(self.clock_timer_id, CLOCK_PERIOD);
+        }
+        self.notify_changed()
+    }
+
+    fn time_out(&mut self) -> windows::Result<()> {
+        self.stop_clock();
+        self.stop_auto_play();
+        self.swipe_queue.clear();
+        self.timed_out = true;
+        self.record_score()?;
+        play_sound(SoundId::GameOver);
+        self.notify_changed()?;
+        send_panel_event(self.id, GameFieldPanelEvent::GameOver)
+    }
+
     pub fn can_undo(&self) -> bool {
-        self.field.can_undo()
+        !self.timed_out && self.game.can_undo()
+    }
+
+    pub fn is_auto_play(&self) -> bool {
+        self.auto_play
+    }
+
+    pub fn set_auto_play(&mut self, auto_play: bool) -> windows::Result<()> {
+        if auto_play != self.auto_play {
+            if auto_play {
+                self.auto_play = true;
+                set_timer(self.id, AUTO_PLAY_PERIOD);
+            } else {
+                self.stop_auto_play();
+            }
+            self.notify_changed()?;
+        }
+        Ok(())
+    }
+
+    fn stop_auto_play(&mut self) {
+        self.auto_play = false;
+        kill_timer(self.id);
+    }
+
+    // Flash an arrow over the board pointing to the move suggested by AI
+    pub fn show_hint(&mut self) -> windows::Result<()> {
+        if let Some(visual) = self.hint_visual.take() {
+            self.game_board_container.Children()?.Remove(visual)?;
+        }
+        let arrow = match ai::best_move(self.field()) {
+            Some(Side::Up) => "↑",
+            Some(Side::Down) => "↓",
+            Some(Side::Left) => "←",
+            Some(Side::Right) => "→",
+            None => return Ok(()),
+        };
+        let board_size = self.get_board_visual_size();
+        let surface = self.composition_graphics_device.CreateDrawingSurface(
+            Size {
+                Width: board_size.X,
+                Height: board_size.Y,
+            },
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXAlphaMode::Premultiplied,
+        )?;
+        let ds = CanvasComposition::CreateDrawingSession(&surface)?;
+        ds.Clear(Colors::Transparent()?)?;
+        let text_format = CanvasTextFormat::new()?;
+        text_format.SetFontFamily("Arial")?;
+        text_format.SetFontSize(board_size.Y / 2.)?;
+        let text_layout = CanvasTextLayout::Create(
+            &self.canvas_device,
+            arrow,
+            text_format,
+            board_size.X,
+            board_size.Y,
+        )?;
+        text_layout.SetVerticalAlignment(CanvasVerticalAlignment::Center)?;
+        text_layout.SetHorizontalAlignment(CanvasHorizontalAlignment::Center)?;
+        ds.DrawTextLayoutAtCoordsWithColor(text_layout, 0., 0., Colors::White()?)?;
+
+        let brush = self.compositor.CreateSurfaceBrush()?;
+        brush.SetSurface(surface)?;
+        let hint = self.compositor.CreateSpriteVisual()?;
+        hint.SetBrush(brush)?;
+        hint.SetSize(board_size)?;
+        hint.SetOpacity(0.)?;
+        self.game_board_container.Children()?.InsertAtTop(&hint)?;
+
+        let animation = self.compositor.CreateScalarKeyFrameAnimation()?;
+        animation.InsertKeyFrame(0.0, 0.)?;
+        animation.InsertKeyFrame(0.3, 0.8)?;
+        animation.InsertKeyFrame(1.0, 0.)?;
+        animation.SetDuration(HINT_DURATION)?;
+        hint.StartAnimation("Opacity", animation)?;
+        self.hint_visual = Some(hint.into());
+        Ok(())
+    }
+
+    // Points of the swipe rise over the merged tiles and fade out. The popup appears when
+    // the moving tiles reach them, together with the merge "pop"
+    fn show_score_popup(&mut self, points: u32) -> windows::Result<()> {
+        if let Some(visual) = self.score_popup_visual.take() {
+            self.game_board_container.Children()?.Remove(visual)?;
+        }
+        let settings = self.current_animation_settings();
+        if points == 0 || !settings.enabled {
+            return Ok(());
+        }
+        let mut merged = Vec::new();
+        for x in 0..self.field().width() {
+            for y in 0..self.field().height() {
+                if let Some(Origin::Merged(..)) = self.field().get(x, y).map(Origin::from) {
+                    merged.push((x as f32 + 0.5, y as f32 + 0.5));
+                }
+            }
+        }
+        if merged.is_empty() {
+            return Ok(());
+        }
+        let count = merged.len() as f32;
+        let center = Vector2 {
+            X: TILE_RECT_SIZE.X * merged.iter().map(|(x, _)| x).sum::<f32>() / count,
+            Y: TILE_RECT_SIZE.Y * merged.iter().map(|(_, y)| y).sum::<f32>() / count,
+        } + &*TILE_OFFSET;
+        let size = Vector2 {
+            X: TILE_RECT_SIZE.X * 2.,
+            Y: TILE_RECT_SIZE.Y,
+        };
+
+        let surface = self.composition_graphics_device.CreateDrawingSurface(
+            Size {
+                Width: (size.X * self.text_scale).ceil(),
+                Height: (size.Y * self.text_scale).ceil(),
+            },
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXAlphaMode::Premultiplied,
+        )?;
+        let ds = CanvasComposition::CreateDrawingSession(&surface)?;
+        ds.Clear(Colors::Transparent()?)?;
+        let text_format = CanvasTextFormat::new()?;
+        text_format.SetFontFamily("Arial")?;
+        text_format.SetFontSize(size.Y * 0.4 * self.text_scale)?;
+        let text_layout = CanvasTextLayout::Create(
+            &self.canvas_device,
+            format!("+{}", self.number_format.format(points as u64)),
+            text_format,
+            size.X * self.text_scale,
+            size.Y * self.text_scale,
+        )?;
+        text_layout.SetVerticalAlignment(CanvasVerticalAlignment::Center)?;
+        text_layout.SetHorizontalAlignment(CanvasHorizontalAlignment::Center)?;
+        ds.DrawTextLayoutAtCoordsWithColor(text_layout, 0., 0., Colors::White()?)?;
+
+        let brush = self.compositor.CreateSurfaceBrush()?;
+        brush.SetSurface(surface)?;
+        let popup = self.compositor.CreateSpriteVisual()?;
+        popup.SetBrush(brush)?;
+        popup.SetSize(&size)?;
+        popup.SetOpacity(0.)?;
+        self.game_board_container.Children()?.InsertAtTop(&popup)?;
+
+        let start = Vector3 {
+            X: center.X - size.X / 2.,
+            Y: center.Y - size.Y / 2.,
+            Z: 0.,
+        };
+        let end = Vector3 {
+            Y: start.Y - TILE_RECT_SIZE.Y / 2.,
+            ..start
+        };
+        let offset_animation = self.compositor.CreateVector3KeyFrameAnimation()?;
+        offset_animation.InsertKeyFrame(0.0, start)?;
+        offset_animation.InsertKeyFrameWithEasingFunction(1.0, end, settings.easing_function()?)?;
+        offset_animation.SetDuration(SCORE_POPUP_DURATION)?;
+        offset_animation.SetDelayTime(settings.time_span())?;
+        offset_animation.SetDelayBehavior(AnimationDelayBehavior::SetInitialValueBeforeDelay)?;
+        let opacity_animation = self.compositor.CreateScalarKeyFrameAnimation()?;
+        opacity_animation.InsertKeyFrame(0.0, 1.)?;
+        opacity_animation.InsertKeyFrame(0.5, 1.)?;
+        opacity_animation.InsertKeyFrame(1.0, 0.)?;
+        opacity_animation.SetDuration(SCORE_POPUP_DURATION)?;
+        opacity_animation.SetDelayTime(settings.time_span())?;
+        popup.StartAnimation("Offset", offset_animation)?;
+        popup.StartAnimation("Opacity", opacity_animation)?;
+        self.score_popup_visual = Some(popup.into());
+        Ok(())
+    }
+
+    pub fn get_target(&self) -> u32 {
+        self.game.target()
+    }
+
+    // Suppress further win events for current game
+    pub fn keep_playing(&mut self) -> windows::Result<()> {
+        self.game.keep_playing();
+        self.save_game();
+        Ok(())
+    }
+
+    // Switches tile palette, tiles are recreated with new colors
+    pub fn set_dark_mode(&mut self, dark_mode: bool) -> windows::Result<()> {
+        if dark_mode != self.dark_mode {
+            self.dark_mode = dark_mode;
+            // Theme is switched together with palette, its corners and shadow too
+            self.tile_shapes.clear();
+            self.tile_shadow_mask = None;
+            self.init_board()?;
+        }
+        Ok(())
+    }
+
+    fn tile_corner_radius() -> Vector2 {
+        let radius = TILE_SIZE.X.min(TILE_SIZE.Y) * theme().corner_radius;
+        Vector2 {
+            X: radius,
+            Y: radius,
+        }
     }
 
     pub fn create_tile_shape(&self, color: Color) -> windows::Result<CompositionShape> {
         let round_rect_geometry = self.compositor.CreateRoundedRectangleGeometry()?;
-        round_rect_geometry.SetCornerRadius(&*TILE_CORNER_RADIUS)?;
+        round_rect_geometry.SetCornerRadius(Self::tile_corner_radius())?;
         round_rect_geometry.SetSize(&*TILE_SIZE)?;
         let brush = self.compositor.CreateColorBrushWithColor(color)?;
         let round_rect = self
@@ -308,22 +1081,28 @@ impl GameFieldPanel {
 
     pub fn create_background_visual(&self) -> windows::Result<ShapeVisual> {
         let background_rect_geometry = self.compositor.CreateRoundedRectangleGeometry()?;
-        background_rect_geometry.SetCornerRadius(&*TILE_CORNER_RADIUS)?;
-        background_rect_geometry.SetSize(self.get_board_size())?;
-        let brush = self
-            .compositor
-            .CreateColorBrushWithColor(Colors::DimGray()?)?;
+        background_rect_geometry.SetCornerRadius(Self::tile_corner_radius())?;
+        background_rect_geometry.SetSize(self.get_board_visual_size())?;
+        let (board_color, cell_color) = if self.dark_mode {
+            (
+                ColorHelper::FromArgb(255, 40, 40, 44)?,
+                ColorHelper::FromArgb(255, 62, 62, 68)?,
+            )
+        } else {
+            (Colors::DimGray()?, Colors::Gray()?)
+        };
+        let brush = self.compositor.CreateColorBrushWithColor(board_color)?;
         let background_rect = self
             .compositor
             .CreateSpriteShapeWithGeometry(background_rect_geometry)?;
         background_rect.SetFillBrush(brush)?;
         background_rect.SetOffset(Vector2 { X: 0., Y: 0. })?;
         let background = self.compositor.CreateShapeVisual()?;
-        background.SetSize(self.get_board_size())?;
+        background.SetSize(self.get_board_visual_size())?;
         background.Shapes()?.Append(background_rect)?;
-        for x in 0..self.field.width() {
-            for y in 0..self.field.height() {
-                let shape = self.create_tile_shape(Colors::Gray()?)?;
+        for x in 0..self.field().width() {
+            for y in 0..self.field().height() {
+                let shape = self.create_tile_shape(cell_color.clone())?;
                 let mut offset = shape.Offset()?;
                 offset.X += TILE_RECT_SIZE.X * x as f32 + TILE_OFFSET.X;
                 offset.Y += TILE_RECT_SIZE.Y * y as f32 + TILE_OFFSET.Y;
@@ -353,63 +1132,146 @@ impl GameFieldPanel {
             X: scale_factor,
             Y: scale_factor,
             Z: 1.0,
-        })
+        })?;
+        self.board_scale = scale_factor;
+        if is_resizing() {
+            Ok(())
+        } else {
+            self.set_text_scale(scale_factor)
+        }
+    }
+
+    // Scale is rounded up to quarter of octave, so resizing window doesn't re-render text
+    // on every step
+    fn set_text_scale(&mut self, board_scale: f32) -> windows::Result<()> {
+        if board_scale.is_nan() || board_scale <= 0. {
+            return Ok(());
+        }
+        let text_scale = 2f32.powf((board_scale.log2() * 4.).ceil() / 4.);
+        if text_scale == self.text_scale {
+            return Ok(());
+        }
+        self.text_scale = text_scale;
+        self.tile_text_layouts.clear();
+        self.redraw_tile_texts()
     }
 
-    pub fn get_tile_shape(&mut self, n: u32) -> windows::Result<CompositionShape> {
-        if let Some(shape) = self.tile_shapes.get(&n) {
+    fn redraw_tile_texts(&mut self) -> windows::Result<()> {
+        let size = self.get_tile_text_surface_size();
+        let surfaces: Vec<((u32, bool), CompositionDrawingSurface)> = self
+            .tile_text_surfaces
+            .iter()
+            .map(|(key, (surface, _))| (*key, surface.clone()))
+            .collect();
+        for ((level, dark_mode), surface) in surfaces {
+            surface.Resize(SizeInt32 {
+                Width: size.Width as i32,
+                Height: size.Height as i32,
+            })?;
+            self.draw_tile_text(&surface, level, dark_mode)?;
+        }
+        Ok(())
+    }
+
+    // Surfaces are shrunk instead of dropped because tile brushes keep referencing them
+    fn release_tile_texts(&mut self) -> windows::Result<()> {
+        if let Some(visual) = self.hint_visual.take() {
+            self.game_board_container.Children()?.Remove(visual)?;
+        }
+        for (surface, _) in self.tile_text_surfaces.values() {
+            surface.Resize(SizeInt32 {
+                Width: 0,
+                Height: 0,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn get_tile_text_surface_size(&self) -> Size {
+        Size {
+            Width: (TILE_RECT_SIZE.X * self.text_scale).ceil(),
+            Height: (TILE_RECT_SIZE.Y * self.text_scale).ceil(),
+        }
+    }
+
+    pub fn get_tile_shape(&mut self, level: u32) -> windows::Result<CompositionShape> {
+        if let Some(shape) = self.tile_shapes.get(&level) {
             Ok(shape.clone())
         } else {
-            let shape = self.create_tile_shape(Self::get_tile_color(n)?)?;
-            self.tile_shapes.insert(n, shape.clone());
+            let shape = self.create_tile_shape(Self::get_tile_color(level, self.dark_mode)?)?;
+            self.tile_shapes.insert(level, shape.clone());
             Ok(shape)
         }
     }
 
-    pub fn get_tile_text_layout(&mut self, n: u32) -> windows::Result<CanvasTextLayout> {
-        if let Some(text_layout) = self.tile_text_layouts.get(&n) {
+    pub fn get_tile_text_layout(&mut self, level: u32) -> windows::Result<CanvasTextLayout> {
+        if let Some(text_layout) = self.tile_text_layouts.get(&level) {
             Ok(text_layout.clone())
         } else {
-            let text_string: String = n.to_string();
+            let text_string: String = if level == BLOCKER_LEVEL {
+                "✖".to_string()
+            } else {
+                let value = self.field().rule().rule().value(level);
+                self.number_format.format(value as u64)
+            };
             let text_format = CanvasTextFormat::new()?;
             text_format.SetFontFamily("Arial")?;
-            text_format.SetFontSize(Self::get_tile_font_size(n))?;
+            text_format.SetFontSize(
+                Self::get_tile_font_size(text_string.chars().count()) * self.text_scale,
+            )?;
 
+            let size = self.get_tile_text_surface_size();
             let text_layout = CanvasTextLayout::Create(
                 &self.canvas_device,
                 text_string,
                 text_format,
-                TILE_RECT_SIZE.X,
-                TILE_RECT_SIZE.Y,
+                size.Width,
+                size.Height,
             )?;
             text_layout.SetVerticalAlignment(CanvasVerticalAlignment::Center)?;
             text_layout.SetHorizontalAlignment(CanvasHorizontalAlignment::Center)?;
-            self.tile_text_layouts.insert(n, text_layout.clone());
+            self.tile_text_layouts.insert(level, text_layout.clone());
             Ok(text_layout)
         }
     }
 
-    fn create_tile_visual(&mut self, x: usize, y: usize, n: u32) -> windows::Result<Visual> {
-        let surface = self.composition_graphics_device.CreateDrawingSurface(
-            Size {
-                Width: TILE_RECT_SIZE.X,
-                Height: TILE_RECT_SIZE.Y,
-            },
-            DirectXPixelFormat::B8G8R8A8UIntNormalized,
-            DirectXAlphaMode::Premultiplied,
-        )?;
-        let ds = CanvasComposition::CreateDrawingSession(&surface)?;
-        ds.Clear(Colors::Transparent()?)?;
+    fn get_tile_text_brush(&mut self, level: u32) -> windows::Result<CompositionSurfaceBrush> {
+        let key = (level, self.dark_mode);
+        if let Some((_, brush)) = self.tile_text_surfaces.get(&key) {
+            Ok(brush.clone())
+        } else {
+            let surface = self.composition_graphics_device.CreateDrawingSurface(
+                self.get_tile_text_surface_size(),
+                DirectXPixelFormat::B8G8R8A8UIntNormalized,
+                DirectXAlphaMode::Premultiplied,
+            )?;
+            self.draw_tile_text(&surface, level, self.dark_mode)?;
+            let brush = self.compositor.CreateSurfaceBrush()?;
+            brush.SetSurface(&surface)?;
+            self.tile_text_surfaces
+                .insert(key, (surface, brush.clone()));
+            Ok(brush)
+        }
+    }
 
+    fn draw_tile_text(
+        &mut self,
+        surface: &CompositionDrawingSurface,
+        level: u32,
+        dark_mode: bool,
+    ) -> windows::Result<()> {
+        let ds = CanvasComposition::CreateDrawingSession(surface)?;
+        ds.Clear(Colors::Transparent()?)?;
         ds.DrawTextLayoutAtCoordsWithColor(
-            self.get_tile_text_layout(n)?,
+            self.get_tile_text_layout(level)?,
             0.,
             0.,
-            Self::get_tile_font_color(n)?,
-        )?;
+            Self::get_tile_font_color(level, dark_mode)?,
+        )
+    }
 
-        let brush = self.compositor.CreateSurfaceBrush()?;
-        brush.SetSurface(surface)?;
+    fn create_tile_visual(&mut self, x: usize, y: usize, n: u32) -> windows::Result<Visual> {
+        let brush = self.get_tile_text_brush(n)?;
         let number = self.compositor.CreateSpriteVisual()?;
         number.SetBrush(brush)?;
         number.SetSize(&*TILE_RECT_SIZE)?;
@@ -421,6 +1283,9 @@ impl GameFieldPanel {
 
         let tile_visual = self.compositor.CreateContainerVisual()?;
         tile_visual.SetSize(&*TILE_RECT_SIZE)?;
+        if let Some(shadow) = self.create_tile_shadow()? {
+            tile_visual.Children()?.InsertAtTop(shadow)?;
+        }
         tile_visual.Children()?.InsertAtTop(tile_box)?;
         tile_visual.Children()?.InsertAtTop(number)?;
 
@@ -433,10 +1298,33 @@ impl GameFieldPanel {
             .Children()?
             .InsertAtTop(&tile_visual)?;
         let visual: Visual = tile_visual.into();
-        Self::animated_appear_tile(&visual)?;
+        Self::animated_appear_tile(&visual, &self.current_animation_settings())?;
         Ok(visual)
     }
 
+    fn create_tile_shadow(&mut self) -> windows::Result<Option<SpriteVisual>> {
+        let style = match theme().shadow {
+            Some(style) => style,
+            None => return Ok(None),
+        };
+        let mask = match &self.tile_shadow_mask {
+            Some(mask) => mask.clone(),
+            None => {
+                let mask = create_rounded_mask(&*TILE_SIZE, Self::tile_corner_radius().X)?;
+                self.tile_shadow_mask = Some(mask.clone());
+                mask
+            }
+        };
+        let scale = TILE_SIZE.X / SHADOW_REFERENCE_TILE_SIZE;
+        let shadow = create_shadow_visual(&*TILE_SIZE, &mask, &style, scale)?;
+        shadow.SetOffset(Vector3 {
+            X: TILE_OFFSET.X,
+            Y: TILE_OFFSET.Y,
+            Z: 0.,
+        })?;
+        Ok(Some(shadow))
+    }
+
     fn hold_tile_visual(&mut self, x: usize, y: usize, n: u32) -> windows::Result<Visual> {
         if let Some((visual, visual_n)) = self.game_board_tiles.remove(&(x, y)) {
             if n == visual_n {
@@ -456,6 +1344,7 @@ impl GameFieldPanel {
         from_y: usize,
         x: usize,
         y: usize,
+        settings: &AnimationSettings,
     ) -> windows::Result<()> {
         let compositor = visual.Compositor()?;
         let animation = compositor.CreateVector3KeyFrameAnimation()?;
@@ -469,13 +1358,20 @@ impl GameFieldPanel {
             Y: TILE_RECT_SIZE.Y * y as f32 + TILE_OFFSET.Y,
             Z: 0.,
         };
+        if !settings.enabled {
+            return visual.SetOffset(animate_to);
+        }
         animation.InsertKeyFrame(0.0, animate_from)?;
-        animation.InsertKeyFrame(1.0, animate_to)?;
+        animation.InsertKeyFrameWithEasingFunction(1.0, animate_to, settings.easing_function()?)?;
+        animation.SetDuration(settings.time_span())?;
         visual.StartAnimation("Offset", animation)?;
         Ok(())
     }
 
-    fn animated_appear_tile(visual: &Visual) -> windows::Result<()> {
+    fn animated_appear_tile(visual: &Visual, settings: &AnimationSettings) -> windows::Result<()> {
+        if !settings.enabled {
+            return Ok(());
+        }
         let compositor = visual.Compositor()?;
 
         let animation = compositor.CreateVector3KeyFrameAnimation()?;
@@ -490,15 +1386,51 @@ impl GameFieldPanel {
             Z: 0.,
         };
         animation.InsertKeyFrame(0.0, animate_from)?;
-        animation.InsertKeyFrame(1.0, animate_to)?;
+        animation.InsertKeyFrameWithEasingFunction(1.0, animate_to, settings.easing_function()?)?;
+        animation.SetDuration(settings.time_span())?;
+        Self::set_center_point(visual)?;
+        visual.StartAnimation("Scale", animation)?;
+        Ok(())
+    }
+
+    // Merged tile appears when moving tiles reach it and briefly grows over its size
+    fn animated_merge_pop_tile(
+        visual: &Visual,
+        settings: &AnimationSettings,
+    ) -> windows::Result<()> {
+        if !settings.enabled {
+            return Ok(());
+        }
+        let compositor = visual.Compositor()?;
+        let animation = compositor.CreateVector3KeyFrameAnimation()?;
+        let scale = |s: f32| Vector3 { X: s, Y: s, Z: 1. };
+        animation.InsertKeyFrame(0.0, scale(0.))?;
+        animation.InsertKeyFrame(0.6, scale(MERGE_POP_SCALE))?;
+        animation.InsertKeyFrameWithEasingFunction(1.0, scale(1.), settings.easing_function()?)?;
+        animation.SetDuration(settings.time_span())?;
+        animation.SetDelayTime(settings.time_span())?;
+        animation.SetDelayBehavior(AnimationDelayBehavior::SetInitialValueBeforeDelay)?;
+        Self::set_center_point(visual)?;
+        visual.StartAnimation("Scale", animation)?;
+        Ok(())
+    }
+
+    // Animations are disabled for swipes which are immediately followed by queued ones
+    fn current_animation_settings(&self) -> AnimationSettings {
+        let mut settings = animation_settings();
+        if self.skip_animations {
+            settings.enabled = false;
+        }
+        settings
+    }
+
+    fn set_center_point(visual: &Visual) -> windows::Result<()> {
         let size = visual.Size()?;
         visual.SetCenterPoint(Vector3 {
             X: size.X / 2.,
             Y: size.Y / 2.,
             Z: 0.,
-        })?;
-        visual.StartAnimation("Scale", animation)?;
-        Ok(())
+        })
     }
 
     fn move_tile_visual(
@@ -510,7 +1442,14 @@ impl GameFieldPanel {
         n: u32,
     ) -> windows::Result<Visual> {
         if let Some((visual, visual_n)) = self.game_board_tiles.remove(&(from_x, from_y)) {
-            Self::animated_move_tile(&visual, from_x, from_y, x, y)?;
+            Self::animated_move_tile(
+                &visual,
+                from_x,
+                from_y,
+                x,
+                y,
+                &self.current_animation_settings(),
+            )?;
             if n == visual_n {
                 Ok(visual)
             } else {
@@ -530,7 +1469,14 @@ impl GameFieldPanel {
         y: usize,
     ) -> windows::Result<()> {
         if let Some((visual, _)) = self.game_board_tiles.remove(&(from_x, from_y)) {
-            Self::animated_move_tile(&visual, from_x, from_y, x, y)?;
+            Self::animated_move_tile(
+                &visual,
+                from_x,
+                from_y,
+                x,
+                y,
+                &self.current_animation_settings(),
+            )?;
             self.removed_tiles.push(visual);
         }
         Ok(())
@@ -547,7 +1493,9 @@ impl GameFieldPanel {
         n: u32,
     ) -> windows::Result<Visual> {
         self.move_tile_visual_then_drop(from_x2, from_y2, x, y)?;
-        self.move_tile_visual(from_x1, from_y1, x, y, n)
+        let visual = self.move_tile_visual(from_x1, from_y1, x, y, n)?;
+        Self::animated_merge_pop_tile(&visual, &self.current_animation_settings())?;
+        Ok(visual)
     }
 
     fn garbage_collect_tiles(&mut self) -> windows::Result<()> {
@@ -557,16 +1505,33 @@ impl GameFieldPanel {
         Ok(())
     }
 
-    fn get_board_size(&self) -> Vector2 {
+    fn get_board_visual_size(&self) -> Vector2 {
+        Self::board_visual_size(self.field())
+    }
+
+    fn board_visual_size(field: &Field) -> Vector2 {
         Vector2 {
-            X: self.field.width() as f32 * TILE_RECT_SIZE.X,
-            Y: self.field.height() as f32 * TILE_RECT_SIZE.Y,
+            X: field.width() as f32 * TILE_RECT_SIZE.X,
+            Y: field.height() as f32 * TILE_RECT_SIZE.Y,
         } + &*TILE_OFFSET * 2.
     }
 
+    // Board is fit into the panel together with the margin, see scale_game_board
+    fn board_aspect_ratio(field: &Field) -> f32 {
+        let size = Self::board_visual_size(field) + &*GAME_BOARD_MARGIN;
+        size.X / size.Y
+    }
+
     fn init_board(&mut self) -> windows::Result<()> {
-        self.game_board_container.SetSize(self.get_board_size())?;
+        self.aspect_ratio
+            .set(Self::board_aspect_ratio(self.field()))?;
+        self.game_board_container
+            .SetSize(self.get_board_visual_size())?;
         self.game_board_container.Children()?.RemoveAll()?;
+        self.game_board_tiles.clear();
+        self.removed_tiles.clear();
+        self.hint_visual = None;
+        self.score_popup_visual = None;
         self.game_board_container
             .Children()?
             .InsertAtBottom(self.create_background_visual()?)?;
@@ -574,13 +1539,99 @@ impl GameFieldPanel {
         self.animate_board()
     }
 
+    // Like init_board, but tile visuals are kept: tiles which stay on their cells don't move,
+    // others slide in from their old cells while the board grows or shrinks to the new size
+    fn migrate_board(&mut self, moves: &[ResizeMove]) -> windows::Result<()> {
+        let old_size = self.game_board_container.Size()?;
+        let old_scale = self.board_scale;
+        self.garbage_collect_tiles()?;
+        let children = self.game_board_container.Children()?;
+        children.RemoveAll()?;
+        self.hint_visual = None;
+        self.score_popup_visual = None;
+        self.game_board_container
+            .SetSize(self.get_board_visual_size())?;
+        self.aspect_ratio
+            .set(Self::board_aspect_ratio(self.field()))?;
+        children.InsertAtBottom(self.create_background_visual()?)?;
+        for (visual, _) in self.game_board_tiles.values() {
+            children.InsertAtTop(visual)?;
+        }
+        self.scale_game_board()?;
+        self.animated_board_resize(old_size, old_scale)?;
+        // The game keeps only held tiles, moved ones get origins outside of the board
+        // for the animation only
+        let mut field = self.field().clone();
+        for ResizeMove { from, to } in moves {
+            if let Some(mut tile) = field.get(to.0, to.1) {
+                tile.set_origin(Origin::Moved(from.0, from.1));
+                field.put(to.0, to.1, Some(tile));
+            }
+        }
+        self.animate_field(&field)
+    }
+
+    fn animated_board_resize(&self, old_size: Vector2, old_scale: f32) -> windows::Result<()> {
+        let settings = self.current_animation_settings();
+        if !settings.enabled {
+            return Ok(());
+        }
+        let size_animation = self.compositor.CreateVector2KeyFrameAnimation()?;
+        size_animation.InsertKeyFrame(0.0, old_size)?;
+        size_animation.InsertKeyFrameWithEasingFunction(
+            1.0,
+            self.game_board_container.Size()?,
+            settings.easing_function()?,
+        )?;
+        size_animation.SetDuration(settings.time_span())?;
+        self.game_board_container
+            .StartAnimation("Size", size_animation)?;
+        let scale = |s: f32| Vector3 { X: s, Y: s, Z: 1. };
+        let scale_animation = self.compositor.CreateVector3KeyFrameAnimation()?;
+        scale_animation.InsertKeyFrame(0.0, scale(old_scale))?;
+        scale_animation.InsertKeyFrameWithEasingFunction(
+            1.0,
+            scale(self.board_scale),
+            settings.easing_function()?,
+        )?;
+        scale_animation.SetDuration(settings.time_span())?;
+        self.game_board_container
+            .StartAnimation("Scale", scale_animation)
+    }
+
     fn animate_board(&mut self) -> windows::Result<()> {
+        let field = self.field().clone();
+        self.animate_field(&field)
+    }
+
+    // Tile visuals are animated from the origins of the field's tiles
+    fn animate_field(&mut self, field: &Field) -> windows::Result<()> {
+        if self.skip_animations {
+            return self.start_board_animations(field);
+        }
+        // All animations started until the batch end are tracked together
+        let batch = self
+            .compositor
+            .CreateScopedBatch(CompositionBatchTypes::Animation)?;
+        self.start_board_animations(field)?;
+        batch.End()?;
+        self.animation_batch += 1;
+        self.animating = true;
+        let id = self.id;
+        let batch_number = self.animation_batch;
+        batch.Completed(TypedEventHandler::new(move |_, _| {
+            send_panel_event(id, AnimationBatchCompleted(batch_number))
+        }))?;
+        Ok(())
+    }
+
+    fn start_board_animations(&mut self, field: &Field) -> windows::Result<()> {
         self.garbage_collect_tiles()?;
         let mut new_board_tiles = HashMap::new();
-        for x in 0..self.field.width() {
-            for y in 0..self.field.height() {
-                if let Some(tile) = self.field.get(x, y) {
-                    let n = tile.get_n();
+        for x in 0..field.width() {
+            for y in 0..field.height() {
+                if let Some(tile) = field.get(x, y) {
+                    let n = tile.get_level();
                     let visual = match tile.get_origin() {
                         Origin::Appear => self.create_tile_visual(x, y, n),
                         Origin::Hold { .. } => self.hold_tile_visual(x, y, n),
@@ -612,41 +1663,65 @@ impl GameFieldPanel {
         Ok(())
     }
 
-    fn get_tile_color(n: u32) -> windows::Result<Color> {
-        match n {
-            1 => Colors::Gray(),
-            2 => ColorHelper::FromArgb(255, 238, 228, 218),
-            4 => ColorHelper::FromArgb(255, 237, 224, 200),
-            8 => ColorHelper::FromArgb(255, 242, 177, 121),
-            16 => ColorHelper::FromArgb(255, 242, 177, 121),
-            32 => ColorHelper::FromArgb(255, 246, 124, 95),
-            64 => ColorHelper::FromArgb(255, 246, 124, 95),
-            128 => ColorHelper::FromArgb(255, 237, 207, 114),
-            256 => ColorHelper::FromArgb(255, 237, 207, 97),
-            512 => ColorHelper::FromArgb(255, 237, 200, 80),
-            1024 => ColorHelper::FromArgb(255, 237, 197, 63),
-            2048 => ColorHelper::FromArgb(255, 237, 194, 46),
+    // Colors are picked by level, so all merge rules share the same palette
+    fn get_tile_color(level: u32, dark_mode: bool) -> windows::Result<Color> {
+        if dark_mode {
+            return Self::get_dark_tile_color(level);
+        }
+        match level {
+            BLOCKER_LEVEL => ColorHelper::FromArgb(255, 119, 110, 101),
+            0 => Colors::Gray(),
+            1 => ColorHelper::FromArgb(255, 238, 228, 218),
+            2 => ColorHelper::FromArgb(255, 237, 224, 200),
+            3 => ColorHelper::FromArgb(255, 242, 177, 121),
+            4 => ColorHelper::FromArgb(255, 242, 177, 121),
+            5 => ColorHelper::FromArgb(255, 246, 124, 95),
+            6 => ColorHelper::FromArgb(255, 246, 124, 95),
+            7 => ColorHelper::FromArgb(255, 237, 207, 114),
+            8 => ColorHelper::FromArgb(255, 237, 207, 97),
+            9 => ColorHelper::FromArgb(255, 237, 200, 80),
+            10 => ColorHelper::FromArgb(255, 237, 197, 63),
+            11 => ColorHelper::FromArgb(255, 237, 194, 46),
             _ => ColorHelper::FromArgb(255, 60, 58, 60),
         }
     }
 
-    fn get_tile_font_color(n: u32) -> windows::Result<Color> {
-        if n < 8 {
+    fn get_dark_tile_color(level: u32) -> windows::Result<Color> {
+        match level {
+            BLOCKER_LEVEL => ColorHelper::FromArgb(255, 44, 44, 52),
+            0 => Colors::DimGray(),
+            1 => ColorHelper::FromArgb(255, 88, 84, 80),
+            2 => ColorHelper::FromArgb(255, 96, 88, 74),
+            3 => ColorHelper::FromArgb(255, 168, 104, 56),
+            4 => ColorHelper::FromArgb(255, 176, 92, 48),
+            5 => ColorHelper::FromArgb(255, 180, 72, 52),
+            6 => ColorHelper::FromArgb(255, 168, 52, 36),
+            7 => ColorHelper::FromArgb(255, 160, 136, 56),
+            8 => ColorHelper::FromArgb(255, 164, 136, 44),
+            9 => ColorHelper::FromArgb(255, 168, 136, 32),
+            10 => ColorHelper::FromArgb(255, 172, 136, 24),
+            11 => ColorHelper::FromArgb(255, 180, 140, 16),
+            _ => ColorHelper::FromArgb(255, 20, 20, 24),
+        }
+    }
+
+    fn get_tile_font_color(level: u32, dark_mode: bool) -> windows::Result<Color> {
+        if dark_mode {
+            Colors::Gainsboro()
+        } else if level < 3 {
             Colors::DimGray()
         } else {
             Colors::WhiteSmoke()
         }
     }
 
-    fn get_tile_font_size(n: u32) -> f32 {
-        if n < 1000 {
-            256.
-        } else if n < 10000 {
-            180.
-        } else if n < 100000 {
-            150.
-        } else {
-            120.
+    // Longer labels get smaller font to fit the tile
+    fn get_tile_font_size(label_len: usize) -> f32 {
+        match label_len {
+            0..=3 => 256.,
+            4 => 180.,
+            5 => 150.,
+            _ => 120.,
         }
     }
 }
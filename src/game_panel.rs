@@ -0,0 +1,820 @@
+use bindings::Windows::{Foundation::Numerics::Vector2, UI::Composition::ContainerVisual};
+use std::{
+    any::Any,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use model::format::NumberFormat;
+use panelgui::{
+    capture_visuals, capture_window_to_png, compositor, delegate_panel, get_next_id, quit, ribbon,
+    send_panel_event, show_toast, spawn_message_box, winrt_error, Accelerator, ButtonPanelHandle,
+    ButtonParamsBuilder, CellAlignment, CellLimit, CellTransition, ConfettiPanelHandle,
+    ConfettiParamsBuilder, Control, ControlManager, EventHandlers, GamepadButton, GamepadInput,
+    Handle, IconId, MessageBoxButton, MessageBoxParamsBuilder, Panel, PanelEvent, PanelHandle,
+    RibbonCellParamsBuilder, RibbonPanel, RibbonPanelHandle, ScoreFormatter, ScorePanelHandle,
+    ScoreParamsBuilder, TextPanelHandle, TextParamsBuilder,
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::{
+    game_field_panel::{GameFieldHandle, GameFieldPanel, GameFieldPanelEvent},
+    high_scores,
+    high_scores_panel::{HighScoresPanel, HighScoresPanelHandle},
+    pause_panel::{PausePanel, PausePanelEvent, PausePanelHandle},
+    settings::Settings,
+    settings_panel::{SettingsPanel, SettingsPanelEvent, SettingsPanelHandle},
+    storage,
+    tutorial_panel::{TutorialPanel, TutorialPanelHandle, TutorialTargets},
+};
+
+const MESSAGE_BOX_RATIO: Vector2 = Vector2 { X: 0.9, Y: 0.4 };
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+// Height of the board relative to the header with buttons
+const BOARD_HEIGHT_RATIO: f32 = 4.;
+// Height of the moves line relative to the score
+const MOVE_STATS_RATIO: f32 = 0.4;
+
+// Screen is as wide as the board and as high as the board with the header
+fn screen_aspect_ratio(board_aspect_ratio: f32) -> f32 {
+    board_aspect_ratio * BOARD_HEIGHT_RATIO / (BOARD_HEIGHT_RATIO + 1.)
+}
+
+fn score_formatter(number_format: NumberFormat) -> ScoreFormatter {
+    Rc::new(move |score: u32| number_format.format(score as u64))
+}
+
+#[derive(PartialEq)]
+pub enum GamePanelEvent {
+    MenuRequested,
+}
+
+#[derive(Copy, Clone)]
+pub struct GamePanelHandle(usize);
+
+impl Handle for GamePanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<GamePanel, GamePanelEvent> for GamePanelHandle {}
+
+// Game screen: the board with header buttons and dialogs opened over it
+pub struct GamePanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    event_handlers: EventHandlers<GamePanel>,
+    game_field_handle: GameFieldHandle,
+    undo_button_handle: ButtonPanelHandle,
+    reset_button_handle: ButtonPanelHandle,
+    auto_play_text_handle: TextPanelHandle,
+    time_left_handle: TextPanelHandle,
+    score_column_handle: RibbonPanelHandle,
+    move_stats_handle: TextPanelHandle,
+    game_panel_handle: RibbonPanelHandle,
+    score_handle: ScorePanelHandle,
+    confetti_handle: ConfettiPanelHandle,
+    settings_panel_handle: Option<SettingsPanelHandle>,
+    high_scores_panel_handle: Option<HighScoresPanelHandle>,
+    pause_panel_handle: Option<PausePanelHandle>,
+    tutorial_panel_handle: Option<TutorialPanelHandle>,
+    settings: Settings,
+    // Detected once from the user's region, see Settings::number_format
+    number_format: NumberFormat,
+}
+
+impl GamePanel {
+    pub fn new() -> windows::Result<Self> {
+        let id = get_next_id();
+        let settings = Settings::load();
+        let number_format = settings.number_format();
+
+        let game_field_panel = GameFieldPanel::new(
+            settings.board_size,
+            settings.game_mode,
+            settings.challenge,
+            settings.merge_rule,
+            settings.spawn_policy,
+        )?;
+        let score_panel = ScoreParamsBuilder::default()
+            .formatter(score_formatter(number_format.clone()))
+            .create()?;
+        let move_stats_panel = TextParamsBuilder::default().create()?;
+        let time_left_panel = TextParamsBuilder::default().create()?;
+        let time_left_handle = time_left_panel.handle();
+        let menu_button_panel = ButtonParamsBuilder::default()
+            .text_with_icon("Menu", "☰")?
+            .create()?;
+        let undo_button_panel = ButtonParamsBuilder::default()
+            .text_with_vector_icon("Undo", IconId::Undo)?
+            .accelerator(Some(Accelerator::ctrl(VirtualKeyCode::Z)))
+            .create()?;
+        let reset_button_panel = ButtonParamsBuilder::default()
+            .text_with_vector_icon("Restart", IconId::Restart)?
+            .accelerator(Some(Accelerator::ctrl(VirtualKeyCode::N)))
+            .create()?;
+        let settings_button_panel = ButtonParamsBuilder::default()
+            .text_with_icon("Settings", "⚙")?
+            .create()?;
+        let high_scores_button_panel = ButtonParamsBuilder::default()
+            .text_with_icon("Scores", "🏆")?
+            .create()?;
+        let share_button_panel = ButtonParamsBuilder::default()
+            .text_with_vector_icon("Share", IconId::Share)?
+            .create()?;
+        let hint_button_panel = ButtonParamsBuilder::default()
+            .text_with_icon("Hint", "💡")?
+            .accelerator(Some(Accelerator::key(VirtualKeyCode::H)))
+            .create()?;
+        let confetti_panel = ConfettiParamsBuilder::default().create()?;
+        let auto_play_text_panel = TextParamsBuilder::default().text("▶").create()?;
+        let auto_play_text_handle = auto_play_text_panel.handle();
+        let auto_play_button_panel = ButtonParamsBuilder::default()
+            .panel(auto_play_text_panel)
+            .create()?;
+
+        let score_property = game_field_panel.score_property();
+        let can_undo_property = game_field_panel.can_undo_property();
+        let aspect_ratio_property = game_field_panel.aspect_ratio_property();
+        let aspect_ratio = screen_aspect_ratio(game_field_panel.get_aspect_ratio());
+
+        let (game_field_handle, score_handle, menu_button_handle, undo_button_handle);
+        let (reset_button_handle, settings_button_handle, high_scores_button_handle);
+        let (hint_button_handle, auto_play_button_handle, share_button_handle);
+        let (score_column_handle, move_stats_handle);
+        let game_panel_handle;
+        let confetti_handle;
+
+        let root_panel = ribbon!(Stack {
+            // Screen keeps the proportions of the board with the header and stays at the top
+            // when the window is too tall
+            ribbon!(Stack => game_panel_handle {
+                ribbon!(Vertical {
+                    ribbon!(Horizontal(spacing: 4.) {
+                        menu_button_panel => menu_button_handle,
+                        undo_button_panel => undo_button_handle,
+                        ribbon!(Vertical => score_column_handle {
+                            score_panel => score_handle,
+                            move_stats_panel => move_stats_handle,
+                        }) => { ratio: 2. },
+                        time_left_panel => { ratio: 1.5 },
+                        hint_button_panel => hint_button_handle,
+                        auto_play_button_panel => auto_play_button_handle,
+                        share_button_panel => share_button_handle,
+                        high_scores_button_panel => high_scores_button_handle,
+                        settings_button_panel => settings_button_handle,
+                        reset_button_panel => reset_button_handle,
+                    }),
+                    ribbon!(Stack {
+                        game_field_panel => game_field_handle,
+                        confetti_panel => confetti_handle { input_transparent: true },
+                    }) => { ratio: BOARD_HEIGHT_RATIO },
+                }),
+            }) => { aspect_ratio: Some(aspect_ratio), vertical_alignment: CellAlignment::Start },
+        });
+
+        let visual = compositor().CreateContainerVisual()?;
+        visual
+            .Children()?
+            .InsertAtTop(root_panel.visual().clone())?;
+
+        let mut control_manager = ControlManager::new();
+        control_manager.add_control(menu_button_handle.clone());
+        control_manager.add_control(undo_button_handle.clone());
+        control_manager.add_control(hint_button_handle.clone());
+        control_manager.add_control(auto_play_button_handle.clone());
+        control_manager.add_control(share_button_handle.clone());
+        control_manager.add_control(high_scores_button_handle.clone());
+        control_manager.add_control(settings_button_handle.clone());
+        control_manager.add_control(reset_button_handle.clone());
+
+        let event_handlers = EventHandlers::new()
+            .on(&menu_button_handle, |panel: &mut Self, _| {
+                send_panel_event(panel.id, GamePanelEvent::MenuRequested)
+            })
+            .on(&undo_button_handle, |panel: &mut Self, _| {
+                panel.game_field_handle.at(&mut panel.root_panel)?.undo()
+            })
+            .on(&reset_button_handle, |panel: &mut Self, _| {
+                panel.show_message_box_reset()
+            })
+            .on(&settings_button_handle, |panel: &mut Self, _| {
+                panel.open_settings()
+            })
+            .on(&high_scores_button_handle, |panel: &mut Self, _| {
+                panel.open_high_scores()
+            })
+            .on(&hint_button_handle, |panel: &mut Self, _| {
+                panel
+                    .game_field_handle
+                    .at(&mut panel.root_panel)?
+                    .show_hint()
+            })
+            .on(&auto_play_button_handle, |panel: &mut Self, _| {
+                let game_field = panel.game_field_handle.at(&mut panel.root_panel)?;
+                let auto_play = game_field.is_auto_play();
+                game_field.set_auto_play(!auto_play)
+            })
+            .on(&share_button_handle, |panel: &mut Self, _| {
+                panel.share_board()
+            })
+            .bind(&score_property, |panel: &mut Self, score| {
+                panel
+                    .score_handle
+                    .at(&mut panel.root_panel)?
+                    .set_score(score)
+            })
+            .bind(&can_undo_property, |panel: &mut Self, can_undo| {
+                panel
+                    .undo_button_handle
+                    .at(&mut panel.root_panel)?
+                    .enable(can_undo)
+            })
+            .bind(&aspect_ratio_property, |panel: &mut Self, aspect_ratio| {
+                panel.root_panel.set_cell_aspect_ratio_of(
+                    &panel.game_panel_handle,
+                    Some(screen_aspect_ratio(aspect_ratio)),
+                )
+            })
+            .on(&game_field_handle, |panel: &mut Self, cmd| match cmd {
+                GameFieldPanelEvent::Changed => panel.update_buttons(),
+                GameFieldPanelEvent::UndoRequested => panel.do_undo(),
+                GameFieldPanelEvent::RedoRequested => panel.do_redo(),
+                GameFieldPanelEvent::ResetRequested => panel.show_message_box_reset(),
+                GameFieldPanelEvent::GameOver => panel.show_message_box_game_over(),
+                GameFieldPanelEvent::Won => {
+                    panel.confetti_handle.at(&mut panel.root_panel)?.burst()?;
+                    panel.show_message_box_won()
+                }
+                GameFieldPanelEvent::NewBestScore => {
+                    panel.confetti_handle.at(&mut panel.root_panel)?.burst()
+                }
+                GameFieldPanelEvent::AnimationFinished => Ok(()),
+                GameFieldPanelEvent::TimeLeftChanged => panel.update_time_left(),
+            });
+
+        Ok(Self {
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            event_handlers,
+            game_field_handle,
+            undo_button_handle,
+            reset_button_handle,
+            auto_play_text_handle,
+            time_left_handle,
+            score_column_handle,
+            move_stats_handle,
+            game_panel_handle,
+            score_handle,
+            confetti_handle,
+            settings_panel_handle: None,
+            high_scores_panel_handle: None,
+            pause_panel_handle: None,
+            tutorial_panel_handle: None,
+            settings,
+            number_format,
+        })
+    }
+
+    pub fn handle(&self) -> GamePanelHandle {
+        GamePanelHandle(self.id)
+    }
+
+    pub fn new_game(&mut self) -> windows::Result<()> {
+        self.game_field_handle.at(&mut self.root_panel)?.reset()
+    }
+
+    fn update_buttons(&mut self) -> windows::Result<()> {
+        // Score and undo button are bound to game field properties
+        let auto_play = self
+            .game_field_handle
+            .at(&mut self.root_panel)?
+            .is_auto_play();
+        self.auto_play_text_handle
+            .at(&mut self.root_panel)?
+            .set_text(if auto_play { "⏸" } else { "▶" })?;
+        self.update_time_left()?;
+        self.update_move_stats()
+    }
+
+    // Moves line is hidden unless enabled in settings
+    fn apply_move_stats(&mut self) -> windows::Result<()> {
+        let limit = CellLimit {
+            ratio: if self.settings.move_stats {
+                MOVE_STATS_RATIO
+            } else {
+                0.
+            },
+            ..CellLimit::default()
+        };
+        self.score_column_handle
+            .at(&mut self.root_panel)?
+            .set_cell_limit_of(&self.move_stats_handle, limit)?;
+        self.update_move_stats()
+    }
+
+    fn update_move_stats(&mut self) -> windows::Result<()> {
+        if !self.settings.move_stats {
+            return Ok(());
+        }
+        let game_field = self.game_field_handle.at(&mut self.root_panel)?;
+        let moves = game_field.get_moves();
+        let moves_per_minute = game_field.get_moves_per_minute();
+        let longest_chain = game_field.get_longest_chain();
+        let moves = self.number_format.format(moves as u64);
+        let mut text = match moves_per_minute {
+            Some(speed) => format!("{} moves · {:.0}/min", moves, speed),
+            None => format!("{} moves", moves),
+        };
+        // Single merges are not a chain
+        if longest_chain > 1 {
+            text += &format!(" · chain ×{}", longest_chain);
+        }
+        self.move_stats_handle
+            .at(&mut self.root_panel)?
+            .set_text(text)
+    }
+
+    // Remaining time is shown in timed modes only
+    fn update_time_left(&mut self) -> windows::Result<()> {
+        let time_left = self.game_field_handle.at(&mut self.root_panel)?.time_left();
+        let text = match time_left {
+            Some(time_left) => {
+                // Round up to not show 0:00 while there is still time to move
+                let seconds = (time_left.as_millis() + 999) / 1000;
+                format!("⏱{}:{:02}", seconds / 60, seconds % 60)
+            }
+            None => String::new(),
+        };
+        self.time_left_handle
+            .at(&mut self.root_panel)?
+            .set_text(text)
+    }
+
+    fn show_message_box_reset(&mut self) -> windows::Result<()> {
+        let game_field_handle = self.game_field_handle;
+        let message_box = MessageBoxParamsBuilder::default()
+            .message("Start new game?")
+            .button_flags(MessageBoxButton::Yes | MessageBoxButton::No)
+            .default_button(MessageBoxButton::Yes)
+            .cancel_button(MessageBoxButton::No)
+            .create()?;
+        spawn_message_box(
+            self.game_panel_handle,
+            MESSAGE_BOX_RATIO,
+            message_box,
+            move |root_panel, answer| {
+                if answer == MessageBoxButton::Yes {
+                    game_field_handle.at(root_panel)?.reset()?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn show_message_box_game_over(&mut self) -> windows::Result<()> {
+        let game_field_handle = self.game_field_handle;
+        let score = self.game_field_handle.at(&mut self.root_panel)?.get_score();
+        let message_box = MessageBoxParamsBuilder::default()
+            .message(format!(
+                "Game over! Score: **{}**",
+                self.number_format.format(score as u64)
+            ))
+            .button_flags(MessageBoxButton::Yes | MessageBoxButton::No)
+            .yes_text("New game")
+            .no_text("Keep board")
+            .default_button(MessageBoxButton::Yes)
+            .cancel_button(MessageBoxButton::No)
+            .create()?;
+        spawn_message_box(
+            self.game_panel_handle,
+            MESSAGE_BOX_RATIO,
+            message_box,
+            move |root_panel, answer| {
+                if answer == MessageBoxButton::Yes {
+                    game_field_handle.at(root_panel)?.reset()?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn show_message_box_won(&mut self) -> windows::Result<()> {
+        let game_field_handle = self.game_field_handle;
+        let target = self
+            .game_field_handle
+            .at(&mut self.root_panel)?
+            .get_target();
+        let message_box = MessageBoxParamsBuilder::default()
+            .message(format!("You reached **{}**!", target))
+            .button_flags(MessageBoxButton::Yes | MessageBoxButton::No)
+            .yes_text("Continue playing")
+            .no_text("New game")
+            .default_button(MessageBoxButton::Yes)
+            .cancel_button(MessageBoxButton::Yes)
+            .create()?;
+        spawn_message_box(
+            self.game_panel_handle,
+            MESSAGE_BOX_RATIO,
+            message_box,
+            move |root_panel, answer| {
+                let game_field = game_field_handle.at(root_panel)?;
+                if answer == MessageBoxButton::No {
+                    game_field.reset()
+                } else {
+                    game_field.keep_playing()
+                }
+            },
+        )
+    }
+
+    // Board with score is copied to clipboard as image. As with screenshots, failure is
+    // only reported by toast
+    fn share_board(&mut self) -> windows::Result<()> {
+        let visuals = [
+            self.score_handle.at(&mut self.root_panel)?.visual(),
+            self.game_field_handle.at(&mut self.root_panel)?.visual(),
+        ];
+        match capture_visuals(&visuals).and_then(|image| image.copy_to_clipboard()) {
+            Ok(()) => show_toast("Board copied to clipboard", TOAST_DURATION),
+            Err(e) => show_toast(format!("Sharing failed: {}", e.message()), TOAST_DURATION),
+        }
+    }
+
+    // Failure to capture is reported by toast, it's not a reason to interrupt the game
+    fn save_screenshot(&mut self) -> windows::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let result = storage::prepare_path(&format!("screenshot-{}.png", timestamp))
+            .map_err(|e| winrt_error(e)())
+            .and_then(|path| capture_window_to_png(&path).map(|_| path));
+        match result {
+            Ok(path) => show_toast(format!("Saved {}", path.display()), TOAST_DURATION),
+            Err(e) => show_toast(
+                format!("Screenshot failed: {}", e.message()),
+                TOAST_DURATION,
+            ),
+        }
+    }
+
+    // Pause dialog is placed over any other dialogs, the game stays paused until it's closed
+    fn pause(&mut self) -> windows::Result<()> {
+        if self.pause_panel_handle.is_some() {
+            return Ok(());
+        }
+        // Board is hidden while paused so it can't be studied with the clock stopped
+        let game_field = self.game_field_handle.at(&mut self.root_panel)?;
+        game_field.set_paused(true)?;
+        game_field.set_visible(false)?;
+        let pause_panel = PausePanel::new()?;
+        self.pause_panel_handle = Some(pause_panel.handle());
+        let cell = RibbonCellParamsBuilder::default()
+            .panel(pause_panel)
+            .content_ratio(MESSAGE_BOX_RATIO)
+            .modal(true)
+            .transition(CellTransition::Fade)
+            .create()?;
+        self.game_panel_handle
+            .at(&mut self.root_panel)?
+            .push_cell(cell)
+    }
+
+    // Tutorial covers the whole game screen and points to the board and the buttons
+    pub fn show_tutorial(&mut self) -> windows::Result<()> {
+        if self.tutorial_panel_handle.is_some() {
+            return Ok(());
+        }
+        let targets = TutorialTargets {
+            board: self.game_field_handle.at(&mut self.root_panel)?.visual(),
+            undo: self.undo_button_handle.at(&mut self.root_panel)?.visual(),
+            restart: self.reset_button_handle.at(&mut self.root_panel)?.visual(),
+        };
+        let tutorial_panel = TutorialPanel::new(targets)?;
+        self.tutorial_panel_handle = Some(tutorial_panel.handle());
+        let cell = RibbonCellParamsBuilder::default()
+            .panel(tutorial_panel)
+            .modal(true)
+            .transition(CellTransition::Fade)
+            .create()?;
+        self.game_panel_handle
+            .at(&mut self.root_panel)?
+            .push_cell(cell)
+    }
+
+    // Finished or skipped tutorial isn't shown on next launches
+    fn close_tutorial(&mut self) -> windows::Result<()> {
+        if let Some(handle) = self.tutorial_panel_handle.take() {
+            self.game_panel_handle
+                .at(&mut self.root_panel)?
+                .remove_panel(handle.id())?;
+            if !self.settings.tutorial_seen {
+                self.settings.tutorial_seen = true;
+                self.settings.save().map_err(|e| winrt_error(e)())?;
+            }
+            Ok(())
+        } else {
+            Err(winrt_error("Tutorial panel was not open")())
+        }
+    }
+
+    fn resume(&mut self) -> windows::Result<()> {
+        if let Some(handle) = self.pause_panel_handle.take() {
+            self.game_panel_handle
+                .at(&mut self.root_panel)?
+                .remove_panel(handle.id())?;
+            let game_field = self.game_field_handle.at(&mut self.root_panel)?;
+            game_field.set_visible(true)?;
+            game_field.set_paused(false)
+        } else {
+            Err(winrt_error("Pause panel was not open")())
+        }
+    }
+
+    fn open_settings(&mut self) -> windows::Result<()> {
+        let settings_panel = SettingsPanel::new(self.settings.clone())?;
+        self.settings_panel_handle = Some(settings_panel.handle());
+        let cell = RibbonCellParamsBuilder::default()
+            .panel(settings_panel)
+            .content_ratio(Vector2 { X: 0.9, Y: 0.9 })
+            .modal(true)
+            .transition(CellTransition::Slide)
+            .create()?;
+        self.game_panel_handle
+            .at(&mut self.root_panel)?
+            .push_cell(cell)?;
+        Ok(())
+    }
+
+    // Dialog can be removed from the stack ribbon bypassing close_* methods
+    fn drop_removed_handles(&mut self, panel_event: &PanelEvent) {
+        if let Some(true) = self
+            .settings_panel_handle
+            .as_ref()
+            .map(|h| h.is_removed(panel_event))
+        {
+            self.settings_panel_handle = None;
+        }
+        if let Some(true) = self
+            .high_scores_panel_handle
+            .as_ref()
+            .map(|h| h.is_removed(panel_event))
+        {
+            self.high_scores_panel_handle = None;
+        }
+        if let Some(true) = self
+            .pause_panel_handle
+            .as_ref()
+            .map(|h| h.is_removed(panel_event))
+        {
+            self.pause_panel_handle = None;
+        }
+        if let Some(true) = self
+            .tutorial_panel_handle
+            .as_ref()
+            .map(|h| h.is_removed(panel_event))
+        {
+            self.tutorial_panel_handle = None;
+        }
+    }
+
+    fn close_settings(&mut self) -> windows::Result<()> {
+        if let Some(handle) = self.settings_panel_handle.take() {
+            let cell = self
+                .game_panel_handle
+                .at(&mut self.root_panel)?
+                .pop_cell()?;
+            assert!(cell.panel().id() == handle.id());
+            Ok(())
+        } else {
+            Err(winrt_error("Settings panel was not open")())
+        }
+    }
+
+    fn apply_settings(&mut self, settings: Settings) -> windows::Result<()> {
+        settings.save().map_err(|e| winrt_error(e)())?;
+        show_toast("Settings saved", TOAST_DURATION)?;
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_board_size(settings.board_size)?;
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_game_mode(settings.game_mode)?;
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_challenge(settings.challenge);
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_spawn_policy(settings.spawn_policy);
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_merge_rule(settings.merge_rule)?;
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_fast_forward(settings.fast_forward);
+        let number_format = settings.number_format();
+        self.score_handle
+            .at(&mut self.root_panel)?
+            .set_formatter(score_formatter(number_format.clone()))?;
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_number_format(number_format.clone())?;
+        settings.apply_sound();
+        self.settings = settings;
+        self.number_format = number_format;
+        self.apply_move_stats()?;
+        self.apply_dark_mode()
+    }
+
+    fn apply_dark_mode(&mut self) -> windows::Result<()> {
+        let dark_mode = self.settings.apply_theme()?;
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_dark_mode(dark_mode)
+    }
+
+    fn open_high_scores(&mut self) -> windows::Result<()> {
+        let high_scores_panel = HighScoresPanel::new(
+            &high_scores::load(),
+            self.settings.game_mode,
+            &self.number_format,
+        )?;
+        self.high_scores_panel_handle = Some(high_scores_panel.handle());
+        let cell = RibbonCellParamsBuilder::default()
+            .panel(high_scores_panel)
+            .content_ratio(Vector2 { X: 0.9, Y: 0.9 })
+            .modal(true)
+            .transition(CellTransition::Slide)
+            .create()?;
+        self.game_panel_handle
+            .at(&mut self.root_panel)?
+            .push_cell(cell)?;
+        Ok(())
+    }
+
+    fn close_high_scores(&mut self) -> windows::Result<()> {
+        if let Some(handle) = self.high_scores_panel_handle.take() {
+            let cell = self
+                .game_panel_handle
+                .at(&mut self.root_panel)?
+                .pop_cell()?;
+            assert!(cell.panel().id() == handle.id());
+            Ok(())
+        } else {
+            Err(winrt_error("High scores panel was not open")())
+        }
+    }
+
+    fn do_undo(&mut self) -> windows::Result<()> {
+        self.game_field_handle.at(&mut self.root_panel)?.undo()?;
+        Ok(())
+    }
+
+    fn do_redo(&mut self) -> windows::Result<()> {
+        self.game_field_handle.at(&mut self.root_panel)?.redo()?;
+        Ok(())
+    }
+}
+
+impl Panel for GamePanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.on_resize(&self.visual().Parent()?.Size()?)?;
+        self.update_buttons()?;
+        self.root_panel.on_init()?;
+        let fast_forward = self.settings.fast_forward;
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_fast_forward(fast_forward);
+        let number_format = self.number_format.clone();
+        self.game_field_handle
+            .at(&mut self.root_panel)?
+            .set_number_format(number_format)?;
+        self.apply_move_stats()?;
+        self.apply_dark_mode()?;
+        if !self.settings.tutorial_seen {
+            self.show_tutorial()?;
+        }
+        Ok(())
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> windows::Result<()> {
+        self.root_panel.on_focus_changed(focused)?;
+        if !focused {
+            self.pause()?;
+        }
+        Ok(())
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: winit::event::KeyboardInput,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        if self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?
+        {
+            return Ok(true);
+        }
+        if input.state == ElementState::Pressed {
+            if input.virtual_keycode == Some(VirtualKeyCode::Escape) {
+                send_panel_event(self.id, GamePanelEvent::MenuRequested)?;
+                return Ok(true);
+            } else if input.virtual_keycode == Some(VirtualKeyCode::P) {
+                self.pause()?;
+                return Ok(true);
+            } else if input.virtual_keycode == Some(VirtualKeyCode::F2) {
+                self.save_screenshot()?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?
+        {
+            return Ok(true);
+        }
+        if input.button == GamepadButton::B && input.state == ElementState::Pressed {
+            send_panel_event(self.id, GamePanelEvent::MenuRequested)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        if self.event_handlers.clone().dispatch(self, panel_event)? {
+            return Ok(());
+        }
+        self.drop_removed_handles(panel_event);
+        // Dialogs are created on demand, so their events are not in the handlers table
+        if self
+            .high_scores_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+            .is_some()
+        {
+            self.close_high_scores()?;
+        } else if let Some(cmd) = self
+            .pause_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+        {
+            self.resume()?;
+            match cmd {
+                PausePanelEvent::Resume => {}
+                PausePanelEvent::NewGame => self.new_game()?,
+                PausePanelEvent::Quit => quit(),
+            }
+        } else if self
+            .tutorial_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+            .is_some()
+        {
+            self.close_tutorial()?;
+        } else if let Some(cmd) = self
+            .settings_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+        {
+            self.close_settings()?;
+            if let SettingsPanelEvent::Applied(settings) = cmd {
+                self.apply_settings(settings)?;
+            }
+        } else {
+            self.control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+        }
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, on_resize, measure, on_close, on_idle, on_timer, on_suspend,
+        on_resume, on_device_lost, on_mouse_move, on_mouse_input, on_mouse_wheel,
+        on_cursor_inside_changed, on_modifiers_changed, on_char);
+}
@@ -0,0 +1,33 @@
+use model::{
+    game_mode::GameMode,
+    high_scores::{HighScore, HighScores},
+};
+
+use crate::storage;
+
+const HIGH_SCORES_FILE_NAME: &str = "high_scores.txt";
+
+pub fn load() -> HighScores {
+    storage::load(HIGH_SCORES_FILE_NAME)
+        .map(|data| HighScores::from_text(&data))
+        .unwrap_or_default()
+}
+
+pub fn save(high_scores: &HighScores) -> std::io::Result<()> {
+    storage::save(HIGH_SCORES_FILE_NAME, &high_scores.to_text())
+}
+
+// Returns position in the table of the game mode if score made it there
+pub fn record(
+    score: u32,
+    width: usize,
+    height: usize,
+    mode: GameMode,
+) -> std::io::Result<Option<usize>> {
+    let mut high_scores = load();
+    let pos = high_scores.add(HighScore::new(score, width, height, mode));
+    if pos.is_some() {
+        save(&high_scores)?;
+    }
+    Ok(pos)
+}
@@ -0,0 +1,165 @@
+use bindings::Windows::UI::Composition::ContainerVisual;
+use model::{
+    format::NumberFormat,
+    game_mode::GameMode,
+    high_scores::{HighScores, MAX_HIGH_SCORES},
+};
+use panelgui::{
+    compositor, delegate_panel, get_next_id, send_panel_event, BackgroundParamsBuilder,
+    ButtonPanelEvent, ButtonPanelHandle, ButtonParamsBuilder, ControlManager, EmptyPanel,
+    GamepadButton, GamepadInput, Handle, Panel, PanelEvent, PanelHandle, RibbonOrientation,
+    RibbonPanel, RibbonParamsBuilder, TextParamsBuilder,
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+#[derive(PartialEq)]
+pub enum HighScoresPanelEvent {
+    Closed,
+}
+
+pub struct HighScoresPanelHandle(usize);
+
+impl Handle for HighScoresPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<HighScoresPanel, HighScoresPanelEvent> for HighScoresPanelHandle {}
+
+pub struct HighScoresPanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    close_handle: ButtonPanelHandle,
+}
+
+impl HighScoresPanel {
+    // Shows the table of the given game mode only
+    pub fn new(
+        high_scores: &HighScores,
+        mode: GameMode,
+        number_format: &NumberFormat,
+    ) -> windows::Result<Self> {
+        let id = get_next_id();
+        let background = BackgroundParamsBuilder::default()
+            .dialog(true)
+            .round_corners(true)
+            .create()?;
+        let title = if mode == GameMode::Classic {
+            "High scores".to_string()
+        } else {
+            format!("High scores: {}", mode.label())
+        };
+        let title = TextParamsBuilder::default()
+            .text(title)
+            .font_scale(1.5)
+            .create()?;
+        let close = ButtonParamsBuilder::default().text("Close")?.create()?;
+        let close_handle = close.handle();
+        let mut control_manager = ControlManager::new();
+        control_manager.add_control(close_handle);
+
+        let mut table = RibbonParamsBuilder::default().orientation(RibbonOrientation::Vertical);
+        let entries = high_scores.entries(mode);
+        for (i, entry) in entries.iter().enumerate() {
+            let text = format!(
+                "{}. {}   {}x{}   {}",
+                i + 1,
+                number_format.format(entry.score as u64),
+                entry.width,
+                entry.height,
+                entry.date()
+            );
+            table = table.add_panel(TextParamsBuilder::default().text(text).create()?)?;
+        }
+        for _ in entries.len()..MAX_HIGH_SCORES {
+            table = table.add_panel(EmptyPanel::new()?)?;
+        }
+        let ribbon = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Vertical)
+            .add_panel(title)?
+            .add_panel_with_ratio(table.create()?, 5.)?
+            .add_panel(close)?
+            .create()?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel(background)?
+            .add_panel(ribbon)?
+            .create()?;
+
+        let visual = compositor().CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            close_handle,
+        })
+    }
+
+    pub fn handle(&self) -> HighScoresPanelHandle {
+        HighScoresPanelHandle(self.id)
+    }
+}
+
+impl Panel for HighScoresPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: winit::event::KeyboardInput,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        if let Some(key) = input.virtual_keycode {
+            if key == VirtualKeyCode::Escape {
+                send_panel_event(self.id, HighScoresPanelEvent::Closed)?;
+                return Ok(true);
+            }
+        }
+        Ok(self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if input.button == GamepadButton::B && input.state == ElementState::Pressed {
+            send_panel_event(self.id, HighScoresPanelEvent::Closed)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        if self.close_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            send_panel_event(self.id, HighScoresPanelEvent::Closed)?;
+        } else {
+            let _ = self
+                .control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+        }
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, on_resize, measure, lifecycle, on_mouse_move, on_mouse_input,
+        on_mouse_wheel, on_cursor_inside_changed, on_modifiers_changed, on_char);
+}
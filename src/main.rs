@@ -1,186 +1,230 @@
-use bindings::Windows::{
-    Foundation::Numerics::Vector2,
-    UI::{Colors, Composition::ContainerVisual},
-};
-use futures::task::LocalSpawnExt;
-use std::any::Any;
-
-use game_field_panel::{GameFieldHandle, GameFieldPanel, GameFieldPanelEvent};
+use bindings::Windows::{Foundation::Numerics::Vector2, UI::Composition::ContainerVisual};
+use std::{any::Any, time::Duration};
+
+use about_panel::{AboutPanel, AboutPanelHandle};
+use game_field_panel::GameFieldPanel;
+use game_panel::{GamePanel, GamePanelEvent, GamePanelHandle};
+use high_scores_panel::{HighScoresPanel, HighScoresPanelHandle};
+use menu_panel::{MenuPanel, MenuPanelEvent, MenuPanelHandle};
 use panelgui::{
-    compositor, get_next_id, init_window, run, spawner, winrt_error, BackgroundParamsBuilder,
-    ButtonPanelEvent, ButtonPanelHandle, ButtonParamsBuilder, Control, ControlManager, EmptyPanel,
-    Handle, MessageBoxButton, MessageBoxPanelHandle, MessageBoxParamsBuilder, Panel, PanelEvent,
-    PanelHandle, RibbonCellParamsBuilder, RibbonOrientation, RibbonPanel, RibbonPanelHandle,
-    RibbonParamsBuilder, TextPanelHandle, TextParamsBuilder,
+    compositor, delegate_panel, get_next_id, init_window_with, quit, register_accelerator, run,
+    scale_factor, set_error_hook, show_toast, unregister_accelerators, winrt_error, Accelerator,
+    AcceleratorEvent, BackgroundParamsBuilder, CellLimit, Panel, PanelEvent, PanelHandle,
+    RibbonCellParamsBuilder, RibbonOrientation, RibbonPanel, RibbonPanelHandle,
+    RibbonParamsBuilder, ScreenManagerHandle, ScreenManagerParamsBuilder, ScreenTransition,
+    TitleBarPanelHandle, TitleBarParamsBuilder,
 };
+use settings::Settings;
+use settings_panel::{SettingsPanel, SettingsPanelEvent, SettingsPanelHandle};
+use window_state::WindowState;
+use winit::event::VirtualKeyCode;
 
+mod about_panel;
 mod game_field_panel;
-
+mod game_panel;
+mod high_scores;
+mod high_scores_panel;
+mod menu_panel;
+mod pause_panel;
+mod settings;
+mod settings_panel;
+mod storage;
+mod tutorial_panel;
+mod window_state;
+
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+const ERROR_LOG_FILE_NAME: &str = "errors.log";
+const DIALOG_RATIO: Vector2 = Vector2 { X: 0.9, Y: 0.9 };
+// Window is created without decorations, the title bar is drawn by the app
+const TITLE_BAR_HEIGHT: f32 = 32.;
+
+// Application root: switches between the main menu, the game and the dialogs opened from the menu
 struct MainPanel {
     id: usize,
     visual: ContainerVisual,
     root_panel: RibbonPanel,
-    control_manager: ControlManager,
-    game_field_handle: GameFieldHandle,
-    undo_button_handle: ButtonPanelHandle,
-    reset_button_handle: ButtonPanelHandle,
-    horizontal_padding_handle: RibbonPanelHandle,
-    vertical_padding_handle: RibbonPanelHandle,
-    game_panel_handle: RibbonPanelHandle,
-    score_handle: TextPanelHandle,
-    message_box_reset_handle: Option<MessageBoxPanelHandle>,
+    window_content_handle: RibbonPanelHandle,
+    title_bar_handle: TitleBarPanelHandle,
+    screen_manager_handle: ScreenManagerHandle,
+    menu_handle: MenuPanelHandle,
+    game_panel_handle: Option<GamePanelHandle>,
+    settings_panel_handle: Option<SettingsPanelHandle>,
+    high_scores_panel_handle: Option<HighScoresPanelHandle>,
+    about_panel_handle: Option<AboutPanelHandle>,
 }
 
 impl MainPanel {
     pub fn new() -> windows::Result<Self> {
         let id = get_next_id();
-
         let background_panel = BackgroundParamsBuilder::default()
-            .color(Colors::White()?)
+            .transparent_to_backdrop(true)
             .create()?;
-        let game_field_panel = GameFieldPanel::new()?;
-        let score_panel = TextParamsBuilder::default().create()?;
-        let undo_button_panel = ButtonParamsBuilder::default().text("⮌")?.create()?;
-        let reset_button_panel = ButtonParamsBuilder::default().text("⭯")?.create()?;
-
-        let game_field_handle = game_field_panel.handle();
-        let score_handle = score_panel.handle();
-        let undo_button_handle = undo_button_panel.handle();
-        let reset_button_handle = reset_button_panel.handle();
-
-        let header_panel = RibbonParamsBuilder::default()
-            .orientation(RibbonOrientation::Horizontal)
-            .add_panel(undo_button_panel)?
-            .add_panel_with_ratio(score_panel, 2.)?
-            .add_panel(reset_button_panel)?
-            .create()?;
-
-        let game_ribbon = RibbonParamsBuilder::default()
-            .orientation(RibbonOrientation::Vertical)
-            .add_panel(header_panel)?
-            .add_panel_with_ratio(game_field_panel, 4.)?
-            .create()?;
-
-        let game_panel = RibbonParamsBuilder::default()
-            .orientation(RibbonOrientation::Stack)
-            .add_panel(game_ribbon)?
+        let menu_panel = MenuPanel::new()?;
+        let menu_handle = menu_panel.handle();
+        let screen_manager = ScreenManagerParamsBuilder::default()
+            .screen(menu_panel)
             .create()?;
-
-        let game_panel_handle = game_panel.handle();
-
-        let vertical_padding_panel = RibbonParamsBuilder::default()
+        let screen_manager_handle = screen_manager.handle();
+        let title_bar = TitleBarParamsBuilder::default().title("2048").create()?;
+        let title_bar_handle = title_bar.handle();
+        let window_content = RibbonParamsBuilder::default()
             .orientation(RibbonOrientation::Vertical)
-            .add_panel(game_panel)?
-            .add_panel(EmptyPanel::new()?)?
+            .add_panel(title_bar)?
+            .add_panel(screen_manager)?
             .create()?;
-
-        let vertical_padding_handle = vertical_padding_panel.handle();
-
-        let horizontal_padding_panel = RibbonParamsBuilder::default()
-            .orientation(RibbonOrientation::Horizontal)
-            .add_panel(EmptyPanel::new()?)?
-            .add_cell(
-                RibbonCellParamsBuilder::default()
-                    .panel(vertical_padding_panel)
-                    .create()?,
-            )
-            .add_panel(EmptyPanel::new()?)?
-            .create()?;
-
-        let horizontal_padding_handle = horizontal_padding_panel.handle();
-
+        let window_content_handle = window_content.handle();
         let root_panel = RibbonParamsBuilder::default()
             .orientation(RibbonOrientation::Stack)
             .add_panel(background_panel)?
-            .add_panel(horizontal_padding_panel)?
+            .add_panel(window_content)?
             .create()?;
-
         let visual = compositor().CreateContainerVisual()?;
-        visual
-            .Children()?
-            .InsertAtTop(root_panel.visual().clone())?;
-
-        let mut control_manager = ControlManager::new();
-        control_manager.add_control(undo_button_handle.clone());
-        control_manager.add_control(reset_button_handle.clone());
-
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
         Ok(Self {
             id,
             visual,
             root_panel,
-            control_manager,
-            game_field_handle,
-            undo_button_handle,
-            reset_button_handle,
-            horizontal_padding_handle,
-            vertical_padding_handle,
-            game_panel_handle,
-            score_handle,
-            message_box_reset_handle: None,
+            window_content_handle,
+            title_bar_handle,
+            screen_manager_handle,
+            menu_handle,
+            game_panel_handle: None,
+            settings_panel_handle: None,
+            high_scores_panel_handle: None,
+            about_panel_handle: None,
         })
     }
 
-    fn update_buttons(&mut self) -> windows::Result<()> {
-        let game_field = self.game_field_handle.at(&mut self.root_panel)?;
-        let can_undo = game_field.can_undo();
-        let score = game_field.get_score();
-        self.undo_button_handle
+    fn update_menu(&mut self) -> windows::Result<()> {
+        self.menu_handle
             .at(&mut self.root_panel)?
-            .enable(can_undo)?;
-        self.score_handle
+            .enable_continue(GameFieldPanel::has_saved_game())
+    }
+
+    fn open_game(&mut self, new_game: bool) -> windows::Result<()> {
+        let game_panel = GamePanel::new()?;
+        let game_panel_handle = game_panel.handle();
+        self.game_panel_handle = Some(game_panel_handle);
+        let cell = RibbonCellParamsBuilder::default()
+            .panel(game_panel)
+            .create()?;
+        self.screen_manager_handle
             .at(&mut self.root_panel)?
-            .set_text(score.to_string())?;
+            .push_screen(cell, ScreenTransition::Slide)?;
+        if new_game {
+            game_panel_handle.at(&mut self.root_panel)?.new_game()?;
+        }
         Ok(())
     }
 
-    fn show_message_box_reset(&mut self) -> windows::Result<()> {
-        let message_box = MessageBoxParamsBuilder::default()
-            .message("Start new game?")
-            .button_flags(MessageBoxButton::Yes | MessageBoxButton::No)
-            .create()?;
+    // Tutorial is shown over the saved game if there is one, otherwise over a new game
+    fn open_help(&mut self) -> windows::Result<()> {
+        self.open_game(!GameFieldPanel::has_saved_game())?;
+        if let Some(game_panel_handle) = self.game_panel_handle {
+            game_panel_handle
+                .at(&mut self.root_panel)?
+                .show_tutorial()?;
+        }
+        Ok(())
+    }
+
+    // Game is saved when its screen is closed
+    fn close_game(&mut self) -> windows::Result<()> {
+        if self.game_panel_handle.take().is_some() {
+            self.screen_manager_handle
+                .at(&mut self.root_panel)?
+                .pop_screen(ScreenTransition::Slide)?;
+            self.update_menu()
+        } else {
+            Err(winrt_error("Game screen was not open")())
+        }
+    }
+
+    fn open_settings(&mut self) -> windows::Result<()> {
+        let settings_panel = SettingsPanel::new(Settings::load())?;
+        self.settings_panel_handle = Some(settings_panel.handle());
         let cell = RibbonCellParamsBuilder::default()
-            .panel(message_box)
-            .content_ratio(Vector2 { X: 0.9, Y: 0.4 })
+            .panel(settings_panel)
+            .content_ratio(DIALOG_RATIO)
+            .modal(true)
             .create()?;
-        self.game_panel_handle
+        self.screen_manager_handle
             .at(&mut self.root_panel)?
-            .push_cell(cell)?;
-        spawner().spawn_local(async {}).unwrap();
+            .push_screen(cell, ScreenTransition::Fade)
+    }
+
+    fn close_settings(&mut self) -> windows::Result<()> {
+        if self.settings_panel_handle.take().is_some() {
+            self.screen_manager_handle
+                .at(&mut self.root_panel)?
+                .pop_screen(ScreenTransition::Fade)
+        } else {
+            Err(winrt_error("Settings panel was not open")())
+        }
+    }
+
+    // Game settings are applied when the game screen is opened
+    fn apply_settings(&mut self, settings: Settings) -> windows::Result<()> {
+        settings.save().map_err(|e| winrt_error(e)())?;
+        show_toast("Settings saved", TOAST_DURATION)?;
+        settings.apply_sound();
+        settings.apply_theme()?;
         Ok(())
     }
 
-    fn open_message_box_reset(&mut self) -> windows::Result<()> {
-        let message_box = MessageBoxParamsBuilder::default()
-            .message("Start new game?")
-            .button_flags(MessageBoxButton::Yes | MessageBoxButton::No)
-            .create()?;
-        self.message_box_reset_handle = Some(message_box.handle());
+    fn open_high_scores(&mut self) -> windows::Result<()> {
+        let settings = Settings::load();
+        let high_scores_panel = HighScoresPanel::new(
+            &high_scores::load(),
+            settings.game_mode,
+            &settings.number_format(),
+        )?;
+        self.high_scores_panel_handle = Some(high_scores_panel.handle());
         let cell = RibbonCellParamsBuilder::default()
-            .panel(message_box)
-            .content_ratio(Vector2 { X: 0.9, Y: 0.4 })
+            .panel(high_scores_panel)
+            .content_ratio(DIALOG_RATIO)
+            .modal(true)
             .create()?;
-        self.game_panel_handle
+        self.screen_manager_handle
             .at(&mut self.root_panel)?
-            .push_cell(cell)?;
-        Ok(())
+            .push_screen(cell, ScreenTransition::Fade)
     }
 
-    fn close_message_box_reset(&mut self) -> windows::Result<()> {
-        if let Some(handle) = self.message_box_reset_handle.take() {
-            let cell = self
-                .game_panel_handle
+    fn close_high_scores(&mut self) -> windows::Result<()> {
+        if self.high_scores_panel_handle.take().is_some() {
+            self.screen_manager_handle
                 .at(&mut self.root_panel)?
-                .pop_cell()?;
-            assert!(cell.panel().id() == handle.id());
-            Ok(())
+                .pop_screen(ScreenTransition::Fade)
         } else {
-            Err(winrt_error("Message box was not open")())
+            Err(winrt_error("High scores panel was not open")())
         }
     }
 
-    fn do_undo(&mut self) -> windows::Result<()> {
-        self.game_field_handle.at(&mut self.root_panel)?.undo()?;
-        Ok(())
+    // Opened from the menu and by F1 on any screen
+    fn open_about(&mut self) -> windows::Result<()> {
+        if self.about_panel_handle.is_some() {
+            return Ok(());
+        }
+        let about_panel = AboutPanel::new()?;
+        self.about_panel_handle = Some(about_panel.handle());
+        let cell = RibbonCellParamsBuilder::default()
+            .panel(about_panel)
+            .content_ratio(DIALOG_RATIO)
+            .modal(true)
+            .create()?;
+        self.screen_manager_handle
+            .at(&mut self.root_panel)?
+            .push_screen(cell, ScreenTransition::Fade)
+    }
+
+    fn close_about(&mut self) -> windows::Result<()> {
+        if self.about_panel_handle.take().is_some() {
+            self.screen_manager_handle
+                .at(&mut self.root_panel)?
+                .pop_screen(ScreenTransition::Fade)
+        } else {
+            Err(winrt_error("About panel was not open")())
+        }
     }
 }
 
@@ -198,107 +242,87 @@ impl Panel for MainPanel {
     }
 
     fn on_init(&mut self) -> windows::Result<()> {
+        let settings = Settings::load();
+        settings.apply_sound();
+        settings.apply_theme()?;
         self.on_resize(&self.visual().Parent()?.Size()?)?;
-        self.update_buttons()?;
-        self.root_panel.on_init()
+        self.root_panel.on_init()?;
+        register_accelerator(self.id, Accelerator::key(VirtualKeyCode::F1))?;
+        self.update_menu()
     }
 
-    fn find_panel(&mut self, id: usize) -> Option<&mut dyn Any> {
-        if id == self.id {
-            Some(self.as_any_mut())
-        } else {
-            self.root_panel.find_panel(id)
-        }
+    fn on_close(&mut self) -> windows::Result<()> {
+        // Failing to remember window placement is not worth reporting
+        let _ = WindowState::current().save();
+        unregister_accelerators(self.id);
+        self.root_panel.on_close()
     }
 
     fn on_resize(&mut self, size: &Vector2) -> windows::Result<()> {
         self.visual().SetSize(size)?;
-        self.root_panel.on_resize(size)?;
-
-        let mut width_limit = self
-            .horizontal_padding_handle
-            .at(&mut self.root_panel)?
-            .get_cell_limit(1)?;
-        let mut height_limit = self
-            .vertical_padding_handle
+        // Cell limits are in physical pixels, so the title bar height follows the scale factor
+        let mut limit = CellLimit::default();
+        limit.set_size(TITLE_BAR_HEIGHT * scale_factor());
+        self.window_content_handle
             .at(&mut self.root_panel)?
-            .get_cell_limit(0)?;
-
-        // size.X / size.Y > 4/5
-        if 5. * size.X > 4. * size.Y {
-            // x is too large limit width
-            height_limit.set_size(size.Y);
-            width_limit.set_size(size.Y * 4. / 5.);
-        } else {
-            // y is too large, limit height
-            height_limit.set_size(size.X * 5. / 4.);
-            width_limit.set_size(size.X);
-        }
-        self.horizontal_padding_handle
-            .at(&mut self.root_panel)?
-            .set_cell_limit(1, width_limit)?;
-        self.vertical_padding_handle
-            .at(&mut self.root_panel)?
-            .set_cell_limit(0, height_limit)?;
-        Ok(())
-    }
-
-    fn on_idle(&mut self) -> windows::Result<()> {
-        self.root_panel.on_idle()
-    }
-
-    fn on_mouse_move(&mut self, position: &Vector2) -> windows::Result<()> {
-        self.root_panel.on_mouse_move(position)
-    }
-
-    fn on_mouse_input(
-        &mut self,
-        button: winit::event::MouseButton,
-        state: winit::event::ElementState,
-    ) -> windows::Result<bool> {
-        self.root_panel.on_mouse_input(button, state)
-    }
-
-    fn on_keyboard_input(&mut self, input: winit::event::KeyboardInput) -> windows::Result<bool> {
-        Ok(self.root_panel.on_keyboard_input(input)?
-            || self
-                .control_manager
-                .process_keyboard_input(input, &mut self.root_panel)?)
+            .set_cell_limit_of(&self.title_bar_handle, limit)?;
+        self.root_panel.on_resize(size)
     }
 
     fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
         self.root_panel.on_panel_event(panel_event)?;
-        if self.undo_button_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
-            self.game_field_handle.at(&mut self.root_panel)?.undo()?;
-        } else if self.reset_button_handle.extract_event(panel_event)
-            == Some(ButtonPanelEvent::Pressed)
-        {
-            // self.show_message_box_reset()?;
-            self.open_message_box_reset()?;
-        } else if let Some(h) = self.message_box_reset_handle.as_ref() {
-            if let Some(cmd) = h.extract_event(panel_event) {
-                self.close_message_box_reset()?;
-                if cmd == MessageBoxButton::Yes {
-                    self.game_field_handle.at(&mut self.root_panel)?.reset()?;
-                }
-            }
-        } else if let Some(cmd) = self.game_field_handle.extract_event(panel_event) {
+        if panel_event.extract::<AcceleratorEvent>(self.id).is_some() {
+            self.open_about()?;
+        } else if let Some(cmd) = self.menu_handle.extract_event(panel_event) {
             match cmd {
-                GameFieldPanelEvent::Changed => self.update_buttons()?,
-                GameFieldPanelEvent::UndoRequested => self.do_undo()?,
-                GameFieldPanelEvent::ResetRequested => self.open_message_box_reset()?,
+                MenuPanelEvent::NewGame => self.open_game(true)?,
+                MenuPanelEvent::Continue => self.open_game(false)?,
+                MenuPanelEvent::Settings => self.open_settings()?,
+                MenuPanelEvent::HighScores => self.open_high_scores()?,
+                MenuPanelEvent::Help => self.open_help()?,
+                MenuPanelEvent::About => self.open_about()?,
+                MenuPanelEvent::Quit => quit(),
+            }
+        } else if self
+            .game_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+            == Some(GamePanelEvent::MenuRequested)
+        {
+            self.close_game()?;
+        } else if self
+            .high_scores_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+            .is_some()
+        {
+            self.close_high_scores()?;
+        } else if self
+            .about_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+            .is_some()
+        {
+            self.close_about()?;
+        } else if let Some(cmd) = self
+            .settings_panel_handle
+            .as_ref()
+            .and_then(|h| h.extract_event(panel_event))
+        {
+            self.close_settings()?;
+            if let SettingsPanelEvent::Applied(settings) = cmd {
+                self.apply_settings(settings)?;
             }
-        } else {
-            self.control_manager
-                .process_panel_event(panel_event, &mut self.root_panel)?;
         }
         Ok(())
     }
+
+    delegate_panel!(root_panel: tree, measure, desired_size, on_idle, on_timer, on_focus_changed, on_suspend,
+        on_resume, on_device_lost, input);
 }
 
 fn prepare() -> windows::Result<MainPanel> {
-    init_window()?;
-    //window.window().set_title("2048");
+    init_window_with(WindowState::load().window_params()?)?;
     let main_panel = MainPanel::new()?;
     Ok(main_panel)
 }
@@ -311,6 +335,10 @@ fn main() {
             error.code().unwrap();
         }
     } else {
+        // User sees the error message box, the log keeps details for bug reports
+        set_error_hook(|error| {
+            let _ = storage::append(ERROR_LOG_FILE_NAME, &format!("{:?}\n", error));
+        });
         run(main_panel.unwrap())
     }
 }
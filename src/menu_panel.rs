@@ -0,0 +1,195 @@
+use bindings::Windows::UI::Composition::ContainerVisual;
+use panelgui::{
+    compositor, delegate_panel, get_next_id, send_panel_event, ButtonPanelEvent, ButtonPanelHandle,
+    ButtonParamsBuilder, Control, ControlManager, EmptyPanel, GamepadInput, Handle, Panel,
+    PanelEvent, PanelHandle, RibbonOrientation, RibbonPanel, RibbonParamsBuilder,
+    TextParamsBuilder,
+};
+
+#[derive(PartialEq)]
+pub enum MenuPanelEvent {
+    NewGame,
+    Continue,
+    Settings,
+    HighScores,
+    Help,
+    About,
+    Quit,
+}
+
+#[derive(Copy, Clone)]
+pub struct MenuPanelHandle(usize);
+
+impl Handle for MenuPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<MenuPanel, MenuPanelEvent> for MenuPanelHandle {}
+
+// Start screen of the application
+pub struct MenuPanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    new_game_handle: ButtonPanelHandle,
+    continue_handle: ButtonPanelHandle,
+    settings_handle: ButtonPanelHandle,
+    high_scores_handle: ButtonPanelHandle,
+    help_handle: ButtonPanelHandle,
+    about_handle: ButtonPanelHandle,
+    quit_handle: ButtonPanelHandle,
+}
+
+impl MenuPanel {
+    pub fn new() -> windows::Result<Self> {
+        let id = get_next_id();
+        let title = TextParamsBuilder::default()
+            .text("2048")
+            .font_scale(3.)
+            .create()?;
+        let new_game = ButtonParamsBuilder::default().text("New game")?.create()?;
+        let continue_game = ButtonParamsBuilder::default().text("Continue")?.create()?;
+        let settings = ButtonParamsBuilder::default().text("Settings")?.create()?;
+        let high_scores = ButtonParamsBuilder::default()
+            .text("High scores")?
+            .create()?;
+        let help = ButtonParamsBuilder::default().text("Help")?.create()?;
+        let about = ButtonParamsBuilder::default().text("About")?.create()?;
+        let quit = ButtonParamsBuilder::default().text("Quit")?.create()?;
+        let new_game_handle = new_game.handle();
+        let continue_handle = continue_game.handle();
+        let settings_handle = settings.handle();
+        let high_scores_handle = high_scores.handle();
+        let help_handle = help.handle();
+        let about_handle = about.handle();
+        let quit_handle = quit.handle();
+
+        let mut control_manager = ControlManager::new();
+        control_manager.add_control(new_game_handle);
+        control_manager.add_control(continue_handle);
+        control_manager.add_control(settings_handle);
+        control_manager.add_control(high_scores_handle);
+        control_manager.add_control(help_handle);
+        control_manager.add_control(about_handle);
+        control_manager.add_control(quit_handle);
+
+        let buttons = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Vertical)
+            .add_panel_with_ratio(title, 2.)?
+            .add_panel(new_game)?
+            .add_panel(continue_game)?
+            .add_panel(settings)?
+            .add_panel(high_scores)?
+            .add_panel(help)?
+            .add_panel(about)?
+            .add_panel(quit)?
+            .create()?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel(EmptyPanel::new()?)?
+            .add_panel_with_ratio(buttons, 2.)?
+            .add_panel(EmptyPanel::new()?)?
+            .create()?;
+
+        let visual = compositor().CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            new_game_handle,
+            continue_handle,
+            settings_handle,
+            high_scores_handle,
+            help_handle,
+            about_handle,
+            quit_handle,
+        })
+    }
+
+    pub fn handle(&self) -> MenuPanelHandle {
+        MenuPanelHandle(self.id)
+    }
+
+    // Continue is available only when there is a saved game
+    pub fn enable_continue(&mut self, enable: bool) -> windows::Result<()> {
+        self.continue_handle
+            .at(&mut self.root_panel)?
+            .enable(enable)
+    }
+}
+
+impl Panel for MenuPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: winit::event::KeyboardInput,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        Ok(self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        Ok(self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        let event = if self.new_game_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            Some(MenuPanelEvent::NewGame)
+        } else if self.continue_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed)
+        {
+            Some(MenuPanelEvent::Continue)
+        } else if self.settings_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed)
+        {
+            Some(MenuPanelEvent::Settings)
+        } else if self.high_scores_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            Some(MenuPanelEvent::HighScores)
+        } else if self.help_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            Some(MenuPanelEvent::Help)
+        } else if self.about_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            Some(MenuPanelEvent::About)
+        } else if self.quit_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            Some(MenuPanelEvent::Quit)
+        } else {
+            let _ = self
+                .control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+            None
+        };
+        if let Some(event) = event {
+            send_panel_event(self.id, event)?;
+        }
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, on_resize, measure, lifecycle, on_mouse_move, on_mouse_input,
+        on_mouse_wheel, on_cursor_inside_changed, on_modifiers_changed, on_char);
+}
@@ -0,0 +1,167 @@
+use bindings::Windows::UI::Composition::ContainerVisual;
+use panelgui::{
+    compositor, delegate_panel, get_next_id, send_panel_event, BackgroundParamsBuilder,
+    ButtonLayout, ButtonPanelEvent, ButtonPanelHandle, ButtonParamsBuilder, ControlManager,
+    GamepadButton, GamepadInput, Handle, IconId, Panel, PanelEvent, PanelHandle, RibbonOrientation,
+    RibbonPanel, RibbonParamsBuilder, TextParamsBuilder,
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+#[derive(PartialEq)]
+pub enum PausePanelEvent {
+    Resume,
+    NewGame,
+    Quit,
+}
+
+#[derive(Copy, Clone)]
+pub struct PausePanelHandle(usize);
+
+impl Handle for PausePanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<PausePanel, PausePanelEvent> for PausePanelHandle {}
+
+// Shown over the game when it's paused, closing it with Escape resumes the game
+pub struct PausePanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    resume_handle: ButtonPanelHandle,
+    new_game_handle: ButtonPanelHandle,
+    quit_handle: ButtonPanelHandle,
+}
+
+impl PausePanel {
+    pub fn new() -> windows::Result<Self> {
+        let id = get_next_id();
+        let background = BackgroundParamsBuilder::default()
+            .dialog(true)
+            .round_corners(true)
+            .create()?;
+        let title = TextParamsBuilder::default()
+            .text("Paused")
+            .font_scale(3.)
+            .create()?;
+        let resume = ButtonParamsBuilder::default()
+            .icon_with_text(IconId::Play, "Resume", ButtonLayout::IconLeft)?
+            .create()?;
+        let new_game = ButtonParamsBuilder::default()
+            .icon_with_text(IconId::Restart, "New game", ButtonLayout::IconLeft)?
+            .create()?;
+        let quit = ButtonParamsBuilder::default()
+            .icon_with_text(IconId::Close, "Quit", ButtonLayout::IconLeft)?
+            .create()?;
+        let resume_handle = resume.handle();
+        let new_game_handle = new_game.handle();
+        let quit_handle = quit.handle();
+
+        let mut control_manager = ControlManager::new();
+        control_manager.add_control(resume_handle);
+        control_manager.add_control(new_game_handle);
+        control_manager.add_control(quit_handle);
+
+        let ribbon = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Vertical)
+            .add_panel_with_ratio(title, 2.)?
+            .add_panel(resume)?
+            .add_panel(new_game)?
+            .add_panel(quit)?
+            .create()?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel(background)?
+            .add_panel(ribbon)?
+            .create()?;
+
+        let visual = compositor().CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            resume_handle,
+            new_game_handle,
+            quit_handle,
+        })
+    }
+
+    pub fn handle(&self) -> PausePanelHandle {
+        PausePanelHandle(self.id)
+    }
+}
+
+impl Panel for PausePanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: winit::event::KeyboardInput,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        if input.state == ElementState::Pressed
+            && input.virtual_keycode == Some(VirtualKeyCode::Escape)
+        {
+            send_panel_event(self.id, PausePanelEvent::Resume)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if input.button == GamepadButton::B && input.state == ElementState::Pressed {
+            send_panel_event(self.id, PausePanelEvent::Resume)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        let event = if self.resume_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            Some(PausePanelEvent::Resume)
+        } else if self.new_game_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed)
+        {
+            Some(PausePanelEvent::NewGame)
+        } else if self.quit_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            Some(PausePanelEvent::Quit)
+        } else {
+            let _ = self
+                .control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+            None
+        };
+        if let Some(event) = event {
+            send_panel_event(self.id, event)?;
+        }
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, on_resize, measure, lifecycle, on_mouse_move, on_mouse_input,
+        on_mouse_wheel, on_cursor_inside_changed, on_modifiers_changed, on_char);
+}
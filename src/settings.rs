@@ -0,0 +1,219 @@
+use bindings::Windows::Globalization::NumberFormatting::DecimalFormatter;
+use model::{
+    format::NumberFormat, game_mode::GameMode, merge_rule::MergeRuleKind, spawn::SpawnPolicy,
+};
+use panelgui::{
+    is_system_dark_mode, set_muted, set_theme, set_window_dark_mode, winrt_error, Theme,
+};
+
+use crate::storage;
+
+const SETTINGS_FILE_NAME: &str = "settings.txt";
+
+pub const MIN_BOARD_SIZE: usize = 3;
+pub const MAX_BOARD_SIZE: usize = 8;
+// Thresholds of compact number notation offered in settings
+pub const COMPACT_NUMBERS_FROM: [Option<u64>; 4] =
+    [None, Some(10_000), Some(100_000), Some(1_000_000)];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub board_size: usize,
+    // None means following Windows light/dark preference
+    pub dark_mode: Option<bool>,
+    // Skip animations of swipes queued during animation
+    pub fast_forward: bool,
+    pub game_mode: GameMode,
+    // New games start with blocker tiles
+    pub challenge: bool,
+    pub merge_rule: MergeRuleKind,
+    // Number and values of tiles appearing after each swipe, applies to new games
+    pub spawn_policy: SpawnPolicy,
+    pub sound: bool,
+    // Tutorial is shown over the first game until it's finished or skipped
+    pub tutorial_seen: bool,
+    // Scores and tiles from this value are shown as "16k", None shows full numbers
+    pub compact_numbers: Option<u64>,
+    // Move count and speed are shown under the score
+    pub move_stats: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            board_size: 4,
+            dark_mode: None,
+            fast_forward: false,
+            game_mode: GameMode::Classic,
+            challenge: false,
+            merge_rule: MergeRuleKind::Classic,
+            spawn_policy: SpawnPolicy::Classic,
+            sound: true,
+            tutorial_seen: false,
+            compact_numbers: None,
+            move_stats: false,
+        }
+    }
+}
+
+impl Settings {
+    // Settings are stored as "key=value" lines, unknown keys and bad values are ignored
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        if let Some(data) = storage::load(SETTINGS_FILE_NAME) {
+            for line in data.lines() {
+                if let Some((key, value)) = line.split_once('=') {
+                    settings.set(key.trim(), value.trim());
+                }
+            }
+        }
+        settings
+    }
+
+    // Detection may be unavailable on older Windows versions
+    pub fn is_dark_mode(&self) -> bool {
+        self.dark_mode
+            .unwrap_or_else(|| is_system_dark_mode().unwrap_or(false))
+    }
+
+    // Returns whether dark theme was set
+    pub fn apply_theme(&self) -> windows::Result<bool> {
+        let dark_mode = self.is_dark_mode();
+        set_theme(if dark_mode {
+            Theme::dark()?
+        } else {
+            Theme::light()?
+        });
+        set_window_dark_mode(dark_mode);
+        Ok(dark_mode)
+    }
+
+    pub fn apply_sound(&self) {
+        set_muted(!self.sound);
+    }
+
+    // Separators follow the user's regional format, default ones are used if it's
+    // not available
+    pub fn number_format(&self) -> NumberFormat {
+        let mut format = locale_number_format().unwrap_or_default();
+        format.compact_from = self.compact_numbers;
+        format
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        storage::save(
+            SETTINGS_FILE_NAME,
+            &format!(
+                "board_size={}\ndark_mode={}\nfast_forward={}\ngame_mode={}\nchallenge={}\nmerge_rule={}\nspawn={}\nsound={}\ntutorial_seen={}\ncompact_numbers={}\nmove_stats={}\n",
+                self.board_size,
+                match self.dark_mode {
+                    None => "auto",
+                    Some(true) => "on",
+                    Some(false) => "off",
+                },
+                if self.fast_forward { "on" } else { "off" },
+                self.game_mode.name(),
+                if self.challenge { "on" } else { "off" },
+                self.merge_rule.name(),
+                self.spawn_policy.name(),
+                if self.sound { "on" } else { "off" },
+                if self.tutorial_seen { "on" } else { "off" },
+                match self.compact_numbers {
+                    None => "off".to_string(),
+                    Some(from) => from.to_string(),
+                },
+                if self.move_stats { "on" } else { "off" },
+            ),
+        )
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "board_size" => {
+                if let Ok(v) = value.parse::<usize>() {
+                    if (MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&v) {
+                        self.board_size = v;
+                    }
+                }
+            }
+            "dark_mode" => match value {
+                "auto" => self.dark_mode = None,
+                "on" => self.dark_mode = Some(true),
+                "off" => self.dark_mode = Some(false),
+                _ => {}
+            },
+            "fast_forward" => match value {
+                "on" => self.fast_forward = true,
+                "off" => self.fast_forward = false,
+                _ => {}
+            },
+            "sound" => match value {
+                "on" => self.sound = true,
+                "off" => self.sound = false,
+                _ => {}
+            },
+            "tutorial_seen" => match value {
+                "on" => self.tutorial_seen = true,
+                "off" => self.tutorial_seen = false,
+                _ => {}
+            },
+            "compact_numbers" => {
+                if value == "off" {
+                    self.compact_numbers = None;
+                } else if let Ok(from) = value.parse::<u64>() {
+                    self.compact_numbers = Some(from);
+                }
+            }
+            "move_stats" => match value {
+                "on" => self.move_stats = true,
+                "off" => self.move_stats = false,
+                _ => {}
+            },
+            "challenge" => match value {
+                "on" => self.challenge = true,
+                "off" => self.challenge = false,
+                _ => {}
+            },
+            "game_mode" => {
+                if let Some(mode) = GameMode::from_name(value) {
+                    self.game_mode = mode;
+                }
+            }
+            "merge_rule" => {
+                if let Some(rule) = MergeRuleKind::from_name(value) {
+                    self.merge_rule = rule;
+                }
+            }
+            "spawn" => {
+                if let Some(policy) = SpawnPolicy::from_name(value) {
+                    self.spawn_policy = policy;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Separators are found in a sample number formatted for the user's region, like
+// "12,345.6" or "12 345,6". Regions with other digits keep the default format
+fn locale_number_format() -> windows::Result<NumberFormat> {
+    let formatter = DecimalFormatter::new()?;
+    formatter.SetIsGrouped(true)?;
+    formatter.SetFractionDigits(1)?;
+    let sample = formatter.FormatDouble(12345.6)?.to_string();
+    let between = |before: char, after: char| {
+        let start = sample.find(before)? + before.len_utf8();
+        let len = sample[start..].find(after)?;
+        Some(sample[start..start + len].to_string())
+    };
+    match (between('2', '3'), between('5', '6')) {
+        (Some(group_separator), Some(decimal_separator)) if !decimal_separator.is_empty() => {
+            Ok(NumberFormat {
+                group_separator,
+                decimal_separator,
+                ..NumberFormat::default()
+            })
+        }
+        _ => Err(winrt_error(format!("Unexpected number format: {}", sample))()),
+    }
+}
@@ -0,0 +1,503 @@
+use bindings::Windows::UI::Composition::ContainerVisual;
+use model::{
+    format::NumberFormat, game_mode::GameMode, merge_rule::MergeRuleKind, spawn::SpawnPolicy,
+};
+use panelgui::{
+    compositor, delegate_panel, get_next_id, send_panel_event, BackgroundParamsBuilder,
+    ButtonPanelEvent, ButtonPanelHandle, ButtonParamsBuilder, Control, ControlManager,
+    GamepadButton, GamepadInput, Handle, Panel, PanelEvent, PanelHandle, RibbonOrientation,
+    RibbonPanel, RibbonParamsBuilder, TextPanelHandle, TextParamsBuilder, TogglePanelEvent,
+    TogglePanelHandle, ToggleParamsBuilder,
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::settings::{Settings, COMPACT_NUMBERS_FROM, MAX_BOARD_SIZE, MIN_BOARD_SIZE};
+
+#[derive(PartialEq)]
+pub enum SettingsPanelEvent {
+    Applied(Settings),
+    Canceled,
+}
+
+pub struct SettingsPanelHandle(usize);
+
+impl Handle for SettingsPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<SettingsPanel, SettingsPanelEvent> for SettingsPanelHandle {}
+
+pub struct SettingsPanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    settings: Settings,
+    board_size_handle: TextPanelHandle,
+    smaller_handle: ButtonPanelHandle,
+    larger_handle: ButtonPanelHandle,
+    game_mode_handle: TextPanelHandle,
+    prev_mode_handle: ButtonPanelHandle,
+    next_mode_handle: ButtonPanelHandle,
+    merge_rule_handle: TextPanelHandle,
+    prev_rule_handle: ButtonPanelHandle,
+    next_rule_handle: ButtonPanelHandle,
+    spawn_policy_handle: TextPanelHandle,
+    prev_spawn_handle: ButtonPanelHandle,
+    next_spawn_handle: ButtonPanelHandle,
+    compact_numbers_handle: TextPanelHandle,
+    prev_compact_handle: ButtonPanelHandle,
+    next_compact_handle: ButtonPanelHandle,
+    system_theme_handle: TogglePanelHandle,
+    dark_mode_handle: TogglePanelHandle,
+    fast_forward_handle: TogglePanelHandle,
+    move_stats_handle: TogglePanelHandle,
+    challenge_handle: TogglePanelHandle,
+    sound_handle: TogglePanelHandle,
+    ok_handle: ButtonPanelHandle,
+    cancel_handle: ButtonPanelHandle,
+}
+
+// Next or previous item of the list in circle, value not in the list counts as the first
+fn cycle<T: PartialEq + Copy>(all: &[T], current: T, forward: bool) -> T {
+    let count = all.len();
+    let index = all.iter().position(|item| *item == current).unwrap_or(0);
+    let index = if forward {
+        (index + 1) % count
+    } else {
+        (index + count - 1) % count
+    };
+    all[index]
+}
+
+impl SettingsPanel {
+    pub fn new(settings: Settings) -> windows::Result<Self> {
+        let id = get_next_id();
+        let background = BackgroundParamsBuilder::default()
+            .dialog(true)
+            .round_corners(true)
+            .create()?;
+        let title = TextParamsBuilder::default()
+            .text("Board size")
+            .font_scale(3.)
+            .create()?;
+        let board_size = TextParamsBuilder::default()
+            .text(Self::board_size_text(&settings))
+            .create()?;
+        let smaller = ButtonParamsBuilder::default().text("-")?.create()?;
+        let larger = ButtonParamsBuilder::default().text("+")?.create()?;
+        let game_mode = TextParamsBuilder::default()
+            .text(settings.game_mode.label())
+            .create()?;
+        let prev_mode = ButtonParamsBuilder::default().text("◀")?.create()?;
+        let next_mode = ButtonParamsBuilder::default().text("▶")?.create()?;
+        let merge_rule = TextParamsBuilder::default()
+            .text(Self::merge_rule_label(settings.merge_rule))
+            .create()?;
+        let prev_rule = ButtonParamsBuilder::default().text("◀")?.create()?;
+        let next_rule = ButtonParamsBuilder::default().text("▶")?.create()?;
+        let spawn_policy = TextParamsBuilder::default()
+            .text(Self::spawn_policy_label(settings.spawn_policy))
+            .create()?;
+        let prev_spawn = ButtonParamsBuilder::default().text("◀")?.create()?;
+        let next_spawn = ButtonParamsBuilder::default().text("▶")?.create()?;
+        let compact_numbers = TextParamsBuilder::default()
+            .text(Self::compact_numbers_label(settings.compact_numbers))
+            .create()?;
+        let prev_compact = ButtonParamsBuilder::default().text("◀")?.create()?;
+        let next_compact = ButtonParamsBuilder::default().text("▶")?.create()?;
+        let system_theme = ToggleParamsBuilder::default()
+            .label("System theme")
+            .checked(settings.dark_mode.is_none())
+            .create()?;
+        let dark_mode = ToggleParamsBuilder::default()
+            .label("Dark mode")
+            .checked(settings.is_dark_mode())
+            .enabled(settings.dark_mode.is_some())
+            .create()?;
+        let fast_forward = ToggleParamsBuilder::default()
+            .label("Skip animations on fast input")
+            .checked(settings.fast_forward)
+            .create()?;
+        let move_stats = ToggleParamsBuilder::default()
+            .label("Show moves and speed")
+            .checked(settings.move_stats)
+            .create()?;
+        let challenge = ToggleParamsBuilder::default()
+            .label("Challenge: blocker tiles")
+            .checked(settings.challenge)
+            .create()?;
+        let sound = ToggleParamsBuilder::default()
+            .label("Sound")
+            .checked(settings.sound)
+            .create()?;
+        let ok = ButtonParamsBuilder::default().text("OK")?.create()?;
+        let cancel = ButtonParamsBuilder::default().text("Cancel")?.create()?;
+        let board_size_handle = board_size.handle();
+        let smaller_handle = smaller.handle();
+        let larger_handle = larger.handle();
+        let game_mode_handle = game_mode.handle();
+        let prev_mode_handle = prev_mode.handle();
+        let next_mode_handle = next_mode.handle();
+        let merge_rule_handle = merge_rule.handle();
+        let prev_rule_handle = prev_rule.handle();
+        let next_rule_handle = next_rule.handle();
+        let spawn_policy_handle = spawn_policy.handle();
+        let prev_spawn_handle = prev_spawn.handle();
+        let next_spawn_handle = next_spawn.handle();
+        let compact_numbers_handle = compact_numbers.handle();
+        let prev_compact_handle = prev_compact.handle();
+        let next_compact_handle = next_compact.handle();
+        let system_theme_handle = system_theme.handle();
+        let dark_mode_handle = dark_mode.handle();
+        let fast_forward_handle = fast_forward.handle();
+        let move_stats_handle = move_stats.handle();
+        let challenge_handle = challenge.handle();
+        let sound_handle = sound.handle();
+        let ok_handle = ok.handle();
+        let cancel_handle = cancel.handle();
+
+        let mut control_manager = ControlManager::new();
+        control_manager.add_control(smaller_handle);
+        control_manager.add_control(larger_handle);
+        control_manager.add_control(prev_mode_handle);
+        control_manager.add_control(next_mode_handle);
+        control_manager.add_control(prev_rule_handle);
+        control_manager.add_control(next_rule_handle);
+        control_manager.add_control(prev_spawn_handle);
+        control_manager.add_control(next_spawn_handle);
+        control_manager.add_control(prev_compact_handle);
+        control_manager.add_control(next_compact_handle);
+        control_manager.add_control(system_theme_handle);
+        control_manager.add_control(dark_mode_handle);
+        control_manager.add_control(fast_forward_handle);
+        control_manager.add_control(move_stats_handle);
+        control_manager.add_control(challenge_handle);
+        control_manager.add_control(sound_handle);
+        control_manager.add_control(ok_handle);
+        control_manager.add_control(cancel_handle);
+
+        let board_size_row = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel(smaller)?
+            .add_panel_with_ratio(board_size, 2.)?
+            .add_panel(larger)?
+            .create()?;
+        let game_mode_row = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel(prev_mode)?
+            .add_panel_with_ratio(game_mode, 2.)?
+            .add_panel(next_mode)?
+            .create()?;
+        let merge_rule_row = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel(prev_rule)?
+            .add_panel_with_ratio(merge_rule, 2.)?
+            .add_panel(next_rule)?
+            .create()?;
+        let spawn_policy_row = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel(prev_spawn)?
+            .add_panel_with_ratio(spawn_policy, 2.)?
+            .add_panel(next_spawn)?
+            .create()?;
+        let compact_numbers_row = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel(prev_compact)?
+            .add_panel_with_ratio(compact_numbers, 2.)?
+            .add_panel(next_compact)?
+            .create()?;
+        let buttons_row = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .add_panel(ok)?
+            .add_panel(cancel)?
+            .create()?;
+        let ribbon = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Vertical)
+            .add_panel(title)?
+            .add_panel(board_size_row)?
+            .add_panel(game_mode_row)?
+            .add_panel(merge_rule_row)?
+            .add_panel(spawn_policy_row)?
+            .add_panel(compact_numbers_row)?
+            .add_panel(challenge)?
+            .add_panel(system_theme)?
+            .add_panel(dark_mode)?
+            .add_panel(fast_forward)?
+            .add_panel(move_stats)?
+            .add_panel(sound)?
+            .add_panel(buttons_row)?
+            .create()?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel(background)?
+            .add_panel(ribbon)?
+            .create()?;
+
+        let visual = compositor().CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            settings,
+            board_size_handle,
+            smaller_handle,
+            larger_handle,
+            game_mode_handle,
+            prev_mode_handle,
+            next_mode_handle,
+            merge_rule_handle,
+            prev_rule_handle,
+            next_rule_handle,
+            spawn_policy_handle,
+            prev_spawn_handle,
+            next_spawn_handle,
+            compact_numbers_handle,
+            prev_compact_handle,
+            next_compact_handle,
+            system_theme_handle,
+            dark_mode_handle,
+            fast_forward_handle,
+            move_stats_handle,
+            challenge_handle,
+            sound_handle,
+            ok_handle,
+            cancel_handle,
+        })
+    }
+
+    pub fn handle(&self) -> SettingsPanelHandle {
+        SettingsPanelHandle(self.id)
+    }
+
+    fn board_size_text(settings: &Settings) -> String {
+        format!("{}x{}", settings.board_size, settings.board_size)
+    }
+
+    fn set_system_theme(&mut self, system_theme: bool) -> windows::Result<()> {
+        let dark_mode = self.dark_mode_handle.at(&mut self.root_panel)?;
+        if system_theme {
+            self.settings.dark_mode = None;
+            dark_mode.set_checked(self.settings.is_dark_mode())?;
+        } else {
+            self.settings.dark_mode = Some(dark_mode.is_checked());
+        }
+        dark_mode.enable(!system_theme)
+    }
+
+    fn set_board_size(&mut self, board_size: usize) -> windows::Result<()> {
+        self.settings.board_size = board_size;
+        let text = Self::board_size_text(&self.settings);
+        self.board_size_handle
+            .at(&mut self.root_panel)?
+            .set_text(text)?;
+        self.update_buttons()
+    }
+
+    fn switch_game_mode(&mut self, forward: bool) -> windows::Result<()> {
+        self.settings.game_mode = cycle(&GameMode::ALL, self.settings.game_mode, forward);
+        self.game_mode_handle
+            .at(&mut self.root_panel)?
+            .set_text(self.settings.game_mode.label())
+    }
+
+    fn merge_rule_label(merge_rule: MergeRuleKind) -> &'static str {
+        match merge_rule {
+            MergeRuleKind::Classic => "2, 4, 8, 16...",
+            MergeRuleKind::Fibonacci => "Fibonacci: 1, 2, 3, 5...",
+            MergeRuleKind::Threes => "Threes: 1 + 2, 3, 6...",
+        }
+    }
+
+    fn switch_merge_rule(&mut self, forward: bool) -> windows::Result<()> {
+        self.settings.merge_rule = cycle(&MergeRuleKind::ALL, self.settings.merge_rule, forward);
+        self.merge_rule_handle
+            .at(&mut self.root_panel)?
+            .set_text(Self::merge_rule_label(self.settings.merge_rule))
+    }
+
+    fn spawn_policy_label(spawn_policy: SpawnPolicy) -> &'static str {
+        match spawn_policy {
+            SpawnPolicy::Classic => "New tiles: classic",
+            SpawnPolicy::Easy => "New tiles: easy",
+            SpawnPolicy::Hard => "New tiles: hard, two per move",
+        }
+    }
+
+    fn switch_spawn_policy(&mut self, forward: bool) -> windows::Result<()> {
+        self.settings.spawn_policy = cycle(&SpawnPolicy::ALL, self.settings.spawn_policy, forward);
+        self.spawn_policy_handle
+            .at(&mut self.root_panel)?
+            .set_text(Self::spawn_policy_label(self.settings.spawn_policy))
+    }
+
+    fn compact_numbers_label(compact_numbers: Option<u64>) -> String {
+        match compact_numbers {
+            None => "Full numbers".to_string(),
+            Some(from) => {
+                let format = NumberFormat {
+                    compact_from: Some(from),
+                    ..NumberFormat::default()
+                };
+                format!("Short numbers from {}", format.format(from))
+            }
+        }
+    }
+
+    fn switch_compact_numbers(&mut self, forward: bool) -> windows::Result<()> {
+        self.settings.compact_numbers = cycle(
+            &COMPACT_NUMBERS_FROM,
+            self.settings.compact_numbers,
+            forward,
+        );
+        self.compact_numbers_handle
+            .at(&mut self.root_panel)?
+            .set_text(Self::compact_numbers_label(self.settings.compact_numbers))
+    }
+
+    fn update_buttons(&mut self) -> windows::Result<()> {
+        let board_size = self.settings.board_size;
+        self.smaller_handle
+            .at(&mut self.root_panel)?
+            .enable(board_size > MIN_BOARD_SIZE)?;
+        self.larger_handle
+            .at(&mut self.root_panel)?
+            .enable(board_size < MAX_BOARD_SIZE)?;
+        Ok(())
+    }
+}
+
+impl Panel for SettingsPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.root_panel.on_init()?;
+        self.update_buttons()
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: winit::event::KeyboardInput,
+        modifiers: winit::event::ModifiersState,
+    ) -> windows::Result<bool> {
+        if let Some(key) = input.virtual_keycode {
+            if key == VirtualKeyCode::Escape {
+                send_panel_event(self.id, SettingsPanelEvent::Canceled)?;
+                return Ok(true);
+            }
+        }
+        Ok(self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if input.button == GamepadButton::B && input.state == ElementState::Pressed {
+            send_panel_event(self.id, SettingsPanelEvent::Canceled)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        if self.smaller_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            if self.settings.board_size > MIN_BOARD_SIZE {
+                self.set_board_size(self.settings.board_size - 1)?;
+            }
+        } else if self.larger_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            if self.settings.board_size < MAX_BOARD_SIZE {
+                self.set_board_size(self.settings.board_size + 1)?;
+            }
+        } else if self.prev_mode_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_game_mode(false)?;
+        } else if self.next_mode_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_game_mode(true)?;
+        } else if self.prev_rule_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_merge_rule(false)?;
+        } else if self.next_rule_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_merge_rule(true)?;
+        } else if self.prev_spawn_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_spawn_policy(false)?;
+        } else if self.next_spawn_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_spawn_policy(true)?;
+        } else if self.prev_compact_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_compact_numbers(false)?;
+        } else if self.next_compact_handle.extract_event(panel_event)
+            == Some(ButtonPanelEvent::Pressed)
+        {
+            self.switch_compact_numbers(true)?;
+        } else if let Some(TogglePanelEvent::Toggled(checked)) =
+            self.system_theme_handle.extract_event(panel_event)
+        {
+            self.set_system_theme(checked)?;
+        } else if let Some(TogglePanelEvent::Toggled(checked)) =
+            self.dark_mode_handle.extract_event(panel_event)
+        {
+            self.settings.dark_mode = Some(checked);
+        } else if let Some(TogglePanelEvent::Toggled(checked)) =
+            self.fast_forward_handle.extract_event(panel_event)
+        {
+            self.settings.fast_forward = checked;
+        } else if let Some(TogglePanelEvent::Toggled(checked)) =
+            self.move_stats_handle.extract_event(panel_event)
+        {
+            self.settings.move_stats = checked;
+        } else if let Some(TogglePanelEvent::Toggled(checked)) =
+            self.challenge_handle.extract_event(panel_event)
+        {
+            self.settings.challenge = checked;
+        } else if let Some(TogglePanelEvent::Toggled(checked)) =
+            self.sound_handle.extract_event(panel_event)
+        {
+            self.settings.sound = checked;
+        } else if self.ok_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            send_panel_event(self.id, SettingsPanelEvent::Applied(self.settings.clone()))?;
+        } else if self.cancel_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            send_panel_event(self.id, SettingsPanelEvent::Canceled)?;
+        } else {
+            let _ = self
+                .control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+        }
+        Ok(())
+    }
+
+    delegate_panel!(root_panel: tree, on_resize, measure, on_close, on_idle, on_timer,
+        on_focus_changed, on_suspend, on_resume, on_device_lost, on_mouse_move, on_mouse_input,
+        on_mouse_wheel, on_cursor_inside_changed, on_modifiers_changed, on_char);
+}
@@ -0,0 +1,50 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+const APP_DIR: &str = "game2048-rs";
+
+// Files are kept in %APPDATA%\game2048-rs, current directory is used if APPDATA is not set
+pub fn data_path(name: &str) -> PathBuf {
+    let mut path = std::env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    path.push(APP_DIR);
+    path.push(name);
+    path
+}
+
+// Path for a file written by other means, e.g. a screenshot, with its directory created
+pub fn prepare_path(name: &str) -> io::Result<PathBuf> {
+    let path = data_path(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(path)
+}
+
+pub fn load(name: &str) -> Option<String> {
+    fs::read_to_string(data_path(name)).ok()
+}
+
+pub fn save(name: &str, data: &str) -> io::Result<()> {
+    let path = data_path(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, data)
+}
+
+pub fn append(name: &str, data: &str) -> io::Result<()> {
+    let path = data_path(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(data.as_bytes())
+}
@@ -0,0 +1,456 @@
+use std::time::Duration;
+
+use bindings::Windows::{
+    Foundation::{
+        Numerics::{Vector2, Vector3},
+        TimeSpan,
+    },
+    UI::Composition::{AnimationIterationBehavior, ContainerVisual},
+};
+use panelgui::{
+    animation_settings, compositor, delegate_panel, get_next_id, kill_timer, relative_offset,
+    send_panel_event, set_timer, theme, BackgroundParamsBuilder, ButtonPanelEvent,
+    ButtonPanelHandle, ButtonParamsBuilder, CellSizing, ControlManager, EmptyPanel, GamepadButton,
+    GamepadInput, Handle, OverlayItemParamsBuilder, OverlayPanelHandle, OverlayParamsBuilder,
+    OverlayPosition, OverlaySize, Panel, PanelEvent, PanelHandle, RibbonCellParamsBuilder,
+    RibbonOrientation, RibbonPanel, RibbonParamsBuilder, TextPanelHandle, TextParamsBuilder,
+};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode};
+
+// Arrow over the board slides once per period, then turns to the next direction
+const SWIPE_PERIOD: Duration = Duration::from_millis(1500);
+const SWIPE_SLIDE: TimeSpan = TimeSpan {
+    Duration: 15_000_000,
+};
+const PULSE_PERIOD: TimeSpan = TimeSpan {
+    Duration: 12_000_000,
+};
+
+// Arrow glyph and the direction it slides to
+const SWIPE_DIRECTIONS: [(&str, f32, f32); 4] =
+    [("→", 1., 0.), ("↓", 0., 1.), ("←", -1., 0.), ("↑", 0., -1.)];
+
+#[derive(PartialEq)]
+pub enum TutorialPanelEvent {
+    // Sent both when the last step is passed and when the tutorial is skipped
+    Finished,
+}
+
+#[derive(Copy, Clone)]
+pub struct TutorialPanelHandle(usize);
+
+impl Handle for TutorialPanelHandle {
+    fn id(&self) -> usize {
+        self.0
+    }
+}
+
+impl PanelHandle<TutorialPanel, TutorialPanelEvent> for TutorialPanelHandle {}
+
+// Parts of the game screen the tutorial points to
+pub struct TutorialTargets {
+    pub board: ContainerVisual,
+    pub undo: ContainerVisual,
+    pub restart: ContainerVisual,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum TutorialStep {
+    Swipe,
+    Undo,
+    Restart,
+}
+
+impl TutorialStep {
+    const ALL: [TutorialStep; 3] = [
+        TutorialStep::Swipe,
+        TutorialStep::Undo,
+        TutorialStep::Restart,
+    ];
+
+    fn text(&self) -> &'static str {
+        match self {
+            TutorialStep::Swipe => {
+                "Swipe or press arrow keys to move all tiles. Equal tiles merge into one"
+            }
+            TutorialStep::Undo => "Made a mistake? **Undo** takes back the last move",
+            TutorialStep::Restart => "**Restart** begins a new game. Reach **2048** to win!",
+        }
+    }
+}
+
+// Step-by-step callouts shown over the game screen. The board step slides an arrow
+// over the board, the button steps cover the button with a pulsing highlight
+pub struct TutorialPanel {
+    id: usize,
+    visual: ContainerVisual,
+    root_panel: RibbonPanel,
+    control_manager: ControlManager,
+    targets: TutorialTargets,
+    step: usize,
+    swipe_direction: usize,
+    overlay_handle: OverlayPanelHandle,
+    arrow_handle: TextPanelHandle,
+    highlight_id: usize,
+    highlight_visual: ContainerVisual,
+    message_handle: TextPanelHandle,
+    next_handle: ButtonPanelHandle,
+    next_text_handle: TextPanelHandle,
+    skip_handle: ButtonPanelHandle,
+    // Offset and size of the target the pointer was placed over, it's placed again
+    // when the layout moves the target
+    placement: Option<(Vector2, Vector2)>,
+}
+
+impl TutorialPanel {
+    pub fn new(targets: TutorialTargets) -> windows::Result<Self> {
+        let id = get_next_id();
+        let arrow = TextParamsBuilder::default()
+            .text(SWIPE_DIRECTIONS[0].0)
+            .color(theme().default_ring)
+            .font_scale(1.)
+            .create()?;
+        let arrow_handle = arrow.handle();
+        let mut highlight_color = theme().default_ring;
+        highlight_color.A = 0x80;
+        let highlight = BackgroundParamsBuilder::default()
+            .color(highlight_color)
+            .round_corners(true)
+            .create()?;
+        let highlight_id = highlight.id();
+        let highlight_visual = highlight.visual();
+        // Pointers are sized and placed over their targets in on_idle
+        let hidden = || OverlaySize::Absolute(Vector2 { X: 0., Y: 0. });
+        let overlay = OverlayParamsBuilder::default()
+            .add_item(
+                OverlayItemParamsBuilder::default()
+                    .panel(highlight)
+                    .size(hidden())
+                    .create()?,
+            )
+            .add_item(
+                OverlayItemParamsBuilder::default()
+                    .panel(arrow)
+                    .size(hidden())
+                    .create()?,
+            )
+            .create()?;
+        let overlay_handle = overlay.handle();
+
+        let message = TextParamsBuilder::default()
+            .text(TutorialStep::ALL[0].text())
+            .markup(true)
+            .wrap(true)
+            .create()?;
+        let message_handle = message.handle();
+        let next_text = TextParamsBuilder::default().text("Next").create()?;
+        let next_text_handle = next_text.handle();
+        let next = ButtonParamsBuilder::default()
+            .accessible_name(Some("Next".into()))
+            .panel(next_text)
+            .default_button(true)
+            .create()?;
+        let next_handle = next.handle();
+        let skip = ButtonParamsBuilder::default().text("Skip")?.create()?;
+        let skip_handle = skip.handle();
+
+        let mut control_manager = ControlManager::with_focus_scope(id);
+        control_manager.add_control(next_handle);
+        control_manager.add_control(skip_handle);
+        control_manager.set_default_control(&next_handle);
+        control_manager.set_cancel_control(&skip_handle);
+
+        let buttons = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Horizontal)
+            .spacing(8.)
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel(skip)
+                    .sizing(CellSizing::Auto)
+                    .create()?,
+            )
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel(next)
+                    .sizing(CellSizing::Auto)
+                    .create()?,
+            )
+            .create()?;
+        let callout = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_panel(
+                BackgroundParamsBuilder::default()
+                    .dialog(true)
+                    .round_corners(true)
+                    .shadow(true)
+                    .create()?,
+            )?
+            .add_panel(
+                RibbonParamsBuilder::default()
+                    .orientation(RibbonOrientation::Vertical)
+                    .padding(8.)
+                    .add_panel_with_ratio(message, 2.)?
+                    .add_panel(buttons)?
+                    .create()?,
+            )?
+            .create()?;
+        // Callout stays at the bottom, so it doesn't cover the board middle and the buttons
+        let column = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Vertical)
+            .add_panel_with_ratio(EmptyPanel::new()?, 3.)?
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel(callout)
+                    .content_ratio(Vector2 { X: 0.9, Y: 0.9 })
+                    .create()?,
+            )
+            .create()?;
+        let root_panel = RibbonParamsBuilder::default()
+            .orientation(RibbonOrientation::Stack)
+            .add_cell(
+                RibbonCellParamsBuilder::default()
+                    .panel(overlay)
+                    .input_transparent(true)
+                    .create()?,
+            )
+            .add_panel(column)?
+            .create()?;
+
+        let visual = compositor().CreateContainerVisual()?;
+        visual.Children()?.InsertAtTop(root_panel.visual())?;
+        Ok(Self {
+            id,
+            visual,
+            root_panel,
+            control_manager,
+            targets,
+            step: 0,
+            swipe_direction: 0,
+            overlay_handle,
+            arrow_handle,
+            highlight_id,
+            highlight_visual,
+            message_handle,
+            next_handle,
+            next_text_handle,
+            skip_handle,
+            placement: None,
+        })
+    }
+
+    pub fn handle(&self) -> TutorialPanelHandle {
+        TutorialPanelHandle(self.id)
+    }
+
+    fn current_step(&self) -> TutorialStep {
+        TutorialStep::ALL[self.step]
+    }
+
+    fn next_step(&mut self) -> windows::Result<()> {
+        if self.step + 1 == TutorialStep::ALL.len() {
+            return send_panel_event(self.id, TutorialPanelEvent::Finished);
+        }
+        self.step += 1;
+        self.show_step()
+    }
+
+    fn show_step(&mut self) -> windows::Result<()> {
+        let step = self.current_step();
+        self.message_handle
+            .at(&mut self.root_panel)?
+            .set_text(step.text())?;
+        if self.step + 1 == TutorialStep::ALL.len() {
+            self.next_text_handle
+                .at(&mut self.root_panel)?
+                .set_text("Got it")?;
+        }
+        let swipe = step == TutorialStep::Swipe;
+        self.arrow_handle
+            .at(&mut self.root_panel)?
+            .set_visible(swipe)?;
+        self.highlight_visual.SetIsVisible(!swipe)?;
+        if swipe {
+            set_timer(self.id, SWIPE_PERIOD);
+        } else {
+            kill_timer(self.id);
+            pulse(&self.highlight_visual)?;
+        }
+        // Pointer is placed over the new target on next idle
+        self.placement = None;
+        Ok(())
+    }
+
+    fn slide_arrow(&mut self) -> windows::Result<()> {
+        let (glyph, x, y) = SWIPE_DIRECTIONS[self.swipe_direction];
+        let arrow = self.arrow_handle.at(&mut self.root_panel)?;
+        arrow.set_text(glyph)?;
+        if !animation_settings().enabled {
+            return Ok(());
+        }
+        let visual = arrow.visual();
+        let size = visual.Size()?;
+        let at = |t: f32| Vector3 {
+            X: x * size.X * t,
+            Y: y * size.Y * t,
+            Z: 0.,
+        };
+        let slide = compositor().CreateVector3KeyFrameAnimation()?;
+        slide.InsertKeyFrame(0.0, at(-0.5))?;
+        slide.InsertKeyFrame(1.0, at(0.5))?;
+        slide.SetDuration(SWIPE_SLIDE)?;
+        visual.StartAnimation("Offset", slide)?;
+        let fade = compositor().CreateScalarKeyFrameAnimation()?;
+        fade.InsertKeyFrame(0.0, 0.)?;
+        fade.InsertKeyFrame(0.3, 1.)?;
+        fade.InsertKeyFrame(0.7, 1.)?;
+        fade.InsertKeyFrame(1.0, 0.)?;
+        fade.SetDuration(SWIPE_SLIDE)?;
+        visual.StartAnimation("Opacity", fade)
+    }
+
+    // Arrow takes the middle third of the board, highlight covers the button
+    fn place_pointer(&mut self) -> windows::Result<()> {
+        let step = self.current_step();
+        let target = match step {
+            TutorialStep::Swipe => &self.targets.board,
+            TutorialStep::Undo => &self.targets.undo,
+            TutorialStep::Restart => &self.targets.restart,
+        };
+        let placement = match relative_offset(target, &self.visual)? {
+            Some(offset) => Some((offset, target.Size()?)),
+            None => None,
+        };
+        if placement == self.placement {
+            return Ok(());
+        }
+        self.placement = placement.clone();
+        let (offset, size) = match placement {
+            Some(placement) => placement,
+            None => return Ok(()),
+        };
+        let (panel_id, offset, size) = if step == TutorialStep::Swipe {
+            let third = Vector2 {
+                X: size.X / 3.,
+                Y: size.Y / 3.,
+            };
+            (
+                self.arrow_handle.id(),
+                Vector2 {
+                    X: offset.X + third.X,
+                    Y: offset.Y + third.Y,
+                },
+                third,
+            )
+        } else {
+            (self.highlight_id, offset, size)
+        };
+        let overlay = self.overlay_handle.at(&mut self.root_panel)?;
+        overlay.set_position(panel_id, OverlayPosition::Absolute(offset))?;
+        overlay.set_size(panel_id, OverlaySize::Absolute(size))?;
+        if step == TutorialStep::Swipe {
+            // Slide distance depends on the arrow size
+            self.slide_arrow()?;
+        }
+        Ok(())
+    }
+}
+
+// Opacity goes up and down to draw attention to the highlighted control
+fn pulse(visual: &ContainerVisual) -> windows::Result<()> {
+    if !animation_settings().enabled {
+        return visual.SetOpacity(1.);
+    }
+    let animation = compositor().CreateScalarKeyFrameAnimation()?;
+    animation.InsertKeyFrame(0.0, 0.3)?;
+    animation.InsertKeyFrame(0.5, 1.)?;
+    animation.InsertKeyFrame(1.0, 0.3)?;
+    animation.SetDuration(PULSE_PERIOD)?;
+    animation.SetIterationBehavior(AnimationIterationBehavior::Forever)?;
+    visual.StartAnimation("Opacity", animation)
+}
+
+impl Panel for TutorialPanel {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn visual(&self) -> ContainerVisual {
+        self.visual.clone()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn on_init(&mut self) -> windows::Result<()> {
+        self.root_panel.on_init()?;
+        self.show_step()
+    }
+
+    fn on_close(&mut self) -> windows::Result<()> {
+        kill_timer(self.id);
+        self.root_panel.on_close()
+    }
+
+    fn on_idle(&mut self) -> windows::Result<()> {
+        self.root_panel.on_idle()?;
+        self.place_pointer()
+    }
+
+    fn on_timer(&mut self, timer_id: usize) -> windows::Result<()> {
+        self.root_panel.on_timer(timer_id)?;
+        if timer_id == self.id {
+            self.swipe_direction = (self.swipe_direction + 1) % SWIPE_DIRECTIONS.len();
+            self.slide_arrow()?;
+        }
+        Ok(())
+    }
+
+    fn on_keyboard_input(
+        &mut self,
+        input: KeyboardInput,
+        modifiers: ModifiersState,
+    ) -> windows::Result<bool> {
+        if input.state == ElementState::Pressed
+            && input.virtual_keycode == Some(VirtualKeyCode::Escape)
+        {
+            send_panel_event(self.id, TutorialPanelEvent::Finished)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_keyboard_input(input, modifiers)?
+            || self.control_manager.process_keyboard_input(
+                input,
+                modifiers,
+                &mut self.root_panel,
+            )?)
+    }
+
+    fn on_gamepad_input(&mut self, input: GamepadInput) -> windows::Result<bool> {
+        if input.button == GamepadButton::B && input.state == ElementState::Pressed {
+            send_panel_event(self.id, TutorialPanelEvent::Finished)?;
+            return Ok(true);
+        }
+        Ok(self.root_panel.on_gamepad_input(input)?
+            || self
+                .control_manager
+                .process_gamepad_input(input, &mut self.root_panel)?)
+    }
+
+    fn on_panel_event(&mut self, panel_event: &mut PanelEvent) -> windows::Result<()> {
+        self.root_panel.on_panel_event(panel_event)?;
+        if self.next_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            self.next_step()
+        } else if self.skip_handle.extract_event(panel_event) == Some(ButtonPanelEvent::Pressed) {
+            send_panel_event(self.id, TutorialPanelEvent::Finished)
+        } else {
+            let _ = self
+                .control_manager
+                .process_panel_event(panel_event, &mut self.root_panel)?;
+            Ok(())
+        }
+    }
+
+    delegate_panel!(root_panel: tree, layout, on_focus_changed, on_suspend, on_resume,
+        on_device_lost, on_mouse_move, on_mouse_input, on_mouse_wheel, on_cursor_inside_changed,
+        on_modifiers_changed, on_char);
+}
@@ -0,0 +1,111 @@
+use bindings::Windows::Foundation::Numerics::Vector2;
+use panelgui::{window_position, window_size, WindowBackdrop, WindowParams, WindowParamsBuilder};
+use winit::window::Icon;
+
+use crate::storage;
+
+const WINDOW_STATE_FILE_NAME: &str = "window.txt";
+const MIN_WINDOW_SIZE: f32 = 200.;
+const ICON_SIZE: u32 = 32;
+const ICON_COLOR: [u8; 3] = [237, 194, 46];
+
+// Window placement in physical pixels, restored on the next run
+#[derive(Default, Clone, Debug)]
+pub struct WindowState {
+    pub position: Option<(i32, i32)>,
+    pub size: Option<(u32, u32)>,
+}
+
+impl WindowState {
+    pub fn load() -> Self {
+        let mut state = Self::default();
+        let mut x = None;
+        let mut y = None;
+        let mut width = None;
+        let mut height = None;
+        if let Some(data) = storage::load(WINDOW_STATE_FILE_NAME) {
+            for line in data.lines() {
+                if let Some((key, value)) = line.split_once('=') {
+                    let value = value.trim();
+                    match key.trim() {
+                        "x" => x = value.parse::<i32>().ok(),
+                        "y" => y = value.parse::<i32>().ok(),
+                        "width" => width = value.parse::<u32>().ok(),
+                        "height" => height = value.parse::<u32>().ok(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if let (Some(x), Some(y)) = (x, y) {
+            state.position = Some((x, y));
+        }
+        if let (Some(width), Some(height)) = (width, height) {
+            // Too small size most likely means the window was minimized
+            if width as f32 >= MIN_WINDOW_SIZE && height as f32 >= MIN_WINDOW_SIZE {
+                state.size = Some((width, height));
+            }
+        }
+        state
+    }
+
+    pub fn current() -> Self {
+        let size = window_size();
+        Self {
+            position: window_position().map(|p| (p.X as i32, p.Y as i32)),
+            size: Some((size.X as u32, size.Y as u32)),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut data = String::new();
+        if let Some((x, y)) = self.position {
+            data += &format!("x={}\ny={}\n", x, y);
+        }
+        if let Some((width, height)) = self.size {
+            data += &format!("width={}\nheight={}\n", width, height);
+        }
+        storage::save(WINDOW_STATE_FILE_NAME, &data)
+    }
+
+    pub fn window_params(&self) -> windows::Result<WindowParams> {
+        let mut builder = WindowParamsBuilder::default()
+            .title("2048")
+            .icon(app_icon())
+            .backdrop(WindowBackdrop::Mica)
+            .decorations(false)
+            .min_size(Vector2 {
+                X: MIN_WINDOW_SIZE,
+                Y: MIN_WINDOW_SIZE,
+            });
+        if let Some((x, y)) = self.position {
+            builder = builder.position(Vector2 {
+                X: x as f32,
+                Y: y as f32,
+            });
+        }
+        if let Some((width, height)) = self.size {
+            builder = builder.size(Vector2 {
+                X: width as f32,
+                Y: height as f32,
+            });
+        }
+        builder.create()
+    }
+}
+
+// Plain tile-colored square with cut corners, so no icon file is needed
+fn app_icon() -> Option<Icon> {
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    let last = ICON_SIZE - 1;
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let dx = x.min(last - x);
+            let dy = y.min(last - y);
+            let alpha = if dx + dy < 3 { 0 } else { 255 };
+            rgba.extend_from_slice(&ICON_COLOR);
+            rgba.push(alpha);
+        }
+    }
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).ok()
+}